@@ -5,7 +5,15 @@ use glob::glob;
 use std::error::Error;
 use std::path::PathBuf;
 
-use crate::cmd::config::{Action, Config, ProjectCreate, Run as RunCfg, Upload as UploadCfg};
+use crate::cmd::config::{
+    Action, ClockAction, Config, InstrumentAction, ProjectCreate, ProjectMeteo, Run as RunCfg,
+    Upload as UploadCfg,
+};
+use fluxrs_core::data_formats::auxdata::DEFAULT_AUX_MAX_GAP_S;
+use fluxrs_core::data_formats::cyclecheck::CycleValidation;
+use fluxrs_core::data_formats::meteodata::{
+    MeteoInterpolation, MeteoStation, DEFAULT_METEO_MAX_GAP_S, DEFAULT_METEO_STATION,
+};
 use fluxrs_core::datatype::DataType;
 use fluxrs_core::gastype::GasType;
 use fluxrs_core::instruments::instruments::InstrumentType;
@@ -44,6 +52,18 @@ pub enum Commands {
 
     /// Run processing / queries (no file uploads here)
     Run(RunArgs),
+
+    /// Manage user-defined instrument profiles
+    Instrument {
+        #[command(subcommand)]
+        cmd: InstrumentCmd,
+    },
+
+    /// Manage instrument clock corrections
+    Clock {
+        #[command(subcommand)]
+        cmd: ClockCmd,
+    },
 }
 
 /* --------------------- project create --------------------- */
@@ -52,6 +72,9 @@ pub enum Commands {
 pub enum ProjectCmd {
     /// Create a new project in the database
     Create(ProjectCreateArgs),
+
+    /// Show or change how meteo values are found for cycles
+    Meteo(ProjectMeteoArgs),
 }
 
 #[derive(Debug, Args)]
@@ -87,6 +110,125 @@ pub struct ProjectCreateArgs {
     /// Timezone of the timestamps e.g., Europe/Helsinki (IANA / tz database format)
     #[arg(long = "tz")]
     pub tz: Tz,
+
+    /// Meteo values between samples: linear or nearest
+    #[arg(long = "meteo-interpolation", default_value = "linear")]
+    pub meteo_interpolation: MeteoInterpolation,
+
+    /// Largest distance from a cycle to a meteo sample in seconds
+    #[arg(long = "meteo-max-gap", default_value_t = DEFAULT_METEO_MAX_GAP_S as u64)]
+    pub meteo_max_gap: u64,
+
+    /// Largest distance from a cycle to an auxiliary sample in seconds
+    #[arg(long = "aux-max-gap", default_value_t = DEFAULT_AUX_MAX_GAP_S as u64)]
+    pub aux_max_gap: u64,
+
+    /// Site elevation in meters, pressure is modelled from it when there is no meteo data
+    #[arg(long = "elevation")]
+    pub elevation: Option<f64>,
+
+    /// Site latitude in degrees
+    #[arg(long = "latitude")]
+    pub latitude: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ProjectMeteoArgs {
+    /// Project name
+    #[arg(short = 'p', long = "project")]
+    pub project: String,
+
+    /// Meteo values between samples: linear or nearest
+    #[arg(long = "interpolation")]
+    pub interpolation: Option<MeteoInterpolation>,
+
+    /// Largest distance from a cycle to a meteo sample in seconds
+    #[arg(long = "max-gap")]
+    pub max_gap: Option<u64>,
+
+    /// Largest distance from a cycle to an auxiliary sample in seconds
+    #[arg(long = "aux-max-gap")]
+    pub aux_max_gap: Option<u64>,
+
+    /// Site elevation in meters, pressure is modelled from it when there is no meteo data
+    #[arg(long = "elevation")]
+    pub elevation: Option<f64>,
+
+    /// Site latitude in degrees
+    #[arg(long = "latitude")]
+    pub latitude: Option<f64>,
+}
+
+/* ------------------- instrument profiles ------------------- */
+
+#[derive(Debug, Subcommand)]
+pub enum InstrumentCmd {
+    /// Register an instrument profile from a TOML or JSON file
+    Register {
+        /// Path to the profile file
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: PathBuf,
+    },
+
+    /// List all available instruments
+    List,
+}
+
+/* ------------------- clock corrections ------------------- */
+
+#[derive(Debug, Subcommand)]
+pub enum ClockCmd {
+    /// Add a clock correction, replaces one with the same instrument and start
+    Add(ClockAddArgs),
+
+    /// List the clock corrections of a project
+    List {
+        /// Project name
+        #[arg(short = 'p', long = "project")]
+        project: String,
+    },
+
+    /// Remove a clock correction
+    Remove {
+        /// Project name
+        #[arg(short = 'p', long = "project")]
+        project: String,
+
+        /// Id of the correction, see `clock list`
+        #[arg(long)]
+        id: i64,
+
+        /// Recalculate the fluxes of the affected days
+        #[arg(long)]
+        rerun: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ClockAddArgs {
+    /// Project name
+    #[arg(short = 'p', long = "project")]
+    pub project: String,
+
+    /// Instrument serial, defaults to the main instrument of the project
+    #[arg(long = "serial")]
+    pub instrument_serial: Option<String>,
+
+    /// Start of the correction in UTC
+    #[arg(long = "from", value_parser = parse_datetime_str)]
+    pub valid_from: DateTime<Utc>,
+
+    /// Seconds added to the instrument time, negative if the instrument clock is ahead
+    #[arg(long = "offset", allow_negative_numbers = true)]
+    pub offset_s: f64,
+
+    /// Seconds per day the instrument clock loses, negative if it gains
+    #[arg(long = "drift", default_value_t = 0.0, allow_negative_numbers = true)]
+    pub drift_s_per_day: f64,
+
+    /// Recalculate the fluxes of the affected days
+    #[arg(long)]
+    pub rerun: bool,
 }
 
 /* ----------------------- upload ----------------------- */
@@ -94,7 +236,7 @@ pub struct ProjectCreateArgs {
 #[derive(Debug, Subcommand)]
 pub enum UploadKind {
     /// Upload cycle data files
-    Cycle(UploadArgs),
+    Cycle(CycleUploadArgs),
 
     /// Upload gas data files
     Gas(UploadArgs),
@@ -103,10 +245,22 @@ pub enum UploadKind {
     Height(UploadArgs),
 
     /// Upload meteo data files
-    Meteo(UploadArgs),
+    Meteo(MeteoUploadArgs),
 
     /// Upload chamber metadata files
     Chamber(UploadArgs),
+
+    /// Upload temperature and pressure measured inside the chambers
+    ChamberMeteo(UploadArgs),
+
+    /// Upload auxiliary variables, e.g. soil temperature or water table depth
+    Aux(UploadArgs),
+
+    /// Upload chamber volumes measured directly, e.g. by tracer injection
+    Volume(UploadArgs),
+
+    /// Upload snow depths inside chambers
+    Snow(UploadArgs),
 }
 
 #[derive(Debug, Args)]
@@ -132,6 +286,40 @@ pub struct UploadArgs {
     #[arg(short = 'z', long = "tz")]
     pub tz: Option<Tz>,
 }
+#[derive(Debug, Args)]
+pub struct CycleUploadArgs {
+    #[command(flatten)]
+    pub upload: UploadArgs,
+
+    /// Column mapping file (.toml or .json) for cycle files the built-in formats don't read
+    #[arg(short = 'm', long = "mapping", value_hint = ValueHint::FilePath)]
+    pub mapping: Option<PathBuf>,
+
+    /// What to do with files whose cycles overlap, have offsets out of order or
+    /// have no gas data: warn or reject
+    #[arg(long = "on-problems", default_value = "warn")]
+    pub on_problems: CycleValidation,
+}
+
+#[derive(Debug, Args)]
+pub struct MeteoUploadArgs {
+    #[command(flatten)]
+    pub upload: UploadArgs,
+
+    /// Meteo station the files are from
+    #[arg(long = "station", default_value = DEFAULT_METEO_STATION)]
+    pub station: String,
+
+    /// Order the stations are tried in, lowest first
+    #[arg(long = "priority", default_value_t = 0)]
+    pub priority: i64,
+
+    /// Largest distance from a cycle to a sample of this station in seconds,
+    /// the project's max gap when not given
+    #[arg(long = "max-gap")]
+    pub max_gap: Option<i64>,
+}
+
 impl UploadArgs {
     /// Expand `inputs` into actual files.
     pub fn resolve_files(&self) -> Vec<PathBuf> {
@@ -212,25 +400,64 @@ impl Cli {
                         min_calc_len: args.min_calc_len as f64,
                         mode: args.mode,
                         tz: args.tz,
+                        meteo_interpolation: args.meteo_interpolation,
+                        meteo_max_gap_s: args.meteo_max_gap as i64,
+                        aux_max_gap_s: args.aux_max_gap as i64,
+                        elevation_m: args.elevation,
+                        latitude: args.latitude,
+                    }),
+                },
+                ProjectCmd::Meteo(args) => Config {
+                    db_path,
+                    progress_receiver: None,
+                    action: Action::ProjectMeteo(ProjectMeteo {
+                        project: args.project,
+                        interpolation: args.interpolation,
+                        max_gap_s: args.max_gap.map(|s| s as i64),
+                        aux_max_gap_s: args.aux_max_gap.map(|s| s as i64),
+                        elevation_m: args.elevation,
+                        latitude: args.latitude,
                     }),
                 },
             },
 
             Commands::Upload { kind } => {
+                let mut cycle_mapping = None;
+                let mut cycle_validation = CycleValidation::default();
+                let mut meteo_station = MeteoStation::default();
                 let (project, file_type, inputs, use_newest, tz) = match kind {
                     UploadKind::Gas(u) => (u.project, DataType::Gas, u.inputs, u.use_newest, u.tz),
                     UploadKind::Height(u) => {
                         (u.project, DataType::Height, u.inputs, u.use_newest, u.tz)
                     },
-                    UploadKind::Meteo(u) => {
+                    UploadKind::Meteo(MeteoUploadArgs {
+                        upload: u,
+                        station,
+                        priority,
+                        max_gap,
+                    }) => {
+                        meteo_station =
+                            MeteoStation { name: station, priority, max_gap_s: max_gap };
                         (u.project, DataType::Meteo, u.inputs, u.use_newest, u.tz)
                     },
-                    UploadKind::Cycle(u) => {
+                    UploadKind::Cycle(CycleUploadArgs { upload: u, mapping, on_problems }) => {
+                        cycle_mapping = mapping;
+                        cycle_validation = on_problems;
                         (u.project, DataType::Cycle, u.inputs, u.use_newest, u.tz)
                     },
                     UploadKind::Chamber(u) => {
                         (u.project, DataType::Chamber, u.inputs, u.use_newest, u.tz)
                     },
+                    UploadKind::ChamberMeteo(u) => {
+                        (u.project, DataType::ChamberMeteo, u.inputs, u.use_newest, u.tz)
+                    },
+                    UploadKind::Aux(u) => (u.project, DataType::Aux, u.inputs, u.use_newest, u.tz),
+                    UploadKind::Volume(u) => {
+                        (u.project, DataType::Volume, u.inputs, u.use_newest, u.tz)
+                    },
+                    UploadKind::Snow(u) => {
+                        (u.project, DataType::Snow, u.inputs, u.use_newest, u.tz)
+                    },
                 };
 
                 Config {
@@ -242,10 +469,41 @@ impl Cli {
                         inputs,
                         use_newest,
                         tz,
+                        cycle_mapping,
+                        cycle_validation,
+                        meteo_station,
                     }),
                 }
             },
 
+            Commands::Instrument { cmd } => Config {
+                db_path,
+                progress_receiver: None,
+                action: Action::Instrument(match cmd {
+                    InstrumentCmd::Register { file } => InstrumentAction::Register(file),
+                    InstrumentCmd::List => InstrumentAction::List,
+                }),
+            },
+
+            Commands::Clock { cmd } => Config {
+                db_path,
+                progress_receiver: None,
+                action: Action::Clock(match cmd {
+                    ClockCmd::Add(args) => ClockAction::Add {
+                        project: args.project,
+                        instrument_serial: args.instrument_serial,
+                        valid_from: args.valid_from,
+                        offset_s: args.offset_s,
+                        drift_s_per_day: args.drift_s_per_day,
+                        rerun: args.rerun,
+                    },
+                    ClockCmd::List { project } => ClockAction::List { project },
+                    ClockCmd::Remove { project, id, rerun } => {
+                        ClockAction::Remove { project, id, rerun }
+                    },
+                }),
+            },
+
             Commands::Run(run) => Config {
                 db_path,
                 progress_receiver: None,
@@ -1,18 +1,37 @@
+use fluxrs_core::cycle::cycle::{get_instruments_by_project_map, load_cycles};
 use fluxrs_core::cycle_processor::{Datasets, Infra, Processor};
+use fluxrs_core::cycle_recalcer::{self, Recalcer};
+use fluxrs_core::data_formats::auxdata::{query_aux_async, upload_aux_data_async};
 use fluxrs_core::data_formats::chamberdata::{query_chamber_async, upload_chamber_metadata_async};
+use fluxrs_core::data_formats::chambermeteo::{
+    query_chamber_meteo_async, upload_chamber_meteo_data_async,
+};
+use fluxrs_core::data_formats::chamberseries::{
+    query_series_async, upload_series_async, HEIGHT, SNOW, VOLUME,
+};
+use fluxrs_core::data_formats::clockdata::{
+    delete_clock_correction, insert_clock_correction, query_clock_corrections, ClockCorrection,
+    ClockCorrections,
+};
+use fluxrs_core::data_formats::cyclecheck::CycleValidation;
+use fluxrs_core::data_formats::cyclemapping::CycleMapping;
 use fluxrs_core::data_formats::gasdata::query_gas_async;
-use fluxrs_core::data_formats::heightdata::{query_height_async, upload_height_data_async};
-use fluxrs_core::data_formats::meteodata::{query_meteo_async, upload_meteo_data_async};
+use fluxrs_core::data_formats::meteodata::{
+    query_meteo_async, upload_meteo_data_async, MeteoInterpolation, MeteoStation,
+};
 use fluxrs_core::data_formats::timedata::{query_cycles_async, upload_cycle_data_async};
 use fluxrs_core::datatype::DataType;
 use fluxrs_core::gastype::GasType;
 use fluxrs_core::instruments::instruments::upload_gas_data_async;
 use fluxrs_core::instruments::instruments::{Instrument, InstrumentType};
+use fluxrs_core::instruments::{save_instrument_profile, InstrumentProfile};
 use fluxrs_core::mode::Mode;
 use fluxrs_core::processevent::{
     InsertEvent, ProcessEvent, ProcessEventSink, ProgressEvent, QueryEvent, ReadEvent,
 };
-use fluxrs_core::project::Project;
+use fluxrs_core::project::{
+    update_aux_max_gap, update_meteo_settings, update_site_settings, Project,
+};
 
 use chrono::{DateTime, Utc};
 use chrono_tz::{Tz, UTC};
@@ -38,8 +57,37 @@ pub struct Config {
 #[derive(Debug, Clone)]
 pub enum Action {
     ProjectCreate(ProjectCreate),
+    ProjectMeteo(ProjectMeteo),
     Upload(Upload),
     Run(Run),
+    Instrument(InstrumentAction),
+    Clock(ClockAction),
+}
+
+#[derive(Debug, Clone)]
+pub enum InstrumentAction {
+    Register(PathBuf),
+    List,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClockAction {
+    Add {
+        project: String,
+        instrument_serial: Option<String>,
+        valid_from: DateTime<Utc>,
+        offset_s: f64,
+        drift_s_per_day: f64,
+        rerun: bool,
+    },
+    List {
+        project: String,
+    },
+    Remove {
+        project: String,
+        id: i64,
+        rerun: bool,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +100,22 @@ pub struct ProjectCreate {
     pub min_calc_len: f64,
     pub mode: Mode,
     pub tz: Tz,
+    pub meteo_interpolation: MeteoInterpolation,
+    pub meteo_max_gap_s: i64,
+    pub aux_max_gap_s: i64,
+    pub elevation_m: Option<f64>,
+    pub latitude: Option<f64>,
+}
+
+/// Meteo settings of a project, unset ones are kept
+#[derive(Debug, Clone)]
+pub struct ProjectMeteo {
+    pub project: String,
+    pub interpolation: Option<MeteoInterpolation>,
+    pub max_gap_s: Option<i64>,
+    pub aux_max_gap_s: Option<i64>,
+    pub elevation_m: Option<f64>,
+    pub latitude: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +125,12 @@ pub struct Upload {
     pub inputs: Vec<String>,
     pub use_newest: bool,
     pub tz: Option<Tz>, // only meaningful for Cycle
+    /// Column mapping file for cycle uploads
+    pub cycle_mapping: Option<PathBuf>,
+    /// Whether cycle files with problems are inserted
+    pub cycle_validation: CycleValidation,
+    /// Station meteo files are uploaded for
+    pub meteo_station: MeteoStation,
 }
 
 #[derive(Debug, Clone)]
@@ -92,8 +162,11 @@ impl Config {
     pub fn run(&mut self) -> Result<(), CmdError> {
         match &self.action.clone() {
             Action::ProjectCreate(p) => self.run_project_create(p),
+            Action::ProjectMeteo(m) => self.run_project_meteo(m),
             Action::Upload(u) => self.run_upload(u),
             Action::Run(r) => self.run_process(r),
+            Action::Instrument(i) => self.run_instrument(i),
+            Action::Clock(c) => self.run_clock(c),
         }
     }
 }
@@ -113,6 +186,11 @@ impl Config {
             min_calc_len: p.min_calc_len,
             mode: p.mode,
             tz: p.tz,
+            meteo_interpolation: p.meteo_interpolation,
+            meteo_max_gap_s: p.meteo_max_gap_s,
+            aux_max_gap_s: p.aux_max_gap_s,
+            elevation_m: p.elevation_m,
+            latitude: p.latitude,
         };
 
         // Project::save expects Option<String> for db path in your API
@@ -135,6 +213,243 @@ impl Config {
         }
     }
 
+    fn run_project_meteo(&self, m: &ProjectMeteo) -> Result<(), CmdError> {
+        let dbp_str = self.db_path.display().to_string();
+        let mut project = Project::load(Some(dbp_str), &m.project)
+            .ok_or_else(|| CmdError::Msg(format!("No project found with name: {}", m.project)))?;
+        if m.interpolation.is_some() || m.max_gap_s.is_some() {
+            project.meteo_interpolation = m.interpolation.unwrap_or(project.meteo_interpolation);
+            project.meteo_max_gap_s = m.max_gap_s.unwrap_or(project.meteo_max_gap_s);
+            let conn = Connection::open(&self.db_path)?;
+            update_meteo_settings(
+                &conn,
+                project.id.unwrap(),
+                project.meteo_interpolation,
+                project.meteo_max_gap_s,
+            )?;
+            println!("Meteo settings saved, fluxes change when they are calculated again.");
+        }
+        if let Some(gap) = m.aux_max_gap_s {
+            project.aux_max_gap_s = gap;
+            let conn = Connection::open(&self.db_path)?;
+            update_aux_max_gap(&conn, project.id.unwrap(), gap)?;
+            println!("Aux settings saved, cycles change when they are processed again.");
+        }
+        if m.elevation_m.is_some() || m.latitude.is_some() {
+            project.elevation_m = m.elevation_m.or(project.elevation_m);
+            project.latitude = m.latitude.or(project.latitude);
+            let conn = Connection::open(&self.db_path)?;
+            update_site_settings(
+                &conn,
+                project.id.unwrap(),
+                project.elevation_m,
+                project.latitude,
+            )?;
+            println!("Site settings saved, fluxes change when they are calculated again.");
+        }
+        let fmt_opt = |v: Option<f64>| v.map_or("not set".to_owned(), |v| v.to_string());
+        println!(
+            "Interpolation: {}, largest gap: {} s, aux gap: {} s, elevation: {} m, latitude: {}",
+            project.meteo_interpolation,
+            project.meteo_max_gap_s,
+            project.aux_max_gap_s,
+            fmt_opt(project.elevation_m),
+            fmt_opt(project.latitude)
+        );
+        Ok(())
+    }
+
+    fn run_instrument(&self, action: &InstrumentAction) -> Result<(), CmdError> {
+        match action {
+            InstrumentAction::Register(path) => {
+                let profile = InstrumentProfile::from_path(path)
+                    .map_err(|e| CmdError::Msg(format!("{}: {}", path.display(), e)))?;
+                let conn = Connection::open(&self.db_path)?;
+                let instrument = save_instrument_profile(&conn, &profile)
+                    .map_err(|e| CmdError::Msg(e.to_string()))?;
+                println!("Instrument profile '{}' registered.", instrument);
+            },
+            InstrumentAction::List => {
+                for instrument in InstrumentType::available_instruments() {
+                    let gases: Vec<String> =
+                        instrument.available_gases().iter().map(|g| g.to_string()).collect();
+                    println!("{}: {}", instrument, gases.join(", "));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn run_clock(&mut self, action: &ClockAction) -> Result<(), CmdError> {
+        let dbp_str = self.db_path.display().to_string();
+        let project_name = match action {
+            ClockAction::Add { project, .. }
+            | ClockAction::List { project }
+            | ClockAction::Remove { project, .. } => project,
+        };
+        let project = Project::load(Some(dbp_str), project_name).ok_or_else(|| {
+            CmdError::Msg(format!("No project found with name: {}", project_name))
+        })?;
+        let project_id = project.id.unwrap();
+        let conn = Connection::open(&self.db_path)?;
+        let instruments = get_instruments_by_project_map(&conn, project_id)?;
+
+        let changed = match action {
+            ClockAction::Add {
+                instrument_serial,
+                valid_from,
+                offset_s,
+                drift_s_per_day,
+                rerun,
+                ..
+            } => {
+                let instrument_id = match instrument_serial {
+                    Some(serial) => instruments
+                        .values()
+                        .find(|i| &i.serial == serial)
+                        .and_then(|i| i.id)
+                        .ok_or_else(|| {
+                            CmdError::Msg(format!(
+                                "No instrument with serial {} in project",
+                                serial
+                            ))
+                        })?,
+                    None => project.instrument.id.unwrap(),
+                };
+                let correction = ClockCorrection {
+                    id: None,
+                    instrument_id,
+                    valid_from: valid_from.timestamp(),
+                    offset_s: *offset_s,
+                    drift_s_per_day: *drift_s_per_day,
+                };
+                let id = insert_clock_correction(&conn, &correction)?;
+                println!("Saved clock correction {}.", id);
+                rerun.then_some(correction)
+            },
+            ClockAction::List { .. } => {
+                println!("id\tserial\tvalid_from\toffset_s\tdrift_s_per_day");
+                for c in query_clock_corrections(&conn, project_id)? {
+                    let serial =
+                        instruments.get(&c.instrument_id).map(|i| i.serial.as_str()).unwrap_or("");
+                    let valid_from = DateTime::from_timestamp(c.valid_from, 0).unwrap_or_default();
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        c.id.unwrap_or_default(),
+                        serial,
+                        valid_from,
+                        c.offset_s,
+                        c.drift_s_per_day
+                    );
+                }
+                None
+            },
+            ClockAction::Remove { id, rerun, .. } => {
+                let removed = delete_clock_correction(&conn, *id)?
+                    .ok_or_else(|| CmdError::Msg(format!("No clock correction with id {}", id)))?;
+                println!("Removed clock correction {}.", id);
+                rerun.then_some(removed)
+            },
+        };
+
+        if let Some(c) = changed {
+            let corrections = ClockCorrections::new(query_clock_corrections(&conn, project_id)?);
+            let (start, end) = corrections.affected_range(c.instrument_id, c.valid_from);
+            drop(conn);
+            let start = DateTime::from_timestamp(start, 0).unwrap_or_default();
+            let end = DateTime::from_timestamp(end, 0).unwrap_or_default();
+            self.run_recalc(&project, start, end)?;
+        }
+        Ok(())
+    }
+
+    /// Recalculate the fluxes already in the db between `start` and `end`
+    fn run_recalc(
+        &mut self,
+        project: &Project,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<(), CmdError> {
+        println!("Recalculating from {} to {}", start, end);
+        let conn = Connection::open(&self.db_path)?;
+        let arc_conn = Arc::new(Mutex::new(conn));
+
+        let (progress_sender, progress_receiver) = unbounded_channel::<ProcessEvent>();
+        self.progress_receiver = Some(progress_receiver);
+
+        let proj = project.clone();
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+        let handle = runtime.spawn(async move {
+            let cycle_result =
+                load_cycles(arc_conn.clone(), start, end, proj.clone(), progress_sender.clone())
+                    .await;
+            let meteo_result = query_meteo_async(arc_conn.clone(), start, end, proj.clone()).await;
+            let chamber_meteo_result =
+                query_chamber_meteo_async(arc_conn.clone(), start, end, proj.clone()).await;
+            let height_result =
+                query_series_async(arc_conn.clone(), &HEIGHT, start, end, proj.clone()).await;
+            let volume_result =
+                query_series_async(arc_conn.clone(), &VOLUME, start, end, proj.clone()).await;
+            let snow_result =
+                query_series_async(arc_conn.clone(), &SNOW, start, end, proj.clone()).await;
+            let chamber_result = query_chamber_async(arc_conn.clone(), proj.clone()).await;
+
+            match (
+                cycle_result,
+                meteo_result,
+                chamber_meteo_result,
+                height_result,
+                volume_result,
+                snow_result,
+                chamber_result,
+            ) {
+                (
+                    Ok(cycles),
+                    Ok(meteo_data),
+                    Ok(chamber_meteo_data),
+                    Ok(height_data),
+                    Ok(volume_data),
+                    Ok(snow_data),
+                    Ok(chamber_data),
+                ) => {
+                    let _ = progress_sender.send(ProcessEvent::Query(QueryEvent::QueryComplete));
+                    if cycles.is_empty() {
+                        let msg = "No calculated fluxes found.";
+                        let _ = progress_sender.send(ProcessEvent::Done(Err(msg.to_owned())));
+                        return;
+                    }
+                    let recalcer = Recalcer::new(
+                        proj,
+                        cycle_recalcer::Datasets {
+                            meteo: meteo_data,
+                            chamber_meteo: chamber_meteo_data,
+                            height: height_data,
+                            volume: volume_data,
+                            snow: snow_data,
+                            chambers: chamber_data,
+                        },
+                        cycle_recalcer::Infra { conn: arc_conn, progress: progress_sender },
+                    );
+                    recalcer.run_recalculating(cycles).await;
+                },
+                e => {
+                    let msg = format!("Failed to query database: {:?}", e);
+                    let _ = progress_sender.send(ProcessEvent::Done(Err(msg)));
+                },
+            }
+        });
+        loop {
+            self.drain_progress_messages();
+            if handle.is_finished() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        runtime.block_on(handle).unwrap();
+        self.drain_progress_messages();
+        Ok(())
+    }
+
     fn run_upload(&mut self, u: &Upload) -> Result<(), CmdError> {
         self.handle_progress_messages();
         let dbp_str = self.db_path.display().to_string();
@@ -175,6 +490,13 @@ impl Config {
             return Ok(());
         }
 
+        let mapping = match &u.cycle_mapping {
+            Some(path) => {
+                Some(CycleMapping::from_path(path).map_err(|e| CmdError::Msg(e.to_string()))?)
+            },
+            None => None,
+        };
+
         let (progress_sender, progress_receiver) = unbounded_channel::<ProcessEvent>();
         self.progress_receiver = Some(progress_receiver);
 
@@ -184,18 +506,39 @@ impl Config {
             DataType::Gas => {
                 upload_gas_data_async(files, &mut conn, &project, &instrument, tz, sender_clone)
             },
-            DataType::Cycle => {
-                upload_cycle_data_async(files, &mut conn, &project, tz, sender_clone)
-            },
-            DataType::Meteo => {
-                upload_meteo_data_async(files, &mut conn, &project, tz, sender_clone)
-            },
+            DataType::Cycle => upload_cycle_data_async(
+                files,
+                &mut conn,
+                &project,
+                tz,
+                mapping.as_ref(),
+                u.cycle_validation,
+                sender_clone,
+            ),
+            DataType::Meteo => upload_meteo_data_async(
+                files,
+                &mut conn,
+                &project,
+                &u.meteo_station,
+                tz,
+                sender_clone,
+            ),
             DataType::Height => {
-                upload_height_data_async(files, &mut conn, &project, tz, sender_clone)
+                upload_series_async(&HEIGHT, files, &mut conn, &project, tz, sender_clone)
             },
             DataType::Chamber => {
                 upload_chamber_metadata_async(files, &mut conn, &project, tz, sender_clone)
             },
+            DataType::ChamberMeteo => {
+                upload_chamber_meteo_data_async(files, &mut conn, &project, tz, sender_clone)
+            },
+            DataType::Aux => upload_aux_data_async(files, &mut conn, &project, tz, sender_clone),
+            DataType::Volume => {
+                upload_series_async(&VOLUME, files, &mut conn, &project, tz, sender_clone)
+            },
+            DataType::Snow => {
+                upload_series_async(&SNOW, files, &mut conn, &project, tz, sender_clone)
+            },
         }
 
         self.handle_progress_messages();
@@ -244,12 +587,44 @@ impl Config {
                 query_gas_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
             let meteo_result =
                 query_meteo_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
+            let chamber_meteo_result =
+                query_chamber_meteo_async(arc_conn.clone(), start_date, end_date, proj.clone())
+                    .await;
+            let aux_result =
+                query_aux_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
             let height_result =
-                query_height_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
+                query_series_async(arc_conn.clone(), &HEIGHT, start_date, end_date, proj.clone())
+                    .await;
+            let volume_result =
+                query_series_async(arc_conn.clone(), &VOLUME, start_date, end_date, proj.clone())
+                    .await;
+            let snow_result =
+                query_series_async(arc_conn.clone(), &SNOW, start_date, end_date, proj.clone())
+                    .await;
             let chamber_result = query_chamber_async(arc_conn.clone(), proj.clone()).await;
 
-            match (cycles_result, gas_result, meteo_result, height_result, chamber_result) {
-                (Ok(times), Ok(gas_data), Ok(meteo_data), Ok(height_data), Ok(chamber_data)) => {
+            match (
+                cycles_result,
+                gas_result,
+                meteo_result,
+                chamber_meteo_result,
+                aux_result,
+                height_result,
+                volume_result,
+                snow_result,
+                chamber_result,
+            ) {
+                (
+                    Ok(times),
+                    Ok(gas_data),
+                    Ok(meteo_data),
+                    Ok(chamber_meteo_data),
+                    Ok(aux_data),
+                    Ok(height_data),
+                    Ok(volume_data),
+                    Ok(snow_data),
+                    Ok(chamber_data),
+                ) => {
                     let _ = progress_sender.send(ProcessEvent::Query(QueryEvent::QueryComplete));
                     if !times.start_time.is_empty() && !gas_data.is_empty() {
                         let processor = Processor::new(
@@ -257,7 +632,11 @@ impl Config {
                             Datasets {
                                 gas: Arc::new(gas_data),
                                 meteo: meteo_data,
+                                chamber_meteo: chamber_meteo_data,
+                                aux: aux_data,
                                 height: height_data,
+                                volume: volume_data,
+                                snow: snow_data,
                                 chambers: chamber_data,
                             },
                             Infra { conn: arc_conn, progress: progress_sender },
@@ -274,7 +653,7 @@ impl Config {
                         let _ = progress_sender.send(ProcessEvent::Done(Err(msg.to_owned())));
                     }
                 },
-                (_, Err(err), _, _, _) => {
+                (_, Err(err), _, _, _, _, _, _, _) => {
                     let _ = progress_sender.send(ProcessEvent::Done(Err(err.to_string())));
                 },
                 e => eprintln!("Failed to query database: {:?}", e),
@@ -414,6 +793,10 @@ impl ProcessEventSink for Config {
                     DataType::Height => "height",
                     DataType::Cycle => "cycle",
                     DataType::Chamber => "chamber metadata",
+                    DataType::ChamberMeteo => "chamber meteo",
+                    DataType::Aux => "auxiliary",
+                    DataType::Volume => "volume",
+                    DataType::Snow => "snow depth",
                 };
                 println!("Could not parse as {} file: {}, {}", what, file, reason);
             },
@@ -441,6 +824,10 @@ impl ProcessEventSink for Config {
                     DataType::Height => "height",
                     DataType::Cycle => "cycle",
                     DataType::Chamber => "chamber metadata",
+                    DataType::ChamberMeteo => "chamber meteo",
+                    DataType::Aux => "auxiliary",
+                    DataType::Volume => "volume",
+                    DataType::Snow => "snow depth",
                 };
                 println!(
                     "Inserted {} rows of {} data, skipped {} duplicates.",
@@ -497,6 +884,10 @@ fn last_ingested_ts(conn: &Connection, dt: DataType) -> Option<DateTime<Utc>> {
         DataType::Meteo => "SELECT MAX(datetime) FROM meteo",
         DataType::Height => "SELECT MAX(datetime) FROM height",
         DataType::Cycle => "SELECT MAX(start_time) FROM cycles",
+        DataType::ChamberMeteo => "SELECT MAX(datetime) FROM chamber_meteo",
+        DataType::Aux => "SELECT MAX(datetime) FROM aux_data",
+        DataType::Volume => "SELECT MAX(datetime) FROM volume",
+        DataType::Snow => "SELECT MAX(datetime) FROM snow",
         DataType::Chamber => return None,
     };
     let ts: Option<i64> = conn.query_row(sql, [], |row| row.get(0)).ok().flatten();
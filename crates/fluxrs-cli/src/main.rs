@@ -5,8 +5,10 @@ use crate::cmd::config::Config;
 
 use fluxrs_core::db::fluxes_schema::initiate_tables;
 use fluxrs_core::db::migrate::migrate_db;
+use fluxrs_core::instruments::load_instrument_profiles;
 
 use clap::Parser;
+use rusqlite::Connection;
 use std::path::Path;
 use std::process;
 
@@ -25,6 +27,19 @@ fn main() {
         process::exit(1);
     }
 
+    // profiles have to be registered before --instrument is parsed
+    match Connection::open("fluxrs.db") {
+        Ok(conn) => {
+            if let Err(e) = load_instrument_profiles(&conn) {
+                eprintln!("Failed to load instrument profiles: {}", e);
+            }
+        },
+        Err(e) => {
+            eprintln!("Err:\n{}", e);
+            process::exit(1);
+        },
+    }
+
     let cli = Cli::parse();
     let mut cfg: Config = cli.into_config();
     if let Err(e) = cfg.run() {
@@ -0,0 +1,232 @@
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// File handed to a reader, either a selected file or a member extracted from
+/// an archive
+#[derive(Debug, Clone)]
+pub struct InputFile {
+    /// Readable file on disk
+    pub path: PathBuf,
+    /// Name stored in data_files. Archive members are named
+    /// "<archive>/<member path>" so every member is its own entry.
+    pub name: String,
+    /// Shown in progress messages, the selected path or the archive path
+    /// followed by the member
+    pub label: String,
+}
+
+/// Selected paths with archives unpacked. Extracted members live in a
+/// temporary directory that is removed when this is dropped.
+#[derive(Debug, Default)]
+pub struct Inputs {
+    pub files: Vec<InputFile>,
+    /// Paths that couldn't be used and why
+    pub failed: Vec<(PathBuf, String)>,
+    _dir: Option<TempDir>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Gzip,
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn archive_kind(name: &str) -> Option<ArchiveKind> {
+    let name = name.to_ascii_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".gz") {
+        Some(ArchiveKind::Gzip)
+    } else if name.ends_with(".zip") {
+        Some(ArchiveKind::Zip)
+    } else {
+        None
+    }
+}
+
+/// Expand gzip, zip, tar and tar.gz files into their members, other paths are
+/// passed through as they are
+pub fn expand_inputs(paths: &[PathBuf]) -> Inputs {
+    let mut inputs = Inputs::default();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            inputs.failed.push((path.clone(), "Invalid file name (non-UTF8)".to_owned()));
+            continue;
+        };
+        if archive_kind(name).is_none() {
+            let label = path.to_string_lossy().to_string();
+            inputs.files.push(InputFile { path: path.clone(), name: name.to_owned(), label });
+            continue;
+        }
+        if inputs._dir.is_none() {
+            match tempfile::Builder::new().prefix("fluxrs-").tempdir() {
+                Ok(dir) => inputs._dir = Some(dir),
+                Err(e) => {
+                    inputs.failed.push((path.clone(), format!("Could not create temp dir: {e}")));
+                    continue;
+                },
+            }
+        }
+        let dir = inputs._dir.as_ref().unwrap().path().to_path_buf();
+        let mut files = Vec::new();
+        match extract(path, name, &dir, &mut files) {
+            Ok(()) if files.is_empty() => {
+                inputs.failed.push((path.clone(), "Archive has no files".to_owned()))
+            },
+            Ok(()) => inputs.files.extend(files.into_iter().map(|mut f| {
+                f.label = path.with_file_name(&f.name).to_string_lossy().to_string();
+                f
+            })),
+            Err(e) => inputs.failed.push((path.clone(), format!("Could not read archive: {e}"))),
+        }
+    }
+    inputs
+}
+
+/// Extract `path` into `dir`, members that are archives themselves are
+/// expanded too
+fn extract(
+    path: &Path,
+    name: &str,
+    dir: &Path,
+    out: &mut Vec<InputFile>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(kind) = archive_kind(name) else {
+        let label = name.to_owned();
+        out.push(InputFile { path: path.to_path_buf(), name: name.to_owned(), label });
+        return Ok(());
+    };
+    let file = File::open(path)?;
+    match kind {
+        ArchiveKind::Gzip => {
+            // the single member is named after the archive without .gz
+            let member = &name[..name.len() - 3];
+            let member = member.rsplit('/').next().unwrap_or(member);
+            let target = write_member(dir, member, &mut GzDecoder::new(file))?;
+            extract(&target, &format!("{name}/{member}"), dir, out)?;
+        },
+        ArchiveKind::Zip => {
+            let mut archive = zip::ZipArchive::new(file)?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                if !entry.is_file() {
+                    continue;
+                }
+                let Some(member) = entry.enclosed_name().and_then(member_name) else {
+                    continue;
+                };
+                let target = write_member(dir, &member, &mut entry)?;
+                extract(&target, &format!("{name}/{member}"), dir, out)?;
+            }
+        },
+        ArchiveKind::Tar => extract_tar(tar::Archive::new(file), name, dir, out)?,
+        ArchiveKind::TarGz => extract_tar(tar::Archive::new(GzDecoder::new(file)), name, dir, out)?,
+    }
+    Ok(())
+}
+
+fn extract_tar<R: Read>(
+    mut archive: tar::Archive<R>,
+    name: &str,
+    dir: &Path,
+    out: &mut Vec<InputFile>,
+) -> Result<(), Box<dyn Error>> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let Some(member) = member_name(entry.path()?.to_path_buf()) else {
+            continue;
+        };
+        let target = write_member(dir, &member, &mut entry)?;
+        extract(&target, &format!("{name}/{member}"), dir, out)?;
+    }
+    Ok(())
+}
+
+/// Member path with forward slashes, hidden files and macOS resource forks
+/// are skipped
+fn member_name(path: PathBuf) -> Option<String> {
+    let parts: Vec<&str> = path.iter().map(|p| p.to_str()).collect::<Option<_>>()?;
+    if parts.iter().any(|p| p.starts_with('.') || *p == "__MACOSX") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// Write a member to its own subdirectory so that readers see the original
+/// file name even when members in different folders share it
+fn write_member(dir: &Path, member: &str, reader: &mut dyn Read) -> io::Result<PathBuf> {
+    let sub = dir.join(fs::read_dir(dir)?.count().to_string());
+    fs::create_dir_all(&sub)?;
+    let base = member.rsplit('/').next().unwrap_or(member);
+    let target = sub.join(base);
+    io::copy(reader, &mut File::create(&target)?)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn members_are_named_after_archive() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let gz_path = dir.path().join("data.txt.gz");
+        let mut gz = GzEncoder::new(File::create(&gz_path).unwrap(), Compression::default());
+        gz.write_all(b"gz content").unwrap();
+        gz.finish().unwrap();
+
+        let zip_path = dir.path().join("month.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for member in ["a/day1.txt", "b/day1.txt", "__MACOSX/._day1.txt"] {
+            zip.start_file(member, options).unwrap();
+            zip.write_all(member.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let tgz_path = dir.path().join("logs.tar.gz");
+        let enc = GzEncoder::new(File::create(&tgz_path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(enc);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(3);
+        header.set_cksum();
+        tar.append_data(&mut header, "x.csv", &b"1,2"[..]).unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let plain = dir.path().join("plain.csv");
+        fs::write(&plain, "plain").unwrap();
+
+        let inputs = expand_inputs(&[gz_path, zip_path, tgz_path, plain]);
+        let names: Vec<&str> = inputs.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "data.txt.gz/data.txt",
+                "month.zip/a/day1.txt",
+                "month.zip/b/day1.txt",
+                "logs.tar.gz/x.csv",
+                "plain.csv"
+            ]
+        );
+        assert!(inputs.failed.is_empty());
+        assert_eq!(fs::read_to_string(&inputs.files[0].path).unwrap(), "gz content");
+        assert_eq!(fs::read_to_string(&inputs.files[2].path).unwrap(), "b/day1.txt");
+        assert_eq!(inputs.files[2].path.file_name().unwrap(), "day1.txt");
+        assert_eq!(fs::read_to_string(&inputs.files[3].path).unwrap(), "1,2");
+    }
+}
@@ -1,9 +1,11 @@
 use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ConcentrationUnit {
     Ppm, // parts per million
     Ppb, // parts per billion
+    Percent,
 }
 
 impl ConcentrationUnit {
@@ -13,6 +15,7 @@ impl ConcentrationUnit {
         match self {
             ConcentrationUnit::Ppm => 1.0,
             ConcentrationUnit::Ppb => 1.0 / 1000.0,
+            ConcentrationUnit::Percent => 10_000.0,
         }
     }
 
@@ -21,6 +24,7 @@ impl ConcentrationUnit {
         match self {
             ConcentrationUnit::Ppm => "ppm",
             ConcentrationUnit::Ppb => "ppb",
+            ConcentrationUnit::Percent => "%",
         }
     }
 }
@@ -30,6 +34,20 @@ impl fmt::Display for ConcentrationUnit {
         match self {
             ConcentrationUnit::Ppb => write!(f, "ppb"),
             ConcentrationUnit::Ppm => write!(f, "ppm"),
+            ConcentrationUnit::Percent => write!(f, "%"),
+        }
+    }
+}
+
+impl FromStr for ConcentrationUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ppm" => Ok(ConcentrationUnit::Ppm),
+            "ppb" => Ok(ConcentrationUnit::Ppb),
+            "%" | "percent" => Ok(ConcentrationUnit::Percent),
+            other => Err(format!("invalid concentration unit: {other}")),
         }
     }
 }
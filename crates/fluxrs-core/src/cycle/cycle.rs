@@ -7,25 +7,31 @@ use crate::db::fluxes_schema::{
     make_update_fluxes,
 };
 use crate::errorcode::{ErrorCode, ErrorMask};
+use crate::flux::dilution::{wet_to_dry, DilutionCorrection};
 use crate::flux::flux::{GasChannelData, MeteoConditions, TimeRange};
 use crate::flux::{
-    ExponentialFlux, FluxFitError, FluxKind, FluxModel, FluxRecord, FluxResult, LinearFlux,
-    PolyFlux, RobustFlux,
+    ExponentialFlux, FluxFitError, FluxKind, FluxModel, FluxRecord, FluxResult, HmrFlux,
+    LinearFlux, PolyFlux, RobustFlux,
 };
-use crate::gaschannel::GasChannel;
+use crate::gaschannel::{GasChannel, MoleFraction};
 use crate::gastype::GasType;
+use crate::instruments::diag::FiredCondition;
 use crate::instruments::instruments::{Instrument, InstrumentType};
 use crate::mode::Mode;
 use crate::processevent::{ProcessEvent, ProgressEvent, QueryEvent};
 use crate::project::Project;
 use crate::stats::stats;
-use crate::stats::{ExpReg, LinReg, PolyReg, RobReg};
+use crate::stats::{ExpReg, HmrReg, LinReg, PolyReg, RobReg};
 
-use crate::data_formats::chamberdata::{query_chambers, Chamber, ChamberShape};
+use crate::data_formats::auxdata::{query_aux, AuxData};
+use crate::data_formats::chamberdata::{query_chambers, Chamber, ChamberData};
+use crate::data_formats::chambermeteo::{
+    query_chamber_meteo, ChamberMeteoData, ChamberMeteoSeries,
+};
+use crate::data_formats::chamberseries::{query_series, HeightData, SnowData, VolumeData, VOLUME};
 use crate::data_formats::gasdata::GasData;
-use crate::data_formats::heightdata::HeightData;
 use crate::data_formats::meteodata::{
-    MeteoData, MeteoPoint, MeteoSource, DEFAULT_PRESSURE, DEFAULT_TEMP,
+    MeteoData, MeteoPoint, MeteoSource, MeteoStations, DEFAULT_PRESSURE, DEFAULT_TEMP,
 };
 use crate::data_formats::timedata::{get_instrument_by_project_and_id, TimeData};
 use crate::types::FastMap;
@@ -34,7 +40,7 @@ use chrono::{DateTime, Utc};
 use rayon::prelude::*;
 use rusqlite::{params, Connection, Error, Result};
 use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error as StdError;
 use std::fmt;
 use std::hash::Hash;
@@ -84,8 +90,19 @@ pub struct Cycle {
     pub chamber: Chamber,
     pub project_id: Option<i64>,
     pub meteo: MeteoConditions,
+    /// meteo stations `meteo` was taken from
+    pub meteo_stations: MeteoStations,
+    /// temperature and pressure logged inside the chamber during the cycle
+    pub headspace: ChamberMeteoSeries,
+    /// meteo used for the fluxes of each gas, from the headspace when it has
+    /// samples in the calculation window
+    pub calc_meteo: FastMap<GasKey, MeteoConditions>,
     pub chamber_height: f64,
     pub snow_depth_m: f64,
+    /// free text written down with the cycle
+    pub note: Option<String>,
+    /// auxiliary variables nearest to the cycle start, by name
+    pub aux: BTreeMap<String, f64>,
     pub error_code: ErrorMask,
     pub is_valid: bool,
     pub gas_is_valid: FastMap<GasKey, bool>,
@@ -550,10 +567,12 @@ impl Cycle {
         self.timing.get_calc_end(key)
     }
     pub fn get_calc_start_i(&self, key: &GasKey) -> usize {
-        (self.get_calc_start(key) - self.get_start()) as usize
+        let start = self.get_calc_start(key);
+        self.get_dt_v(&key.id).partition_point(|&t| t < start)
     }
     pub fn get_calc_end_i(&self, key: &GasKey) -> usize {
-        (self.get_calc_end(key) - self.get_start()) as usize
+        let end = self.get_calc_end(key);
+        self.get_dt_v(&key.id).partition_point(|&t| t < end)
     }
 
     pub fn get_calc_range(&self, key: &GasKey) -> f64 {
@@ -569,7 +588,7 @@ impl Cycle {
 
     pub fn set_automatic_valid(&mut self, valid: bool) {
         if self.override_valid.is_none() {
-            self.is_valid = valid && self.error_code.0 == 0; // Ensure error codes affect validity
+            self.is_valid = valid && !self.error_code.invalidates(); // Ensure error codes affect validity
         }
     }
     pub fn toggle_manual_valid(&mut self) {
@@ -684,37 +703,37 @@ impl Cycle {
         None
     }
     pub fn check_diag(&mut self) {
-        // let diag_v = self.get_measurement_diag(&self.main_key());
-        // let total_count = diag_v.len();
-        // let nonzero_count = diag_v.iter().filter(|&&x| x != 0).count();
-        // println!("{}/{}", nonzero_count, total_count);
-        let nonzero_count = self
-            .diag_v
-            .get(&self.main_instrument.id.unwrap())
-            .unwrap()
-            .iter()
-            .filter(|&&x| x != 0)
-            .count();
-        let total_count = self.diag_v.get(&self.main_instrument.id.unwrap()).unwrap().len();
+        let decoder = self.main_instrument.model.diag_decoder();
+        let diag_v = self.diag_v.get(&self.main_instrument.id.unwrap()).unwrap();
+        let invalid_count = diag_v.iter().filter(|&&x| decoder.is_invalid(x)).count();
+        let total_count = diag_v.len();
 
-        // Check if more than 50% of the values are nonzero
-        let check = (nonzero_count as f64 / total_count as f64) > 0.5;
+        // Check if more than 50% of the values have invalidating diagnostics
+        let check = (invalid_count as f64 / total_count as f64) > 0.5;
         if check {
             self.add_error(ErrorCode::ErrorsInMeasurement)
         } else {
             self.remove_error(ErrorCode::ErrorsInMeasurement)
         }
     }
+    /// Flag the cycle with the error codes of the diagnostic conditions that
+    /// fired in the measurement, returns true if any of them invalidates
     pub fn check_measurement_diag(&mut self) -> bool {
-        let nonzero_count =
-            self.get_measurement_diag(&self.main_key()).iter().filter(|&&x| x != 0).count();
-        let check = nonzero_count > 0;
-        if check {
-            self.add_error(ErrorCode::ErrorsInMeasurement)
-        } else {
-            self.remove_error(ErrorCode::ErrorsInMeasurement)
+        let fired = self.fired_diag_conditions();
+        let codes: Vec<ErrorCode> = fired.iter().filter_map(|f| f.condition.error_code()).collect();
+        for code in ErrorCode::diag_codes() {
+            if codes.contains(&code) {
+                self.add_error(code)
+            } else {
+                self.remove_error(code)
+            }
         }
-        check
+        codes.iter().any(|c| !c.is_warning())
+    }
+    /// Decoded diagnostic conditions of the main instrument in the measurement
+    pub fn fired_diag_conditions(&self) -> Vec<FiredCondition> {
+        let diag = self.get_measurement_diag(&self.main_key());
+        self.main_instrument.model.diag_decoder().fired(&diag)
     }
 
     pub fn calculate_max_y(&mut self) {
@@ -808,20 +827,16 @@ impl Cycle {
         let (dt_v, gas_v) = self.get_measurement_data(key);
         // Precompute timestamp gaps (difference > 1.0 sec)
         let gaps: Vec<bool> = dt_v.windows(2).map(|w| (w[1] - w[0]).abs() > 1.0).collect();
+        let min_len = samples_in(&dt_v, self.get_min_calc_len());
 
-        if gas_v.len() < self.get_min_calc_len() as usize
-            || dt_v.len() < self.get_min_calc_len() as usize
-        {
+        if gas_v.len() < min_len || dt_v.len() < min_len {
             return;
         }
 
-        if let Some((start, end, _r)) = find_best_window_for_gas_par(
-            &dt_v,
-            &gas_v,
-            &gaps,
-            self.get_min_calc_len() as usize,
-            WINDOW_INCREMENT,
-        ) {
+        let step = WINDOW_INCREMENT * samples_in(&dt_v, 1.0);
+        if let Some((start, end, _r)) =
+            find_best_window_for_gas_par(&dt_v, &gas_v, &gaps, min_len, step)
+        {
             let start_time = dt_v[start];
             let end_time = dt_v[end - 1];
             self.timing.set_calc_start(key, start_time);
@@ -845,7 +860,6 @@ impl Cycle {
             dt_vecs.insert(key, dv);
         }
 
-        let min_len = self.get_min_calc_len() as usize;
         let gap_threshold = 1.0;
 
         let results: Vec<_> = keys
@@ -853,6 +867,8 @@ impl Cycle {
             .filter_map(|key| {
                 let gas_v = gas_vecs.get(key)?;
                 let dt_v = dt_vecs.get(key)?;
+                // min_calc_len is in seconds, fast analyzers have more samples
+                let min_len = samples_in(dt_v, self.get_min_calc_len());
 
                 if gas_v.len() < min_len || dt_v.len() < min_len {
                     return None;
@@ -861,7 +877,9 @@ impl Cycle {
                 let gaps: Vec<bool> =
                     dt_v.windows(2).map(|w| (w[1] - w[0]).abs() > gap_threshold).collect();
 
-                find_best_window_for_gas_par(dt_v, gas_v, &gaps, min_len, WINDOW_INCREMENT).map(
+                // windows move a second at a time regardless of the sampling rate
+                let step = WINDOW_INCREMENT * samples_in(dt_v, 1.0);
+                find_best_window_for_gas_par(dt_v, gas_v, &gaps, min_len, step).map(
                     |(start, end, r)| {
                         let start_time = dt_v[start];
                         let end_time = dt_v[end - 1];
@@ -928,13 +946,13 @@ impl Cycle {
     }
     pub fn add_error(&mut self, error: ErrorCode) {
         self.error_code |= error;
-        if self.error_code != ErrorMask(0) {
+        if self.error_code.invalidates() {
             self.is_valid = false; // Automatically invalidate on error
         }
     }
     pub fn remove_error(&mut self, error: ErrorCode) {
         self.error_code.0 &= !error.to_mask();
-        if self.error_code.0 == 0 {
+        if !self.error_code.invalidates() {
             self.is_valid = true; // If no errors remain, revalidate
         }
     }
@@ -963,9 +981,11 @@ impl Cycle {
             self.gas_v.get(&(GasKey::from((&self.main_gas, &self.main_instrument.id.unwrap()))))
         {
             let valid_count = values.iter().filter(|v| v.is_some()).count();
-            let threshold = self.get_end_offset() as f64 * 0.7;
+            let per_second = samples_in(&self.get_dt_v(&self.main_instrument.id.unwrap()), 1.0);
+            let expected = per_second as f64 * self.get_end_offset() as f64;
+            let threshold = expected * 0.7;
             let check = (valid_count as f64) < threshold;
-            let check2 = values.len() < (self.get_end_offset() as f64 * 0.99) as usize;
+            let check2 = values.len() < (expected * 0.99) as usize;
             if check || check2 {
                 self.add_error(ErrorCode::TooFewMeasurements);
             } else {
@@ -979,7 +999,7 @@ impl Cycle {
         self.check_main_r();
         self.check_measurement_diag();
         self.check_missing();
-        if self.error_code.0 == 0 || self.override_valid == Some(true) {
+        if !self.error_code.invalidates() || self.override_valid == Some(true) {
             self.is_valid = true
         }
     }
@@ -1064,8 +1084,8 @@ impl Cycle {
 
     pub fn get_calc_data(&mut self, key: &GasKey) {
         if let Some(gas_v) = self.gas_v.get(key) {
-            let s = (self.get_calc_start(key) - self.get_start_ts() as f64) as usize;
-            let e = (self.get_calc_end(key) - self.get_start_ts() as f64) as usize;
+            let s = self.get_calc_start_i(key);
+            let e = self.get_calc_end_i(key);
 
             // Clear previous results
             self.calc_gas_v.insert(*key, gas_v[s..e].to_vec());
@@ -1106,7 +1126,7 @@ impl Cycle {
     //     }
     //     ret[s..e].to_vec()
     // }
-    pub fn get_measurement_diag(&mut self, key: &GasKey) -> Vec<i64> {
+    pub fn get_measurement_diag(&self, key: &GasKey) -> Vec<i64> {
         let start_time = self.get_adjusted_close();
         let end_time = self.get_adjusted_open();
 
@@ -1198,6 +1218,15 @@ impl Cycle {
                 );
                 let _ = sender.send(ProcessEvent::Progress(ProgressEvent::Generic(msg)));
             }
+
+            // HMR
+            if let Err(err) = self.calculate_hmr_flux(key) {
+                let msg = format!(
+                    "HMR flux failed for {} {} {:?}: {}",
+                    start, instrument.serial, key, err
+                );
+                let _ = sender.send(ProcessEvent::Progress(ProgressEvent::Generic(msg)));
+            }
         }
 
         // final Done event
@@ -1210,6 +1239,7 @@ impl Cycle {
             let _ = self.calculate_poly_flux(key);
             let _ = self.calculate_roblin_flux(key);
             let _ = self.calculate_exp_flux(key);
+            let _ = self.calculate_hmr_flux(key);
         }
     }
     pub fn compute_single_flux(&mut self, key: &GasKey) {
@@ -1217,6 +1247,7 @@ impl Cycle {
         let _ = self.calculate_poly_flux(key);
         let _ = self.calculate_roblin_flux(key);
         let _ = self.calculate_exp_flux(key);
+        let _ = self.calculate_hmr_flux(key);
     }
 
     // pub fn get_calc_dt(&self, key: GasType) -> Vec<f64> {
@@ -1243,7 +1274,19 @@ impl Cycle {
         ret[s..e].to_vec()
     }
 
+    /// Time and concentration in the calculation range. Wet channels are
+    /// converted to dry mole fractions with the H2O of the same instrument.
     pub fn get_calc_data2(&self, key: &GasKey) -> (Vec<f64>, Vec<f64>) {
+        let (dt, gas, _) = self.calc_data_dry(key);
+        (dt, gas)
+    }
+
+    /// How the water vapour dilution of `key` was handled
+    pub fn dilution_correction(&self, key: &GasKey) -> DilutionCorrection {
+        self.calc_data_dry(key).2
+    }
+
+    fn calc_data_dry(&self, key: &GasKey) -> (Vec<f64>, Vec<f64>, DilutionCorrection) {
         let start_time = self.get_calc_start(key);
         let end_time = self.get_calc_end(key);
 
@@ -1251,21 +1294,44 @@ impl Cycle {
 
         let gas_vec = match self.gas_v.get(key) {
             Some(vec) => vec,
-            None => return (vec![], vec![]),
+            None => return (vec![], vec![], DilutionCorrection::NotNeeded),
+        };
+
+        let is_wet = key.gas_type != GasType::H2O
+            && self.gas_channels.get(key).is_some_and(|ch| ch.mole_fraction == MoleFraction::Wet);
+        let h2o_key = GasKey::new(GasType::H2O, key.id);
+        // H2O as mol/mol, on the same timestamps as the gas
+        let h2o = match (is_wet, self.gas_v.get(&h2o_key), self.gas_channels.get(&h2o_key)) {
+            (true, Some(values), Some(ch)) => {
+                let factor = ch.unit.to_ppm_factor() * 1e-6;
+                Some((values, factor))
+            },
+            _ => None,
         };
 
         let mut filtered_dt = Vec::new();
         let mut filtered_gas = Vec::new();
+        let mut filtered_h2o = Vec::new();
 
         for (i, &t) in dt_vec.iter().enumerate() {
             if t >= start_time && t < end_time {
                 filtered_dt.push(t);
                 let gas_value = gas_vec.get(i).and_then(|v| *v).unwrap_or(f64::NAN);
                 filtered_gas.push(gas_value);
+                if let Some((values, factor)) = h2o {
+                    filtered_h2o.push(values.get(i).and_then(|v| *v).map(|v| v * factor));
+                }
             }
         }
 
-        (filtered_dt, filtered_gas)
+        if !is_wet {
+            return (filtered_dt, filtered_gas, DilutionCorrection::NotNeeded);
+        }
+        if h2o.is_none() {
+            return (filtered_dt, filtered_gas, DilutionCorrection::Uncorrected);
+        }
+        let (dry, correction) = wet_to_dry(&filtered_dt, &filtered_gas, &filtered_h2o);
+        (filtered_dt, dry, correction)
     }
     // pub fn get_measurement_dt_v(&self) -> Vec<f64> {
     //     self.measurement_dt_v.iter().map(|s| s.timestamp() as f64).collect()
@@ -1284,6 +1350,45 @@ impl Cycle {
             .unwrap_or_default()
     }
 
+    /// Meteo for fluxes calculated between `start` and `end`, the mean of the
+    /// headspace samples in the window where there are any and the cycle
+    /// meteo otherwise. Kept in `calc_meteo` for the flux rows.
+    fn window_meteo(&mut self, key: &GasKey, start: f64, end: f64) -> MeteoConditions {
+        let mut meteo = self.meteo;
+        let chamber_point = |value| MeteoPoint {
+            value: Some(value),
+            source: MeteoSource::Chamber,
+            distance_from_target: None,
+        };
+        if let Some(t) = self.headspace.mean_temperature(start, end) {
+            meteo.temperature = chamber_point(t);
+        }
+        if let Some(p) = self.headspace.mean_pressure(start, end) {
+            meteo.pressure = chamber_point(p);
+        }
+        self.calc_meteo.insert(*key, meteo);
+        meteo
+    }
+
+    /// Meteo the fluxes of `key` were calculated with
+    pub fn meteo_for(&self, key: &GasKey) -> MeteoConditions {
+        self.calc_meteo.get(key).copied().unwrap_or(self.meteo)
+    }
+
+    /// Stations the temperature and pressure used for `key` came from, None
+    /// for values that weren't read from a meteo station
+    pub fn meteo_stations_for(&self, key: &GasKey) -> (Option<&str>, Option<&str>) {
+        let meteo = self.meteo_for(key);
+        let from_station = |source| matches!(source, MeteoSource::Raw | MeteoSource::Interpolated);
+        (
+            self.meteo_stations
+                .temperature
+                .as_deref()
+                .filter(|_| from_station(meteo.temperature.source)),
+            self.meteo_stations.pressure.as_deref().filter(|_| from_station(meteo.pressure.source)),
+        )
+    }
+
     pub fn calculate_lin_flux(&mut self, key: &GasKey) -> FluxResult<()> {
         let (x, y) = self.get_calc_data2(key);
         let s = x.first().unwrap_or(&0.);
@@ -1298,7 +1403,7 @@ impl Cycle {
 
         let channel = self.gas_channels.get(key).unwrap().clone();
         let xydata = GasChannelData::new(channel, &x, &y);
-        let meteo = self.meteo;
+        let meteo = self.window_meteo(key, *s, *e);
         let range = TimeRange::new(*s, *e);
         let data = LinearFlux::from_data(&xydata, &range, &meteo, &self.chamber)?;
 
@@ -1325,7 +1430,7 @@ impl Cycle {
 
         let channel = self.gas_channels.get(key).unwrap().clone();
         let xydata = GasChannelData::new(channel, &x, &y);
-        let meteo = self.meteo;
+        let meteo = self.window_meteo(key, *s, *e);
         let range = TimeRange::new(*s, *e);
         let data = PolyFlux::from_data(&xydata, &range, &meteo, &self.chamber)?;
         self.fluxes
@@ -1343,7 +1448,7 @@ impl Cycle {
 
         let channel = self.gas_channels.get(key).unwrap().clone();
         let xydata = GasChannelData::new(channel, &x, &y);
-        let meteo = self.meteo;
+        let meteo = self.window_meteo(key, *s, *e);
         let range = TimeRange::new(*s, *e);
         let data = RobustFlux::from_data(&xydata, &range, &meteo, &self.chamber)?;
 
@@ -1369,7 +1474,7 @@ impl Cycle {
         let channel = self.gas_channels.get(key).unwrap().clone();
 
         let xydata = GasChannelData::new(channel, &x, &y);
-        let meteo = self.meteo;
+        let meteo = self.window_meteo(key, *s, *e);
         let range = TimeRange::new(*s, *e);
         let data = ExponentialFlux::from_data(&xydata, &range, &meteo, &self.chamber)?;
 
@@ -1380,6 +1485,27 @@ impl Cycle {
         Ok(())
     }
 
+    pub fn calculate_hmr_flux(&mut self, key: &GasKey) -> FluxResult<()> {
+        let (x, y) = self.get_calc_data2(key);
+        let s = x.first().unwrap_or(&0.0);
+        let e = x.last().unwrap_or(&0.0);
+
+        if x.len() < 4 || y.len() < 4 || x.len() != y.len() {
+            return Err(FluxFitError::NotEnoughPoints { len: x.len().min(y.len()), needed: 4 });
+        }
+
+        let channel = self.gas_channels.get(key).unwrap().clone();
+
+        let xydata = GasChannelData::new(channel, &x, &y);
+        let meteo = self.window_meteo(key, *s, *e);
+        let range = TimeRange::new(*s, *e);
+        let data = HmrFlux::from_data(&xydata, &range, &meteo, &self.chamber)?;
+
+        self.fluxes
+            .insert((*key, FluxKind::Hmr), FluxRecord { model: Box::new(data), is_valid: true });
+        Ok(())
+    }
+
     pub fn update_cycle(&mut self, _project: String) {
         // self.get_calc_datas();
         // self.get_measurement_datas();
@@ -1422,7 +1548,7 @@ impl Cycle {
                         .iter()
                         .map(|(id, dt_list)| {
                             let timestamps =
-                                dt_list.iter().map(|t| *t as f64).collect::<Vec<f64>>();
+                                dt_list.iter().map(|t| ms_to_s(*t)).collect::<Vec<f64>>();
                             (*id, timestamps)
                         })
                         .collect(),
@@ -1621,6 +1747,8 @@ impl CycleBuilder {
             instruments: FastMap::default(),
             chamber: Chamber::default(),
             snow_depth_m,
+            note: None,
+            aux: BTreeMap::new(),
             project_id: project.id,
             error_code: ErrorMask(0),
             main_gas: GasType::CH4,
@@ -1641,6 +1769,9 @@ impl CycleBuilder {
             measurement_diag_v: FastMap::default(),
             gases: vec![],
             meteo: MeteoConditions::default(),
+            meteo_stations: MeteoStations::default(),
+            headspace: ChamberMeteoSeries::default(),
+            calc_meteo: FastMap::default(),
             chamber_height: 1.,
             is_valid: true,
             gas_is_valid: FastMap::default(),
@@ -1790,6 +1921,8 @@ fn execute_history_insert(
         let roblin = robustlinear.map(|m| m.model.as_ref());
         let exponential = cycle.fluxes.get(&(key, FluxKind::Exponential));
         let exp = exponential.map(|m| m.model.as_ref());
+        let hmr = cycle.fluxes.get(&(key, FluxKind::Hmr)).map(|m| m.model.as_ref());
+        let hmr_fit = hmr.and_then(|m| m.as_any().downcast_ref::<HmrFlux>());
         // NOTE: for a specific
         let lin_valid = linear.map(|m| m.is_valid).unwrap_or(false);
         let deadband = cycle.get_deadband(&key);
@@ -1801,6 +1934,8 @@ fn execute_history_insert(
         }
         let instrument_id = &key.id;
         let main_key = &GasKey::from((&cycle.main_gas, instrument_id));
+        let meteo = cycle.meteo_for(&key);
+        let (temperature_station, pressure_station) = cycle.meteo_stations_for(&key);
         let instrument = cycle.instruments.get(instrument_id).unwrap();
 
         stmt.execute(params![
@@ -1818,12 +1953,12 @@ fn execute_history_insert(
             cycle.get_end_lag() as i64,
             cycle.get_start_lag() as i64,
             cycle.get_min_calc_len(),
-            cycle.meteo.pressure.value.unwrap(),
-            cycle.meteo.pressure.source.as_int(),
-            cycle.meteo.pressure.distance_from_target,
-            cycle.meteo.temperature.value.unwrap(),
-            cycle.meteo.temperature.source.as_int(),
-            cycle.meteo.temperature.distance_from_target,
+            meteo.pressure.value.unwrap(),
+            meteo.pressure.source.as_int(),
+            meteo.pressure.distance_from_target,
+            meteo.temperature.value.unwrap(),
+            meteo.temperature.source.as_int(),
+            meteo.temperature.distance_from_target,
             cycle.chamber_height,
             cycle.snow_depth_m,
             cycle.error_code.0,
@@ -1891,6 +2026,21 @@ fn execute_history_insert(
             exp.and_then(|m| m.as_any().downcast_ref::<ExponentialFlux>())
                 .map(|m| m.model.b)
                 .unwrap_or(0.0),
+            cycle.dilution_correction(&key).as_int(),
+            temperature_station,
+            pressure_station,
+            hmr.and_then(|m| m.flux()),
+            hmr.and_then(|m| m.r2()),
+            hmr.and_then(|m| m.adj_r2()),
+            hmr.and_then(|m| m.intercept()),
+            hmr.and_then(|m| m.slope()),
+            hmr.and_then(|m| m.sigma()),
+            hmr.and_then(|m| m.p_value()),
+            hmr.and_then(|m| m.aic()),
+            hmr.and_then(|m| m.rmse()),
+            hmr.and_then(|m| m.cv()),
+            hmr_fit.map(|m| m.se),
+            hmr_fit.map(|m| m.model.kappa),
         ])?;
     }
     Ok(())
@@ -1911,6 +2061,8 @@ fn execute_insert(
         let roblin = robustlinear.map(|m| m.model.as_ref());
         let exponential = cycle.fluxes.get(&(key, FluxKind::Exponential));
         let exp = exponential.map(|m| m.model.as_ref());
+        let hmr = cycle.fluxes.get(&(key, FluxKind::Hmr)).map(|m| m.model.as_ref());
+        let hmr_fit = hmr.and_then(|m| m.as_any().downcast_ref::<HmrFlux>());
         // NOTE: FluxRecord is gas specific
         let lin_valid = linear.map(|m| m.is_valid).unwrap_or(false);
         let deadband = cycle.get_deadband(&key);
@@ -1924,6 +2076,8 @@ fn execute_insert(
         let tx = Connection::open("fluxrs.db").expect("Failed to open database");
         let instrument_id = &key.id;
         let main_key = &GasKey::from((&cycle.main_gas, instrument_id));
+        let meteo = cycle.meteo_for(&key);
+        let (temperature_station, pressure_station) = cycle.meteo_stations_for(&key);
 
         let inserts = stmt.execute(params![
             cycle.get_start_utc_ts(),
@@ -1939,12 +2093,12 @@ fn execute_insert(
             cycle.get_end_lag() as i64,
             cycle.get_start_lag() as i64,
             cycle.get_min_calc_len(),
-            cycle.meteo.pressure.value.unwrap(),
-            cycle.meteo.pressure.source.as_int(),
-            cycle.meteo.pressure.distance_from_target,
-            cycle.meteo.temperature.value.unwrap(),
-            cycle.meteo.temperature.source.as_int(),
-            cycle.meteo.temperature.distance_from_target,
+            meteo.pressure.value.unwrap(),
+            meteo.pressure.source.as_int(),
+            meteo.pressure.distance_from_target,
+            meteo.temperature.value.unwrap(),
+            meteo.temperature.source.as_int(),
+            meteo.temperature.distance_from_target,
             cycle.chamber_height,
             cycle.snow_depth_m,
             cycle.error_code.0,
@@ -2017,6 +2171,21 @@ fn execute_insert(
             exp.and_then(|m| m.as_any().downcast_ref::<ExponentialFlux>())
                 .map(|m| m.model.b)
                 .unwrap_or(0.0),
+            cycle.dilution_correction(&key).as_int(),
+            temperature_station,
+            pressure_station,
+            hmr.and_then(|m| m.flux()),
+            hmr.and_then(|m| m.r2()),
+            hmr.and_then(|m| m.adj_r2()),
+            hmr.and_then(|m| m.intercept()),
+            hmr.and_then(|m| m.slope()),
+            hmr.and_then(|m| m.sigma()),
+            hmr.and_then(|m| m.p_value()),
+            hmr.and_then(|m| m.aic()),
+            hmr.and_then(|m| m.rmse()),
+            hmr.and_then(|m| m.cv()),
+            hmr_fit.map(|m| m.se),
+            hmr_fit.map(|m| m.model.kappa),
         ])?;
         affected += inserts;
     }
@@ -2033,6 +2202,8 @@ fn execute_update(stmt: &mut rusqlite::Statement, cycle: &Cycle) -> Result<usize
         let roblin = robustlinear.map(|m| m.model.as_ref());
         let exponential = cycle.fluxes.get(&(key, FluxKind::Exponential));
         let exp = exponential.map(|m| m.model.as_ref());
+        let hmr = cycle.fluxes.get(&(key, FluxKind::Hmr)).map(|m| m.model.as_ref());
+        let hmr_fit = hmr.and_then(|m| m.as_any().downcast_ref::<HmrFlux>());
         let lin_valid = linear.map(|m| m.is_valid).unwrap_or(false);
         let deadband = cycle.get_deadband(&key);
         // Skip row if neither model exists
@@ -2042,6 +2213,8 @@ fn execute_update(stmt: &mut rusqlite::Statement, cycle: &Cycle) -> Result<usize
         }
         let instrument_id = &key.id;
         let main_key = &GasKey::from((&cycle.main_gas, instrument_id));
+        let meteo = cycle.meteo_for(&key);
+        let (temperature_station, pressure_station) = cycle.meteo_stations_for(&key);
 
         let inserts = stmt.execute(params![
             cycle.get_start_utc_ts(),
@@ -2057,12 +2230,12 @@ fn execute_update(stmt: &mut rusqlite::Statement, cycle: &Cycle) -> Result<usize
             cycle.get_end_lag() as i64,
             cycle.get_start_lag() as i64,
             cycle.get_min_calc_len(),
-            cycle.meteo.pressure.value.unwrap(),
-            cycle.meteo.pressure.source.as_int(),
-            cycle.meteo.pressure.distance_from_target,
-            cycle.meteo.temperature.value.unwrap(),
-            cycle.meteo.temperature.source.as_int(),
-            cycle.meteo.temperature.distance_from_target,
+            meteo.pressure.value.unwrap(),
+            meteo.pressure.source.as_int(),
+            meteo.pressure.distance_from_target,
+            meteo.temperature.value.unwrap(),
+            meteo.temperature.source.as_int(),
+            meteo.temperature.distance_from_target,
             cycle.chamber_height,
             cycle.snow_depth_m,
             cycle.error_code.0,
@@ -2130,6 +2303,21 @@ fn execute_update(stmt: &mut rusqlite::Statement, cycle: &Cycle) -> Result<usize
             exp.and_then(|m| m.as_any().downcast_ref::<ExponentialFlux>())
                 .map(|m| m.model.b)
                 .unwrap_or(0.0),
+            cycle.dilution_correction(&key).as_int(),
+            temperature_station,
+            pressure_station,
+            hmr.and_then(|m| m.flux()),
+            hmr.and_then(|m| m.r2()),
+            hmr.and_then(|m| m.adj_r2()),
+            hmr.and_then(|m| m.intercept()),
+            hmr.and_then(|m| m.slope()),
+            hmr.and_then(|m| m.sigma()),
+            hmr.and_then(|m| m.p_value()),
+            hmr.and_then(|m| m.aic()),
+            hmr.and_then(|m| m.rmse()),
+            hmr.and_then(|m| m.cv()),
+            hmr_fit.map(|m| m.se),
+            hmr_fit.map(|m| m.model.kappa),
         ])?;
         affected += inserts;
     }
@@ -2169,6 +2357,25 @@ pub fn load_cycles_sync(
     let mut date: Option<String> = None;
     let gas_data = query_gas2(conn, start, end, project.to_owned())?;
     let chamber_metadata = query_chambers(conn, project.id.unwrap())?;
+    let chamber_meteo = query_chamber_meteo(
+        conn,
+        DateTime::<Utc>::from_timestamp(start, 0).unwrap(),
+        DateTime::<Utc>::from_timestamp(end, 0).unwrap(),
+        project.id.unwrap(),
+    )?;
+    let aux_data = query_aux(
+        conn,
+        DateTime::<Utc>::from_timestamp(start, 0).unwrap(),
+        DateTime::<Utc>::from_timestamp(end, 0).unwrap(),
+        project,
+    )?;
+    let volume_data = query_series(
+        conn,
+        &VOLUME,
+        DateTime::<Utc>::from_timestamp(start, 0).unwrap(),
+        DateTime::<Utc>::from_timestamp(end, 0).unwrap(),
+        project.id.unwrap(),
+    )?;
     let instruments = get_instruments_by_project_map(conn, project.id.unwrap())?;
     let mut stmt = conn.prepare(
         "
@@ -2178,6 +2385,7 @@ pub fn load_cycles_sync(
                 c.close_offset              AS close_offset,
                 c.open_offset               AS open_offset,
                 c.end_offset                AS end_offset,
+                c.note                      AS cycle_note,
                 main_i.id                   AS main_instrument_id,
                 main_i.instrument_model     AS main_instrument_model,
                 main_i.instrument_serial    AS main_instrument_serial,
@@ -2224,7 +2432,8 @@ pub fn load_cycles_sync(
         serials.insert(instrument_serial.clone());
         let chamber_id: String = row.get(*column_index.get("chamber_id").unwrap())?;
 
-        let mut chamber = chamber_metadata.get(&chamber_id).cloned().unwrap_or_default();
+        let mut chamber =
+            chamber_metadata.get(&chamber_id, start_time).cloned().unwrap_or_default();
         let main_model_string: String =
             row.get(*column_index.get("main_instrument_model").unwrap())?;
         let main_instrument_serial: String =
@@ -2291,9 +2500,15 @@ pub fn load_cycles_sync(
         let temperature_source: i32 = row.get(*column_index.get("temperature_source").unwrap())?;
         let temperature_dist: Option<i64> =
             row.get(*column_index.get("temperature_dist").unwrap())?;
+        let temperature_station: Option<String> =
+            row.get(*column_index.get("temperature_station").unwrap())?;
+        let pressure_station: Option<String> =
+            row.get(*column_index.get("pressure_station").unwrap())?;
         let chamber_height: f64 = row.get(*column_index.get("chamber_height").unwrap())?;
         let snow_depth_m: f64 = row.get(*column_index.get("snow_depth_m").unwrap())?;
+        let note: Option<String> = row.get(*column_index.get("cycle_note").unwrap())?;
         chamber.set_snow_height(snow_depth_m);
+        chamber.measured_volume_m3 = volume_data.nearest_previous(utc_start, &chamber_id);
 
         let end_time = utc_start + end_offset;
 
@@ -2342,6 +2557,7 @@ pub fn load_cycles_sync(
                             gas: *gas,
                             unit: ch.unit,
                             instrument_id: ch.instrument_id.clone(),
+                            mole_fraction: ch.mole_fraction,
                         };
                         gas_channels.insert(gas_key, chan);
                     }
@@ -2398,8 +2614,13 @@ pub fn load_cycles_sync(
                 chamber,
                 project_id: Some(project.id.unwrap()),
                 meteo,
+                meteo_stations: MeteoStations::default(),
+                headspace: chamber_meteo.window(&chamber_id, utc_start, end_time),
+                calc_meteo: FastMap::default(),
                 chamber_height,
                 snow_depth_m,
+                note,
+                aux: aux_data.values_at(&chamber_id, utc_start, project.aux_max_gap_s),
                 error_code,
                 is_valid,
                 gas_is_valid: FastMap::default(),
@@ -2425,9 +2646,15 @@ pub fn load_cycles_sync(
                 gas_channels: gas_channels.clone(),
                 timing,
             });
+            // each gas row has the meteo its fluxes were calculated with
+            cycle.calc_meteo.insert(gas_key, meteo);
+            let stations = &mut cycle.meteo_stations;
+            stations.temperature = stations.temperature.take().or(temperature_station);
+            stations.pressure = stations.pressure.take().or(pressure_station);
             if let Some(g_values) = gas_data_day.gas.get(&gas_key) {
-                let start_target = utc_start + start_lag_s as i64;
-                let end_target = end_time + end_lag_s as i64;
+                // gas data is in milliseconds
+                let start_target = (utc_start + start_lag_s as i64) * 1000;
+                let end_target = (end_time + end_lag_s as i64) * 1000;
 
                 let matching_indices: Vec<usize> = dt_values
                     .iter()
@@ -2435,12 +2662,8 @@ pub fn load_cycles_sync(
                     .filter(|(_, &t)| t >= start_target && t < end_target)
                     .map(|(i, _)| i)
                     .collect();
-                let (meas_dt, meas_vals) = filter_data_in_range(
-                    dt_values,
-                    g_values,
-                    start_target as f64,
-                    end_target as f64,
-                );
+                let (meas_dt, meas_vals) =
+                    filter_data_in_range(dt_values, g_values, start_target, end_target);
                 // let (_, diag_vals) = filter_diag_data(
                 //     dt_values,
                 //     diag_values,
@@ -2457,7 +2680,7 @@ pub fn load_cycles_sync(
                 let gas_slice: Vec<Option<f64>> =
                     matching_indices.iter().filter_map(|&i| g_values.get(i).copied()).collect();
                 let dt_slice: Vec<f64> =
-                    matching_indices.iter().map(|&i| dt_values[i] as f64).collect();
+                    matching_indices.iter().map(|&i| ms_to_s(dt_values[i])).collect();
 
                 if meas_vals.is_empty() {
                     continue;
@@ -2466,7 +2689,7 @@ pub fn load_cycles_sync(
                 let target = dt_values
                     .iter()
                     .enumerate()
-                    .find(|(_, &t)| t as f64 >= measurement_range_start)
+                    .find(|(_, &t)| ms_to_s(t) >= measurement_range_start)
                     .map(|(i, _)| i);
 
                 cycle.set_dt_v(instrument_id, &dt_slice);
@@ -2707,6 +2930,65 @@ pub fn load_cycles_sync(
                     FluxRecord { model: Box::new(poly), is_valid: gas_is_valid },
                 );
             }
+            if let (
+                Ok(flux),
+                Ok(r2),
+                Ok(adjusted_r2),
+                Ok(c0),
+                Ok(f0),
+                Ok(sigma),
+                Ok(p_value),
+                Ok(aic),
+                Ok(rmse),
+                Ok(cv),
+                Ok(se),
+                Ok(kappa),
+                Ok(gas_i),
+                Ok(instrument_id),
+            ) = (
+                row.get(*column_index.get("hmr_flux").unwrap()),
+                row.get(*column_index.get("hmr_r2").unwrap()),
+                row.get(*column_index.get("hmr_adj_r2").unwrap()),
+                row.get(*column_index.get("hmr_intercept").unwrap()),
+                row.get(*column_index.get("hmr_slope").unwrap()),
+                row.get(*column_index.get("hmr_sigma").unwrap()),
+                row.get(*column_index.get("hmr_p_value").unwrap()),
+                row.get(*column_index.get("hmr_aic").unwrap()),
+                row.get(*column_index.get("hmr_rmse").unwrap()),
+                row.get(*column_index.get("hmr_cv").unwrap()),
+                row.get(*column_index.get("hmr_se").unwrap()),
+                row.get(*column_index.get("hmr_kappa").unwrap()),
+                row.get(*column_index.get("gas").unwrap()),
+                row.get(*column_index.get("instrument_id").unwrap()),
+            ) {
+                let gas_type = GasType::from_int(gas_i).unwrap();
+                if gas_type != gk.gas_type {
+                    continue;
+                }
+                let key: GasKey = GasKey::from((&gas_type, &instrument_id));
+                let gas_channel = gas_channels.get(&key).unwrap().clone();
+                cycle.set_calc_start(&gk, calc_range_start);
+                cycle.set_calc_end(&gk, calc_range_end);
+                let hmr = HmrFlux {
+                    gas_channel,
+                    flux,
+                    r2,
+                    adjusted_r2,
+                    model: HmrReg::from_val(c0, f0, kappa),
+                    p_value,
+                    se,
+                    sigma,
+                    aic,
+                    rmse,
+                    cv,
+                    range_start: calc_range_start,
+                    range_end: calc_range_end,
+                };
+                cycle.fluxes.insert(
+                    (gk, FluxKind::Hmr),
+                    FluxRecord { model: Box::new(hmr), is_valid: gas_is_valid },
+                );
+            }
         }
     }
     let mut cycles: Vec<Cycle> = cycle_map.into_values().collect();
@@ -2719,22 +3001,36 @@ pub fn load_cycles_sync(
     Ok(cycles)
 }
 
+/// Millisecond timestamp of the gas data as seconds, the unit of the dt vectors
+pub fn ms_to_s(t: i64) -> f64 {
+    t as f64 / 1000.0
+}
+
+/// Number of samples `dt_v` has in `seconds`, from the median sampling
+/// interval. 1 Hz data gives `seconds` back.
+pub fn samples_in(dt_v: &[f64], seconds: f64) -> usize {
+    let mut steps: Vec<f64> = dt_v.windows(2).map(|w| w[1] - w[0]).filter(|d| *d > 0.0).collect();
+    if steps.is_empty() {
+        return seconds.round().max(1.0) as usize;
+    }
+    steps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let interval = steps[steps.len() / 2];
+    (seconds / interval).round().max(1.0) as usize
+}
+
+/// Values between `range_start` and `range_end` milliseconds, times are
+/// returned in seconds
 fn filter_data_in_range(
     datetimes: &[i64],
     values: &[Option<f64>],
-    range_start: f64,
-    range_end: f64,
+    range_start: i64,
+    range_end: i64,
 ) -> (Vec<f64>, Vec<Option<f64>>) {
-    // Zip the datetimes and values, filter by comparing each datetime's timestamp
-    // to the given range, and then unzip the filtered pairs.
     datetimes
         .iter()
         .zip(values.iter())
-        .filter(|(dt, _)| {
-            let t = **dt as f64;
-            t >= range_start && t <= range_end
-        })
-        .map(|(dt, &v)| (*dt as f64, v))
+        .filter(|(dt, _)| **dt >= range_start && **dt <= range_end)
+        .map(|(dt, &v)| (ms_to_s(*dt), v))
         .unzip()
 }
 fn filter_diag_data(
@@ -2871,12 +3167,17 @@ pub fn calculate_min_y_from_vec(values: &[Option<f64>]) -> f64 {
     values.iter().filter_map(|&v| v).filter(|v| !v.is_nan()).fold(f64::INFINITY, f64::min)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn process_cycles<V>(
     timev: &TimeData,
     gas_by_day: &HashMap<String, V>,
     meteo_data: &MeteoData,
+    chamber_meteo: &ChamberMeteoData,
+    aux_data: &AuxData,
     height_data: &HeightData,
-    chamber_data: &HashMap<String, Chamber>,
+    volume_data: &VolumeData,
+    snow_data: &SnowData,
+    chamber_data: &ChamberData,
     project: &Project,
     sender: ProgSender,
 ) -> Result<Vec<Option<Cycle>>, Box<dyn std::error::Error + Send + Sync>>
@@ -2886,8 +3187,8 @@ where
     let mut cycle_vec = Vec::new();
     let instruments = project.load_instruments()?;
 
-    for (chamber, start, close, open, end, snow_depth, id, project_id, instrument_id) in
-        timev.iter()
+    for (i, (chamber, start, close, open, end, snow_depth, id, project_id, instrument_id)) in
+        timev.iter().enumerate()
     {
         let dt_utc = DateTime::<Utc>::from_timestamp(*start, 0).unwrap();
         let day = dt_utc.format("%Y-%m-%d").to_string();
@@ -2910,22 +3211,26 @@ where
             continue;
         };
         cycle.id = *id;
+        cycle.note = timev.note[i].clone();
 
         let mut found_data = false;
 
+        // gas data is in milliseconds, cycle times in seconds
+        let start_ms = start * 1000;
+
         // Iterate only the serials that actually exist that day
         for (ser, datetimes) in &cur_data.datetime {
             // Skip serials that don’t cover the start time
             if datetimes.is_empty()
-                || start < datetimes.first().unwrap()
-                || start > datetimes.last().unwrap()
+                || start_ms < *datetimes.first().unwrap()
+                || start_ms > *datetimes.last().unwrap()
             {
                 continue;
             }
 
             // Use binary search instead of linear find to align start index exactly
             // (avoids the equality “hack” later).
-            let si_idx = match datetimes.binary_search(start) {
+            let si_idx = match datetimes.binary_search(&start_ms) {
                 Ok(i) => i,
                 Err(i) => {
                     // first >= start; if start isn’t exactly present, we still align to the window
@@ -2937,7 +3242,7 @@ where
             };
 
             let si_time = datetimes[si_idx];
-            let ei_time = si_time + *end;
+            let ei_time = start_ms + *end * 1000;
 
             // Find end index (first index with ts >= ei_time). This emulates
             // std::slice::partition_point (stable since 1.52 in Rust core nightly; manual here).
@@ -2962,10 +3267,11 @@ where
 
             // Timestamp vector (keep as i64 where possible; only cast when storing if required)
             let dt_slice: Vec<f64> =
-                datetimes[idx_range.clone()].iter().map(|t| *t as f64).collect();
+                datetimes[idx_range.clone()].iter().map(|t| ms_to_s(*t)).collect();
 
-            // If you still want to enforce exact start alignment, compare to seconds (no FP issues)
-            if cycle.get_start_ts() != si_time {
+            // The first sample has to be within the start second, sub-second
+            // data rarely lands exactly on it
+            if si_time - start_ms >= 1000 {
                 // If this is *too strict*, you can relax or log instead of skipping entirely.
                 continue;
             }
@@ -2999,6 +3305,7 @@ where
                                     gas: ch.gas,
                                     unit: ch.unit,
                                     instrument_id: ch.instrument_id.clone(),
+                                    mole_fraction: ch.mole_fraction,
                                 },
                             );
                             break; // we found the matching channel; stop to avoid accidental overwrites
@@ -3028,10 +3335,11 @@ where
 
             // Meteo
 
-            let nearest = meteo_data.get_nearest(target);
+            let nearest =
+                meteo_data.get_at(target, project.meteo_interpolation, project.meteo_max_gap_s);
 
             // NOTE: clear this mess up...
-            let (temp_point, press_point) = nearest.unwrap_or((
+            let (temp_point, press_point, stations) = nearest.unwrap_or((
                 MeteoPoint {
                     value: Some(DEFAULT_TEMP),
                     source: MeteoSource::Default,
@@ -3042,26 +3350,47 @@ where
                     source: MeteoSource::Default,
                     distance_from_target: None,
                 },
+                MeteoStations::default(),
             ));
 
+            cycle.meteo_stations = stations;
             cycle.meteo.temperature = temp_point.or_default(DEFAULT_TEMP);
             cycle.meteo.pressure = press_point.or_default(DEFAULT_PRESSURE);
+            // temperature measured at the chamber is closer than the meteo station
+            if let Some(t) = timev.air_temperature[i] {
+                cycle.meteo.temperature = MeteoPoint {
+                    value: Some(t),
+                    source: MeteoSource::Cycle,
+                    distance_from_target: None,
+                };
+            }
+            // without measured pressure it's modelled from the site elevation
+            cycle.meteo.pressure = cycle.meteo.pressure.or_modelled(
+                project.modelled_pressure(cycle.meteo.temperature.value.unwrap_or(DEFAULT_TEMP)),
+            );
+            cycle.headspace = chamber_meteo.window(chamber, *start, *start + *end);
+            cycle.aux = aux_data.values_at(chamber, *start, project.aux_max_gap_s);
 
             // Height
-            let maybe_height = height_data.get_nearest_previous_height(target, &cycle.chamber_id);
+            let maybe_height = height_data.nearest_previous(target, &cycle.chamber_id);
 
-            cycle.chamber = chamber_data.get(chamber).cloned().unwrap_or_default();
+            cycle.chamber = chamber_data.get(chamber, *start).cloned().unwrap_or_default();
+            // a measured volume replaces the one from the geometry
+            cycle.chamber.measured_volume_m3 = volume_data.nearest_previous(*start, chamber);
 
             if let Some(h) = maybe_height {
                 cycle.chamber_height = h;
                 cycle.chamber.set_height(h);
             } else {
-                cycle.chamber_height = match &cycle.chamber.shape {
-                    ChamberShape::Cylinder { height_m, .. }
-                    | ChamberShape::Box { height_m, .. } => *height_m,
-                };
+                cycle.chamber_height = cycle.chamber.shape.height_m();
             }
 
+            // uploaded snow depths are used over the one in the cycle file
+            if let Some(depth) = snow_data.nearest_previous(*start, chamber) {
+                cycle.snow_depth_m = depth;
+            }
+            cycle.chamber.set_snow_height(cycle.snow_depth_m);
+
             // Deadbands
             // NOTE: figure out how to get rid of the clone
             for &gas_key in &cycle.gases.clone() {
@@ -3252,6 +3581,7 @@ mod tests {
                 gas: GasType::CH4,
                 unit: crate::concentrationunit::ConcentrationUnit::Ppb,
                 instrument_id: "asd".to_owned(),
+                mole_fraction: crate::gaschannel::MoleFraction::Dry,
             }
         }
 
@@ -3338,4 +3668,143 @@ mod tests {
         let result = cycle.best_flux_by_aic(&GasType::CH4);
         assert_eq!(result, Some(20.0)); // Lowest AIC is 80.0 -> flux = 20.0
     }
+
+    #[test]
+    fn hmr_columns_round_trip() {
+        let mut conn = crate::db::test_conn();
+        let instrument = get_instrument_by_project_and_id(&conn, 1, 1).unwrap().unwrap();
+        let project = Project { id: Some(1), instrument: instrument.clone(), ..Project::default() };
+        let start = 1_700_000_000;
+        conn.execute_batch(
+            "INSERT INTO data_files (id, file_name, data_type, project_link)
+                VALUES (1, 'cycles.csv', 'cycle', 1);
+            INSERT INTO cycles (id, chamber_id, start_time, close_offset, open_offset,
+                end_offset, snow_depth, file_link, project_link, instrument_link)
+                VALUES (1, 'A', 1700000000, 60, 240, 300, 0, 1, 1, 1);",
+        )
+        .unwrap();
+        // cycles are only loaded for days with gas data
+        conn.execute(
+            "INSERT INTO measurements (datetime, gas, value, diag, file_link, project_link,
+                instrument_link) VALUES (?1, ?2, 420.0, 0, 1, 1, 1)",
+            params![(start + 100) * 1000, GasType::CO2.as_int()],
+        )
+        .unwrap();
+
+        let key = GasKey::from((&GasType::CO2, &1));
+        let channel = GasChannel {
+            gas: GasType::CO2,
+            unit: crate::concentrationunit::ConcentrationUnit::Ppm,
+            instrument_id: "1".to_owned(),
+            mole_fraction: MoleFraction::Dry,
+        };
+        let (lo, hi) = ((start + 60) as f64, (start + 240) as f64);
+        let lin = LinearFlux {
+            gas_channel: channel.clone(),
+            flux: 1.0,
+            r2: 0.9,
+            adjusted_r2: 0.89,
+            model: LinReg::from_val(420.0, 0.5),
+            sigma: 0.1,
+            p_value: 0.01,
+            aic: 10.0,
+            rmse: 0.1,
+            cv: 0.01,
+            range_start: lo,
+            range_end: hi,
+        };
+        let hmr = HmrFlux {
+            gas_channel: channel,
+            flux: 1.5,
+            r2: 0.98,
+            adjusted_r2: 0.97,
+            model: HmrReg::from_val(419.5, 0.8, 0.004),
+            p_value: 0.001,
+            se: 0.02,
+            sigma: 0.05,
+            aic: -3.5,
+            rmse: 0.045,
+            cv: 0.0001,
+            range_start: lo,
+            range_end: hi,
+        };
+        let point = |value| MeteoPoint {
+            value: Some(value),
+            source: MeteoSource::Raw,
+            distance_from_target: None,
+        };
+        let mut fluxes = FastMap::default();
+        fluxes.insert((key, FluxKind::Linear), FluxRecord { model: Box::new(lin), is_valid: true });
+        fluxes.insert((key, FluxKind::Hmr), FluxRecord { model: Box::new(hmr), is_valid: true });
+        let cycle = Cycle {
+            id: 1,
+            chamber_id: "A".to_owned(),
+            main_instrument: instrument.clone(),
+            instruments: FastMap::from_iter([(1, instrument)]),
+            chamber: Chamber::default(),
+            project_id: project.id,
+            meteo: MeteoConditions::new(point(10.0), point(980.0)),
+            meteo_stations: MeteoStations::default(),
+            headspace: ChamberMeteoSeries::default(),
+            calc_meteo: FastMap::default(),
+            chamber_height: 1.,
+            snow_depth_m: 0.,
+            note: None,
+            aux: BTreeMap::new(),
+            error_code: ErrorMask(0),
+            is_valid: true,
+            gas_is_valid: FastMap::default(),
+            override_valid: None,
+            manual_valid: false,
+            main_gas: GasType::CO2,
+            max_idx: 0.,
+            gases: vec![key],
+            manual_adjusted: false,
+            min_y: FastMap::default(),
+            max_y: FastMap::default(),
+            flux: FastMap::default(),
+            fluxes,
+            measurement_r2: FastMap::default(),
+            calc_r2: FastMap::default(),
+            t0_concentration: FastMap::default(),
+            gas_v: FastMap::default(),
+            gas_v_mole: FastMap::default(),
+            calc_gas_v: FastMap::default(),
+            measurement_gas_v: FastMap::default(),
+            diag_v: FastMap::default(),
+            measurement_diag_v: FastMap::default(),
+            gas_channels: FastMap::default(),
+            timing: CycleTiming::new_from_offsets(start, 60, 240, 300, 60.),
+        };
+        assert_eq!(insert_fluxes_ignore_duplicates(&mut conn, &[Some(cycle)], &1).unwrap().0, 1);
+
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let cycles = load_cycles_sync(&conn, &project, start - 60, start + 600, sender).unwrap();
+        let loaded = cycles[0].fluxes.get(&(key, FluxKind::Hmr)).unwrap();
+        let loaded = loaded.model.as_any().downcast_ref::<HmrFlux>().unwrap();
+        assert_eq!(loaded.flux, 1.5);
+        assert_eq!(loaded.r2, 0.98);
+        assert_eq!(loaded.adjusted_r2, 0.97);
+        assert_eq!(loaded.model.c0, 419.5);
+        assert_eq!(loaded.model.f0, 0.8);
+        assert_eq!(loaded.model.kappa, 0.004);
+        assert_eq!(loaded.sigma, 0.05);
+        assert_eq!(loaded.p_value, 0.001);
+        assert_eq!(loaded.aic, -3.5);
+        assert_eq!(loaded.rmse, 0.045);
+        assert_eq!(loaded.cv, 0.0001);
+        assert_eq!(loaded.se, 0.02);
+    }
+
+    #[test]
+    fn sub_second_samples_are_kept() {
+        let ms: Vec<i64> = (0..50).map(|i| 1_718_193_600_000 + i * 100).collect();
+        let values: Vec<Option<f64>> = (0..50).map(|i| Some(i as f64)).collect();
+        let (dt, v) = filter_data_in_range(&ms, &values, ms[10], ms[19]);
+
+        assert_eq!(v.len(), 10);
+        assert!((dt[1] - dt[0] - 0.1).abs() < 1e-6);
+        assert_eq!(samples_in(&dt, 1.0), 10);
+        assert_eq!(samples_in(&[0.0, 1.0, 2.0, 4.0], 60.0), 60);
+    }
 }
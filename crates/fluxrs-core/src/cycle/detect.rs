@@ -0,0 +1,408 @@
+use crate::data_formats::clockdata::{query_clock_corrections, ClockCorrections};
+use crate::data_formats::timedata::{insert_cycles, TimeData};
+use crate::datatype::DataType;
+use crate::project::Project;
+use crate::utils::{get_or_insert_data_file, touch_if_exists_updated, DataFileError};
+use chrono::DateTime;
+use rusqlite::{params, Connection, Result};
+
+/// Chamber id of detected cycles when no chamber sequence is given
+pub const DETECTED_CHAMBER: &str = "detected";
+
+/// Settings of cycle detection from concentration data, slopes are in units
+/// of the main gas per second
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectSettings {
+    /// Length of the window the slope is fitted over, seconds
+    pub window_s: i64,
+    /// Absolute slope that starts a closure and, in the other direction,
+    /// marks the opening
+    pub min_slope: f64,
+    /// Shortest closure kept, seconds
+    pub min_duration_s: i64,
+    /// Closures that aren't opened within this are discarded, seconds
+    pub max_duration_s: i64,
+    /// Concentration is back at ambient when within this fraction of the
+    /// closure change from the level before closing
+    pub ambient_tolerance: f64,
+    /// Seconds before the closure the cycle starts
+    pub lead_s: i64,
+    /// Seconds after opening the cycle ends when concentration doesn't return
+    /// to ambient within `max_duration_s`
+    pub tail_s: i64,
+    /// Chamber ids assigned to the detected cycles in order, repeating
+    pub chamber_sequence: Vec<String>,
+}
+
+impl Default for DetectSettings {
+    fn default() -> Self {
+        Self {
+            window_s: 30,
+            min_slope: 0.05,
+            min_duration_s: 60,
+            max_duration_s: 1800,
+            ambient_tolerance: 0.2,
+            lead_s: 60,
+            tail_s: 60,
+            chamber_sequence: Vec::new(),
+        }
+    }
+}
+
+/// Cycle proposed from concentration data, times in seconds like the cycles
+/// table
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCycle {
+    pub chamber_id: String,
+    pub start_time: i64,
+    pub close_offset: i64,
+    pub open_offset: i64,
+    pub end_offset: i64,
+}
+
+impl DetectedCycle {
+    pub fn close_time(&self) -> i64 {
+        self.start_time + self.close_offset
+    }
+    pub fn open_time(&self) -> i64 {
+        self.start_time + self.open_offset
+    }
+    pub fn end_time(&self) -> i64 {
+        self.start_time + self.end_offset
+    }
+}
+
+/// Comma separated chamber ids, blanks are dropped
+pub fn parse_chamber_sequence(s: &str) -> Vec<String> {
+    s.split(',').map(str::trim).filter(|c| !c.is_empty()).map(str::to_owned).collect()
+}
+
+/// Main gas of the project's main instrument between `start` and `end`
+/// (seconds) as (milliseconds, value) in chamber controller time
+pub fn query_main_gas(
+    conn: &Connection,
+    project: &Project,
+    start: i64,
+    end: i64,
+) -> Result<Vec<(i64, f64)>> {
+    let (Some(project_id), Some(instrument_id), Some(gas)) =
+        (project.id, project.instrument.id, project.main_gas)
+    else {
+        return Ok(Vec::new());
+    };
+    let clock = ClockCorrections::new(query_clock_corrections(conn, project_id)?);
+    let margin = clock.max_shift(start, end) * 1000;
+    let mut stmt = conn.prepare(
+        "SELECT datetime, value FROM measurements
+         WHERE project_link = ?1 AND instrument_link = ?2 AND gas = ?3
+           AND datetime BETWEEN ?4 AND ?5 AND value IS NOT NULL
+         ORDER BY datetime",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            project_id,
+            instrument_id,
+            gas.as_int(),
+            start * 1000 - margin,
+            end * 1000 + margin
+        ],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+    )?;
+    let mut series = Vec::new();
+    for row in rows {
+        let (t, v) = row?;
+        let t = clock.apply(instrument_id, t);
+        if t >= start * 1000 && t <= end * 1000 && v.is_finite() {
+            series.push((t, v));
+        }
+    }
+    series.sort_by_key(|(t, _)| *t);
+    Ok(series)
+}
+
+/// Mean of each whole second, `series` in milliseconds and sorted
+fn bin_seconds(series: &[(i64, f64)]) -> Vec<(i64, f64)> {
+    let mut bins: Vec<(i64, f64)> = Vec::new();
+    let mut count = 0.0;
+    for &(t, v) in series {
+        let s = t.div_euclid(1000);
+        match bins.last_mut() {
+            Some((last, sum)) if *last == s => {
+                *sum += v;
+                count += 1.0;
+            },
+            _ => {
+                if let Some((_, sum)) = bins.last_mut() {
+                    *sum /= count;
+                }
+                bins.push((s, v));
+                count = 1.0;
+            },
+        }
+    }
+    if let Some((_, sum)) = bins.last_mut() {
+        *sum /= count;
+    }
+    bins
+}
+
+/// Least squares slope of (t, y), None with less than three points
+fn slope(points: &[(i64, f64)]) -> Option<f64> {
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let t0 = points[0].0;
+    let mean_t = points.iter().map(|(t, _)| (t - t0) as f64).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for &(t, y) in points {
+        let dt = (t - t0) as f64 - mean_t;
+        sxy += dt * (y - mean_y);
+        sxx += dt * dt;
+    }
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+/// Slope of the window starting at each bin, windows with less than half of
+/// their seconds present have none
+fn forward_slopes(bins: &[(i64, f64)], window_s: i64) -> Vec<Option<f64>> {
+    let mut slopes = Vec::with_capacity(bins.len());
+    let mut end = 0;
+    for i in 0..bins.len() {
+        end = end.max(i);
+        while end < bins.len() && bins[end].0 < bins[i].0 + window_s {
+            end += 1;
+        }
+        let points = &bins[i..end];
+        if (points.len() as i64) * 2 < window_s {
+            slopes.push(None);
+        } else {
+            slopes.push(slope(points));
+        }
+    }
+    slopes
+}
+
+/// Time where a flat line turns into a slope in `dir`, the best two segment
+/// fit y = a + m * max(0, t - c) over the candidates c
+fn hinge_onset(points: &[(i64, f64)], dir: f64) -> Option<i64> {
+    let mut best: Option<(f64, i64)> = None;
+    for k in 2..points.len().saturating_sub(2) {
+        let c = points[k].0;
+        let xy: Vec<(f64, f64)> = points.iter().map(|&(t, y)| ((t - c).max(0) as f64, y)).collect();
+        let n = xy.len() as f64;
+        let mean_x = xy.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = xy.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = xy.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        if sxx == 0.0 {
+            continue;
+        }
+        let m = xy.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / sxx;
+        if m * dir <= 0.0 {
+            continue;
+        }
+        let a = mean_y - m * mean_x;
+        let sse: f64 = xy.iter().map(|(x, y)| (y - a - m * x).powi(2)).sum();
+        if best.is_none_or(|(b, _)| sse < b) {
+            best = Some((sse, c));
+        }
+    }
+    best.map(|(_, c)| c)
+}
+
+fn mean(points: &[(i64, f64)]) -> Option<f64> {
+    (!points.is_empty()).then(|| points.iter().map(|(_, y)| y).sum::<f64>() / points.len() as f64)
+}
+
+/// Bins with times in `[from, to)`
+fn range(bins: &[(i64, f64)], from: i64, to: i64) -> &[(i64, f64)] {
+    let a = bins.partition_point(|(t, _)| *t < from);
+    let b = bins.partition_point(|(t, _)| *t < to);
+    &bins[a..b.max(a)]
+}
+
+/// Find chamber closures in a concentration series in milliseconds.
+///
+/// A closure starts where the slope over `window_s` first reaches `min_slope`
+/// in either direction, refined to the change point of a flat and a sloped
+/// segment. The chamber is opened where the slope turns as steep the other
+/// way, at the extreme concentration, and the cycle ends once the
+/// concentration is back near the level before closing.
+pub fn detect_cycles(series: &[(i64, f64)], settings: &DetectSettings) -> Vec<DetectedCycle> {
+    let bins = bin_seconds(series);
+    let window = settings.window_s.max(3);
+    let slopes = forward_slopes(&bins, window);
+    let steep = |i: usize, dir: f64| slopes[i].is_some_and(|s| s * dir >= settings.min_slope);
+
+    let mut cycles = Vec::new();
+    let mut prev_end = i64::MIN;
+    let mut i = 0;
+    while i < bins.len() {
+        let Some(s) = slopes[i].filter(|s| s.abs() >= settings.min_slope) else {
+            i += 1;
+            continue;
+        };
+        let dir = s.signum();
+        let t_i = bins[i].0;
+        let close =
+            hinge_onset(range(&bins, t_i - window, t_i + window), dir).unwrap_or(t_i).max(prev_end);
+        let baseline = mean(range(&bins, close - window, close)).unwrap_or(bins[i].1);
+
+        let first = bins.partition_point(|(t, _)| *t < close + settings.min_duration_s);
+        let last = bins.partition_point(|(t, _)| *t <= close + settings.max_duration_s);
+        let Some(turn) = (first..last).find(|&j| steep(j, -dir)) else {
+            // not opened in time, skip past this rise
+            while i < bins.len() && steep(i, dir) {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        };
+
+        // the chamber is opened at the extreme concentration
+        let (open, peak) = range(&bins, close, bins[turn].0 + window)
+            .iter()
+            .copied()
+            .max_by(|a, b| (a.1 * dir).total_cmp(&(b.1 * dir)))
+            .unwrap_or(bins[turn]);
+
+        let limit = (peak - baseline).abs() * settings.ambient_tolerance;
+        let end = range(&bins, open + 1, open + settings.max_duration_s + 1)
+            .iter()
+            .find(|(_, v)| (v - baseline) * dir <= limit)
+            .map(|(t, _)| *t)
+            .unwrap_or(open + settings.tail_s);
+
+        let start = (close - settings.lead_s).max(prev_end);
+        cycles.push(DetectedCycle {
+            chamber_id: String::new(),
+            start_time: start,
+            close_offset: close - start,
+            open_offset: open - start,
+            end_offset: end - start,
+        });
+        prev_end = end;
+        i = bins.partition_point(|(t, _)| *t < end).max(i + 1);
+        // the rest of the return to ambient isn't a new closure
+        while i < bins.len() && steep(i, -dir) {
+            i += 1;
+        }
+    }
+    assign_chambers(&mut cycles, &settings.chamber_sequence);
+    cycles
+}
+
+/// Assign chamber ids from a repeating sequence in cycle order
+pub fn assign_chambers(cycles: &mut [DetectedCycle], sequence: &[String]) {
+    for (i, cycle) in cycles.iter_mut().enumerate() {
+        cycle.chamber_id = if sequence.is_empty() {
+            DETECTED_CHAMBER.to_owned()
+        } else {
+            sequence[i % sequence.len()].clone()
+        };
+    }
+}
+
+/// Detected cycles as `TimeData` of the project's main instrument
+pub fn to_time_data(cycles: &[DetectedCycle], project: &Project) -> TimeData {
+    let mut data = TimeData::new();
+    for c in cycles {
+        data.chamber_id.push(c.chamber_id.clone());
+        data.start_time.push(c.start_time);
+        data.close_offset.push(c.close_offset);
+        data.open_offset.push(c.open_offset);
+        data.end_offset.push(c.end_offset);
+        data.snow_depth.push(0.0);
+        data.air_temperature.push(None);
+        data.note.push(None);
+        data.project_id.push(project.id.unwrap_or_default());
+        data.instrument_id.push(project.instrument.id.unwrap_or_default());
+    }
+    data
+}
+
+/// Insert accepted cycles, they are linked to a data file named after the
+/// detected range so they can be deleted like uploaded cycles
+pub fn insert_detected_cycles(
+    conn: &mut Connection,
+    project: &Project,
+    cycles: &[DetectedCycle],
+) -> std::result::Result<(usize, usize), DataFileError> {
+    let (Some(project_id), Some(first), Some(last)) = (
+        project.id,
+        cycles.iter().map(|c| c.start_time).min(),
+        cycles.iter().map(|c| c.start_time).max(),
+    ) else {
+        return Ok((0, 0));
+    };
+    let fmt = |t: i64| {
+        DateTime::from_timestamp(t, 0)
+            .map(|d| d.format("%Y%m%dT%H%M%S").to_string())
+            .unwrap_or_default()
+    };
+    let file_name = format!("detected_{}_{}", fmt(first), fmt(last));
+
+    let tx = conn.transaction()?;
+    let mut file_exists = None;
+    let file_id = match get_or_insert_data_file(&tx, DataType::Cycle, &file_name, project_id) {
+        Ok(id) => id,
+        Err(DataFileError::FileAlreadyExists(id)) => {
+            file_exists = Some(id);
+            id
+        },
+        Err(e) => return Err(e),
+    };
+    let (inserts, skips) =
+        insert_cycles(&tx, &to_time_data(cycles, project), &project_id, &file_id)?;
+    touch_if_exists_updated(file_exists, inserts, &tx);
+    tx.commit()?;
+    Ok((inserts, skips))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 Hz series at 420 with a small ripple and chamber closures of
+    /// (close, open, slope)
+    fn series(len: i64, closures: &[(i64, i64, f64)]) -> Vec<(i64, f64)> {
+        (0..len)
+            .map(|t| {
+                let mut v = 420.0 + 0.05 * (t as f64 * 0.7).sin();
+                for &(close, open, slope) in closures {
+                    if t >= close && t < open {
+                        v += slope * (t - close) as f64;
+                    } else if t >= open {
+                        // back to ambient with a 15 s time constant
+                        let peak = slope * (open - close) as f64;
+                        v += peak * (-((t - open) as f64) / 15.0).exp();
+                    }
+                }
+                (t * 1000 + 250, v)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closures_are_found() {
+        let data = series(3000, &[(300, 480, 0.5), (1200, 1440, -0.2), (2700, 3000, 0.5)]);
+        let settings = DetectSettings {
+            chamber_sequence: parse_chamber_sequence("A, B,"),
+            ..DetectSettings::default()
+        };
+        let cycles = detect_cycles(&data, &settings);
+
+        // the last closure never opens
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].chamber_id, "A");
+        assert_eq!(cycles[1].chamber_id, "B");
+        assert!((cycles[0].close_time() - 300).abs() <= 2, "{:?}", cycles[0]);
+        assert!((cycles[0].open_time() - 480).abs() <= 2, "{:?}", cycles[0]);
+        assert_eq!(cycles[0].close_offset, 60);
+        assert!((cycles[1].close_time() - 1200).abs() <= 2, "{:?}", cycles[1]);
+        assert!((cycles[1].open_time() - 1440).abs() <= 2, "{:?}", cycles[1]);
+        // 20 % of the change is left after ln(5) time constants
+        assert!((cycles[1].end_time() - 1440 - 24).abs() <= 2, "{:?}", cycles[1]);
+    }
+}
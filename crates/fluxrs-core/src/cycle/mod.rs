@@ -1,4 +1,5 @@
 pub mod cycle;
 mod cycletiming;
+pub mod detect;
 pub mod gaskey;
 
@@ -2,11 +2,13 @@ use crate::cycle::cycle::{
     insert_flux_results, insert_fluxes_ignore_duplicates, load_cycles, process_cycles,
     update_fluxes, Cycle,
 };
-use crate::data_formats::chamberdata::{insert_chamber_metadata, read_chamber_metadata, Chamber};
-use crate::data_formats::gasdata::{insert_measurements, GasData};
-use crate::data_formats::heightdata::{
-    insert_height_data, query_height, read_height_csv, HeightData,
+use crate::data_formats::auxdata::AuxData;
+use crate::data_formats::chamberdata::{
+    insert_chamber_metadata, read_chamber_metadata, ChamberData,
 };
+use crate::data_formats::chambermeteo::ChamberMeteoData;
+use crate::data_formats::chamberseries::{HeightData, SnowData, VolumeData};
+use crate::data_formats::gasdata::{insert_measurements, GasData};
 use crate::data_formats::meteodata::{insert_meteo_data, read_meteo_csv, MeteoData};
 use crate::data_formats::timedata::{insert_cycles, try_all_formats, TimeData};
 use crate::processevent::{
@@ -23,7 +25,7 @@ use tokio::sync::mpsc::{error::TryRecvError, UnboundedReceiver, UnboundedSender}
 const MAX_CONCURRENT_TASKS: usize = 10;
 type GasDataSet = HashMap<String, Arc<GasData>>;
 type HeightDataSet = HeightData;
-type ChamberDataSet = HashMap<String, Chamber>;
+type ChamberDataSet = ChamberData;
 type MeteoDataSet = MeteoData;
 type TimeDataSet = TimeData;
 type CycleDataSet = Vec<Cycle>;
@@ -31,7 +33,11 @@ type CycleDataSet = Vec<Cycle>;
 pub struct Datasets {
     pub gas: Arc<GasDataSet>,
     pub meteo: MeteoDataSet,
+    pub chamber_meteo: ChamberMeteoData,
+    pub aux: AuxData,
     pub height: HeightDataSet,
+    pub volume: VolumeData,
+    pub snow: SnowData,
     pub chambers: ChamberDataSet,
 }
 
@@ -87,7 +93,11 @@ impl Processor {
                 }
 
                 let meteo = self.data.meteo.clone();
+                let chamber_meteo = self.data.chamber_meteo.clone();
+                let aux = self.data.aux.clone();
                 let height = self.data.height.clone();
+                let volume = self.data.volume.clone();
+                let snow = self.data.snow.clone();
                 let chambers = self.data.chambers.clone();
                 let project_clone = self.project.clone();
                 let progress_sender = self.infra.progress.clone();
@@ -98,7 +108,11 @@ impl Processor {
                         &chunk,
                         &chunk_gas_data,
                         &meteo,
+                        &chamber_meteo,
+                        &aux,
                         &height,
+                        &volume,
+                        &snow,
                         &chambers,
                         &project_clone,
                         progress_sender.clone(),
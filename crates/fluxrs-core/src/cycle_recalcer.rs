@@ -1,23 +1,26 @@
 use crate::cycle::cycle::{update_fluxes, Cycle};
-use crate::data_formats::chamberdata::Chamber;
-use crate::data_formats::heightdata::HeightData;
+use crate::data_formats::chamberdata::ChamberData;
+use crate::data_formats::chambermeteo::ChamberMeteoData;
+use crate::data_formats::chamberseries::{HeightData, SnowData, VolumeData};
 use crate::data_formats::meteodata::{
-    MeteoData, MeteoPoint, MeteoSource, DEFAULT_PRESSURE, DEFAULT_TEMP,
+    MeteoData, MeteoPoint, MeteoSource, MeteoStations, DEFAULT_PRESSURE, DEFAULT_TEMP,
 };
 use crate::processevent::{ProcessEvent, ProgressEvent};
 use crate::project::Project;
 
-use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::UnboundedSender;
 
 type HeightDataSet = HeightData;
-type ChamberDataSet = HashMap<String, Chamber>;
+type ChamberDataSet = ChamberData;
 type MeteoDataSet = MeteoData;
 
 pub struct Datasets {
     pub meteo: MeteoDataSet,
+    pub chamber_meteo: ChamberMeteoData,
     pub height: HeightDataSet,
+    pub volume: VolumeData,
+    pub snow: SnowData,
     pub chambers: ChamberDataSet,
 }
 
@@ -45,20 +48,33 @@ impl Recalcer {
 
         let total_cycles = cycles.len();
         for c in &mut cycles {
-            let old_height = c.chamber.internal_height();
+            // geometry valid when the cycle started
+            if let Some(chamber) = self.data.chambers.get(&c.chamber_id, c.get_start_ts()) {
+                c.chamber = *chamber
+            }
+            let old_height = c.chamber.shape.height_m();
 
             // set new chamber height
-            // BUG: Something should be done with snowheight
             c.chamber.set_height(
                 self.data
                     .height
-                    .get_nearest_previous_height(c.timing.get_start_utc_ts(), &c.chamber_id)
+                    .nearest_previous(c.timing.get_start_utc_ts(), &c.chamber_id)
                     .unwrap_or(old_height),
             );
 
-            let nearest = self.data.meteo.get_nearest(c.get_start_ts());
+            // uploaded snow depths are used over the one in the cycle file
+            if let Some(depth) = self.data.snow.nearest_previous(c.get_start_ts(), &c.chamber_id) {
+                c.snow_depth_m = depth;
+            }
+            c.chamber.set_snow_height(c.snow_depth_m);
+
+            let nearest = self.data.meteo.get_at(
+                c.get_start_ts(),
+                self.project.meteo_interpolation,
+                self.project.meteo_max_gap_s,
+            );
 
-            let (mut temp_point, mut press_point) = nearest.unwrap_or((
+            let (mut temp_point, mut press_point, stations) = nearest.unwrap_or((
                 MeteoPoint {
                     value: Some(DEFAULT_TEMP),
                     source: MeteoSource::Default,
@@ -69,19 +85,26 @@ impl Recalcer {
                     source: MeteoSource::Default,
                     distance_from_target: None,
                 },
+                MeteoStations::default(),
             ));
 
             // Make sure Missing never leaks into pipeline:
             temp_point = temp_point.or_default(DEFAULT_TEMP);
             press_point = press_point.or_default(DEFAULT_PRESSURE);
 
-            // Assign final values to cycle
-            c.meteo.temperature = temp_point;
-            c.meteo.pressure = press_point;
-
-            if let Some(chamber) = self.data.chambers.get(&c.chamber_id) {
-                c.chamber = *chamber
+            // Assign final values to cycle, temperatures from the cycle file are kept
+            if c.meteo.temperature.source != MeteoSource::Cycle {
+                c.meteo.temperature = temp_point;
             }
+            c.meteo_stations = stations;
+            c.meteo.pressure = press_point.or_modelled(
+                self.project.modelled_pressure(c.meteo.temperature.value.unwrap_or(DEFAULT_TEMP)),
+            );
+            c.headspace =
+                self.data.chamber_meteo.window(&c.chamber_id, c.get_start_ts(), c.get_end_ts());
+
+            c.chamber.measured_volume_m3 =
+                self.data.volume.nearest_previous(c.get_start_ts(), &c.chamber_id);
             c.compute_all_fluxes();
             let _ =
                 progsender.send(ProcessEvent::Progress(ProgressEvent::Recalced(1, total_cycles)));
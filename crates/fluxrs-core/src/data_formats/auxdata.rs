@@ -0,0 +1,332 @@
+use crate::archive::expand_inputs;
+use crate::datatype::DataType;
+use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
+use crate::project::Project;
+use crate::utils::{
+    ensure_utf8, get_or_insert_data_file, parse_datetime, touch_if_exists_updated, DataFileError,
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, Result};
+use tokio::sync::mpsc;
+use tokio::task;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Largest distance from a cycle to an auxiliary sample by default, seconds.
+/// Slow series like daily water table need a larger one.
+pub const DEFAULT_AUX_MAX_GAP_S: i64 = 3600;
+
+/// Samples of one auxiliary variable, `datetime` in seconds
+#[derive(Debug, Default, Clone)]
+pub struct AuxSeries {
+    pub datetime: Vec<i64>,
+    pub value: Vec<f64>,
+}
+
+impl AuxSeries {
+    /// Value and distance of the sample nearest to `target`, None if there
+    /// is none within `max_gap_s`. `datetime` has to be sorted.
+    pub fn nearest(&self, target: i64, max_gap_s: i64) -> Option<(f64, i64)> {
+        let idx = self.datetime.partition_point(|&t| t < target);
+        [idx.checked_sub(1), Some(idx)]
+            .into_iter()
+            .flatten()
+            .filter(|&i| i < self.datetime.len())
+            .map(|i| (self.value[i], (self.datetime[i] - target).abs()))
+            .filter(|(_, dist)| *dist <= max_gap_s)
+            .min_by_key(|(_, dist)| *dist)
+    }
+
+    fn push(&mut self, datetime: i64, value: f64) {
+        self.datetime.push(datetime);
+        self.value.push(value);
+    }
+}
+
+/// Auxiliary series keyed by chamber id and variable name. Series logged for
+/// the whole project are under an empty chamber id.
+#[derive(Debug, Default, Clone)]
+pub struct AuxData {
+    pub chambers: HashMap<String, HashMap<String, AuxSeries>>,
+}
+
+impl AuxData {
+    pub fn is_empty(&self) -> bool {
+        self.chambers.is_empty()
+    }
+
+    /// Names of all variables, sorted
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.chambers.values().flat_map(|vars| vars.keys().cloned()).collect()
+    }
+
+    /// Values of every variable nearest to `target` within `max_gap_s`.
+    /// Samples of `chamber_id` are preferred over the project level ones.
+    pub fn values_at(
+        &self,
+        chamber_id: &str,
+        target: i64,
+        max_gap_s: i64,
+    ) -> BTreeMap<String, f64> {
+        let mut values = BTreeMap::new();
+        for key in ["", chamber_id] {
+            let Some(vars) = self.chambers.get(key) else { continue };
+            for (name, series) in vars {
+                if let Some((value, _)) = series.nearest(target, max_gap_s) {
+                    values.insert(name.clone(), value);
+                }
+            }
+        }
+        values
+    }
+
+    fn push(&mut self, chamber_id: &str, variable: &str, datetime: i64, value: f64) {
+        self.chambers
+            .entry(chamber_id.to_owned())
+            .or_default()
+            .entry(variable.to_owned())
+            .or_default()
+            .push(datetime, value);
+    }
+}
+
+pub fn insert_aux_data(
+    tx: &Connection,
+    file_id: &i64,
+    project_id: &i64,
+    data: &AuxData,
+) -> Result<(usize, usize)> {
+    let mut inserted = 0;
+    let mut skipped = 0;
+
+    let mut stmt = tx.prepare(
+        "INSERT OR IGNORE INTO aux_data
+            (project_link, datetime, chamber_id, variable, value, file_link)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+
+    for (chamber_id, vars) in &data.chambers {
+        for (variable, series) in vars {
+            for (datetime, value) in series.datetime.iter().zip(&series.value) {
+                let affected = stmt
+                    .execute(params![project_id, datetime, chamber_id, variable, value, file_id])?;
+                if affected > 0 {
+                    inserted += 1;
+                } else {
+                    skipped += 1
+                }
+            }
+        }
+    }
+
+    Ok((inserted, skipped))
+}
+
+/// Auxiliary samples between `start` and `end`, widened by the project's aux
+/// max gap so cycles at the edges still find their nearest sample
+pub fn query_aux(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project: &Project,
+) -> Result<AuxData> {
+    println!("Querying auxiliary data");
+
+    let mut stmt = conn.prepare(
+        "SELECT datetime, chamber_id, variable, value
+         FROM aux_data
+         WHERE datetime BETWEEN ?1 AND ?2
+           AND project_link = ?3
+         ORDER BY chamber_id, variable, datetime",
+    )?;
+
+    let margin = project.aux_max_gap_s;
+    let rows = stmt.query_map(
+        params![start.timestamp() - margin, end.timestamp() + margin, project.id.unwrap()],
+        |row| {
+            let datetime: i64 = row.get(0)?;
+            let chamber_id: String = row.get(1)?;
+            let variable: String = row.get(2)?;
+            let value: f64 = row.get(3)?;
+            Ok((datetime, chamber_id, variable, value))
+        },
+    )?;
+
+    let mut data = AuxData::default();
+    for row in rows {
+        let (datetime, chamber_id, variable, value) = row?;
+        data.push(&chamber_id, &variable, datetime, value);
+    }
+
+    Ok(data)
+}
+
+pub async fn query_aux_async(
+    conn: Arc<Mutex<Connection>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project: Project,
+) -> Result<AuxData> {
+    let result = task::spawn_blocking(move || {
+        let conn = conn.lock().unwrap();
+        query_aux(&conn, start, end, &project)
+    })
+    .await;
+
+    match result {
+        Ok(inner) => inner,
+        Err(_) => Err(rusqlite::Error::ExecuteReturnedResults),
+    }
+}
+
+/// Reads `datetime,[chamber_id,]<variable>,<variable>...` rows. Every column
+/// after the datetime and optional chamber_id is a variable named by its
+/// header, empty cells are skipped.
+pub fn read_aux_csv<P: AsRef<Path>>(file_path: P, tz: Tz) -> Result<AuxData, Box<dyn Error>> {
+    let content = ensure_utf8(&file_path)?;
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(content.as_bytes());
+
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.trim().to_owned()).collect();
+    let has_chamber = headers.get(1).is_some_and(|h| h == "chamber_id");
+    let first_var = if has_chamber { 2 } else { 1 };
+    if headers.len() <= first_var {
+        return Err("No variable columns after datetime".into());
+    }
+    if let Some(col) = headers.iter().skip(first_var).position(|h| h.is_empty()) {
+        return Err(format!("Variable column {} has no name", col + first_var + 1).into());
+    }
+
+    let mut data = AuxData::default();
+    for result in rdr.records() {
+        let record = result?;
+        let datetime_str = record.get(0).ok_or("Missing datetime field")?;
+        let timestamp = parse_datetime(datetime_str, tz)?;
+        let chamber_id = if has_chamber { record.get(1).map(str::trim).unwrap_or("") } else { "" };
+
+        for (col, name) in headers.iter().enumerate().skip(first_var) {
+            let field = record.get(col).map(str::trim).unwrap_or("");
+            if field.is_empty() {
+                continue;
+            }
+            let value: f64 = field.parse().map_err(|e| {
+                format!("Bad {} value '{}' at {}: {}", name, field, datetime_str, e)
+            })?;
+            data.push(chamber_id, name, timestamp, value);
+        }
+    }
+
+    Ok(data)
+}
+
+pub fn upload_aux_data_async(
+    selected_paths: Vec<PathBuf>,
+    conn: &mut Connection,
+    project: &Project,
+    tz: Tz,
+    progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+) {
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::aux_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
+        let project_id = project.id.unwrap();
+
+        let file_name = input.name.as_str();
+
+        let tx = match conn.transaction() {
+            Ok(tx) => tx,
+            Err(e) => {
+                eprintln!("Failed to start transaction: {}", e);
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "Could not start transaction for '{}': {}",
+                    file_name, e
+                ))));
+                continue;
+            },
+        };
+
+        let mut file_exists: Option<i64> = None;
+        let file_id = match get_or_insert_data_file(&tx, DataType::Aux, file_name, project_id) {
+            Ok(id) => id,
+            Err(DataFileError::FileAlreadyExists(id)) => {
+                file_exists = Some(id);
+                id
+            },
+            Err(e) => {
+                eprintln!("Failed to insert/find data file '{}': {}", file_name, e);
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "File '{}' skipped: {}",
+                    file_name, e
+                ))));
+                continue;
+            },
+        };
+
+        match read_aux_csv(path, tz) {
+            Ok(res) => match insert_aux_data(&tx, &file_id, &project_id, &res) {
+                Ok((inserts, skips)) => {
+                    touch_if_exists_updated(file_exists, inserts, &tx);
+
+                    let _ = progress_sender
+                        .send(ProcessEvent::Insert(InsertEvent::aux_okskip(inserts, skips)));
+                    if let Err(e) = tx.commit() {
+                        eprintln!("Failed to commit transaction for '{}': {}", file_name, e);
+                        let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(
+                            format!("Commit failed for file '{}': {}", file_name, e),
+                        )));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    let msg = format!("Failed to insert auxiliary data to db. Error {}", e);
+                    let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(msg)));
+                },
+            },
+            Err(e) => {
+                let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::aux_fail(
+                    input.label.clone(),
+                    e.to_string(),
+                )));
+            },
+        }
+
+        let _ = progress_sender.send(ProcessEvent::Done(Ok(())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chamber_values_before_project_values() {
+        let mut data = AuxData::default();
+        data.push("", "soil_t_5cm", 0, 4.0);
+        data.push("", "soil_t_5cm", 600, 5.0);
+        data.push("", "par", 0, 120.0);
+        data.push("1", "soil_t_5cm", 300, 6.5);
+
+        let values = data.values_at("1", 290, 60);
+        assert_eq!(values.get("soil_t_5cm"), Some(&6.5));
+        assert_eq!(values.get("par"), None);
+
+        let values = data.values_at("2", 100, 300);
+        assert_eq!(values.get("soil_t_5cm"), Some(&4.0));
+        assert_eq!(values.get("par"), Some(&120.0));
+
+        assert_eq!(
+            data.variables().into_iter().collect::<Vec<_>>(),
+            vec!["par".to_owned(), "soil_t_5cm".to_owned()]
+        );
+    }
+}
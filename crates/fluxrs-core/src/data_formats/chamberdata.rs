@@ -1,8 +1,9 @@
+use crate::archive::expand_inputs;
 use crate::project::Project;
 
 use crate::datatype::DataType;
 use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
-use crate::utils::ensure_utf8;
+use crate::utils::{ensure_utf8, parse_datetime};
 use crate::utils::{get_or_insert_data_file, touch_if_exists_updated, DataFileError};
 
 use chrono_tz::Tz;
@@ -23,6 +24,8 @@ use tokio::task;
 pub enum ChamberShapeType {
     Box,
     Cylinder,
+    Frustum,
+    Custom,
 }
 
 impl ChamberShapeType {
@@ -30,6 +33,8 @@ impl ChamberShapeType {
         match self {
             ChamberShapeType::Box => 0,
             ChamberShapeType::Cylinder => 1,
+            ChamberShapeType::Frustum => 2,
+            ChamberShapeType::Custom => 3,
         }
     }
 
@@ -37,9 +42,47 @@ impl ChamberShapeType {
         match i {
             0 => Some(Self::Box),
             1 => Some(Self::Cylinder),
+            2 => Some(Self::Frustum),
+            3 => Some(Self::Custom),
             _ => None,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChamberShapeType::Box => "box",
+            ChamberShapeType::Cylinder => "cylinder",
+            ChamberShapeType::Frustum => "frustum",
+            ChamberShapeType::Custom => "custom",
+        }
+    }
+
+    /// Shape of this type from the dimensions it uses
+    fn build(&self, dims: ShapeDims) -> ChamberShape {
+        let snow_height_m = 0.0;
+        match self {
+            ChamberShapeType::Box => ChamberShape::Box {
+                width_m: dims.width,
+                length_m: dims.length,
+                height_m: dims.height,
+                snow_height_m,
+            },
+            ChamberShapeType::Cylinder => ChamberShape::Cylinder {
+                diameter_m: dims.diameter,
+                height_m: dims.height,
+                snow_height_m,
+            },
+            ChamberShapeType::Frustum => ChamberShape::Frustum {
+                bottom_diameter_m: dims.diameter,
+                top_diameter_m: dims.top_diameter,
+                height_m: dims.height,
+                snow_height_m,
+            },
+            ChamberShapeType::Custom => {
+                ChamberShape::Custom { volume_m3: dims.volume, area_m2: dims.area, snow_height_m }
+            },
+        }
+    }
 }
 
 impl FromStr for ChamberShapeType {
@@ -49,15 +92,53 @@ impl FromStr for ChamberShapeType {
         match s.to_lowercase().as_str() {
             "box" => Ok(ChamberShapeType::Box),
             "cylinder" => Ok(ChamberShapeType::Cylinder),
+            "frustum" => Ok(ChamberShapeType::Frustum),
+            "custom" => Ok(ChamberShapeType::Custom),
             _ => Err(()),
         }
     }
 }
 
+/// Dimensions as stored in chamber_metadata and the metadata CSV, the ones a
+/// shape doesn't use are 0
+#[derive(Debug, Default, Clone, Copy)]
+struct ShapeDims {
+    diameter: f64,
+    top_diameter: f64,
+    height: f64,
+    width: f64,
+    length: f64,
+    volume: f64,
+    area: f64,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ChamberShape {
-    Cylinder { diameter_m: f64, height_m: f64, snow_height_m: f64 },
-    Box { width_m: f64, length_m: f64, height_m: f64, snow_height_m: f64 },
+    Cylinder {
+        diameter_m: f64,
+        height_m: f64,
+        snow_height_m: f64,
+    },
+    Box {
+        width_m: f64,
+        length_m: f64,
+        height_m: f64,
+        snow_height_m: f64,
+    },
+    /// Truncated cone standing on its bottom, the area is that of the bottom
+    Frustum {
+        bottom_diameter_m: f64,
+        top_diameter_m: f64,
+        height_m: f64,
+        snow_height_m: f64,
+    },
+    /// Irregular chamber with a known volume and area. Snow is assumed to
+    /// fill it as if the walls were vertical.
+    Custom {
+        volume_m3: f64,
+        area_m2: f64,
+        snow_height_m: f64,
+    },
 }
 
 impl Default for ChamberShape {
@@ -76,6 +157,7 @@ impl ChamberShape {
             ChamberShape::Box { width_m, length_m, height_m, snow_height_m, .. } => {
                 width_m * length_m * (height_m - snow_height_m)
             },
+            ChamberShape::Frustum { .. } | ChamberShape::Custom { .. } => self.adjusted_volume(),
         }
     }
 
@@ -86,20 +168,44 @@ impl ChamberShape {
                 std::f64::consts::PI * r * r
             },
             ChamberShape::Box { width_m, length_m, .. } => width_m * length_m,
+            ChamberShape::Frustum { bottom_diameter_m, .. } => {
+                let r = bottom_diameter_m / 2.0;
+                std::f64::consts::PI * r * r
+            },
+            ChamberShape::Custom { area_m2, .. } => *area_m2,
         }
     }
 
-    pub fn internal_height(&self) -> f64 {
+    /// Height of the chamber, for a custom chamber the height a chamber with
+    /// vertical walls of the same volume would have
+    pub fn height_m(&self) -> f64 {
         match self {
-            ChamberShape::Cylinder { height_m, snow_height_m, .. } => {
-                (*height_m - *snow_height_m).max(0.0)
-            },
-            ChamberShape::Box { height_m, snow_height_m, .. } => {
-                (*height_m - *snow_height_m).max(0.0)
+            ChamberShape::Cylinder { height_m, .. }
+            | ChamberShape::Box { height_m, .. }
+            | ChamberShape::Frustum { height_m, .. } => *height_m,
+            ChamberShape::Custom { volume_m3, area_m2, .. } => {
+                if *area_m2 > 0.0 {
+                    volume_m3 / area_m2
+                } else {
+                    0.0
+                }
             },
         }
     }
 
+    pub fn snow_height_m(&self) -> f64 {
+        match self {
+            ChamberShape::Cylinder { snow_height_m, .. }
+            | ChamberShape::Box { snow_height_m, .. }
+            | ChamberShape::Frustum { snow_height_m, .. }
+            | ChamberShape::Custom { snow_height_m, .. } => *snow_height_m,
+        }
+    }
+
+    pub fn internal_height(&self) -> f64 {
+        (self.height_m() - self.snow_height_m()).max(0.0)
+    }
+
     pub fn adjusted_volume(&self) -> f64 {
         let adjusted_height = self.internal_height();
         match self {
@@ -108,6 +214,19 @@ impl ChamberShape {
                 std::f64::consts::PI * r * r * adjusted_height
             },
             ChamberShape::Box { width_m, length_m, .. } => width_m * length_m * adjusted_height,
+            ChamberShape::Frustum { bottom_diameter_m, top_diameter_m, height_m, .. } => {
+                if *height_m <= 0.0 {
+                    return 0.0;
+                }
+                // radius at the snow surface
+                let r_top = top_diameter_m / 2.0;
+                let r_bottom = bottom_diameter_m / 2.0;
+                let r_snow = r_top + (r_bottom - r_top) * adjusted_height / height_m;
+                std::f64::consts::PI / 3.0
+                    * adjusted_height
+                    * (r_snow * r_snow + r_snow * r_top + r_top * r_top)
+            },
+            ChamberShape::Custom { area_m2, .. } => area_m2 * adjusted_height,
         }
     }
 
@@ -115,13 +234,18 @@ impl ChamberShape {
         match self {
             ChamberShape::Box { .. } => ChamberShapeType::Box,
             ChamberShape::Cylinder { .. } => ChamberShapeType::Cylinder,
+            ChamberShape::Frustum { .. } => ChamberShapeType::Frustum,
+            ChamberShape::Custom { .. } => ChamberShapeType::Custom,
         }
     }
 
+    /// The volume of a custom chamber is fixed, so its height isn't changed
     pub fn set_height(&mut self, new_height: f64) {
         match self {
             ChamberShape::Cylinder { height_m, .. } => *height_m = new_height,
             ChamberShape::Box { height_m, .. } => *height_m = new_height,
+            ChamberShape::Frustum { height_m, .. } => *height_m = new_height,
+            ChamberShape::Custom { .. } => {},
         }
     }
 
@@ -129,6 +253,33 @@ impl ChamberShape {
         match self {
             ChamberShape::Cylinder { snow_height_m, .. } => *snow_height_m = new_snow_height,
             ChamberShape::Box { snow_height_m, .. } => *snow_height_m = new_snow_height,
+            ChamberShape::Frustum { snow_height_m, .. } => *snow_height_m = new_snow_height,
+            ChamberShape::Custom { snow_height_m, .. } => *snow_height_m = new_snow_height,
+        }
+    }
+
+    fn dims(&self) -> ShapeDims {
+        match *self {
+            ChamberShape::Cylinder { diameter_m, height_m, .. } => {
+                ShapeDims { diameter: diameter_m, height: height_m, ..Default::default() }
+            },
+            ChamberShape::Box { width_m, length_m, height_m, .. } => ShapeDims {
+                width: width_m,
+                length: length_m,
+                height: height_m,
+                ..Default::default()
+            },
+            ChamberShape::Frustum { bottom_diameter_m, top_diameter_m, height_m, .. } => {
+                ShapeDims {
+                    diameter: bottom_diameter_m,
+                    top_diameter: top_diameter_m,
+                    height: height_m,
+                    ..Default::default()
+                }
+            },
+            ChamberShape::Custom { volume_m3, area_m2, .. } => {
+                ShapeDims { volume: volume_m3, area: area_m2, ..Default::default() }
+            },
         }
     }
 }
@@ -146,6 +297,23 @@ impl fmt::Display for ChamberShape {
                 "Box: L={:.2}m, W={:.2}m, H={:.2}m, S={:.2}m",
                 height_m, length_m, width_m, snow_height_m
             ),
+            ChamberShape::Frustum {
+                bottom_diameter_m,
+                top_diameter_m,
+                height_m,
+                snow_height_m,
+            } => {
+                write!(
+                    f,
+                    "Frustum: H={:.2}m, D bottom={:.2}m, D top={:.2}m, S={:.2}m",
+                    height_m, bottom_diameter_m, top_diameter_m, snow_height_m
+                )
+            },
+            ChamberShape::Custom { volume_m3, area_m2, snow_height_m } => write!(
+                f,
+                "Custom: V={:.4}m³, A={:.4}m², S={:.2}m",
+                volume_m3, area_m2, snow_height_m
+            ),
         }
     }
 }
@@ -167,11 +335,27 @@ pub enum ChamberOrigin {
 pub struct Chamber {
     pub shape: ChamberShape,
     pub origin: ChamberOrigin,
+    /// Height of the collar above the soil surface, the collar has the area
+    /// of the chamber
+    pub collar_height_m: f64,
+    /// Volume outside the chamber, e.g. analyzer cell and tubing
+    pub system_volume_m3: f64,
+    /// Volume taken by vegetation inside the chamber
+    pub vegetation_volume_m3: f64,
+    /// Volume measured directly, used instead of the one from the geometry
+    pub measured_volume_m3: Option<f64>,
 }
 
 impl Default for Chamber {
     fn default() -> Self {
-        Self { shape: ChamberShape::default(), origin: ChamberOrigin::Default }
+        Self {
+            shape: ChamberShape::default(),
+            origin: ChamberOrigin::Default,
+            collar_height_m: 0.0,
+            system_volume_m3: 0.0,
+            vegetation_volume_m3: 0.0,
+            measured_volume_m3: None,
+        }
     }
 }
 
@@ -196,8 +380,21 @@ impl Chamber {
         self.shape.internal_height()
     }
 
+    /// Headspace volume used for fluxes. Without a measured volume it's the
+    /// chamber and collar above the snow plus the system volume, minus the
+    /// vegetation.
     pub fn adjusted_volume(&self) -> f64 {
-        self.shape.adjusted_volume()
+        self.measured_volume_m3.unwrap_or_else(|| {
+            // snow fills the collar before the chamber
+            let snow_height = self.shape.snow_height_m();
+            let mut shape = self.shape;
+            shape.set_snow_height((snow_height - self.collar_height_m).max(0.0));
+            let collar_volume = self.area_m2() * (self.collar_height_m - snow_height).max(0.0);
+
+            (shape.adjusted_volume() + collar_volume + self.system_volume_m3
+                - self.vegetation_volume_m3)
+                .max(0.0)
+        })
     }
 
     pub fn kind(&self) -> ChamberShapeType {
@@ -219,63 +416,98 @@ impl fmt::Display for Chamber {
             ChamberOrigin::Default => "default",
             ChamberOrigin::Raw => "raw",
         };
-        write!(f, "{} (origin: {})", self.shape, origin_str)
+        write!(f, "{} (origin: {})", self.shape, origin_str)?;
+        if self.collar_height_m != 0.0 {
+            write!(f, ", collar H={:.2}m", self.collar_height_m)?;
+        }
+        if self.system_volume_m3 != 0.0 {
+            write!(f, ", system V={:.4}m³", self.system_volume_m3)?;
+        }
+        if self.vegetation_volume_m3 != 0.0 {
+            write!(f, ", vegetation V={:.4}m³", self.vegetation_volume_m3)?;
+        }
+        if let Some(v) = self.measured_volume_m3 {
+            write!(f, ", measured V={:.4}m³", v)?;
+        }
+        Ok(())
+    }
+}
+
+/// A chamber geometry and the period it's valid for. `valid_from` is
+/// inclusive, `valid_to` exclusive, None leaves that side open.
+#[derive(Debug, Clone, Copy)]
+pub struct ChamberPeriod {
+    pub valid_from: Option<i64>,
+    pub valid_to: Option<i64>,
+    pub chamber: Chamber,
+}
+
+impl ChamberPeriod {
+    pub fn contains(&self, time: i64) -> bool {
+        self.valid_from.is_none_or(|from| from <= time) && self.valid_to.is_none_or(|to| time < to)
+    }
+}
+
+/// Chamber geometries keyed by chamber id, each with the periods it was used
+#[derive(Debug, Default, Clone)]
+pub struct ChamberData {
+    pub chambers: HashMap<String, Vec<ChamberPeriod>>,
+}
+
+impl ChamberData {
+    pub fn is_empty(&self) -> bool {
+        self.chambers.is_empty()
+    }
+
+    /// Geometry of `chamber_id` valid at `time`. If periods overlap, the one
+    /// that started last wins.
+    pub fn get(&self, chamber_id: &str, time: i64) -> Option<&Chamber> {
+        self.chambers
+            .get(chamber_id)?
+            .iter()
+            .filter(|p| p.contains(time))
+            .max_by_key(|p| p.valid_from.unwrap_or(i64::MIN))
+            .map(|p| &p.chamber)
+    }
+
+    pub fn insert(&mut self, chamber_id: String, period: ChamberPeriod) {
+        self.chambers.entry(chamber_id).or_default().push(period);
     }
 }
 
 /// Query chambers from the DB for a project.
 /// All returned chambers have origin = `ChamberOrigin::Db`.
-pub fn query_chambers(conn: &Connection, project: i64) -> Result<HashMap<String, Chamber>> {
+pub fn query_chambers(conn: &Connection, project: i64) -> Result<ChamberData> {
     println!("Querying chamber data");
-    let mut chamber_map: HashMap<String, Chamber> = HashMap::new();
+    let mut chamber_data = ChamberData::default();
 
     let mut stmt = conn.prepare(
-        "SELECT chamber_id, shape, diameter, width, length, height
+        "SELECT *
          FROM chamber_metadata
-         WHERE project_link = ?1",
+         WHERE project_link = ?1
+         ORDER BY chamber_id, valid_from",
     )?;
 
     let rows = stmt.query_map(params![project], |row| {
         let chamber_id: String = row.get("chamber_id")?;
-        let shape_str: String = row.get("shape")?;
-        let diameter_m: f64 = row.get("diameter")?;
-        let width_m: f64 = row.get("width")?;
-        let length_m: f64 = row.get("length")?;
-        let height_m: f64 = row.get("height")?;
-        let snow_height_m = 0.0;
-
-        let shape_type = shape_str.parse::<ChamberShapeType>().map_err(|_| {
-            rusqlite::Error::FromSqlConversionFailure(
-                0,
-                rusqlite::types::Type::Text,
-                Box::new(std::fmt::Error),
-            )
-        })?;
-
-        let shape = match shape_type {
-            ChamberShapeType::Box => {
-                ChamberShape::Box { width_m, length_m, height_m, snow_height_m }
-            },
-            ChamberShapeType::Cylinder => {
-                ChamberShape::Cylinder { diameter_m, height_m, snow_height_m }
-            },
-        };
-
-        Ok((chamber_id, Chamber { shape, origin: ChamberOrigin::Raw }))
+        let valid_from: Option<i64> = row.get("valid_from")?;
+        let valid_to: Option<i64> = row.get("valid_to")?;
+        let chamber = chamber_from_row(row, "shape")?;
+        Ok((chamber_id, ChamberPeriod { valid_from, valid_to, chamber }))
     })?;
 
     for row in rows {
-        let (chamber_id, chamber) = row?;
-        chamber_map.insert(chamber_id, chamber);
+        let (chamber_id, period) = row?;
+        chamber_data.insert(chamber_id, period);
     }
 
-    Ok(chamber_map)
+    Ok(chamber_data)
 }
 
 pub async fn query_chamber_async(
     conn: Arc<Mutex<Connection>>, // Arc<Mutex> for shared async access
     project: Project,
-) -> Result<HashMap<String, Chamber>> {
+) -> Result<ChamberData> {
     let result = task::spawn_blocking(move || {
         let conn = conn.lock().unwrap();
         query_chambers(&conn, project.id.unwrap())
@@ -291,33 +523,46 @@ pub async fn query_chamber_async(
     }
 }
 
-/// Insert chamber metadata into DB from a map.
+/// Insert chamber metadata into DB, one row per validity period.
 /// Geometry comes from `chamber.shape`; origin is *not* stored in DB.
 pub fn insert_chamber_metadata(
     tx: &Connection,
-    chambers: &HashMap<String, Chamber>,
+    chambers: &ChamberData,
     project_id: &i64,
     file_id: &i64,
 ) -> Result<(usize, usize)> {
     let mut inserts = 0;
     let mut skips = 0;
-    for (chamber_id, chamber) in chambers {
-        let shape = &chamber.shape;
-
-        let (shape_str, diameter, width, length, height) = match shape {
-            ChamberShape::Cylinder { diameter_m, height_m, .. } => {
-                ("cylinder", *diameter_m, 0.0, 0.0, *height_m)
-            },
-            ChamberShape::Box { width_m, length_m, height_m, .. } => {
-                ("box", 0.0, *width_m, *length_m, *height_m)
-            },
-        };
+    let periods =
+        chambers.chambers.iter().flat_map(|(id, periods)| periods.iter().map(move |p| (id, p)));
+    for (chamber_id, period) in periods {
+        let chamber = &period.chamber;
+        let dims = chamber.shape.dims();
 
         let affected = tx.execute(
             "INSERT OR IGNORE INTO chamber_metadata (
-                chamber_id, shape, diameter, width, length, height, project_link, file_link
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![chamber_id, shape_str, diameter, width, length, height, project_id, file_id],
+                chamber_id, shape, diameter, top_diameter, width, length, height, volume, area,
+                collar_height, system_volume, vegetation_volume, valid_from, valid_to,
+                project_link, file_link
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                chamber_id,
+                chamber.kind().as_str(),
+                dims.diameter,
+                dims.top_diameter,
+                dims.width,
+                dims.length,
+                dims.height,
+                dims.volume,
+                dims.area,
+                chamber.collar_height_m,
+                chamber.system_volume_m3,
+                chamber.vegetation_volume_m3,
+                period.valid_from,
+                period.valid_to,
+                project_id,
+                file_id
+            ],
         )?;
         if affected > 0 {
             inserts += 1
@@ -334,77 +579,108 @@ impl TryFrom<&Row<'_>> for Chamber {
     type Error = rusqlite::Error;
 
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
-        let shape_type_str: String = row.get("shape_type")?;
-        let shape = match shape_type_str.to_lowercase().as_str() {
-            "box" => ChamberShape::Box {
-                width_m: row.get("width")?,
-                length_m: row.get("length")?,
-                height_m: row.get("height")?,
-                snow_height_m: 0.0,
-            },
-            "cylinder" => ChamberShape::Cylinder {
-                diameter_m: row.get("diameter")?,
-                height_m: row.get("height")?,
-                snow_height_m: 0.0,
-            },
-            _ => {
-                return Err(rusqlite::Error::FromSqlConversionFailure(
-                    0,
-                    rusqlite::types::Type::Text,
-                    Box::new(rusqlite::types::FromSqlError::Other(
-                        "Unknown chamber shape type".into(),
-                    )),
-                ))
-            },
-        };
-
-        Ok(Chamber { shape, origin: ChamberOrigin::Raw })
+        chamber_from_row(row, "shape_type")
     }
 }
 
-/// Read chamber metadata from CSV.
+/// Chamber from a chamber_metadata row, the shape type read from `shape_col`.
+/// Columns added after the table was created can be NULL.
+fn chamber_from_row(row: &Row, shape_col: &str) -> Result<Chamber> {
+    let shape_str: String = row.get(shape_col)?;
+    let shape_type = shape_str.parse::<ChamberShapeType>().map_err(|_| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            Box::new(rusqlite::types::FromSqlError::Other("Unknown chamber shape type".into())),
+        )
+    })?;
+    let get = |col: &str| -> Result<f64> { Ok(row.get::<_, Option<f64>>(col)?.unwrap_or(0.0)) };
+
+    let dims = ShapeDims {
+        diameter: get("diameter")?,
+        top_diameter: get("top_diameter")?,
+        height: get("height")?,
+        width: get("width")?,
+        length: get("length")?,
+        volume: get("volume")?,
+        area: get("area")?,
+    };
+
+    Ok(Chamber {
+        shape: shape_type.build(dims),
+        origin: ChamberOrigin::Raw,
+        collar_height_m: get("collar_height")?,
+        system_volume_m3: get("system_volume")?,
+        vegetation_volume_m3: get("vegetation_volume")?,
+        measured_volume_m3: None,
+    })
+}
+
+/// Read chamber metadata from CSV. The optional valid_from and valid_to
+/// columns limit the period a geometry is used for, the columns after them
+/// hold the frustum and custom dimensions and the extra volume terms.
 /// All chambers loaded here have origin = `ChamberOrigin::Csv`.
 pub fn read_chamber_metadata<P: AsRef<Path>>(
     path: P,
-) -> Result<HashMap<String, Chamber>, Box<dyn Error>> {
+    tz: Tz,
+) -> Result<ChamberData, Box<dyn Error>> {
     let content = ensure_utf8(&path)?;
     let mut rdr =
         csv::ReaderBuilder::new().has_headers(true).flexible(true).from_reader(content.as_bytes());
 
-    let mut chambers = HashMap::new();
+    let mut chambers = ChamberData::default();
 
     for (i, result) in rdr.records().enumerate() {
         let record = result?;
 
         let chamber_id = record.get(0).ok_or("Missing chamber_id")?.to_string();
         let shape = record.get(1).ok_or("Missing shape")?.to_lowercase();
-        let diameter = parse_f64_field(&record, 2)?;
-        let height = parse_f64_field(&record, 3)?;
-        let width = parse_f64_field(&record, 4)?;
-        let length = parse_f64_field(&record, 5)?;
-
-        let shape_val = match shape.as_str() {
-            "cylinder" => ChamberShape::Cylinder {
-                diameter_m: diameter,
-                height_m: height,
-                snow_height_m: 0.0,
-            },
-            "box" => ChamberShape::Box {
-                width_m: width,
-                length_m: length,
-                height_m: height,
-                snow_height_m: 0.0,
-            },
-            _ => {
-                eprintln!("Unknown shape '{}', skipping chamber {}", shape, chamber_id);
-                continue;
-            },
+        let dims = ShapeDims {
+            diameter: parse_f64_field(&record, 2)?,
+            height: parse_f64_field(&record, 3)?,
+            width: parse_f64_field(&record, 4)?,
+            length: parse_f64_field(&record, 5)?,
+            top_diameter: parse_f64_field(&record, 8)?,
+            volume: parse_f64_field(&record, 9)?,
+            area: parse_f64_field(&record, 10)?,
+        };
+        let valid_from = parse_datetime_field(&record, 6, tz)?;
+        let valid_to = parse_datetime_field(&record, 7, tz)?;
+        if let (Some(from), Some(to)) = (valid_from, valid_to) {
+            if to <= from {
+                return Err(format!(
+                    "valid_to has to be after valid_from for chamber {} at row {}",
+                    chamber_id,
+                    i + 2
+                )
+                .into());
+            }
+        }
+
+        let Ok(shape_type) = shape.parse::<ChamberShapeType>() else {
+            eprintln!("Unknown shape '{}', skipping chamber {}", shape, chamber_id);
+            continue;
         };
+        if shape_type == ChamberShapeType::Custom && (dims.volume <= 0.0 || dims.area <= 0.0) {
+            return Err(format!(
+                "Custom chamber {} needs a positive volume and area at row {}",
+                chamber_id,
+                i + 2
+            )
+            .into());
+        }
 
-        let chamber = Chamber { shape: shape_val, origin: ChamberOrigin::Raw };
+        let chamber = Chamber {
+            shape: shape_type.build(dims),
+            origin: ChamberOrigin::Raw,
+            collar_height_m: parse_f64_field(&record, 11)?,
+            system_volume_m3: parse_f64_field(&record, 12)?,
+            vegetation_volume_m3: parse_f64_field(&record, 13)?,
+            measured_volume_m3: None,
+        };
 
         println!("{}", chamber);
-        chambers.insert(chamber_id, chamber);
+        chambers.insert(chamber_id, ChamberPeriod { valid_from, valid_to, chamber });
     }
 
     Ok(chambers)
@@ -414,29 +690,40 @@ fn parse_f64_field(record: &csv::StringRecord, idx: usize) -> Result<f64, Box<dy
     Ok(record.get(idx).filter(|s| !s.trim().is_empty()).unwrap_or("0").parse()?)
 }
 
+fn parse_datetime_field(
+    record: &csv::StringRecord,
+    idx: usize,
+    tz: Tz,
+) -> Result<Option<i64>, Box<dyn Error>> {
+    record
+        .get(idx)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| parse_datetime(s, tz))
+        .transpose()
+}
+
 /// Upload chamber metadata from selected CSV paths, insert into DB,
 /// and send progress events. Chambers from CSV are marked as `Csv` origin.
 pub fn upload_chamber_metadata_async(
     selected_paths: Vec<PathBuf>,
     conn: &mut Connection,
     project: &Project,
-    _tz: Tz,
+    tz: Tz,
     progress_sender: mpsc::UnboundedSender<ProcessEvent>,
 ) {
-    for path in &selected_paths {
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::chamber_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
         let project_id = project.id.unwrap();
 
-        let file_name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => {
-                eprintln!("Skipping path with invalid filename: {:?}", path);
-                let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::chamber_fail(
-                    path.to_string_lossy().to_string(),
-                    "Invalid file name (non-UTF8)".to_string(),
-                )));
-                continue;
-            },
-        };
+        let file_name = input.name.as_str();
 
         let tx = match conn.transaction() {
             Ok(tx) => tx,
@@ -467,7 +754,7 @@ pub fn upload_chamber_metadata_async(
             },
         };
 
-        match read_chamber_metadata(path) {
+        match read_chamber_metadata(path, tz) {
             Ok(chambers) => {
                 match insert_chamber_metadata(&tx, &chambers, &project.id.unwrap(), &file_id) {
                     Ok((inserts, skips)) => {
@@ -492,7 +779,7 @@ pub fn upload_chamber_metadata_async(
             },
             Err(e) => {
                 let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::chamber_fail(
-                    path.to_string_lossy().to_string(),
+                    input.label.clone(),
                     e.to_string(),
                 )));
             },
@@ -501,3 +788,78 @@ pub fn upload_chamber_metadata_async(
 
     let _ = progress_sender.send(ProcessEvent::Done(Ok(())));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn period(valid_from: Option<i64>, valid_to: Option<i64>, height_m: f64) -> ChamberPeriod {
+        let mut chamber = Chamber { origin: ChamberOrigin::Raw, ..Default::default() };
+        chamber.set_height(height_m);
+        ChamberPeriod { valid_from, valid_to, chamber }
+    }
+
+    #[test]
+    fn geometry_valid_at_cycle_start() {
+        let mut data = ChamberData::default();
+        data.insert("1".to_owned(), period(None, Some(1000), 0.3));
+        data.insert("1".to_owned(), period(Some(1000), None, 0.5));
+        data.insert("1".to_owned(), period(Some(2000), Some(3000), 0.7));
+
+        let height = |t| data.get("1", t).map(|c| c.internal_height());
+        assert_eq!(height(999), Some(0.3));
+        assert_eq!(height(1000), Some(0.5));
+        assert_eq!(height(2500), Some(0.7));
+        assert_eq!(height(3000), Some(0.5));
+        assert!(data.get("2", 0).is_none());
+    }
+
+    #[test]
+    fn frustum_custom_and_volume_terms() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        // a frustum with equal diameters is a cylinder
+        let mut frustum = ChamberShape::Frustum {
+            bottom_diameter_m: 0.4,
+            top_diameter_m: 0.4,
+            height_m: 0.5,
+            snow_height_m: 0.1,
+        };
+        let cylinder =
+            ChamberShape::Cylinder { diameter_m: 0.4, height_m: 0.5, snow_height_m: 0.1 };
+        assert!(close(frustum.adjusted_volume(), cylinder.adjusted_volume()));
+        assert!(close(frustum.area_m2(), cylinder.area_m2()));
+
+        // snow halfway up a cone-like frustum leaves the top part
+        frustum = ChamberShape::Frustum {
+            bottom_diameter_m: 0.4,
+            top_diameter_m: 0.2,
+            height_m: 1.0,
+            snow_height_m: 0.5,
+        };
+        let pi = std::f64::consts::PI;
+        assert!(close(frustum.adjusted_volume(), pi / 3.0 * 0.5 * (0.0225 + 0.015 + 0.01)));
+
+        let custom = ChamberShape::Custom { volume_m3: 0.08, area_m2: 0.2, snow_height_m: 0.1 };
+        assert!(close(custom.height_m(), 0.4));
+        assert!(close(custom.adjusted_volume(), 0.06));
+
+        let mut chamber = Chamber {
+            shape: ChamberShape::Box {
+                width_m: 1.0,
+                length_m: 1.0,
+                height_m: 0.5,
+                snow_height_m: 0.0,
+            },
+            collar_height_m: 0.1,
+            system_volume_m3: 0.002,
+            vegetation_volume_m3: 0.01,
+            ..Default::default()
+        };
+        assert!(close(chamber.adjusted_volume(), 0.5 + 0.1 + 0.002 - 0.01));
+        // snow fills the collar first
+        chamber.set_snow_height(0.15);
+        assert!(close(chamber.adjusted_volume(), 0.45 + 0.002 - 0.01));
+        assert!(close(chamber.area_m2(), 1.0));
+    }
+}
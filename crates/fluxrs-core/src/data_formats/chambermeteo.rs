@@ -0,0 +1,326 @@
+use crate::archive::expand_inputs;
+use crate::datatype::DataType;
+use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
+use crate::project::Project;
+use crate::utils::{
+    ensure_utf8, get_or_insert_data_file, parse_datetime, touch_if_exists_updated, DataFileError,
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, Result};
+use tokio::sync::mpsc;
+use tokio::task;
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Temperature and pressure logged inside one chamber, `datetime` in seconds
+#[derive(Debug, Default, Clone)]
+pub struct ChamberMeteoSeries {
+    pub datetime: Vec<i64>,
+    pub temperature: Vec<Option<f64>>,
+    pub pressure: Vec<Option<f64>>,
+}
+
+impl ChamberMeteoSeries {
+    pub fn is_empty(&self) -> bool {
+        self.datetime.is_empty()
+    }
+
+    /// Samples between `start` and `end`, inclusive. `datetime` has to be sorted.
+    pub fn window(&self, start: i64, end: i64) -> ChamberMeteoSeries {
+        let from = self.datetime.partition_point(|&t| t < start);
+        let to = self.datetime.partition_point(|&t| t <= end);
+        ChamberMeteoSeries {
+            datetime: self.datetime[from..to].to_vec(),
+            temperature: self.temperature[from..to].to_vec(),
+            pressure: self.pressure[from..to].to_vec(),
+        }
+    }
+
+    /// Mean temperature of the samples between `start` and `end`, None if
+    /// there are none with a value
+    pub fn mean_temperature(&self, start: f64, end: f64) -> Option<f64> {
+        self.mean(&self.temperature, start, end)
+    }
+
+    /// Mean pressure of the samples between `start` and `end`, None if there
+    /// are none with a value
+    pub fn mean_pressure(&self, start: f64, end: f64) -> Option<f64> {
+        self.mean(&self.pressure, start, end)
+    }
+
+    fn mean(&self, values: &[Option<f64>], start: f64, end: f64) -> Option<f64> {
+        let (sum, n) = self
+            .datetime
+            .iter()
+            .zip(values)
+            .filter(|(t, _)| (start..=end).contains(&(**t as f64)))
+            .filter_map(|(_, v)| *v)
+            .fold((0.0, 0), |(sum, n), v| (sum + v, n + 1));
+        (n > 0).then(|| sum / n as f64)
+    }
+
+    fn push(&mut self, datetime: i64, temperature: Option<f64>, pressure: Option<f64>) {
+        self.datetime.push(datetime);
+        self.temperature.push(temperature);
+        self.pressure.push(pressure);
+    }
+}
+
+/// Chamber meteo series keyed by chamber id
+#[derive(Debug, Default, Clone)]
+pub struct ChamberMeteoData {
+    pub chambers: HashMap<String, ChamberMeteoSeries>,
+}
+
+impl ChamberMeteoData {
+    /// Samples of `chamber_id` between `start` and `end`, empty if the
+    /// chamber has none
+    pub fn window(&self, chamber_id: &str, start: i64, end: i64) -> ChamberMeteoSeries {
+        self.chambers.get(chamber_id).map(|s| s.window(start, end)).unwrap_or_default()
+    }
+}
+
+pub fn insert_chamber_meteo_data(
+    tx: &Connection,
+    file_id: &i64,
+    project_id: &i64,
+    data: &ChamberMeteoData,
+) -> Result<(usize, usize)> {
+    let mut inserted = 0;
+    let mut skipped = 0;
+
+    let mut stmt = tx.prepare(
+        "INSERT OR IGNORE INTO chamber_meteo
+            (project_link, datetime, chamber_id, temperature, pressure, file_link)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+
+    for (chamber_id, series) in &data.chambers {
+        for i in 0..series.datetime.len() {
+            let affected = stmt.execute(params![
+                project_id,
+                series.datetime[i],
+                chamber_id,
+                series.temperature[i],
+                series.pressure[i],
+                file_id
+            ])?;
+            if affected > 0 {
+                inserted += 1;
+            } else {
+                skipped += 1
+            }
+        }
+    }
+
+    Ok((inserted, skipped))
+}
+
+pub fn query_chamber_meteo(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project_id: i64,
+) -> Result<ChamberMeteoData> {
+    println!("Querying chamber meteo data");
+
+    let mut stmt = conn.prepare(
+        "SELECT datetime, chamber_id, temperature, pressure
+         FROM chamber_meteo
+         WHERE datetime BETWEEN ?1 AND ?2
+           AND project_link = ?3
+         ORDER BY chamber_id, datetime",
+    )?;
+
+    // cycles are at most a day long, same margin as the meteo query
+    let rows = stmt.query_map(
+        params![start.timestamp() - 86400, end.timestamp() + 86400, project_id],
+        |row| {
+            let datetime: i64 = row.get(0)?;
+            let chamber_id: String = row.get(1)?;
+            let temp: Option<f64> = row.get(2)?;
+            let press: Option<f64> = row.get(3)?;
+            Ok((datetime, chamber_id, temp, press))
+        },
+    )?;
+
+    let mut data = ChamberMeteoData::default();
+    for row in rows {
+        let (datetime, chamber_id, temp, press) = row?;
+        data.chambers.entry(chamber_id).or_default().push(datetime, temp, press);
+    }
+
+    Ok(data)
+}
+
+pub async fn query_chamber_meteo_async(
+    conn: Arc<Mutex<Connection>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project: Project,
+) -> Result<ChamberMeteoData> {
+    let result = task::spawn_blocking(move || {
+        let conn = conn.lock().unwrap();
+        query_chamber_meteo(&conn, start, end, project.id.unwrap())
+    })
+    .await;
+
+    match result {
+        Ok(inner) => inner,
+        Err(_) => Err(rusqlite::Error::ExecuteReturnedResults),
+    }
+}
+
+fn parse_optional(field: Option<&str>) -> Result<Option<f64>, Box<dyn Error>> {
+    match field.map(str::trim) {
+        Some(s) if !s.is_empty() => Ok(Some(s.parse()?)),
+        _ => Ok(None),
+    }
+}
+
+/// Reads `datetime,chamber_id,temperature,pressure` rows, either value may be
+/// empty
+pub fn read_chamber_meteo_csv<P: AsRef<Path>>(
+    file_path: P,
+    tz: Tz,
+) -> Result<ChamberMeteoData, Box<dyn Error>> {
+    let content = ensure_utf8(&file_path)?;
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(content.as_bytes());
+
+    let mut data = ChamberMeteoData::default();
+    for result in rdr.records() {
+        let record = result?;
+        let datetime_str = record.get(0).ok_or("Missing datetime field")?;
+        let timestamp = parse_datetime(datetime_str, tz)?;
+        let chamber_id = record.get(1).map(str::trim).unwrap_or("");
+        if chamber_id.is_empty() {
+            return Err(format!("Missing chamber_id at {}", datetime_str).into());
+        }
+        let temperature = parse_optional(record.get(2))?;
+        let pressure = parse_optional(record.get(3))?;
+
+        data.chambers.entry(chamber_id.to_owned()).or_default().push(
+            timestamp,
+            temperature,
+            pressure,
+        );
+    }
+
+    Ok(data)
+}
+
+pub fn upload_chamber_meteo_data_async(
+    selected_paths: Vec<PathBuf>,
+    conn: &mut Connection,
+    project: &Project,
+    tz: Tz,
+    progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+) {
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::chamber_meteo_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
+        let project_id = project.id.unwrap();
+
+        let file_name = input.name.as_str();
+
+        let tx = match conn.transaction() {
+            Ok(tx) => tx,
+            Err(e) => {
+                eprintln!("Failed to start transaction: {}", e);
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "Could not start transaction for '{}': {}",
+                    file_name, e
+                ))));
+                continue;
+            },
+        };
+
+        let mut file_exists: Option<i64> = None;
+        let file_id =
+            match get_or_insert_data_file(&tx, DataType::ChamberMeteo, file_name, project_id) {
+                Ok(id) => id,
+                Err(DataFileError::FileAlreadyExists(id)) => {
+                    file_exists = Some(id);
+                    id
+                },
+                Err(e) => {
+                    eprintln!("Failed to insert/find data file '{}': {}", file_name, e);
+                    let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                        "File '{}' skipped: {}",
+                        file_name, e
+                    ))));
+                    continue;
+                },
+            };
+
+        match read_chamber_meteo_csv(path, tz) {
+            Ok(res) => match insert_chamber_meteo_data(&tx, &file_id, &project_id, &res) {
+                Ok((inserts, skips)) => {
+                    touch_if_exists_updated(file_exists, inserts, &tx);
+
+                    let _ = progress_sender.send(ProcessEvent::Insert(
+                        InsertEvent::chamber_meteo_okskip(inserts, skips),
+                    ));
+                    if let Err(e) = tx.commit() {
+                        eprintln!("Failed to commit transaction for '{}': {}", file_name, e);
+                        let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(
+                            format!("Commit failed for file '{}': {}", file_name, e),
+                        )));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    let msg = format!("Failed to insert chamber meteo data to db. Error {}", e);
+                    let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(msg)));
+                },
+            },
+            Err(e) => {
+                let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::chamber_meteo_fail(
+                    input.label.clone(),
+                    e.to_string(),
+                )));
+            },
+        }
+
+        let _ = progress_sender.send(ProcessEvent::Done(Ok(())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series() -> ChamberMeteoSeries {
+        let mut s = ChamberMeteoSeries::default();
+        s.push(0, Some(10.0), Some(990.0));
+        s.push(60, Some(20.0), None);
+        s.push(120, None, None);
+        s.push(180, Some(30.0), Some(1000.0));
+        s
+    }
+
+    #[test]
+    fn means_over_window() {
+        let s = series();
+        assert_eq!(s.mean_temperature(0.0, 180.0), Some(20.0));
+        assert_eq!(s.mean_temperature(30.0, 150.0), Some(20.0));
+        assert_eq!(s.mean_pressure(30.0, 150.0), None);
+        assert_eq!(s.mean_pressure(0.0, 180.0), Some(995.0));
+        assert_eq!(s.mean_temperature(200.0, 300.0), None);
+
+        let w = s.window(60, 120);
+        assert_eq!(w.datetime, vec![60, 120]);
+        assert_eq!(w.temperature, vec![Some(20.0), None]);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::archive::expand_inputs;
+use crate::datatype::DataType;
+use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
+use crate::project::Project;
+use crate::utils::get_or_insert_data_file;
+use crate::utils::{ensure_utf8, parse_datetime, touch_if_exists_updated, DataFileError};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, Result};
+use std::error::Error;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task;
+
+/// A value logged per chamber at irregular times, eg. chamber height or snow
+/// depth. Each kind has its own table with
+/// `chamber_id, datetime, <column>, project_link, file_link` columns and is
+/// uploaded from `datetime,chamber_id,<value>` csv files.
+pub struct SeriesKind {
+    pub data_type: DataType,
+    pub table: &'static str,
+    pub column: &'static str,
+    /// name of the value in messages
+    pub name: &'static str,
+    /// checks a value read from a file and converts it to the stored unit
+    pub parse: fn(f64) -> Result<f64, &'static str>,
+}
+
+/// Chamber heights, in m
+pub const HEIGHT: SeriesKind = SeriesKind {
+    data_type: DataType::Height,
+    table: "height",
+    column: "height",
+    name: "height",
+    parse: Ok,
+};
+
+/// Chamber volumes measured directly, e.g. by tracer injection, in m³
+pub const VOLUME: SeriesKind = SeriesKind {
+    data_type: DataType::Volume,
+    table: "volume",
+    column: "volume",
+    name: "volume",
+    parse: |v| if v > 0.0 { Ok(v) } else { Err("must be positive") },
+};
+
+/// Snow depths inside chambers, in m. Files have them in cm like the manual
+/// cycle files.
+pub const SNOW: SeriesKind = SeriesKind {
+    data_type: DataType::Snow,
+    table: "snow",
+    column: "snow_depth",
+    name: "snow depth",
+    parse: |cm| if cm >= 0.0 { Ok(cm / 100.) } else { Err("can't be negative") },
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct ChamberSeries {
+    pub datetime: Vec<i64>,
+    pub chamber_id: Vec<String>,
+    pub value: Vec<f64>,
+}
+
+pub type HeightData = ChamberSeries;
+pub type VolumeData = ChamberSeries;
+pub type SnowData = ChamberSeries;
+
+impl ChamberSeries {
+    /// the latest value of a chamber at or before a given timestamp
+    pub fn nearest_previous(&self, target_datetime: i64, target_chamber_id: &str) -> Option<f64> {
+        let mut nearest_index: Option<usize> = None;
+        let mut smallest_time_diff = i64::MAX;
+
+        for (i, (dt, chamber)) in self.datetime.iter().zip(&self.chamber_id).enumerate() {
+            if chamber == target_chamber_id && *dt <= target_datetime {
+                let time_diff = target_datetime - dt; // guaranteed to be >= 0
+                if time_diff < smallest_time_diff {
+                    smallest_time_diff = time_diff;
+                    nearest_index = Some(i);
+                }
+            }
+        }
+
+        nearest_index.map(|i| self.value[i])
+    }
+
+    fn push(&mut self, datetime: i64, chamber_id: &str, value: f64) {
+        self.datetime.push(datetime);
+        self.chamber_id.push(chamber_id.to_owned());
+        self.value.push(value);
+    }
+}
+
+pub fn insert_series(
+    tx: &Connection,
+    kind: &SeriesKind,
+    data: &ChamberSeries,
+    file_id: &i64,
+    project_id: &i64,
+) -> Result<(usize, usize)> {
+    let mut inserts = 0;
+    let mut skips = 0;
+    if data.datetime.len() != data.chamber_id.len() || data.datetime.len() != data.value.len() {
+        return Err(rusqlite::Error::InvalidQuery); // Ensure all vectors have the same length
+    }
+
+    let mut stmt = tx.prepare(&format!(
+        "INSERT OR IGNORE INTO {} (chamber_id, project_link, datetime, {}, file_link)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        kind.table, kind.column
+    ))?;
+
+    for i in 0..data.datetime.len() {
+        let affected = stmt.execute(params![
+            &data.chamber_id[i],
+            project_id,
+            data.datetime[i],
+            data.value[i],
+            file_id,
+        ])?;
+        if affected > 0 {
+            inserts += 1
+        } else {
+            skips += 1
+        }
+    }
+    Ok((inserts, skips))
+}
+
+/// Latest value of `chamber_id` at or before `time`, None if there is none
+pub fn get_previous_value(
+    conn: &Connection,
+    kind: &SeriesKind,
+    project_id: i64,
+    chamber_id: &str,
+    time: i64,
+) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
+             FROM {}
+             WHERE project_link = ?1
+             AND chamber_id = ?2
+             AND datetime <= ?3
+             ORDER BY datetime DESC
+             LIMIT 1",
+        kind.column, kind.table
+    ))?;
+
+    match stmt.query_row(params![project_id, chamber_id, time], |row| row.get(0)) {
+        Ok(value) => Ok(Some(value)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Values between `start` and `end`, widened by a year so that cycles at the
+/// edges still find the previous value
+pub fn query_series(
+    conn: &Connection,
+    kind: &SeriesKind,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project_id: i64,
+) -> Result<ChamberSeries> {
+    println!("Querying {} data", kind.name);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT datetime, {}, chamber_id
+             FROM {}
+             WHERE datetime BETWEEN ?1 AND ?2
+             and project_link = ?3
+             ORDER BY datetime",
+        kind.column, kind.table
+    ))?;
+
+    let rows = stmt.query_map(
+        params![start.timestamp() - (86400 * 365), end.timestamp() + (86400 * 365), project_id],
+        |row| {
+            let datetime_unix: i64 = row.get(0)?;
+            let value: f64 = row.get(1)?;
+            let chamber_id: String = row.get(2)?;
+
+            Ok((datetime_unix, value, chamber_id))
+        },
+    )?;
+
+    let mut series = ChamberSeries::default();
+    for row in rows {
+        let (time, value, chamber_id) = row?;
+        series.push(time, &chamber_id, value);
+    }
+    Ok(series)
+}
+
+pub async fn query_series_async(
+    conn: Arc<Mutex<Connection>>,
+    kind: &'static SeriesKind,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    project: Project,
+) -> Result<ChamberSeries> {
+    let result = task::spawn_blocking(move || {
+        let conn = conn.lock().unwrap();
+        query_series(&conn, kind, start, end, project.id.unwrap())
+    })
+    .await;
+    match result {
+        Ok(inner) => inner,
+        Err(_) => Err(rusqlite::Error::ExecuteReturnedResults),
+    }
+}
+
+/// Reads `datetime,chamber_id,<value>` rows
+pub fn read_series_csv<P: AsRef<Path>>(
+    file_path: P,
+    kind: &SeriesKind,
+    tz: Tz,
+) -> Result<ChamberSeries, Box<dyn Error>> {
+    let content = ensure_utf8(&file_path)?;
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(content.as_bytes());
+
+    let mut series = ChamberSeries::default();
+    for (i, result) in rdr.records().enumerate() {
+        let row = i + 2;
+        let record = result.map_err(|e| format!("CSV read error at row {}: {}", row, e))?;
+
+        let datetime_str =
+            record.get(0).ok_or_else(|| format!("Missing datetime at row {}", row))?.trim();
+        let ch = record.get(1).ok_or_else(|| format!("Missing chamber_id at row {}", row))?.trim();
+        let raw: f64 = record
+            .get(2)
+            .ok_or_else(|| format!("Missing {} at row {}", kind.name, row))?
+            .trim()
+            .parse()
+            .map_err(|e| format!("Invalid {} at row {}: {}", kind.name, row, e))?;
+        let value = (kind.parse)(raw)
+            .map_err(|e| format!("{} {} at row {}", capitalize(kind.name), e, row))?;
+
+        let timestamp = parse_datetime(datetime_str, tz)
+            .map_err(|e| format!("Datetime parse error at row {}: {}", row, e))?;
+
+        series.push(timestamp, ch, value);
+    }
+
+    Ok(series)
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn upload_series_async(
+    kind: &SeriesKind,
+    selected_paths: Vec<PathBuf>,
+    conn: &mut Connection,
+    project: &Project,
+    tz: Tz,
+    progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+) {
+    let read_fail = |file: String, reason: String| {
+        ProcessEvent::Read(ReadEvent::DataFail { kind: kind.data_type, file, reason })
+    };
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(read_fail(path.to_string_lossy().to_string(), e.clone()));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
+        let project_id = project.id.unwrap();
+        let file_name = input.name.as_str();
+        let tx = match conn.transaction() {
+            Ok(tx) => tx,
+            Err(e) => {
+                eprintln!("Failed to start transaction: {}", e);
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "Could not start transaction for '{}': {}",
+                    file_name, e
+                ))));
+                continue;
+            },
+        };
+        let mut file_exists = None;
+        let file_id = match get_or_insert_data_file(&tx, kind.data_type, file_name, project_id) {
+            Ok(id) => id,
+            Err(DataFileError::FileAlreadyExists(id)) => {
+                file_exists = Some(id);
+                id
+            },
+            Err(e) => {
+                eprintln!("Failed to insert/find data file '{}': {}", file_name, e);
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "File '{}' skipped: {}",
+                    file_name, e
+                ))));
+                continue;
+            },
+        };
+
+        match read_series_csv(path, kind, tz) {
+            Ok(res) => match insert_series(&tx, kind, &res, &file_id, &project_id) {
+                Ok((inserts, skips)) => {
+                    touch_if_exists_updated(file_exists, inserts, &tx);
+
+                    let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::DataOkSkip {
+                        kind: kind.data_type,
+                        inserts,
+                        skips,
+                    }));
+
+                    if let Err(e) = tx.commit() {
+                        eprintln!("Failed to commit transaction for '{}': {}", file_name, e);
+                        let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(
+                            format!("Commit failed for file '{}': {}", file_name, e),
+                        )));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    let msg = format!("Failed to insert {} data to db. Error {}", kind.name, e);
+                    let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(msg)));
+                },
+            },
+            Err(e) => {
+                let _ = progress_sender.send(read_fail(input.label.clone(), e.to_string()));
+            },
+        }
+        let _ = progress_sender.send(ProcessEvent::Done(Ok(())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_formats::chamberdata::Chamber;
+
+    #[test]
+    fn nearest_previous_per_chamber() {
+        let data = ChamberSeries {
+            datetime: vec![100, 300, 200],
+            chamber_id: vec!["1".to_owned(), "1".to_owned(), "2".to_owned()],
+            value: vec![0.1, 0.25, 0.4],
+        };
+        assert_eq!(data.nearest_previous(99, "1"), None);
+        assert_eq!(data.nearest_previous(299, "1"), Some(0.1));
+        assert_eq!(data.nearest_previous(300, "1"), Some(0.25));
+        assert_eq!(data.nearest_previous(1000, "2"), Some(0.4));
+
+        let mut chamber = Chamber::default();
+        assert_eq!(chamber.adjusted_volume(), 1.0);
+        chamber.measured_volume_m3 = data.nearest_previous(300, "1");
+        assert_eq!(chamber.adjusted_volume(), 0.25);
+    }
+
+    #[test]
+    fn values_are_checked_and_converted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("series.csv");
+        std::fs::write(&path, "datetime,chamber_id,value\n2024-01-10 12:00:00,1,25\n").unwrap();
+
+        let snow = read_series_csv(&path, &SNOW, Tz::UTC).unwrap();
+        assert_eq!(snow.value, vec![0.25]);
+        assert_eq!(snow.chamber_id, vec!["1".to_owned()]);
+        let height = read_series_csv(&path, &HEIGHT, Tz::UTC).unwrap();
+        assert_eq!(height.value, vec![25.0]);
+
+        std::fs::write(&path, "datetime,chamber_id,value\n2024-01-10 12:00:00,1,0\n").unwrap();
+        let err = read_series_csv(&path, &VOLUME, Tz::UTC).unwrap_err();
+        assert_eq!(err.to_string(), "Volume must be positive at row 2");
+    }
+}
@@ -0,0 +1,189 @@
+use crate::types::FastMap;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+const SECONDS_IN_DAY: i64 = 86_400;
+/// End of the re-run range when no later correction exists, 2100-01-01
+const FAR_FUTURE: i64 = 4_102_444_800;
+
+/// Clock correction of one instrument, valid from `valid_from` until the next
+/// correction of the same instrument.
+///
+/// Instrument timestamps are moved to chamber controller time by adding
+/// `offset_s + drift_s_per_day * days since valid_from`, so an analyzer whose
+/// clock runs 30 s ahead gets an offset of -30.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClockCorrection {
+    pub id: Option<i64>,
+    pub instrument_id: i64,
+    pub valid_from: i64,
+    pub offset_s: f64,
+    pub drift_s_per_day: f64,
+}
+
+impl ClockCorrection {
+    /// Seconds to add to instrument time `t`
+    pub fn shift_at(&self, t: i64) -> f64 {
+        let days = (t - self.valid_from) as f64 / SECONDS_IN_DAY as f64;
+        self.offset_s + self.drift_s_per_day * days
+    }
+}
+
+/// Clock corrections of a project grouped by instrument
+#[derive(Debug, Default, Clone)]
+pub struct ClockCorrections {
+    by_instrument: FastMap<i64, Vec<ClockCorrection>>,
+}
+
+impl ClockCorrections {
+    pub fn new(corrections: Vec<ClockCorrection>) -> Self {
+        let mut by_instrument: FastMap<i64, Vec<ClockCorrection>> = FastMap::default();
+        for c in corrections {
+            by_instrument.entry(c.instrument_id).or_default().push(c);
+        }
+        for list in by_instrument.values_mut() {
+            list.sort_by_key(|c| c.valid_from);
+        }
+        Self { by_instrument }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_instrument.is_empty()
+    }
+
+    fn active(&self, instrument_id: i64, t: i64) -> Option<&ClockCorrection> {
+        let list = self.by_instrument.get(&instrument_id)?;
+        let idx = list.partition_point(|c| c.valid_from <= t);
+        idx.checked_sub(1).map(|i| &list[i])
+    }
+
+    /// Instrument time `t_ms` in milliseconds in chamber controller time
+    pub fn apply(&self, instrument_id: i64, t_ms: i64) -> i64 {
+        let t = t_ms.div_euclid(1000);
+        match self.active(instrument_id, t) {
+            Some(c) => t_ms + (c.shift_at(t) * 1000.0).round() as i64,
+            None => t_ms,
+        }
+    }
+
+    /// Largest shift in whole seconds between `start` and `end`, queries are
+    /// widened by this so that shifted data at the edges isn't lost
+    pub fn max_shift(&self, start: i64, end: i64) -> i64 {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut max: f64 = 0.0;
+        for list in self.by_instrument.values() {
+            for (i, c) in list.iter().enumerate() {
+                let seg_end = list.get(i + 1).map(|n| n.valid_from).unwrap_or(i64::MAX);
+                if seg_end <= start || c.valid_from > end {
+                    continue;
+                }
+                let a = c.shift_at(c.valid_from.max(start)).abs();
+                let b = c.shift_at(seg_end.min(end)).abs();
+                max = max.max(a).max(b);
+            }
+        }
+        max.ceil() as i64 + 1
+    }
+
+    /// Whole UTC days whose data changes when the correction of `instrument_id`
+    /// starting at `valid_from` is added or removed
+    pub fn affected_range(&self, instrument_id: i64, valid_from: i64) -> (i64, i64) {
+        let start = valid_from.div_euclid(SECONDS_IN_DAY) * SECONDS_IN_DAY;
+        let next = self
+            .by_instrument
+            .get(&instrument_id)
+            .and_then(|list| list.iter().find(|c| c.valid_from > valid_from))
+            .map(|c| c.valid_from);
+        let end = match next {
+            Some(t) => (t.div_euclid(SECONDS_IN_DAY) + 1) * SECONDS_IN_DAY,
+            None => FAR_FUTURE,
+        };
+        (start, end)
+    }
+}
+
+/// Insert a correction, an existing one of the same instrument and start time is replaced
+pub fn insert_clock_correction(conn: &Connection, c: &ClockCorrection) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO instrument_clock_corrections
+            (instrument_link, valid_from, offset_s, drift_s_per_day)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(instrument_link, valid_from) DO UPDATE SET
+            offset_s = excluded.offset_s,
+            drift_s_per_day = excluded.drift_s_per_day
+         RETURNING id",
+        params![c.instrument_id, c.valid_from, c.offset_s, c.drift_s_per_day],
+        |row| row.get(0),
+    )
+}
+
+/// Delete a correction, the removed row is returned so the caller can re-run its days
+pub fn delete_clock_correction(conn: &Connection, id: i64) -> Result<Option<ClockCorrection>> {
+    conn.query_row(
+        "DELETE FROM instrument_clock_corrections WHERE id = ?1
+         RETURNING id, instrument_link, valid_from, offset_s, drift_s_per_day",
+        params![id],
+        row_to_correction,
+    )
+    .optional()
+}
+
+pub fn query_clock_corrections(conn: &Connection, project_id: i64) -> Result<Vec<ClockCorrection>> {
+    let mut stmt = conn.prepare(
+        "SELECT c.id, c.instrument_link, c.valid_from, c.offset_s, c.drift_s_per_day
+         FROM instrument_clock_corrections c
+         JOIN instruments i ON c.instrument_link = i.id
+         WHERE i.project_link = ?1
+         ORDER BY c.instrument_link, c.valid_from",
+    )?;
+    let rows = stmt.query_map(params![project_id], row_to_correction)?;
+    rows.collect()
+}
+
+fn row_to_correction(row: &rusqlite::Row) -> Result<ClockCorrection> {
+    Ok(ClockCorrection {
+        id: Some(row.get(0)?),
+        instrument_id: row.get(1)?,
+        valid_from: row.get(2)?,
+        offset_s: row.get(3)?,
+        drift_s_per_day: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correction(valid_from: i64, offset_s: f64, drift_s_per_day: f64) -> ClockCorrection {
+        ClockCorrection { id: None, instrument_id: 1, valid_from, offset_s, drift_s_per_day }
+    }
+
+    #[test]
+    fn offset_and_drift_are_applied() {
+        let day = SECONDS_IN_DAY;
+        let corrections =
+            ClockCorrections::new(vec![correction(10 * day, -5.0, 0.0), correction(0, 30.0, -2.0)]);
+
+        let ms = |s: i64| s * 1000;
+        assert_eq!(corrections.apply(1, -1), -1);
+        assert_eq!(corrections.apply(1, 0), ms(30));
+        assert_eq!(corrections.apply(1, ms(2 * day)), ms(2 * day + 26));
+        assert_eq!(corrections.apply(1, ms(day / 2)), ms(day / 2 + 29));
+        assert_eq!(corrections.apply(1, ms(10 * day) + 250), ms(10 * day - 5) + 250);
+        assert_eq!(corrections.apply(2, ms(2 * day)), ms(2 * day));
+        assert_eq!(corrections.max_shift(0, 20 * day), 31);
+    }
+
+    #[test]
+    fn affected_range_ends_at_next_correction() {
+        let day = SECONDS_IN_DAY;
+        let corrections = ClockCorrections::new(vec![
+            correction(day + 100, 1.0, 0.0),
+            correction(5 * day, 2.0, 0.0),
+        ]);
+
+        assert_eq!(corrections.affected_range(1, day + 100), (day, 6 * day));
+        assert_eq!(corrections.affected_range(1, 5 * day), (5 * day, FAR_FUTURE));
+    }
+}
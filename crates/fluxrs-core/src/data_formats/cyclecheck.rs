@@ -0,0 +1,270 @@
+use crate::data_formats::clockdata::{query_clock_corrections, ClockCorrections};
+use crate::data_formats::timedata::TimeData;
+use crate::types::FastMap;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Longest cycle looked for in the db when checking overlaps, seconds
+const MAX_CYCLE_S: i64 = 86_400;
+
+/// What is done with a cycle file that has problems
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CycleValidation {
+    /// Problems are reported and the cycles inserted
+    #[default]
+    Warn,
+    /// Problems are reported and nothing from the file is inserted
+    Reject,
+}
+
+impl fmt::Display for CycleValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CycleValidation::Warn => write!(f, "warn"),
+            CycleValidation::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+impl FromStr for CycleValidation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "warn" | "warning" => Ok(CycleValidation::Warn),
+            "reject" => Ok(CycleValidation::Reject),
+            other => Err(format!("invalid cycle validation: {other}")),
+        }
+    }
+}
+
+/// Chamber closure of one cycle, times in seconds
+struct Closure {
+    instrument_id: i64,
+    chamber_id: String,
+    start_time: i64,
+    close: i64,
+    open: i64,
+    uploaded: bool,
+}
+
+fn fmt_time(t: i64, tz: &Tz) -> String {
+    DateTime::from_timestamp(t, 0)
+        .map(|dt| dt.with_timezone(tz).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| t.to_string())
+}
+
+/// Problems in parsed cycles before they are inserted:
+/// - offsets that aren't in the order close < open <= end
+/// - the same instrument closed on two chambers at once, in the file or
+///   against cycles already in the db
+/// - no gas data of the instrument while the chamber is closed
+///
+/// Cycles already in the db with the same start time and chamber are the
+/// duplicates `insert_cycles` skips and aren't reported.
+pub fn check_cycles(
+    conn: &Connection,
+    cycles: &TimeData,
+    project_id: i64,
+    tz: &Tz,
+) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut closures = Vec::new();
+    for i in 0..cycles.start_time.len() {
+        let (start, chamber) = (cycles.start_time[i], &cycles.chamber_id[i]);
+        let (close, open, end) =
+            (cycles.close_offset[i], cycles.open_offset[i], cycles.end_offset[i]);
+        if !(0 <= close && close < open && open <= end) {
+            problems.push(format!(
+                "Cycle {chamber} at {}: offsets out of order, close {close} s, open {open} s, end {end} s",
+                fmt_time(start, tz)
+            ));
+            continue;
+        }
+        closures.push(Closure {
+            instrument_id: cycles.instrument_id[i],
+            chamber_id: chamber.clone(),
+            start_time: start,
+            close: start + close,
+            open: start + open,
+            uploaded: true,
+        });
+    }
+
+    problems.extend(overlaps(conn, closures, project_id, tz)?);
+    problems.extend(missing_gas(conn, cycles, project_id, tz)?);
+    Ok(problems)
+}
+
+fn overlaps(
+    conn: &Connection,
+    uploaded: Vec<Closure>,
+    project_id: i64,
+    tz: &Tz,
+) -> Result<Vec<String>> {
+    let mut by_instrument: FastMap<i64, Vec<Closure>> = FastMap::default();
+    for c in uploaded {
+        by_instrument.entry(c.instrument_id).or_default().push(c);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT chamber_id, start_time, close_offset, open_offset FROM cycles
+         WHERE project_link = ?1 AND instrument_link = ?2
+           AND start_time BETWEEN ?3 AND ?4",
+    )?;
+    let mut problems = Vec::new();
+    for (instrument_id, mut closures) in by_instrument {
+        let first = closures.iter().map(|c| c.close).min().unwrap_or_default();
+        let last = closures.iter().map(|c| c.open).max().unwrap_or_default();
+        let rows =
+            stmt.query_map(params![project_id, instrument_id, first - MAX_CYCLE_S, last], |row| {
+                let start: i64 = row.get(1)?;
+                Ok(Closure {
+                    instrument_id,
+                    chamber_id: row.get(0)?,
+                    start_time: start,
+                    close: start + row.get::<_, i64>(2)?,
+                    open: start + row.get::<_, i64>(3)?,
+                    uploaded: false,
+                })
+            })?;
+        for row in rows {
+            let c = row?;
+            let duplicate = closures.iter().any(|u| {
+                u.uploaded && u.start_time == c.start_time && u.chamber_id == c.chamber_id
+            });
+            if !duplicate {
+                closures.push(c);
+            }
+        }
+
+        closures.sort_by_key(|c| (c.close, c.open));
+        // closure reaching furthest so far, a later one starting before its
+        // opening overlaps it
+        let mut reach: Option<usize> = None;
+        for i in 0..closures.len() {
+            if let Some(r) = reach {
+                let (a, b) = (&closures[r], &closures[i]);
+                if b.close < a.open && (a.uploaded || b.uploaded) {
+                    let place = |c: &Closure| if c.uploaded { "" } else { " already in the db" };
+                    problems.push(format!(
+                        "Cycle {} at {} overlaps cycle {} at {}{} measured with the same instrument",
+                        b.chamber_id,
+                        fmt_time(b.start_time, tz),
+                        a.chamber_id,
+                        fmt_time(a.start_time, tz),
+                        place(a),
+                    ));
+                }
+            }
+            if reach.is_none_or(|r| closures[i].open > closures[r].open) {
+                reach = Some(i);
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn missing_gas(
+    conn: &Connection,
+    cycles: &TimeData,
+    project_id: i64,
+    tz: &Tz,
+) -> Result<Vec<String>> {
+    // corrected instrument clocks can move data into the closure, the check
+    // is widened by the largest shift like gas queries are
+    let clock = ClockCorrections::new(query_clock_corrections(conn, project_id)?);
+    let mut stmt = conn.prepare(
+        "SELECT EXISTS (SELECT 1 FROM measurements
+         WHERE project_link = ?1 AND instrument_link = ?2 AND datetime BETWEEN ?3 AND ?4)",
+    )?;
+    let mut problems = Vec::new();
+    for i in 0..cycles.start_time.len() {
+        let close = cycles.start_time[i] + cycles.close_offset[i];
+        let open = cycles.start_time[i] + cycles.open_offset[i];
+        if open <= close {
+            continue;
+        }
+        let margin = clock.max_shift(close, open) * 1000;
+        let found: bool = stmt.query_row(
+            params![
+                project_id,
+                cycles.instrument_id[i],
+                close * 1000 - margin,
+                open * 1000 + margin
+            ],
+            |row| row.get(0),
+        )?;
+        if !found {
+            problems.push(format!(
+                "Cycle {} at {}: no gas data while the chamber is closed",
+                cycles.chamber_id[i],
+                fmt_time(cycles.start_time[i], tz)
+            ));
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded_conn() -> Connection {
+        let conn = crate::db::test_conn();
+        conn.execute_batch(
+            "INSERT INTO data_files (id, file_name, data_type, project_link)
+                VALUES (1, 'cycles.csv', 'cycle', 1);
+            INSERT INTO cycles (chamber_id, start_time, close_offset, open_offset, end_offset,
+                snow_depth, file_link, project_link, instrument_link)
+                VALUES ('A', 1000, 60, 360, 420, 0, 1, 1, 1);
+            INSERT INTO measurements (datetime, gas, file_link, project_link, instrument_link)
+                VALUES (1350000, 0, 1, 1, 1), (3000000, 0, 1, 1, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn cycles(rows: &[(&str, i64, i64, i64, i64)]) -> TimeData {
+        let mut data = TimeData::new();
+        for &(chamber, start, close, open, end) in rows {
+            data.chamber_id.push(chamber.to_owned());
+            data.start_time.push(start);
+            data.close_offset.push(close);
+            data.open_offset.push(open);
+            data.end_offset.push(end);
+            data.snow_depth.push(0.0);
+            data.air_temperature.push(None);
+            data.note.push(None);
+            data.project_id.push(1);
+            data.instrument_id.push(1);
+        }
+        data
+    }
+
+    #[test]
+    fn problems_are_found() {
+        let conn = seeded_conn();
+        let data = cycles(&[
+            // duplicate of the cycle in the db
+            ("A", 1000, 60, 360, 420),
+            // closed while A is
+            ("B", 1300, 0, 120, 180),
+            ("C", 2900, 60, 360, 420),
+            // closes after it opens
+            ("D", 4000, 300, 200, 400),
+            // no gas data
+            ("E", 5000, 60, 360, 420),
+        ]);
+        let problems = check_cycles(&conn, &data, 1, &Tz::UTC).unwrap();
+
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].starts_with("Cycle D"), "{}", problems[0]);
+        assert!(problems[1].starts_with("Cycle B"), "{}", problems[1]);
+        assert!(problems[1].contains("overlaps cycle A"), "{}", problems[1]);
+        assert!(problems[2].starts_with("Cycle E"), "{}", problems[2]);
+    }
+}
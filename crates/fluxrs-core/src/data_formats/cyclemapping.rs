@@ -0,0 +1,468 @@
+use crate::data_formats::chamberseries::SNOW;
+use crate::data_formats::timedata::{TimeData, TimeFormatParser};
+use crate::instruments::instruments::{get_or_insert_instrument, Instrument};
+use crate::processevent::{ProcessEvent, ReadEvent};
+use crate::project::Project;
+use chrono::{Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use csv::StringRecord;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+/// Column mapping of a cycle file, for stations that log chamber timing in
+/// a layout the built-in formats don't know.
+///
+/// Mappings are written by the user as TOML or JSON and picked when cycle
+/// files are uploaded.
+///
+/// ```toml
+/// name = "Station X"
+/// sep = ";"
+/// chamber_col = "plot"
+/// date_col = "date"
+/// date_fmt = "%d.%m.%Y"
+/// start_col = "start"
+/// time_fmt = "%H:%M:%S"
+/// times = "absolute"
+/// close_col = "closed"
+/// open_col = "opened"
+/// end_col = "end"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CycleMapping {
+    /// Shown in progress messages
+    pub name: String,
+    /// Single character column separator, "tab" and "\t" are both accepted
+    #[serde(default = "default_sep")]
+    pub sep: String,
+    /// Rows to skip before the header
+    #[serde(default)]
+    pub skiprows: usize,
+    pub chamber_col: String,
+    /// Date in its own column, the time columns then only hold the time of day
+    #[serde(default)]
+    pub date_col: Option<String>,
+    /// chrono format of `date_col`, required with it
+    #[serde(default)]
+    pub date_fmt: Option<String>,
+    /// Start of the cycle
+    pub start_col: String,
+    /// chrono format of the start column, and of close, open and end when
+    /// they are absolute times
+    #[serde(default = "default_time_fmt")]
+    pub time_fmt: String,
+    /// "offset" (default), close, open and end are seconds from the start,
+    /// or "absolute"
+    #[serde(default)]
+    pub times: Option<String>,
+    pub close_col: String,
+    pub open_col: String,
+    /// End of the cycle, open + 60 seconds if unset
+    #[serde(default)]
+    pub end_col: Option<String>,
+    /// Snow depth in the chamber in cm like the snow file, 0 if unset
+    #[serde(default)]
+    pub snow_depth_col: Option<String>,
+}
+
+fn default_sep() -> String {
+    ",".to_owned()
+}
+
+fn default_time_fmt() -> String {
+    "%Y-%m-%d %H:%M:%S".to_owned()
+}
+
+/// How close, open and end are given in a mapped cycle file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CycleTimes {
+    /// Seconds from the start of the cycle
+    #[default]
+    Offset,
+    /// Times in the same format as the start
+    Absolute,
+}
+
+impl fmt::Display for CycleTimes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CycleTimes::Offset => write!(f, "offset"),
+            CycleTimes::Absolute => write!(f, "absolute"),
+        }
+    }
+}
+
+impl FromStr for CycleTimes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "offset" | "offsets" => Ok(CycleTimes::Offset),
+            "absolute" => Ok(CycleTimes::Absolute),
+            other => Err(format!("invalid times: {other}, use offset or absolute")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MappingError {
+    Io(std::io::Error),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingError::Io(e) => write!(f, "Could not read cycle mapping: {}", e),
+            MappingError::Parse(msg) => write!(f, "Could not parse cycle mapping: {}", msg),
+            MappingError::Invalid(msg) => write!(f, "Invalid cycle mapping: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+impl From<std::io::Error> for MappingError {
+    fn from(err: std::io::Error) -> Self {
+        MappingError::Io(err)
+    }
+}
+
+/// Column indices of a mapping in one file
+struct Columns {
+    chamber: usize,
+    date: Option<usize>,
+    start: usize,
+    close: usize,
+    open: usize,
+    end: Option<usize>,
+    snow_depth: Option<usize>,
+}
+
+impl CycleMapping {
+    /// Read a mapping from a .toml or .json file, the mapping is validated
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, MappingError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let mapping =
+            match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()) {
+                Some(ext) if ext == "toml" => Self::from_toml(&content)?,
+                Some(ext) if ext == "json" => Self::from_json(&content)?,
+                _ => {
+                    return Err(MappingError::Parse(format!(
+                        "{} is not a .toml or .json file",
+                        path.display()
+                    )))
+                },
+            };
+        mapping.validate()?;
+        Ok(mapping)
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, MappingError> {
+        toml::from_str(content).map_err(|e| MappingError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, MappingError> {
+        serde_json::from_str(content).map_err(|e| MappingError::Parse(e.to_string()))
+    }
+
+    pub fn cycle_times(&self) -> Result<CycleTimes, MappingError> {
+        match &self.times {
+            Some(s) => s.parse::<CycleTimes>().map_err(MappingError::Invalid),
+            None => Ok(CycleTimes::Offset),
+        }
+    }
+
+    fn sep_byte(&self) -> Result<u8, MappingError> {
+        match self.sep.as_str() {
+            "\t" | "\\t" | "tab" => Ok(b'\t'),
+            s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+            s => Err(MappingError::Invalid(format!("separator must be a single character: {s:?}"))),
+        }
+    }
+
+    /// Check the fields that serde can't
+    pub fn validate(&self) -> Result<(), MappingError> {
+        if self.name.trim().is_empty() {
+            return Err(MappingError::Invalid("name is empty".to_owned()));
+        }
+        self.sep_byte()?;
+        self.cycle_times()?;
+        if self.date_col.is_some() && self.date_fmt.is_none() {
+            return Err(MappingError::Invalid("date_col needs date_fmt".to_owned()));
+        }
+        Ok(())
+    }
+
+    fn columns(&self, header: &StringRecord) -> Result<Columns, MappingError> {
+        let find = |name: &str| {
+            header.iter().position(|h| h.trim() == name).ok_or_else(|| {
+                MappingError::Invalid(format!("column '{name}' not found in header"))
+            })
+        };
+        let find_opt = |name: &Option<String>| name.as_deref().map(find).transpose();
+        Ok(Columns {
+            chamber: find(&self.chamber_col)?,
+            date: find_opt(&self.date_col)?,
+            start: find(&self.start_col)?,
+            close: find(&self.close_col)?,
+            open: find(&self.open_col)?,
+            end: find_opt(&self.end_col)?,
+            snow_depth: find_opt(&self.snow_depth_col)?,
+        })
+    }
+
+    /// Date and time of one time column, times of day are put on `date`
+    fn parse_time(&self, value: &str, date: Option<NaiveDate>) -> Result<NaiveDateTime, String> {
+        let value = value.trim();
+        match date {
+            Some(date) => NaiveTime::parse_from_str(value, &self.time_fmt)
+                .map(|t| date.and_time(t))
+                .map_err(|e| format!("'{value}' doesn't match '{}': {e}", self.time_fmt)),
+            None => NaiveDateTime::parse_from_str(value, &self.time_fmt)
+                .map_err(|e| format!("'{value}' doesn't match '{}': {e}", self.time_fmt)),
+        }
+    }
+
+    /// Seconds from `start` to a close, open or end column
+    fn parse_offset(
+        &self,
+        value: &str,
+        times: CycleTimes,
+        start: NaiveDateTime,
+        date: Option<NaiveDate>,
+    ) -> Result<i64, String> {
+        match times {
+            CycleTimes::Offset => value
+                .trim()
+                .parse::<f64>()
+                .map(|v| v.round() as i64)
+                .map_err(|_| format!("'{}' is not a number of seconds", value.trim())),
+            CycleTimes::Absolute => {
+                let mut t = self.parse_time(value, date)?;
+                // times of day after midnight belong to the next day
+                if date.is_some() && t < start {
+                    t += Duration::days(1);
+                }
+                Ok((t - start).num_seconds())
+            },
+        }
+    }
+
+    /// Start, close, open and end offsets and snow depth of one row
+    fn parse_row(
+        &self,
+        record: &StringRecord,
+        cols: &Columns,
+        times: CycleTimes,
+        tz: &Tz,
+    ) -> Result<(i64, i64, i64, i64, f64), String> {
+        let field = |i: usize| record.get(i).unwrap_or("");
+        let date = match cols.date {
+            Some(i) => {
+                let fmt = self.date_fmt.as_deref().unwrap_or_default();
+                let value = field(i).trim();
+                Some(
+                    NaiveDate::parse_from_str(value, fmt)
+                        .map_err(|e| format!("'{value}' doesn't match '{fmt}': {e}"))?,
+                )
+            },
+            None => None,
+        };
+        let start = self.parse_time(field(cols.start), date)?;
+        let start_utc = match tz.from_local_datetime(&start) {
+            LocalResult::Single(dt) => dt.with_timezone(&Utc),
+            LocalResult::Ambiguous(dt, _) => dt.with_timezone(&Utc),
+            LocalResult::None => return Err(format!("{start} doesn't exist in {tz}")),
+        };
+        let close = self.parse_offset(field(cols.close), times, start, date)?;
+        let open = self.parse_offset(field(cols.open), times, start, date)?;
+        let end = match cols.end {
+            Some(i) => self.parse_offset(field(i), times, start, date)?,
+            None => open + 60,
+        };
+        if !(0 <= close && close < open && open <= end) {
+            return Err(format!("close {close} s, open {open} s and end {end} s are not in order"));
+        }
+        let snow_depth = match cols.snow_depth.and_then(|i| field(i).trim().parse::<f64>().ok()) {
+            Some(cm) => (SNOW.parse)(cm).map_err(|e| format!("Snow depth {e}"))?,
+            None => 0.0,
+        };
+        Ok((start_utc.timestamp(), close, open, end, snow_depth))
+    }
+
+    pub(crate) fn parse_reader<R: Read>(
+        &self,
+        reader: R,
+        path: &Path,
+        tz: &Tz,
+        project: &Project,
+        conn: &Connection,
+        progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+    ) -> Result<TimeData, Box<dyn Error>> {
+        let times = self.cycle_times()?;
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(self.sep_byte()?)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader);
+        let mut records = rdr.records().skip(self.skiprows);
+        let header = records.next().ok_or("file has no header row")??;
+        let cols = self.columns(&header)?;
+
+        // cycles are measured with the main instrument, same as the default format
+        let instrument = Instrument {
+            model: project.instrument.model,
+            serial: project.instrument.serial.clone(),
+            id: None,
+        };
+        let instrument_id = get_or_insert_instrument(conn, &instrument, project.id.unwrap())?;
+
+        let mut data = TimeData::new();
+        for (i, record) in records.enumerate() {
+            // header is on row skiprows + 1
+            let row = self.skiprows + i + 2;
+            let parsed = record
+                .map_err(|e| e.to_string())
+                .and_then(|record| Ok((self.parse_row(&record, &cols, times, tz)?, record)));
+            let ((start, close, open, end, snow_depth), record) =
+                match parsed {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::RowFail(
+                            format!("Skipped row {} in file {}: {}", row, path.display(), e),
+                        )));
+                        continue;
+                    },
+                };
+            data.chamber_id.push(record.get(cols.chamber).unwrap_or("").trim().to_owned());
+            data.start_time.push(start);
+            data.close_offset.push(close);
+            data.open_offset.push(open);
+            data.end_offset.push(end);
+            data.snow_depth.push(snow_depth);
+            data.air_temperature.push(None);
+            data.note.push(None);
+            data.project_id.push(project.id.unwrap());
+            data.instrument_id.push(instrument_id);
+        }
+
+        if data.start_time.is_empty() {
+            return Err(format!("no valid rows found with mapping '{}'", self.name).into());
+        }
+        Ok(data)
+    }
+}
+
+impl TimeFormatParser for CycleMapping {
+    fn name(&self) -> &'static str {
+        "Cycle mapping"
+    }
+
+    fn parse(
+        &self,
+        path: &Path,
+        tz: &Tz,
+        project: &Project,
+        conn: &Connection,
+        progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+    ) -> Result<TimeData, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open file {}: {}", path.display(), e))?;
+
+        self.parse_reader(file, path, tz, project, conn, progress_sender).map_err(|e| {
+            format!("failed to parse {} with mapping '{}': {}", path.display(), self.name, e).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_conn;
+    use std::io::Cursor;
+
+    fn parse(mapping: &CycleMapping, csv: &str) -> Result<TimeData, Box<dyn Error>> {
+        let (sender, _) = mpsc::unbounded_channel();
+        let project = Project { id: Some(1), ..Project::default() };
+        let conn = test_conn();
+        mapping.parse_reader(
+            Cursor::new(csv),
+            Path::new("t.csv"),
+            &Tz::UTC,
+            &project,
+            &conn,
+            sender,
+        )
+    }
+
+    #[test]
+    fn absolute_times_with_separate_date() {
+        let mapping = CycleMapping::from_toml(
+            r#"
+            name = "Station X"
+            sep = ";"
+            skiprows = 1
+            chamber_col = "plot"
+            date_col = "date"
+            date_fmt = "%d.%m.%Y"
+            start_col = "start"
+            time_fmt = "%H:%M:%S"
+            times = "absolute"
+            close_col = "closed"
+            open_col = "opened"
+            "#,
+        )
+        .unwrap();
+        mapping.validate().unwrap();
+        let csv = "logger v2\n\
+                   plot;date;start;closed;opened\n\
+                   P1;02.01.2024;10:00:00;10:01:00;10:06:00\n\
+                   P2;02.01.2024;23:58:00;23:59:00;00:04:00\n\
+                   P3;02.01.2024;bad;10:01:00;10:06:00\n";
+        let data = parse(&mapping, csv).unwrap();
+
+        assert_eq!(data.chamber_id, vec!["P1", "P2"]);
+        assert_eq!(data.start_time[0], 1704189600);
+        assert_eq!(data.close_offset, vec![60, 60]);
+        assert_eq!(data.open_offset, vec![360, 360]);
+        assert_eq!(data.end_offset, vec![420, 420]);
+    }
+
+    #[test]
+    fn offsets_and_missing_columns() {
+        let mut mapping = CycleMapping::from_toml(
+            r#"
+            name = "Offsets"
+            chamber_col = "id"
+            start_col = "t"
+            close_col = "c"
+            open_col = "o"
+            end_col = "e"
+            snow_depth_col = "snow"
+            "#,
+        )
+        .unwrap();
+        let csv = "id,t,c,o,e,snow\nA,2024-01-02 10:00:00,30,330,400,15\n\
+                   B,2024-01-02 11:00:00,30,330,400,-5\n";
+        let data = parse(&mapping, csv).unwrap();
+        assert_eq!(data.chamber_id, vec!["A"]);
+        assert_eq!(data.end_offset, vec![400]);
+        assert_eq!(data.snow_depth, vec![0.15]);
+
+        mapping.open_col = "open".to_owned();
+        assert!(parse(&mapping, csv).is_err());
+
+        mapping.times = Some("relative".to_owned());
+        assert!(matches!(mapping.validate(), Err(MappingError::Invalid(_))));
+    }
+}
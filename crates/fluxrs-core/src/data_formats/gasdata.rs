@@ -1,5 +1,7 @@
 use crate::constants::ERROR_INT;
 use crate::cycle::gaskey::GasKey;
+use crate::data_formats::clockdata::{query_clock_corrections, ClockCorrections};
+use crate::gastype::GasType;
 use crate::instruments::instruments::get_or_insert_instrument;
 use crate::instruments::instruments::{Instrument, InstrumentMeasurement, InstrumentType};
 use crate::project::Project;
@@ -9,7 +11,7 @@ use crate::types::FastMap;
 use chrono::prelude::DateTime;
 use chrono::Utc;
 use csv::StringRecord;
-use rusqlite::{params, params_from_iter, Connection};
+use rusqlite::{params, Connection};
 use tokio::task;
 
 use std::collections::HashMap;
@@ -57,6 +59,7 @@ pub struct GasData {
     // NOTE: Change to InstrumentType
     pub instruments: HashSet<Instrument>,
     pub model_key: FastMap<i64, InstrumentType>,
+    /// Unix time in milliseconds
     pub datetime: FastMap<i64, Vec<i64>>,
     pub gas: FastMap<GasKey, Vec<Option<f64>>>,
     pub diag: FastMap<i64, Vec<i64>>,
@@ -185,6 +188,39 @@ pub async fn query_gas_async(
     }
 }
 
+// All gas rows of one timestamp of one instrument
+struct MeasurementRow {
+    datetime: i64,
+    diag: i64,
+    instrument: Instrument,
+    instrument_id: i64,
+    values: FastMap<GasType, Option<f64>>,
+}
+
+fn push_gas_row(grouped_data: &mut HashMap<String, GasData>, row: MeasurementRow) {
+    let dt_utc: DateTime<Utc> = chrono::DateTime::from_timestamp_millis(row.datetime).unwrap();
+    let date_key = dt_utc.format("%Y-%m-%d").to_string();
+    let instrument_id = row.instrument_id;
+    let available_gases = row.instrument.model.available_gases();
+
+    let entry = grouped_data.entry(date_key).or_default();
+
+    // Keep these up-to-date if multiple instruments share a day
+    entry.model_key.insert(instrument_id, row.instrument.model);
+    entry.instruments.insert(row.instrument);
+
+    // Time + diag
+    entry.datetime.entry(instrument_id).or_default().push(row.datetime);
+    entry.diag.entry(instrument_id).or_default().push(row.diag);
+
+    // Gas vectors, gases missing from this timestamp are pushed as None
+    for gas in &available_gases {
+        let gas_key = GasKey::from((gas, &instrument_id));
+        let value = row.values.get(gas).copied().flatten();
+        entry.gas.entry(gas_key).or_default().push(value);
+    }
+}
+
 pub fn query_gas2(
     conn: &Connection,
     start: i64,
@@ -196,11 +232,17 @@ pub fn query_gas2(
     let mut saw_selected_instrument = false;
     let mut grouped_data: HashMap<String, GasData> = HashMap::new();
 
-    // Static columns must match dynamic index logic below
+    // instrument clocks are corrected to chamber controller time, the query is
+    // widened so that data shifted into the range is found
+    let clock = ClockCorrections::new(query_clock_corrections(conn, project.id.unwrap())?);
+    let margin = clock.max_shift(start, end) * 1000;
+    // measurements are stored in milliseconds
+    let (start, end) = (start * 1000, end * 1000);
+
+    // One row per gas, rows of the same timestamp are next to each other
     let mut stmt = conn.prepare(
         "SELECT m.datetime,
-                    m.co2, m.ch4,
-                    m.h2o, m.n2o,
+                    m.gas, m.value,
                     m.diag,
                     i.instrument_serial,
                     i.instrument_model,
@@ -209,38 +251,61 @@ pub fn query_gas2(
          LEFT JOIN instruments i ON m.instrument_link = i.id
          WHERE m.datetime BETWEEN ?1 AND ?2
            AND m.project_link = ?3
-         ORDER BY i.instrument_serial, m.datetime",
+         ORDER BY i.instrument_serial, m.instrument_link, m.datetime",
     )?;
 
-    let rows = stmt.query_map(params![start, end, project.id.unwrap()], |row| {
-        let datetime_unix: i64 = row.get(0)?;
-        let diag: i64 = row.get(5)?;
-        let instrument_serial: Option<String> = row.get(6)?;
-        let instrument_model: Option<String> = row.get(7)?;
-        let instrument_id: Option<i64> = row.get(8)?;
-
-        // Collect gas values into a Vec<Option<f64>>
-        let gases = vec![
-            row.get::<_, Option<f64>>(1)?,
-            row.get::<_, Option<f64>>(2)?,
-            row.get::<_, Option<f64>>(3)?,
-            row.get::<_, Option<f64>>(4)?,
-        ];
-
-        Ok((datetime_unix, gases, diag, instrument_serial, instrument_model, instrument_id))
-    })?;
-
+    let rows =
+        stmt.query_map(params![start - margin, end + margin, project.id.unwrap()], |row| {
+            let datetime_unix: i64 = row.get(0)?;
+            let gas_i: usize = row.get(1)?;
+            let value: Option<f64> = row.get(2)?;
+            let diag: i64 = row.get(3)?;
+            let instrument_serial: Option<String> = row.get(4)?;
+            let instrument_model: Option<String> = row.get(5)?;
+            let instrument_id: Option<i64> = row.get(6)?;
+
+            Ok((
+                datetime_unix,
+                gas_i,
+                value,
+                diag,
+                instrument_serial,
+                instrument_model,
+                instrument_id,
+            ))
+        })?;
+
+    let mut current: Option<MeasurementRow> = None;
     for row in rows {
-        let (dt_unix, gas_values, diag, instrument_serial, instrument_model, instrument_id) = row?;
+        let (dt_unix, gas_i, value, diag, instrument_serial, instrument_model, instrument_id) =
+            row?;
 
         // Skip rows lacking serial/model (you can choose to error instead)
         let (serial, model) = match (instrument_serial, instrument_model) {
             (Some(s), Some(m)) => (s, m),
             _ => continue,
         };
+        let instrument_id = instrument_id.unwrap();
+        let dt_unix = clock.apply(instrument_id, dt_unix);
+        if dt_unix < start || dt_unix > end {
+            continue;
+        }
 
-        let dt_utc: DateTime<Utc> = chrono::DateTime::from_timestamp(dt_unix, 0).unwrap();
-        let date_key = dt_utc.format("%Y-%m-%d").to_string();
+        if let Some(prev) = &mut current {
+            if prev.instrument_id == instrument_id && prev.datetime == dt_unix {
+                if let Some(gas) = GasType::from_int(gas_i) {
+                    prev.values.insert(gas, value);
+                }
+                continue;
+            }
+            // a later correction can move the clock back, keep the time increasing
+            if prev.instrument_id == instrument_id && dt_unix < prev.datetime {
+                continue;
+            }
+        }
+        if let Some(prev) = current.take() {
+            push_gas_row(&mut grouped_data, prev);
+        }
 
         let instrument_type = match model.parse::<InstrumentType>() {
             Ok(val) => val,
@@ -250,49 +315,25 @@ pub fn query_gas2(
             },
         };
         if let Some(main_instrument) = project.instrument.id {
-            if instrument_id.unwrap() == main_instrument {
+            if instrument_id == main_instrument {
                 saw_selected_instrument = true;
             }
         }
-        let available_gases = instrument_type.available_gases();
-
-        let entry = grouped_data.entry(date_key.clone()).or_insert_with(|| GasData {
-            header: StringRecord::new(),
-            instruments: HashSet::new(),
-            model_key: FastMap::default(),
-            datetime: FastMap::default(),
-            gas: FastMap::default(),
-            diag: FastMap::default(),
-        });
-
-        // Keep these up-to-date if multiple instruments share a day
-        entry.instruments.insert(Instrument {
-            model: instrument_type,
-            serial: serial.clone(),
-            id: instrument_id,
-        });
-        entry.model_key.insert(instrument_id.unwrap(), instrument_type);
-
-        // Time + diag
-        entry.datetime.entry(instrument_id.unwrap()).or_default().push(dt_unix);
-        entry.diag.entry(instrument_id.unwrap()).or_default().push(diag);
-
-        // Gas vectors
-        for gas in &available_gases {
-            let idx = gas.as_int();
-            let gas_key = GasKey::from((gas, &instrument_id.unwrap()));
-            let gas_vec = entry.gas.entry(gas_key).or_default();
 
-            if let Some(gas_val) = gas_values.get(idx).copied().flatten() {
-                gas_vec.push(Some(gas_val));
-            } else {
-                gas_vec.push(None);
-            }
-        }
-        let no_datetime_data = entry.datetime.values().all(|v| v.is_empty());
-        if no_datetime_data {
-            grouped_data.remove(&date_key);
+        let mut values = FastMap::default();
+        if let Some(gas) = GasType::from_int(gas_i) {
+            values.insert(gas, value);
         }
+        current = Some(MeasurementRow {
+            datetime: dt_unix,
+            diag,
+            instrument: Instrument { model: instrument_type, serial, id: Some(instrument_id) },
+            instrument_id,
+            values,
+        });
+    }
+    if let Some(prev) = current.take() {
+        push_gas_row(&mut grouped_data, prev);
     }
     if !saw_selected_instrument {
         return Err(QueryError::SelectedInstrumentNotFound { instrument: project.instrument });
@@ -311,30 +352,28 @@ pub fn query_gas_all(
     project_id: i64,
 ) -> Result<GasData> {
     println!("Querying gas data");
+    let clock = ClockCorrections::new(query_clock_corrections(conn, project_id)?);
+    let margin = clock.max_shift(start.timestamp(), end.timestamp()) * 1000;
+    let (start, end) = (start.timestamp_millis(), end.timestamp_millis());
 
     let mut stmt = conn.prepare(
-        "SELECT m.datetime, m.co2, m.ch4, m.h2o, m.n2o, m.diag, i.instrument_serial, i.instrument_model, i.id
+        "SELECT m.datetime, m.gas, m.value, m.diag, i.instrument_serial, i.instrument_model, i.id
          FROM measurements m
          LEFT JOIN instruments i ON m.instrument_link = i.id
          WHERE m.datetime BETWEEN ?1 AND ?2
          AND m.project_link = ?3
-         ORDER BY i.instrument_serial, m.datetime",
+         ORDER BY i.instrument_serial, m.instrument_link, m.datetime",
     )?;
 
-    let rows = stmt.query_map(params![start.timestamp(), end.timestamp(), project_id], |row| {
+    let rows = stmt.query_map(params![start - margin, end + margin, project_id], |row| {
         let datetime_unix: i64 = row.get(0)?;
-        let diag: i64 = row.get(5)?;
-        let instrument_serial: String = row.get(6)?;
-        let instrument_model: String = row.get(7)?;
-        let instrument_id: i64 = row.get(8)?;
-
-        // Collect gas values into a vector in matching order
-        let gas_values = vec![
-            row.get::<_, Option<f64>>(1)?, // co2
-            row.get::<_, Option<f64>>(2)?, // ch4
-            row.get::<_, Option<f64>>(3)?, // h2o
-            row.get::<_, Option<f64>>(4)?, // n2o
-        ];
+        let gas_i: usize = row.get(1)?;
+        let value: Option<f64> = row.get(2)?;
+        let diag: i64 = row.get(3)?;
+        let instrument_serial: String = row.get(4)?;
+        let instrument_model: String = row.get(5)?;
+        let instrument_id: i64 = row.get(6)?;
+
         let instrument_type =
             instrument_model.parse::<InstrumentType>().expect("Invalid instrument type in DB");
 
@@ -344,7 +383,7 @@ pub fn query_gas_all(
             id: Some(instrument_id),
         };
 
-        Ok((datetime_unix, gas_values, diag, instrument))
+        Ok((datetime_unix, gas_i, value, diag, instrument))
     })?;
 
     let mut entry = GasData {
@@ -356,31 +395,36 @@ pub fn query_gas_all(
         diag: FastMap::default(),
     };
 
+    let mut last_raw: Option<(i64, i64)> = None;
+    let mut last: Option<(i64, i64)> = None;
+    let mut keep = false;
     for row in rows {
-        let (dt_unix, gas_values, diag, instrument) = row?;
-        let serial = instrument.serial.clone();
-        let model = instrument.model.clone();
-
-        let available_gases = instrument.model.available_gases();
-
-        entry.model_key.insert(instrument.id.unwrap(), instrument.model);
-
-        // Append dynamic gas values
-        //
-        for gas in &available_gases {
-            let idx = gas.as_int();
-            if let Some(gas_val) = gas_values.get(idx).copied().flatten() {
-                entry
-                    .gas
-                    .entry(GasKey::from((gas, &instrument.id.unwrap())))
-                    .or_default()
-                    .push(Some(gas_val));
+        let (raw_unix, gas_i, value, diag, instrument) = row?;
+        let instrument_id = instrument.id.unwrap();
+
+        entry.model_key.insert(instrument_id, instrument.model);
+
+        if last_raw != Some((instrument_id, raw_unix)) {
+            last_raw = Some((instrument_id, raw_unix));
+            let dt_unix = clock.apply(instrument_id, raw_unix);
+            // drop rows that the correction moved out of range or onto/behind the previous row
+            keep = dt_unix >= start
+                && dt_unix <= end
+                && last.is_none_or(|(id, t)| id != instrument_id || dt_unix > t);
+            if keep {
+                entry.datetime.entry(instrument_id).or_default().push(dt_unix);
+                entry.diag.entry(instrument_id).or_default().push(diag);
+                last = Some((instrument_id, dt_unix));
             }
         }
+        if !keep {
+            continue;
+        }
 
-        // entry.datetime.push(datetime);
-        entry.datetime.entry(instrument.id.unwrap()).or_default().push(dt_unix);
-        entry.diag.entry(instrument.id.unwrap()).or_default().push(diag);
+        // Only gases with a value are kept, same as before the long format
+        if let (Some(gas), Some(gas_val)) = (GasType::from_int(gas_i), value) {
+            entry.gas.entry(GasKey::from((&gas, &instrument_id))).or_default().push(Some(gas_val));
+        }
     }
 
     Ok(entry)
@@ -401,59 +445,36 @@ pub fn insert_measurements(
 
     let instrument_id = get_or_insert_instrument(&tx, &all_gas.instrument, project.id.unwrap())?;
 
-    // Dynamically build SQL
-    let mut columns = vec!["datetime".to_string()];
-    let mut placeholders = vec!["?1".to_string()]; // datetime is always first
+    let mut stmt = tx.prepare(
+        "INSERT OR IGNORE INTO measurements
+            (datetime, gas, value, diag, instrument_link, project_link, file_link)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
 
     let available_gases = &all_gas.instrument.model.available_gases();
-    let mut gas_keys = vec![];
-    for (idx, gas) in available_gases.iter().enumerate() {
-        columns.push(format!("{}", gas).to_lowercase()); // assumes gas types match DB column names
-        placeholders.push(format!("?{}", idx + 2)); // next placeholders
-        gas_keys.push(gas); // to get values during loop
-    }
-
-    // Add fixed fields
-    let base_idx = placeholders.len();
-    columns.extend([
-        "diag".to_owned(),
-        "instrument_link".to_owned(),
-        "project_link".to_owned(),
-        "file_link".to_owned(),
-    ]);
-    for i in 0..4 {
-        placeholders.push(format!("?{}", base_idx + i + 1));
-    }
-
-    let sql = format!(
-        "INSERT OR IGNORE INTO measurements ({}) VALUES ({})",
-        columns.join(", "),
-        placeholders.join(", ")
-    );
-    let mut stmt = tx.prepare(&sql)?;
+    let project_id = project.id.unwrap();
 
     println!("Pushing data!");
     for i in 0..data_len {
-        // If no duplicate, insert the new record
-        let mut values: Vec<&dyn rusqlite::ToSql> = Vec::new();
-        let id = project.id.unwrap();
-        let ins_id = &instrument_id;
-        let dt = &all_gas.datetime[i] as &dyn rusqlite::ToSql;
-        let diag = &all_gas.diag[i] as &dyn rusqlite::ToSql;
-
-        values.push(dt);
-
-        for gas in &gas_keys {
-            let g = all_gas.gas.get(gas).unwrap()[i].as_ref().unwrap() as &dyn rusqlite::ToSql;
-            values.push(g);
-        }
-
-        values.push(diag);
-        values.push(&ins_id);
-        values.push(&id);
-        values.push(&file_id);
+        let dt = all_gas.datetime[i];
+        let diag = diag_vec[i];
 
-        let affected = stmt.execute(params_from_iter(values))?;
+        // A timestamp counts as inserted if any of its gases was new
+        let mut affected = 0;
+        for gas in available_gases {
+            let Some(value) = all_gas.gas.get(gas).and_then(|v| v[i]) else {
+                continue;
+            };
+            affected += stmt.execute(params![
+                dt,
+                gas.as_int(),
+                value,
+                diag,
+                instrument_id,
+                project_id,
+                file_id
+            ])?;
+        }
         if affected > 0 {
             inserted += 1;
         } else {
@@ -1,3 +1,4 @@
+use crate::archive::expand_inputs;
 use crate::datatype::DataType;
 use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
 use crate::project::Project;
@@ -8,7 +9,7 @@ use crate::utils::{
 
 use chrono::{DateTime, Utc};
 use chrono_tz::Tz;
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OptionalExtension, Result};
 use tokio::sync::mpsc;
 use tokio::task;
 
@@ -16,10 +17,36 @@ use std::error::Error;
 use std::fmt;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 pub const DEFAULT_TEMP: f64 = 10.0;
 pub const DEFAULT_PRESSURE: f64 = 980.0;
+/// Largest distance from a cycle to a meteo sample used for it, seconds
+pub const DEFAULT_METEO_MAX_GAP_S: i64 = 1800;
+/// Station of meteo files uploaded without one
+pub const DEFAULT_METEO_STATION: &str = "default";
+/// Standard sea level pressure, hPa
+pub const SEA_LEVEL_PRESSURE: f64 = 1013.25;
+
+/// Air pressure in hPa at `elevation_m` above sea level from the barometric
+/// formula, for an air column at `temperature_c`. Gravity is corrected for the
+/// latitude when it's known.
+pub fn barometric_pressure(elevation_m: f64, temperature_c: f64, latitude: Option<f64>) -> f64 {
+    let molar_mass = 0.028_964_4; // kg/mol, dry air
+    let r = 8.314_f64; // J/(mol·K)
+    let g = match latitude {
+        // international gravity formula
+        Some(lat) => {
+            let phi = lat.to_radians();
+            9.780_327
+                * (1. + 0.005_302_4 * phi.sin().powi(2) - 0.000_005_8 * (2. * phi).sin().powi(2))
+        },
+        None => 9.806_65,
+    };
+    let t_kelvin = temperature_c + 273.15;
+    SEA_LEVEL_PRESSURE * (-g * molar_mass * elevation_m / (r * t_kelvin)).exp()
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MeteoSource {
@@ -29,6 +56,14 @@ pub enum MeteoSource {
     Default,
     /// No value is available
     Missing,
+    /// Written down in the cycle file
+    Cycle,
+    /// Linearly interpolated between two samples
+    Interpolated,
+    /// Mean of the samples inside the chamber over the calculation window
+    Chamber,
+    /// From the barometric formula with the site elevation
+    Modelled,
 }
 
 impl fmt::Display for MeteoSource {
@@ -37,6 +72,10 @@ impl fmt::Display for MeteoSource {
             MeteoSource::Raw => write!(f, "Raw"),
             MeteoSource::Default => write!(f, "Default"),
             MeteoSource::Missing => write!(f, "Missing"),
+            MeteoSource::Cycle => write!(f, "Cycle file"),
+            MeteoSource::Interpolated => write!(f, "Interpolated"),
+            MeteoSource::Chamber => write!(f, "Chamber"),
+            MeteoSource::Modelled => write!(f, "Modelled"),
         }
     }
 }
@@ -46,6 +85,10 @@ impl MeteoSource {
             MeteoSource::Raw => 0,
             MeteoSource::Default => 1,
             MeteoSource::Missing => 2,
+            MeteoSource::Cycle => 3,
+            MeteoSource::Interpolated => 4,
+            MeteoSource::Chamber => 5,
+            MeteoSource::Modelled => 6,
         }
     }
 
@@ -54,18 +97,70 @@ impl MeteoSource {
             0 => Some(MeteoSource::Raw),
             1 => Some(MeteoSource::Default),
             2 => Some(MeteoSource::Missing),
+            3 => Some(MeteoSource::Cycle),
+            4 => Some(MeteoSource::Interpolated),
+            5 => Some(MeteoSource::Chamber),
+            6 => Some(MeteoSource::Modelled),
             _ => None, // safe fallback
         }
     }
 }
 
+/// How meteo values are found for a cycle between samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeteoInterpolation {
+    /// The nearest sample
+    Nearest,
+    /// Linear between the samples before and after
+    #[default]
+    Linear,
+}
+
+impl fmt::Display for MeteoInterpolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeteoInterpolation::Nearest => write!(f, "nearest"),
+            MeteoInterpolation::Linear => write!(f, "linear"),
+        }
+    }
+}
+
+impl FromStr for MeteoInterpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "nearest" => Ok(MeteoInterpolation::Nearest),
+            "linear" => Ok(MeteoInterpolation::Linear),
+            other => Err(format!("invalid meteo interpolation: {other}, use nearest or linear")),
+        }
+    }
+}
+
+impl MeteoInterpolation {
+    pub fn as_int(self) -> i32 {
+        match self {
+            MeteoInterpolation::Nearest => 0,
+            MeteoInterpolation::Linear => 1,
+        }
+    }
+
+    pub fn from_int(v: i32) -> Option<MeteoInterpolation> {
+        match v {
+            0 => Some(MeteoInterpolation::Nearest),
+            1 => Some(MeteoInterpolation::Linear),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MeteoPoint {
     pub value: Option<f64>,
     pub source: MeteoSource,
     /// Distance (in seconds) from the target timestamp used in nearest lookup.
     /// - For raw DB/CSV data: None
-    /// - For results of get_nearest / get_nearest_meteo_data: Some(abs(dt - target))
+    /// - For results of get_at / get_nearest_meteo_data: Some(dt - target)
     pub distance_from_target: Option<i64>,
 }
 
@@ -129,6 +224,17 @@ impl MeteoPoint {
             _ => self,
         }
     }
+    /// Replaces a default value with `modelled` when there is one
+    pub fn or_modelled(self, modelled: Option<f64>) -> MeteoPoint {
+        match (self.source, modelled) {
+            (MeteoSource::Default, Some(value)) => MeteoPoint {
+                value: Some(value),
+                source: MeteoSource::Modelled,
+                distance_from_target: None,
+            },
+            _ => self,
+        }
+    }
 }
 
 impl Default for MeteoPoint {
@@ -137,97 +243,162 @@ impl Default for MeteoPoint {
     }
 }
 
+/// A source of meteo data in a project. Stations are tried in ascending
+/// `priority`, each within its own `max_gap_s` or the project's when unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MeteoStation {
+    pub name: String,
+    pub priority: i64,
+    pub max_gap_s: Option<i64>,
+}
+
+impl Default for MeteoStation {
+    fn default() -> Self {
+        MeteoStation { name: DEFAULT_METEO_STATION.to_owned(), priority: 0, max_gap_s: None }
+    }
+}
+
+/// Names of the stations the temperature and pressure of a cycle were taken from
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MeteoStations {
+    pub temperature: Option<String>,
+    pub pressure: Option<String>,
+}
+
+/// Meteo series of every station, in the order they are tried
 #[derive(Debug, Default, Clone)]
 pub struct MeteoData {
-    pub datetime: Vec<i64>,
-    pub temperature: Vec<MeteoPoint>,
-    pub pressure: Vec<MeteoPoint>,
+    pub stations: Vec<(MeteoStation, MeteoSeries)>,
 }
 
 impl MeteoData {
-    /// Returns the nearest meteo values within ±30 minutes of `target_timestamp`,
-    /// independently for temperature and pressure.
-    ///
-    /// - If nothing usable is found for both, returns None.
-    /// - Otherwise returns Some((temp_point, press_point)), where each point has
-    ///   distance_from_target = Some(diff_seconds) if it was found by this lookup.
-    pub fn get_nearest(&self, target_timestamp: i64) -> Option<(MeteoPoint, MeteoPoint)> {
-        if self.datetime.is_empty() {
-            return None;
+    /// Returns temperature and pressure at `target_timestamp` like
+    /// [`MeteoSeries::get_at`], with the stations they came from. Each is
+    /// taken from the first station that has a value for it, stations without
+    /// their own window use `max_gap_s`.
+    pub fn get_at(
+        &self,
+        target_timestamp: i64,
+        interpolation: MeteoInterpolation,
+        max_gap_s: i64,
+    ) -> Option<(MeteoPoint, MeteoPoint, MeteoStations)> {
+        let missing =
+            MeteoPoint { value: None, source: MeteoSource::Missing, distance_from_target: None };
+        let (mut temp, mut press) = (missing, missing);
+        let mut stations = MeteoStations::default();
+
+        for (station, series) in &self.stations {
+            let max_gap_s = station.max_gap_s.unwrap_or(max_gap_s);
+            if temp.value.is_none() {
+                let point = series.interpolate(
+                    &series.temperature,
+                    target_timestamp,
+                    interpolation,
+                    max_gap_s,
+                );
+                if point.value.is_some() {
+                    temp = point;
+                    stations.temperature = Some(station.name.clone());
+                }
+            }
+            if press.value.is_none() {
+                let point = series.interpolate(
+                    &series.pressure,
+                    target_timestamp,
+                    interpolation,
+                    max_gap_s,
+                );
+                if point.value.is_some() {
+                    press = point;
+                    stations.pressure = Some(station.name.clone());
+                }
+            }
         }
 
-        // (index, diff)
-        let mut best_temp: Option<(usize, i64)> = None;
-        let mut best_press: Option<(usize, i64)> = None;
+        match (temp.source, press.source) {
+            (MeteoSource::Missing, MeteoSource::Missing) => None,
+            _ => Some((temp, press, stations)),
+        }
+    }
+}
 
-        for (idx, &ts) in self.datetime.iter().enumerate() {
-            let signed_diff = ts - target_timestamp; // preserve sign
-            let diff = signed_diff.abs(); // for nearest comparison
+/// Samples of one meteo station
+#[derive(Debug, Default, Clone)]
+pub struct MeteoSeries {
+    pub datetime: Vec<i64>,
+    pub temperature: Vec<MeteoPoint>,
+    pub pressure: Vec<MeteoPoint>,
+}
 
-            // respect ±30 minutes window
-            if diff > 1800 {
-                continue;
-            }
+impl MeteoSeries {
+    /// Returns temperature and pressure at `target_timestamp`, independently
+    /// for each. `datetime` has to be sorted, samples are found with a binary
+    /// search.
+    ///
+    /// - `Linear` interpolates between the samples on both sides of the target
+    ///   when both are within `max_gap_s`, otherwise the nearest sample within
+    ///   `max_gap_s` is used
+    /// - `Nearest` uses the nearest sample within `max_gap_s`
+    ///
+    /// Returns None if neither has a value, the distance of a point is the
+    /// signed distance to the nearest sample used.
+    pub fn get_at(
+        &self,
+        target_timestamp: i64,
+        interpolation: MeteoInterpolation,
+        max_gap_s: i64,
+    ) -> Option<(MeteoPoint, MeteoPoint)> {
+        let temp = self.interpolate(&self.temperature, target_timestamp, interpolation, max_gap_s);
+        let press = self.interpolate(&self.pressure, target_timestamp, interpolation, max_gap_s);
+        match (temp.source, press.source) {
+            (MeteoSource::Missing, MeteoSource::Missing) => None,
+            _ => Some((temp, press)),
+        }
+    }
 
-            // temperature
-            let t = &self.temperature[idx];
-            if t.value.is_some() {
-                match best_temp {
-                    None => best_temp = Some((idx, signed_diff)),
-                    Some((_, best_diff)) if diff < best_diff.abs() => {
-                        best_temp = Some((idx, signed_diff))
-                    },
-                    _ => {},
+    fn interpolate(
+        &self,
+        points: &[MeteoPoint],
+        target: i64,
+        interpolation: MeteoInterpolation,
+        max_gap_s: i64,
+    ) -> MeteoPoint {
+        let split = self.datetime.partition_point(|&t| t < target);
+        let within = |i: &usize| (self.datetime[*i] - target).abs() <= max_gap_s;
+        // closest samples with a value on each side, at or after the target on the right
+        let before = (0..split).rev().take_while(within).find(|&i| points[i].value.is_some());
+        let after =
+            (split..self.datetime.len()).take_while(within).find(|&i| points[i].value.is_some());
+
+        let sample = |i: usize| MeteoPoint {
+            value: points[i].value,
+            source: points[i].source,
+            distance_from_target: Some(self.datetime[i] - target),
+        };
+        match (before, after) {
+            (Some(b), Some(a))
+                if interpolation == MeteoInterpolation::Linear && self.datetime[a] != target =>
+            {
+                let (t0, t1) = (self.datetime[b], self.datetime[a]);
+                let (v0, v1) = (points[b].value.unwrap(), points[a].value.unwrap());
+                let value = v0 + (v1 - v0) * (target - t0) as f64 / (t1 - t0) as f64;
+                let nearest = if target - t0 <= t1 - target { t0 } else { t1 };
+                MeteoPoint {
+                    value: Some(value),
+                    source: MeteoSource::Interpolated,
+                    distance_from_target: Some(nearest - target),
                 }
-            }
-
-            // pressure
-            let p = &self.pressure[idx];
-            if p.value.is_some() {
-                match best_press {
-                    None => best_press = Some((idx, signed_diff)),
-                    Some((_, best_diff)) if diff < best_diff.abs() => {
-                        best_press = Some((idx, signed_diff))
-                    },
-                    _ => {},
+            },
+            (Some(b), Some(a)) => {
+                if target - self.datetime[b] < self.datetime[a] - target {
+                    sample(b)
+                } else {
+                    sample(a)
                 }
-            }
-        }
-
-        match (best_temp, best_press) {
-            (None, None) => None,
-            (t_opt, p_opt) => {
-                let temp_point = t_opt
-                    .map(|(i, diff)| {
-                        let base = &self.temperature[i];
-                        MeteoPoint {
-                            value: base.value,
-                            source: base.source,
-                            distance_from_target: Some(diff),
-                        }
-                    })
-                    .unwrap_or(MeteoPoint {
-                        value: None,
-                        source: MeteoSource::Missing,
-                        distance_from_target: None,
-                    });
-
-                let press_point = p_opt
-                    .map(|(i, diff)| {
-                        let base = &self.pressure[i];
-                        MeteoPoint {
-                            value: base.value,
-                            source: base.source,
-                            distance_from_target: Some(diff),
-                        }
-                    })
-                    .unwrap_or(MeteoPoint {
-                        value: None,
-                        source: MeteoSource::Missing,
-                        distance_from_target: None,
-                    });
-
-                Some((temp_point, press_point))
+            },
+            (Some(i), None) | (None, Some(i)) => sample(i),
+            (None, None) => {
+                MeteoPoint { value: None, source: MeteoSource::Missing, distance_from_target: None }
             },
         }
     }
@@ -242,11 +413,33 @@ impl MeteoData {
     }
 }
 
+/// Id of the station called `station.name` in the project. An existing
+/// station takes the priority and window of the latest upload.
+pub fn get_or_insert_meteo_station(
+    tx: &Connection,
+    project_id: &i64,
+    station: &MeteoStation,
+) -> Result<i64> {
+    tx.execute(
+        "INSERT INTO meteo_stations (name, priority, max_gap, project_link)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (name, project_link)
+         DO UPDATE SET priority = excluded.priority, max_gap = excluded.max_gap",
+        params![station.name, station.priority, station.max_gap_s, project_id],
+    )?;
+    tx.query_row(
+        "SELECT id FROM meteo_stations WHERE name = ?1 AND project_link = ?2",
+        params![station.name, project_id],
+        |row| row.get(0),
+    )
+}
+
 pub fn insert_meteo_data(
     tx: &Connection,
     file_id: &i64,
     project_id: &i64,
-    meteo_data: &MeteoData,
+    station_id: &i64,
+    meteo_data: &MeteoSeries,
 ) -> Result<(usize, usize)> {
     let mut inserted = 0;
     let mut skipped = 0;
@@ -258,8 +451,9 @@ pub fn insert_meteo_data(
     }
 
     let mut stmt = tx.prepare(
-        "INSERT OR IGNORE INTO meteo (project_link, datetime, temperature, pressure, file_link)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT OR IGNORE INTO meteo
+            (project_link, station_link, datetime, temperature, pressure, file_link)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
     )?;
 
     for i in 0..meteo_data.datetime.len() {
@@ -267,7 +461,8 @@ pub fn insert_meteo_data(
         let temp_val = meteo_data.temperature[i].value;
         let press_val = meteo_data.pressure[i].value;
 
-        let affected = stmt.execute(params![project_id, datetime, temp_val, press_val, file_id])?;
+        let affected =
+            stmt.execute(params![project_id, station_id, datetime, temp_val, press_val, file_id])?;
         if affected > 0 {
             inserted += 1;
         } else {
@@ -278,55 +473,68 @@ pub fn insert_meteo_data(
     Ok((inserted, skipped))
 }
 
+/// Nearest temperature and pressure to `time`, each from the first station in
+/// priority order that has a sample within its window.
 pub fn get_nearest_meteo_data(
     conn: &Connection,
     project_id: i64,
     time: i64,
-) -> Result<(MeteoPoint, MeteoPoint)> {
-    let mut stmt = conn.prepare(
-        "SELECT datetime, temperature, pressure
-         FROM meteo
-         WHERE project_link = ?1
-         ORDER BY ABS(datetime - ?2)
-         LIMIT 1",
+) -> Result<(MeteoPoint, MeteoPoint, MeteoStations)> {
+    let mut stations = conn.prepare(
+        "SELECT s.id, s.name, COALESCE(s.max_gap, p.meteo_max_gap)
+         FROM meteo_stations s
+         JOIN projects p ON p.id = s.project_link
+         WHERE s.project_link = ?1
+         ORDER BY s.priority, s.name",
     )?;
-
-    let result = stmt.query_row(params![project_id, time], |row| {
-        let dt: i64 = row.get(0)?;
-        let t: Option<f64> = row.get(1)?;
-        let p: Option<f64> = row.get(2)?;
-        Ok((dt, t, p))
-    });
-
-    match result {
-        Ok((dt, t, p)) => {
-            let diff = (dt - time).abs();
-            Ok((
-                MeteoPoint {
-                    value: t,
-                    source: match t {
-                        Some(_) => MeteoSource::Raw,
-                        None => MeteoSource::Missing,
-                    },
-                    distance_from_target: Some(diff),
-                },
-                MeteoPoint {
-                    value: p,
-                    source: match p {
-                        Some(_) => MeteoSource::Raw,
-                        None => MeteoSource::Missing,
-                    },
-                    distance_from_target: Some(diff),
-                },
-            ))
-        },
-        Err(_) => Ok((
-            MeteoPoint { value: None, source: MeteoSource::Missing, distance_from_target: None },
-            MeteoPoint { value: None, source: MeteoSource::Missing, distance_from_target: None },
-        )),
-    }
+    let stations = stations
+        .query_map(params![project_id], |row| {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            let max_gap: i64 = row.get(2)?;
+            Ok((id, name, max_gap))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    let nearest = |column: &str| -> Result<(MeteoPoint, Option<String>)> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT datetime, {column}
+             FROM meteo
+             WHERE station_link = ?1
+               AND {column} IS NOT NULL
+               AND datetime BETWEEN ?2 - ?3 AND ?2 + ?3
+             ORDER BY ABS(datetime - ?2)
+             LIMIT 1"
+        ))?;
+        for (id, name, max_gap) in &stations {
+            let row = stmt
+                .query_row(params![id, time, max_gap], |row| {
+                    let dt: i64 = row.get(0)?;
+                    let value: f64 = row.get(1)?;
+                    Ok((dt, value))
+                })
+                .optional()?;
+            if let Some((dt, value)) = row {
+                let point = MeteoPoint {
+                    value: Some(value),
+                    source: MeteoSource::Raw,
+                    distance_from_target: Some(dt - time),
+                };
+                return Ok((point, Some(name.clone())));
+            }
+        }
+        let missing =
+            MeteoPoint { value: None, source: MeteoSource::Missing, distance_from_target: None };
+        Ok((missing, None))
+    };
+
+    let (temp, temp_station) = nearest("temperature")?;
+    let (press, press_station) = nearest("pressure")?;
+    Ok((temp, press, MeteoStations { temperature: temp_station, pressure: press_station }))
 }
 
+/// Meteo of every station of the project between `start` and `end`, with a
+/// day of margin or the station's window if that is longer
 pub fn query_meteo(
     conn: &Connection,
     start: DateTime<Utc>,
@@ -336,29 +544,34 @@ pub fn query_meteo(
     println!("Querying meteo data");
 
     let mut stmt = conn.prepare(
-        "SELECT datetime, temperature, pressure
-         FROM meteo
-         WHERE datetime BETWEEN ?1 AND ?2
-           AND project_link = ?3
-         ORDER BY datetime",
+        "SELECT s.name, s.priority, s.max_gap, m.datetime, m.temperature, m.pressure
+         FROM meteo m
+         JOIN meteo_stations s ON s.id = m.station_link
+         WHERE m.datetime BETWEEN ?1 - MAX(86400, COALESCE(s.max_gap, 0))
+                              AND ?2 + MAX(86400, COALESCE(s.max_gap, 0))
+           AND m.project_link = ?3
+         ORDER BY s.priority, s.name, m.datetime",
     )?;
 
-    let rows = stmt.query_map(
-        params![start.timestamp() - 86400, end.timestamp() + 86400, project_id],
-        |row| {
-            let datetime_unix: i64 = row.get(0)?;
-            let temp: Option<f64> = row.get(1)?;
-            let press: Option<f64> = row.get(2)?;
-            Ok((datetime_unix, temp, press))
-        },
-    )?;
+    let rows = stmt.query_map(params![start.timestamp(), end.timestamp(), project_id], |row| {
+        let station =
+            MeteoStation { name: row.get(0)?, priority: row.get(1)?, max_gap_s: row.get(2)? };
+        let datetime_unix: i64 = row.get(3)?;
+        let temp: Option<f64> = row.get(4)?;
+        let press: Option<f64> = row.get(5)?;
+        Ok((station, datetime_unix, temp, press))
+    })?;
 
     let mut meteos = MeteoData::default();
 
     for row in rows {
-        let (time, temp, press) = row?;
-        meteos.datetime.push(time);
-        meteos.temperature.push(MeteoPoint {
+        let (station, time, temp, press) = row?;
+        if meteos.stations.last().is_none_or(|(last, _)| last.name != station.name) {
+            meteos.stations.push((station, MeteoSeries::default()));
+        }
+        let (_, series) = meteos.stations.last_mut().unwrap();
+        series.datetime.push(time);
+        series.temperature.push(MeteoPoint {
             value: temp,
             source: match temp {
                 Some(_) => MeteoSource::Raw,
@@ -366,7 +579,7 @@ pub fn query_meteo(
             },
             distance_from_target: None,
         });
-        meteos.pressure.push(MeteoPoint {
+        series.pressure.push(MeteoPoint {
             value: press,
             source: match press {
                 Some(_) => MeteoSource::Raw,
@@ -397,7 +610,7 @@ pub async fn query_meteo_async(
     }
 }
 
-pub fn read_meteo_csv<P: AsRef<Path>>(file_path: P, tz: Tz) -> Result<MeteoData, Box<dyn Error>> {
+pub fn read_meteo_csv<P: AsRef<Path>>(file_path: P, tz: Tz) -> Result<MeteoSeries, Box<dyn Error>> {
     let content = ensure_utf8(&file_path)?;
     let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(content.as_bytes());
 
@@ -437,30 +650,30 @@ pub fn read_meteo_csv<P: AsRef<Path>>(file_path: P, tz: Tz) -> Result<MeteoData,
         pressure.push(press_point);
     }
 
-    Ok(MeteoData { datetime, temperature, pressure })
+    Ok(MeteoSeries { datetime, temperature, pressure })
 }
 
+/// Uploads meteo files as measurements of `station`
 pub fn upload_meteo_data_async(
     selected_paths: Vec<PathBuf>,
     conn: &mut Connection,
     project: &Project,
+    station: &MeteoStation,
     tz: Tz,
     progress_sender: mpsc::UnboundedSender<ProcessEvent>,
 ) {
-    for path in &selected_paths {
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::meteo_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
         let project_id = project.id.unwrap();
 
-        let file_name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => {
-                eprintln!("Skipping path with invalid filename: {:?}", path);
-                let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::meteo_fail(
-                    path.to_string_lossy().to_string(),
-                    "Invalid file name (non-UTF8)".to_string(),
-                )));
-                return;
-            },
-        };
+        let file_name = input.name.as_str();
 
         let tx = match conn.transaction() {
             Ok(tx) => tx,
@@ -491,8 +704,19 @@ pub fn upload_meteo_data_async(
             },
         };
 
+        let station_id = match get_or_insert_meteo_station(&tx, &project_id, station) {
+            Ok(id) => id,
+            Err(e) => {
+                let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(format!(
+                    "File '{}' skipped, could not add station '{}': {}",
+                    file_name, station.name, e
+                ))));
+                continue;
+            },
+        };
+
         match read_meteo_csv(path, tz) {
-            Ok(res) => match insert_meteo_data(&tx, &file_id, &project.id.unwrap(), &res) {
+            Ok(res) => match insert_meteo_data(&tx, &file_id, &project_id, &station_id, &res) {
                 Ok((inserts, skips)) => {
                     touch_if_exists_updated(file_exists, inserts, &tx);
 
@@ -513,7 +737,7 @@ pub fn upload_meteo_data_async(
             },
             Err(e) => {
                 let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::meteo_fail(
-                    path.to_string_lossy().to_string(),
+                    input.label.clone(),
                     e.to_string(),
                 )));
             },
@@ -522,3 +746,98 @@ pub fn upload_meteo_data_async(
         let _ = progress_sender.send(ProcessEvent::Done(Ok(())));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(value: Option<f64>) -> MeteoPoint {
+        let source = if value.is_some() { MeteoSource::Raw } else { MeteoSource::Missing };
+        MeteoPoint { value, source, distance_from_target: None }
+    }
+
+    fn data() -> MeteoSeries {
+        // 10 minute samples, pressure missing at 1200
+        MeteoSeries {
+            datetime: vec![0, 600, 1200, 1800, 5400],
+            temperature: [0.0, 6.0, 12.0, 18.0, 20.0].map(|v| point(Some(v))).to_vec(),
+            pressure: [Some(1000.0), Some(1001.0), None, Some(1003.0), Some(1004.0)]
+                .map(point)
+                .to_vec(),
+        }
+    }
+
+    #[test]
+    fn linear_between_samples() {
+        let (t, p) = data().get_at(900, MeteoInterpolation::Linear, 1800).unwrap();
+        assert_eq!(t.value, Some(9.0));
+        assert_eq!(t.source, MeteoSource::Interpolated);
+        assert_eq!(t.distance_from_target, Some(-300));
+        // the missing sample is stepped over
+        assert!((p.value.unwrap() - 1001.5).abs() < 1e-9);
+
+        let (t, _) = data().get_at(1200, MeteoInterpolation::Linear, 1800).unwrap();
+        assert_eq!(
+            (t.value, t.source, t.distance_from_target),
+            (Some(12.0), MeteoSource::Raw, Some(0))
+        );
+    }
+
+    #[test]
+    fn nearest_and_max_gap() {
+        let (t, p) = data().get_at(1000, MeteoInterpolation::Nearest, 1800).unwrap();
+        assert_eq!((t.value, t.distance_from_target), (Some(12.0), Some(200)));
+        assert_eq!((p.value, p.distance_from_target), (Some(1001.0), Some(-400)));
+
+        // 5400 is too far for interpolation, the sample before is used
+        let (t, _) = data().get_at(3000, MeteoInterpolation::Linear, 1800).unwrap();
+        assert_eq!((t.value, t.source), (Some(18.0), MeteoSource::Raw));
+
+        assert!(data().get_at(3700, MeteoInterpolation::Linear, 1500).is_none());
+        assert!(MeteoData::default().get_at(0, MeteoInterpolation::Linear, 1800).is_none());
+    }
+
+    #[test]
+    fn stations_in_priority_order() {
+        let station = |name: &str, priority, max_gap_s| MeteoStation {
+            name: name.to_owned(),
+            priority,
+            max_gap_s,
+        };
+        // the site station only logs temperature, hourly
+        let site = MeteoSeries {
+            datetime: vec![0, 3600],
+            temperature: [1.0, 2.0].map(|v| point(Some(v))).to_vec(),
+            pressure: vec![point(None); 2],
+        };
+        let meteo = MeteoData {
+            stations: vec![
+                (station("site", 0, Some(900)), site),
+                (station("airport", 1, None), data()),
+            ],
+        };
+
+        let (t, p, stations) = meteo.get_at(600, MeteoInterpolation::Linear, 1800).unwrap();
+        assert_eq!((t.value, stations.temperature.as_deref()), (Some(1.0), Some("site")));
+        assert_eq!((p.value, stations.pressure.as_deref()), (Some(1001.0), Some("airport")));
+
+        // out of the site station's own window, the next one is used
+        let (t, _, stations) = meteo.get_at(1800, MeteoInterpolation::Linear, 1800).unwrap();
+        assert_eq!((t.value, stations.temperature.as_deref()), (Some(18.0), Some("airport")));
+    }
+
+    #[test]
+    fn pressure_from_elevation() {
+        assert!((barometric_pressure(0.0, 15.0, None) - SEA_LEVEL_PRESSURE).abs() < 1e-9);
+        // about 815 hPa at 1800 m on a mild day
+        let p = barometric_pressure(1800.0, 10.0, Some(46.5));
+        assert!((p - 815.0).abs() < 5.0, "{p}");
+        assert!(barometric_pressure(1800.0, -10.0, None) < p);
+
+        let defaulted = MeteoPoint::new(DEFAULT_PRESSURE);
+        let modelled = defaulted.or_modelled(Some(p));
+        assert_eq!((modelled.value, modelled.source), (Some(p), MeteoSource::Modelled));
+        assert_eq!(defaulted.or_modelled(None).source, MeteoSource::Default);
+        assert_eq!(point(Some(990.0)).or_modelled(Some(p)).value, Some(990.0));
+    }
+}
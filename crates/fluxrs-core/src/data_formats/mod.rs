@@ -1,5 +1,11 @@
+pub mod auxdata;
 pub mod chamberdata;
+pub mod chambermeteo;
+pub mod chamberseries;
+pub mod clockdata;
+pub mod cyclecheck;
+pub mod cyclemapping;
 pub mod gasdata;
-pub mod heightdata;
 pub mod meteodata;
+pub mod multiplexer;
 pub mod timedata;
@@ -0,0 +1,353 @@
+use crate::data_formats::timedata::{TimeData, TimeFormatParser};
+use crate::instruments::instruments::{get_or_insert_instrument, Instrument};
+use crate::processevent::{ProcessEvent, ReadEvent};
+use crate::project::Project;
+use chrono::{LocalResult, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use csv::StringRecord;
+use rusqlite::Connection;
+use std::error::Error;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::sync::mpsc;
+
+/// Seconds before the closure a cycle starts and after the opening it ends,
+/// shortened when the previous or next chamber moves sooner
+const MARGIN_S: i64 = 60;
+/// Lines searched for the header row
+const MAX_PREAMBLE: usize = 20;
+
+/// State a multiplexer reports for a chamber
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChamberState {
+    Closing,
+    Closed,
+    Opening,
+    Open,
+}
+
+impl ChamberState {
+    /// The chamber is moving away from the soil, ends a closure
+    fn is_opening(self) -> bool {
+        matches!(self, ChamberState::Opening | ChamberState::Open)
+    }
+}
+
+impl fmt::Display for ChamberState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChamberState::Closing => write!(f, "closing"),
+            ChamberState::Closed => write!(f, "closed"),
+            ChamberState::Opening => write!(f, "opening"),
+            ChamberState::Open => write!(f, "open"),
+        }
+    }
+}
+
+impl FromStr for ChamberState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "closing" => Ok(ChamberState::Closing),
+            "closed" | "close" | "1" => Ok(ChamberState::Closed),
+            "opening" => Ok(ChamberState::Opening),
+            "open" | "opened" | "0" => Ok(ChamberState::Open),
+            other => Err(format!("invalid chamber state: {other}")),
+        }
+    }
+}
+
+/// State change of one chamber, time in seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub time: i64,
+    pub chamber: String,
+    pub state: ChamberState,
+}
+
+/// Chamber, start time and close, open and end offsets of a cycle
+type Cycle = (String, i64, i64, i64, i64);
+
+/// Cycles from the state changes of a multiplexer.
+///
+/// A cycle is closed at the first `Closed` of a chamber and opened at the
+/// following `Opening` or `Open` of the same chamber. It starts 60 s before
+/// closing, or at the previous opening of any chamber if that is later, and
+/// ends 60 s after opening, or at the next closing of any chamber if that is
+/// sooner. Closures that are never opened are returned as errors.
+pub fn transitions_to_cycles(transitions: &[Transition]) -> (Vec<Cycle>, Vec<String>) {
+    let mut sorted = transitions.to_vec();
+    sorted.sort_by_key(|t| t.time);
+
+    let mut cycles = Vec::new();
+    let mut errors = Vec::new();
+    // chamber and time of closures not yet opened
+    let mut closed: Vec<(String, i64)> = Vec::new();
+    for (i, t) in sorted.iter().enumerate() {
+        let pending = closed.iter().position(|(c, _)| *c == t.chamber);
+        match (t.state, pending) {
+            (ChamberState::Closed, None) => closed.push((t.chamber.clone(), t.time)),
+            (state, Some(p)) if state.is_opening() => {
+                let (chamber, close) = closed.remove(p);
+                let start = sorted[..i]
+                    .iter()
+                    .rev()
+                    .find(|o| o.time < close && o.state.is_opening())
+                    .map_or(close - MARGIN_S, |o| o.time.max(close - MARGIN_S));
+                let end = sorted[i + 1..]
+                    .iter()
+                    .find(|n| !n.state.is_opening())
+                    .map_or(t.time + MARGIN_S, |n| n.time.min(t.time + MARGIN_S));
+                if t.time > close {
+                    cycles.push((chamber, start, close - start, t.time - start, end - start));
+                }
+            },
+            _ => {},
+        }
+    }
+    for (chamber, close) in closed {
+        errors.push(format!("chamber {chamber} closed at {close} was never opened"));
+    }
+    cycles.sort_by_key(|c| c.1);
+    (cycles, errors)
+}
+
+/// State log of an automated chamber multiplexer, one row per state change
+#[derive(Debug, Clone, Copy)]
+pub struct MultiplexerLog {
+    pub name: &'static str,
+    /// Columns joined with a space make the timestamp
+    pub time_cols: &'static [&'static str],
+    pub time_fmt: &'static str,
+    pub chamber_col: &'static str,
+    pub state_col: &'static str,
+}
+
+/// LI-8150 port sequence log, `Date`, `Time`, `Port` and `Action` columns
+pub const LI8150: MultiplexerLog = MultiplexerLog {
+    name: "LI-8150 port log",
+    time_cols: &["Date", "Time"],
+    time_fmt: "%Y-%m-%d %H:%M:%S%.f",
+    chamber_col: "Port",
+    state_col: "Action",
+};
+
+/// eosAC chamber state log, `Timestamp`, `Chamber` and `State` columns
+pub const EOSAC: MultiplexerLog = MultiplexerLog {
+    name: "eosAC state log",
+    time_cols: &["Timestamp"],
+    time_fmt: "%Y-%m-%d %H:%M:%S%.f",
+    chamber_col: "Chamber",
+    state_col: "State",
+};
+
+struct Columns {
+    time: Vec<usize>,
+    chamber: usize,
+    state: usize,
+}
+
+impl MultiplexerLog {
+    fn columns(&self, header: &StringRecord) -> Option<Columns> {
+        let find = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+        Some(Columns {
+            time: self.time_cols.iter().map(|c| find(c)).collect::<Option<_>>()?,
+            chamber: find(self.chamber_col)?,
+            state: find(self.state_col)?,
+        })
+    }
+
+    fn parse_row(
+        &self,
+        record: &StringRecord,
+        cols: &Columns,
+        tz: &Tz,
+    ) -> Result<Transition, String> {
+        let field = |i: usize| record.get(i).unwrap_or("").trim();
+        let value = cols.time.iter().map(|&i| field(i)).collect::<Vec<_>>().join(" ");
+        let naive = NaiveDateTime::parse_from_str(&value, self.time_fmt)
+            .map_err(|e| format!("'{value}' doesn't match '{}': {e}", self.time_fmt))?;
+        let time = match tz.from_local_datetime(&naive) {
+            LocalResult::Single(dt) => dt.timestamp(),
+            LocalResult::Ambiguous(dt, _) => dt.timestamp(),
+            LocalResult::None => return Err(format!("{naive} doesn't exist in {tz}")),
+        };
+        let chamber = field(cols.chamber);
+        if chamber.is_empty() {
+            return Err("chamber is empty".to_owned());
+        }
+        Ok(Transition { time, chamber: chamber.to_owned(), state: field(cols.state).parse()? })
+    }
+
+    pub(crate) fn parse_reader<R: Read>(
+        &self,
+        reader: R,
+        path: &Path,
+        tz: &Tz,
+        project: &Project,
+        conn: &Connection,
+        progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+    ) -> Result<TimeData, Box<dyn Error>> {
+        let mut lines = BufReader::new(reader).lines();
+        // loggers write a few lines of their own before the header
+        let mut skipped = 0;
+        let (header_line, sep, cols) = loop {
+            let Some(line) = lines.next().transpose()? else {
+                return Err(format!("not a {}, header not found", self.name).into());
+            };
+            let sep = if line.contains('\t') { b'\t' } else { b',' };
+            let header = StringRecord::from(line.split(sep as char).collect::<Vec<_>>());
+            if let Some(cols) = self.columns(&header) {
+                break (skipped + 1, sep, cols);
+            }
+            skipped += 1;
+            if skipped >= MAX_PREAMBLE {
+                return Err(format!("not a {}, header not found", self.name).into());
+            }
+        };
+
+        let rest: String = lines.collect::<Result<Vec<_>, _>>()?.join("\n");
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(sep)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(rest.as_bytes());
+
+        let mut transitions = Vec::new();
+        for (i, record) in rdr.records().enumerate() {
+            let row = header_line + i + 1;
+            match record.map_err(|e| e.to_string()).and_then(|r| self.parse_row(&r, &cols, tz)) {
+                Ok(t) => transitions.push(t),
+                Err(e) => {
+                    let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::RowFail(format!(
+                        "Skipped row {} in file {}: {}",
+                        row,
+                        path.display(),
+                        e
+                    ))));
+                },
+            }
+        }
+
+        let (cycles, errors) = transitions_to_cycles(&transitions);
+        for e in errors {
+            let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::RowFail(format!(
+                "In file {}: {}",
+                path.display(),
+                e
+            ))));
+        }
+        if cycles.is_empty() {
+            return Err(format!("no chamber closures found in {}", self.name).into());
+        }
+
+        // cycles are measured with the main instrument, same as the default format
+        let instrument = Instrument {
+            model: project.instrument.model,
+            serial: project.instrument.serial.clone(),
+            id: None,
+        };
+        let instrument_id = get_or_insert_instrument(conn, &instrument, project.id.unwrap())?;
+
+        let mut data = TimeData::new();
+        for (chamber, start, close, open, end) in cycles {
+            data.chamber_id.push(chamber);
+            data.start_time.push(start);
+            data.close_offset.push(close);
+            data.open_offset.push(open);
+            data.end_offset.push(end);
+            data.snow_depth.push(0.0);
+            data.air_temperature.push(None);
+            data.note.push(None);
+            data.project_id.push(project.id.unwrap());
+            data.instrument_id.push(instrument_id);
+        }
+        Ok(data)
+    }
+}
+
+impl TimeFormatParser for MultiplexerLog {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn parse(
+        &self,
+        path: &Path,
+        tz: &Tz,
+        project: &Project,
+        conn: &Connection,
+        progress_sender: mpsc::UnboundedSender<ProcessEvent>,
+    ) -> Result<TimeData, Box<dyn Error>> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("failed to open file {}: {}", path.display(), e))?;
+
+        self.parse_reader(file, path, tz, project, conn, progress_sender).map_err(|e| {
+            format!("failed to parse {} as {}: {}", path.display(), self.name, e).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_conn;
+    use std::io::Cursor;
+
+    fn parse(log: &MultiplexerLog, content: &str) -> Result<TimeData, Box<dyn Error>> {
+        let (sender, _) = mpsc::unbounded_channel();
+        let project = Project { id: Some(1), ..Project::default() };
+        let conn = test_conn();
+        log.parse_reader(
+            Cursor::new(content),
+            Path::new("t.txt"),
+            &Tz::UTC,
+            &project,
+            &conn,
+            sender,
+        )
+    }
+
+    #[test]
+    fn li8150_port_sequence() {
+        let log = "LI-8150 multiplexer\n\
+                   Date\tTime\tPort\tAction\n\
+                   2024-01-02\t10:00:00\t1\tClose\n\
+                   2024-01-02\t10:03:00\t1\tOpen\n\
+                   2024-01-02\t10:03:30\t2\tClose\n\
+                   2024-01-02\t10:06:30\t2\tOpen\n\
+                   2024-01-02\t10:07:00\t3\tbroken\n\
+                   2024-01-02\t10:07:30\t3\tClose\n";
+        let data = parse(&LI8150, log).unwrap();
+
+        // port 3 is never opened
+        assert_eq!(data.chamber_id, vec!["1", "2"]);
+        assert_eq!(data.start_time, vec![1704189540, 1704189780]);
+        assert_eq!(data.close_offset, vec![60, 30]);
+        assert_eq!(data.open_offset, vec![240, 210]);
+        assert_eq!(data.end_offset, vec![270, 270]);
+        assert!(parse(&EOSAC, log).is_err());
+    }
+
+    #[test]
+    fn eosac_intermediate_states() {
+        let log = "Timestamp,Chamber,State\n\
+                   2024-01-02 10:00:00,4,Closing\n\
+                   2024-01-02 10:00:20,4,Closed\n\
+                   2024-01-02 10:05:20,4,Opening\n\
+                   2024-01-02 10:05:40,4,Open\n\
+                   2024-01-02 10:20:00,5,Closing\n\
+                   2024-01-02 10:20:20,5,Closed\n\
+                   2024-01-02 10:25:20,5,Opening\n";
+        let data = parse(&EOSAC, log).unwrap();
+
+        assert_eq!(data.chamber_id, vec!["4", "5"]);
+        assert_eq!(data.start_time[0], 1704189600 + 20 - 60);
+        assert_eq!(data.close_offset, vec![60, 60]);
+        assert_eq!(data.open_offset, vec![360, 360]);
+        assert_eq!(data.end_offset, vec![420, 420]);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::archive::expand_inputs;
+use crate::data_formats::cyclecheck::{check_cycles, CycleValidation};
+use crate::data_formats::cyclemapping::CycleMapping;
+use crate::data_formats::multiplexer::{MultiplexerLog, EOSAC, LI8150};
 use crate::instruments::instruments::get_or_insert_instrument;
 use crate::instruments::instruments::{Instrument, InstrumentType};
 use crate::processevent::{
@@ -6,8 +10,9 @@ use crate::processevent::{
 use crate::project::Project;
 use crate::traits::EqualLen;
 use crate::utils::{ensure_utf8, touch_if_exists_updated, DataFileError};
-use chrono::{DateTime, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
 use chrono_tz::{Tz, UTC};
+use csv::StringRecord;
 use rusqlite::{params, Connection, Result};
 use std::error::Error;
 use std::io::Read;
@@ -22,6 +27,7 @@ use std::path::PathBuf;
 enum ParserType {
     Oulanka(OulankaManualFormat),
     Default(DefaultFormat),
+    Multiplexer(MultiplexerLog),
 }
 
 impl TimeFormatParser for ParserType {
@@ -29,6 +35,7 @@ impl TimeFormatParser for ParserType {
         match self {
             ParserType::Oulanka(p) => p.name(),
             ParserType::Default(p) => p.name(),
+            ParserType::Multiplexer(p) => p.name(),
         }
     }
 
@@ -43,6 +50,7 @@ impl TimeFormatParser for ParserType {
         match self {
             ParserType::Oulanka(p) => p.parse(path, tz, project, conn, progress_sender),
             ParserType::Default(p) => p.parse(path, tz, project, conn, progress_sender),
+            ParserType::Multiplexer(p) => p.parse(path, tz, project, conn, progress_sender),
         }
     }
 }
@@ -68,6 +76,9 @@ pub struct TimeData {
     pub open_offset: Vec<i64>,
     pub end_offset: Vec<i64>,
     pub snow_depth: Vec<f64>,
+    /// Air temperature written down in the field, used instead of meteo data
+    pub air_temperature: Vec<Option<f64>>,
+    pub note: Vec<Option<String>>,
     pub id: Vec<i64>,
     pub project_id: Vec<i64>,
     pub instrument_id: Vec<i64>,
@@ -82,24 +93,144 @@ impl EqualLen for TimeData {
             &self.open_offset.len(),
             &self.end_offset.len(),
             &self.snow_depth.len(),
+            &self.air_temperature.len(),
+            &self.note.len(),
         ];
-        let mut check: bool = true;
-
-        for vec_len in lengths.iter() {
-            let len = vec_len;
-            if vec_len != len {
-                check = false;
-                break;
-            } else {
-                continue;
-            };
-        }
-        check
+        lengths.iter().all(|len| *len == lengths[0])
     }
 }
+/// Cycles written down in the field during manual chamber measurements.
+///
+/// ```text
+/// ,240621
+/// ,120
+/// ,LI7810
+/// ,TG10-01169
+/// chamber_id,start_time,snow_depth,duration,air_temperature,note
+/// CH1,1234,0
+/// CH2,123530,12,300,-4.5,"lid frozen, closed twice"
+/// ```
+///
+/// The first rows are the date (YYMMDD), the measurement time in seconds,
+/// and the model and serial of the instrument. Start times are HHMM or
+/// HHMMSS, colons are allowed. Columns after the start time are found from
+/// the header and are all optional:
+/// - `snow_depth` in cm, read from the third column when no column is named
+///   so in the header
+/// - `date` (YYMMDD or YYYY-MM-DD), date of the row and the rows after it
+/// - `duration` in seconds, replaces the measurement time of the file
+/// - `air_temperature` in °C, used instead of meteo data
+/// - `note`, free text stored with the cycle
+///
+/// Without a date, a start time over 12 hours before the previous one is on
+/// the next day. Cycles start 60 seconds before the given time and end 60
+/// seconds after the chamber is opened.
 struct OulankaManualFormat;
 
+/// Seconds recorded before closing and after opening the chamber
+const MANUAL_PADDING_S: i64 = 60;
+
+/// Optional columns of a manual file
+struct ManualColumns {
+    snow_depth: Option<usize>,
+    date: Option<usize>,
+    duration: Option<usize>,
+    air_temperature: Option<usize>,
+    note: Option<usize>,
+}
+
+impl ManualColumns {
+    fn from_header(header: &StringRecord) -> Self {
+        let find = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+        let date = find("date");
+        let duration = find("duration");
+        let air_temperature = find("air_temperature");
+        let note = find("note");
+        // older files have the snow depth in the third column under any name
+        let snow_depth = find("snow_depth").or_else(|| {
+            let taken = [date, duration, air_temperature, note].contains(&Some(2));
+            (!taken).then_some(2)
+        });
+        ManualColumns { snow_depth, date, duration, air_temperature, note }
+    }
+}
+
+/// One parsed row of a manual file
+struct ManualRow {
+    start: NaiveDateTime,
+    duration: i64,
+    snow_depth: f64,
+    air_temperature: Option<f64>,
+    note: Option<String>,
+}
+
+/// Time of day as HHMM or HHMMSS, with or without colons
+fn parse_time_of_day(s: &str) -> Option<NaiveTime> {
+    let digits: String = s.trim().chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match digits.len() {
+        3 | 4 => NaiveTime::parse_from_str(&format!("{digits:0>4}"), "%H%M").ok(),
+        5 | 6 => NaiveTime::parse_from_str(&format!("{digits:0>6}"), "%H%M%S").ok(),
+        _ => None,
+    }
+}
+
+fn parse_manual_date(s: &str) -> Option<NaiveDate> {
+    let s = s.trim();
+    NaiveDate::parse_from_str(s, "%y%m%d")
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d"))
+        .ok()
+}
+
 impl OulankaManualFormat {
+    fn parse_row(
+        record: &StringRecord,
+        cols: &ManualColumns,
+        date: NaiveDate,
+        previous: Option<NaiveDateTime>,
+        measurement_time: i64,
+    ) -> Result<ManualRow, String> {
+        let field = |i: Option<usize>| {
+            i.and_then(|i| record.get(i)).map(str::trim).filter(|s| !s.is_empty())
+        };
+
+        let time_str = record.get(1).unwrap_or("").trim();
+        let time = parse_time_of_day(time_str)
+            .ok_or_else(|| format!("start time '{time_str}' is not HHMM or HHMMSS"))?;
+        let start = match field(cols.date) {
+            Some(s) => parse_manual_date(s)
+                .ok_or_else(|| format!("date '{s}' is not YYMMDD or YYYY-MM-DD"))?
+                .and_time(time),
+            None => {
+                let mut start = previous.map_or(date, |p| p.date()).and_time(time);
+                if previous.is_some_and(|p| start < p - Duration::hours(12)) {
+                    start += Duration::days(1);
+                }
+                start
+            },
+        };
+
+        let duration = match field(cols.duration) {
+            Some(s) => match s.parse::<i64>() {
+                Ok(d) if d > 0 => d,
+                _ => return Err(format!("duration '{s}' is not a positive number of seconds")),
+            },
+            None => measurement_time,
+        };
+        let air_temperature = field(cols.air_temperature)
+            .map(|s| s.parse::<f64>().map_err(|_| format!("air temperature '{s}' is not a number")))
+            .transpose()?;
+        let snow_depth = field(cols.snow_depth)
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(|cm| cm / 100.)
+            .unwrap_or(0.0);
+        let note = field(cols.note).map(str::to_owned);
+
+        Ok(ManualRow { start, duration, snow_depth, air_temperature, note })
+    }
+
     fn parse_reader<R: Read>(
         &self,
         reader: R,
@@ -113,28 +244,17 @@ impl OulankaManualFormat {
             csv::ReaderBuilder::new().flexible(true).has_headers(false).from_reader(reader);
 
         let mut date = NaiveDate::default();
-        let mut instrument_serial = Vec::new();
-        let mut instrument_model = Vec::new();
         let mut insmodel = InstrumentType::default();
         let mut insserial = String::new();
         let mut measurement_time: i64 = 0;
 
-        let mut chamber_id: Vec<String> = Vec::new();
-        let mut start_time: Vec<i64> = Vec::new();
-        let mut close_offset: Vec<i64> = Vec::new();
-        let mut open_offset: Vec<i64> = Vec::new();
-        let mut end_offset: Vec<i64> = Vec::new();
-        let mut project_id_vec: Vec<i64> = Vec::new();
-        let mut instrument_id_vec: Vec<i64> = Vec::new();
-        let mut snow_in_chamber: Vec<f64> = Vec::new();
-
         let mut records = rdr.records();
 
         if let Some(result) = records.next() {
             let date_str = result?.get(1).unwrap_or("").to_string();
-            match NaiveDate::parse_from_str(&date_str, "%y%m%d") {
-                Ok(ndate) => date = ndate,
-                Err(_) => {
+            match parse_manual_date(&date_str) {
+                Some(ndate) => date = ndate,
+                None => {
                     let msg = format!("Failed to parse first row {} as YYMMDD", date_str);
                     return Err(msg.into());
                 },
@@ -174,9 +294,16 @@ impl OulankaManualFormat {
             insserial = parsed_serial
         }
 
-        // Skip header row before data
-        records.next();
+        let cols = match records.next() {
+            Some(header) => ManualColumns::from_header(&header?),
+            None => return Err("File has no header row".into()),
+        };
+
+        let instrument = Instrument { model: insmodel, serial: insserial, id: None };
+        let instrument_id = get_or_insert_instrument(conn, &instrument, project.id.unwrap())?;
 
+        let mut data = TimeData::new();
+        let mut previous: Option<NaiveDateTime> = None;
         for (i, r) in records.enumerate() {
             let record = match r {
                 Ok(rec) => rec,
@@ -191,75 +318,49 @@ impl OulankaManualFormat {
                 },
             };
 
-            match NaiveTime::parse_from_str(&record[1], "%H%M") {
-                Ok(naive_time) => {
-                    let naive_dt = date.and_time(naive_time);
-                    let dt_utc = match tz.from_local_datetime(&naive_dt) {
-                        LocalResult::Single(dt) => dt.with_timezone(&Utc),
-                        LocalResult::Ambiguous(dt1, _) => dt1.with_timezone(&Utc),
-                        LocalResult::None => {
-                            return Err(format!(
-                        "Impossible local time {} on row {}. Selected timezone ({}) is likely incorrect.",
-                        i+5,naive_dt, tz
-                    )
-                            .into());
-                        },
-                    };
-
-                    let dt_unix = dt_utc.timestamp();
-                    start_time.push(dt_unix - 60);
-                },
-                Err(_) => {
+            let row = match Self::parse_row(&record, &cols, date, previous, measurement_time) {
+                Ok(row) => row,
+                Err(e) => {
                     let row_string = record.iter().collect::<Vec<_>>().join(",");
                     let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::RowFail(format!(
-                        "Failed to parse datetime on row {}: '{}' in file {}",
+                        "Failed to parse row {}: '{}' in file {}: {}",
                         i + 6,
                         row_string,
-                        path.to_string_lossy()
+                        path.to_string_lossy(),
+                        e
                     ))));
                     continue;
                 },
-            }
-
-            if let Some(snow_str) = record.get(2) {
-                match snow_str.parse::<f64>() {
-                    Ok(value) => snow_in_chamber.push(value / 100.),
-                    Err(e) => {
-                        snow_in_chamber.push(0.0);
-                        eprintln!("No snow_depth supplied, using 0.0");
-                    },
-                }
-            } else {
-                snow_in_chamber.push(0.0);
-                eprintln!("Missing column 2 in row {}", i + 1);
-            }
+            };
+            previous = Some(row.start);
 
-            let conn = Connection::open("fluxrs.db").expect("Failed to open database");
-            let instrument = Instrument { model: insmodel, serial: insserial.clone(), id: None };
-            let instrument_id = get_or_insert_instrument(&conn, &instrument, project.id.unwrap())?;
+            let dt_utc = match tz.from_local_datetime(&row.start) {
+                LocalResult::Single(dt) => dt.with_timezone(&Utc),
+                LocalResult::Ambiguous(dt1, _) => dt1.with_timezone(&Utc),
+                LocalResult::None => {
+                    return Err(format!(
+                        "Impossible local time {} on row {}. Selected timezone ({}) is likely incorrect.",
+                        row.start,
+                        i + 6,
+                        tz
+                    )
+                    .into());
+                },
+            };
 
-            chamber_id.push(record[0].to_owned());
-            instrument_model.push(insmodel);
-            instrument_serial.push(insserial.clone());
-            close_offset.push(60);
-            open_offset.push(measurement_time + 60);
-            end_offset.push(measurement_time + 120);
-            project_id_vec.push(project.id.unwrap());
-            instrument_id_vec.push(instrument_id)
+            data.chamber_id.push(record[0].trim().to_owned());
+            data.start_time.push(dt_utc.timestamp() - MANUAL_PADDING_S);
+            data.close_offset.push(MANUAL_PADDING_S);
+            data.open_offset.push(row.duration + MANUAL_PADDING_S);
+            data.end_offset.push(row.duration + 2 * MANUAL_PADDING_S);
+            data.snow_depth.push(row.snow_depth);
+            data.air_temperature.push(row.air_temperature);
+            data.note.push(row.note);
+            data.project_id.push(project.id.unwrap());
+            data.instrument_id.push(instrument_id);
         }
-        let id = Vec::new();
 
-        Ok(TimeData {
-            chamber_id,
-            start_time,
-            close_offset,
-            open_offset,
-            end_offset,
-            id,
-            snow_depth: snow_in_chamber,
-            project_id: project_id_vec,
-            instrument_id: instrument_id_vec,
-        })
+        Ok(data)
     }
 }
 impl TimeFormatParser for OulankaManualFormat {
@@ -301,6 +402,8 @@ impl DefaultFormat {
         let mut open_offset: Vec<i64> = Vec::new();
         let mut end_offset: Vec<i64> = Vec::new();
         let mut snow_in_chamber: Vec<f64> = Vec::new();
+        let mut air_temperature: Vec<Option<f64>> = Vec::new();
+        let mut note: Vec<Option<String>> = Vec::new();
         let mut project_id: Vec<i64> = Vec::new();
         let mut instrument_id: Vec<i64> = Vec::new();
 
@@ -372,6 +475,8 @@ impl DefaultFormat {
             project_id.push(project.id.unwrap());
             instrument_id.push(ins_id);
             snow_in_chamber.push(0.0);
+            air_temperature.push(None);
+            note.push(None);
 
             parsed_any = true;
         }
@@ -392,6 +497,8 @@ impl DefaultFormat {
             end_offset,
             id,
             snow_depth: snow_in_chamber,
+            air_temperature,
+            note,
             project_id,
             instrument_id,
         };
@@ -435,6 +542,8 @@ impl TimeData {
             end_offset: Vec::new(),
             id: Vec::new(),
             snow_depth: Vec::new(),
+            air_temperature: Vec::new(),
+            note: Vec::new(),
             project_id: Vec::new(),
             instrument_id: Vec::new(),
         }
@@ -454,6 +563,8 @@ impl TimeData {
                 chamber_id: self.chamber_id[i..end].to_vec(),
                 id: self.id[i..end].to_vec(),
                 snow_depth: self.snow_depth[i..end].to_vec(),
+                air_temperature: self.air_temperature[i..end].to_vec(),
+                note: self.note[i..end].to_vec(),
                 project_id: self.project_id[i..end].to_vec(),
                 instrument_id: self.instrument_id[i..end].to_vec(),
             };
@@ -493,7 +604,8 @@ pub fn query_cycles(
 ) -> Result<TimeData> {
     println!("Querying cycles");
     let mut stmt = conn.prepare(
-        "SELECT c.chamber_id, c.start_time, c.close_offset, c.open_offset, c.end_offset, c.snow_depth, c.id, i.id AS instrument_id, p.id
+        "SELECT c.chamber_id, c.start_time, c.close_offset, c.open_offset, c.end_offset, c.snow_depth, c.id, i.id AS instrument_id, p.id,
+                c.air_temperature, c.note
          FROM cycles c
          LEFT JOIN instruments i ON c.instrument_link = i.id
          LEFT JOIN projects p ON c.project_link = p.id
@@ -514,6 +626,8 @@ pub fn query_cycles(
             let id: i64 = row.get(6)?;
             let instrument_id: i64 = row.get(7)?;
             let project_id: i64 = row.get(8)?;
+            let air_temperature: Option<f64> = row.get(9)?;
+            let note: Option<String> = row.get(10)?;
 
             times.chamber_id.push(chamber_id);
             times.start_time.push(start_timestamp);
@@ -522,6 +636,8 @@ pub fn query_cycles(
             times.end_offset.push(end_offset);
             times.id.push(id);
             times.snow_depth.push(snow_depth);
+            times.air_temperature.push(air_temperature);
+            times.note.push(note);
             times.project_id.push(project_id);
             times.instrument_id.push(instrument_id);
 
@@ -569,6 +685,8 @@ pub fn insert_cycles(
     let ins_id_vec = &cycles.instrument_id;
     let proj_id_vec = &cycles.project_id;
     let start_vec = &cycles.start_time;
+    let temp_vec = &cycles.air_temperature;
+    let note_vec = &cycles.note;
 
     if !(close_vec.len() == open_vec.len()
         && open_vec.len() == end_vec.len()
@@ -576,7 +694,9 @@ pub fn insert_cycles(
         && chamber_vec.len() == snow_vec.len()
         && snow_vec.len() == ins_id_vec.len()
         && ins_id_vec.len() == proj_id_vec.len()
-        && proj_id_vec.len() == start_vec.len())
+        && proj_id_vec.len() == start_vec.len()
+        && start_vec.len() == temp_vec.len()
+        && temp_vec.len() == note_vec.len())
     {
         return Err(rusqlite::Error::FromSqlConversionFailure(
             0,
@@ -585,7 +705,8 @@ pub fn insert_cycles(
                 std::io::ErrorKind::InvalidData,
                 format!(
                     "Mismatched vector lengths:\n\
-            close: {}, open: {}, end: {}, chamber: {}, snow: {}, ins_id: {}, proj_id: {}, start: {}",
+            close: {}, open: {}, end: {}, chamber: {}, snow: {}, ins_id: {}, proj_id: {}, start: {}, \
+            air_temperature: {}, note: {}",
                     close_vec.len(),
                     open_vec.len(),
                     end_vec.len(),
@@ -593,7 +714,9 @@ pub fn insert_cycles(
                     snow_vec.len(),
                     ins_id_vec.len(),
                     proj_id_vec.len(),
-                    start_vec.len()
+                    start_vec.len(),
+                    temp_vec.len(),
+                    note_vec.len()
                 ),
             )),
         ));
@@ -628,8 +751,10 @@ pub fn insert_cycles(
             snow_depth,
             project_link,
             instrument_link,
-            file_link
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            file_link,
+            air_temperature,
+            note
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
     )?;
 
     for i in 0..start_vec.len() {
@@ -684,6 +809,8 @@ pub fn insert_cycles(
             project_id,
             instrument.id,
             file_id,
+            temp_vec[i],
+            note_vec[i],
         ])?;
 
         inserted += 1;
@@ -697,15 +824,26 @@ pub fn insert_cycles(
     Ok((inserted, duplicates))
 }
 
+/// Parse a cycle file with `mapping`, or with the first built-in format that
+/// accepts it when no mapping is given
 pub fn try_all_formats(
     path: &Path,
     tz: &Tz,
     project: &Project,
     conn: &Connection,
+    mapping: Option<&CycleMapping>,
     progress_sender: mpsc::UnboundedSender<ProcessEvent>,
 ) -> Result<(TimeData, &'static str), Box<dyn Error>> {
-    let parsers =
-        vec![ParserType::Oulanka(OulankaManualFormat), ParserType::Default(DefaultFormat)];
+    if let Some(mapping) = mapping {
+        let data = mapping.parse(path, tz, project, conn, progress_sender)?;
+        return Ok((data, mapping.name()));
+    }
+    let parsers = vec![
+        ParserType::Oulanka(OulankaManualFormat),
+        ParserType::Default(DefaultFormat),
+        ParserType::Multiplexer(LI8150),
+        ParserType::Multiplexer(EOSAC),
+    ];
 
     for parser in parsers {
         let _ = progress_sender.send(ProcessEvent::Progress(ProgressEvent::Generic(format!(
@@ -725,36 +863,37 @@ pub fn try_all_formats(
 
     Err("Could not parse as a cycle file, check that your file is correct.".into())
 }
+/// Parse and insert cycle files, problems found by `check_cycles` are
+/// reported and with `CycleValidation::Reject` the file is skipped
 pub fn upload_cycle_data_async(
     selected_paths: Vec<PathBuf>,
     conn: &mut Connection,
     project: &Project,
     tz: Tz,
+    mapping: Option<&CycleMapping>,
+    validation: CycleValidation,
     progress_sender: mpsc::UnboundedSender<ProcessEvent>,
 ) {
     let mut all_times = TimeData::new();
 
-    for path in &selected_paths {
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::cycle_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
         if ensure_utf8(path).is_err() {
             let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::FileFail(
-                path.to_string_lossy().to_string(),
+                input.label.clone(),
                 "Invalid UTF-8, make sure your file is UTF-8 encoded.".to_owned(),
             )));
             continue;
         }
         let project_id = project.id.unwrap();
-        let file_name = match path.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => {
-                eprintln!("Skipping path with invalid filename: {:?}", path);
-                // Optionally notify UI:
-                let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::cycle_fail(
-                    path.to_string_lossy().to_string(),
-                    "Invalid file name (non-UTF8)".to_string(),
-                )));
-                return (); // or `continue` if this is in a loop
-            },
-        };
+        let file_name = input.name.as_str();
 
         let tx = match conn.transaction() {
             Ok(tx) => tx,
@@ -785,13 +924,33 @@ pub fn upload_cycle_data_async(
                 continue; // or return if not inside a loop
             },
         };
-        match try_all_formats(path, &tz, project, &tx, progress_sender.clone()) {
+        match try_all_formats(path, &tz, project, &tx, mapping, progress_sender.clone()) {
             //   Pass `path` directly
             Ok((res, _)) => {
                 if res.validate_lengths() {
-                    let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::File(
-                        path.to_string_lossy().to_string(),
-                    )));
+                    let _ = progress_sender
+                        .send(ProcessEvent::Read(ReadEvent::File(input.label.clone())));
+                    let problems = match check_cycles(&tx, &res, project_id, &project.tz) {
+                        Ok(problems) => problems,
+                        Err(e) => {
+                            let _ = progress_sender.send(ProcessEvent::Insert(InsertEvent::Fail(
+                                format!("Could not check cycles of '{}': {}", file_name, e),
+                            )));
+                            continue;
+                        },
+                    };
+                    for problem in &problems {
+                        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::RowFail(
+                            format!("{}: {}", input.label, problem),
+                        )));
+                    }
+                    if validation == CycleValidation::Reject && !problems.is_empty() {
+                        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::FileFail(
+                            input.label.clone(),
+                            format!("Rejected, {} problems in cycles.", problems.len()),
+                        )));
+                        continue;
+                    }
                     match insert_cycles(&tx, &res, &project.id.unwrap(), &file_id) {
                         Ok((inserts, skips)) => {
                             touch_if_exists_updated(file_exists, inserts, &tx);
@@ -819,7 +978,7 @@ pub fn upload_cycle_data_async(
                     }
                 } else {
                     let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::FileFail(
-                        path.to_string_lossy().to_string(),
+                        input.label.clone(),
                         "Skipped, data vectors are not equal length, check your data file."
                             .to_owned(),
                     )));
@@ -827,7 +986,7 @@ pub fn upload_cycle_data_async(
             },
             Err(e) => {
                 let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::cycle_fail(
-                    path.to_string_lossy().to_string(),
+                    input.label.clone(),
                     e.to_string(),
                 )));
             },
@@ -1008,4 +1167,50 @@ CH2,1250,";
         assert_eq!(data.chamber_id.len(), 1); // Only CH2 should succeed
         assert_eq!(data.chamber_id[0], "CH2");
     }
+
+    #[test]
+    fn seconds_durations_notes_and_midnight() {
+        let conn = crate::db::test_conn();
+
+        let csv = "\
+,240621
+,120
+,LI7810
+,TG10-01169
+chamber_id,start_time,snow_depth,duration,air_temperature,note,date
+CH1,23:55:30,10,,,,
+CH2,000500,0,300,-2.5,\"lid frozen, closed twice\",
+CH3,1000,0,,abc,,
+CH4,0930,0,,,,
+CH5,1200,0,,,,2024-07-01";
+
+        let (progress_sender, _) = mpsc::unbounded_channel();
+        let data = OulankaManualFormat
+            .parse_reader(
+                Cursor::new(csv),
+                mock_path(),
+                &UTC,
+                &Project { id: Some(1), ..Project::default() },
+                &conn,
+                progress_sender,
+            )
+            .unwrap();
+
+        // CH3 has an invalid temperature
+        assert_eq!(data.chamber_id, vec!["CH1", "CH2", "CH4", "CH5"]);
+        let start = |date: &str| {
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap().and_utc().timestamp()
+                - 60
+        };
+        assert_eq!(data.start_time[0], start("2024-06-21 23:55:30"));
+        assert_eq!(data.start_time[1], start("2024-06-22 00:05:00"));
+        assert_eq!(data.start_time[2], start("2024-06-22 09:30:00"));
+        assert_eq!(data.start_time[3], start("2024-07-01 12:00:00"));
+        assert_eq!(data.open_offset, vec![180, 360, 180, 180]);
+        assert_eq!(data.end_offset[1], 420);
+        assert_eq!(data.snow_depth[0], 0.1);
+        assert_eq!(data.air_temperature, vec![None, Some(-2.5), None, None]);
+        assert_eq!(data.note[1].as_deref(), Some("lid frozen, closed twice"));
+        assert!(data.validate_lengths());
+    }
 }
@@ -7,6 +7,10 @@ pub enum DataType {
     Meteo,
     Height,
     Chamber,
+    ChamberMeteo,
+    Aux,
+    Volume,
+    Snow,
 }
 impl fmt::Display for DataType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -16,6 +20,10 @@ impl fmt::Display for DataType {
             DataType::Meteo => write!(f, "Meteo data"),
             DataType::Height => write!(f, "Height data"),
             DataType::Chamber => write!(f, "Chamber metadata"),
+            DataType::ChamberMeteo => write!(f, "Chamber meteo data"),
+            DataType::Aux => write!(f, "Auxiliary data"),
+            DataType::Volume => write!(f, "Volume data"),
+            DataType::Snow => write!(f, "Snow depth data"),
         }
     }
 }
@@ -28,6 +36,10 @@ impl DataType {
             DataType::Meteo => "meteo",
             DataType::Height => "height",
             DataType::Chamber => "chamber_meta",
+            DataType::ChamberMeteo => "chamber_meteo",
+            DataType::Aux => "aux",
+            DataType::Volume => "volume",
+            DataType::Snow => "snow",
         }
     }
 }
@@ -1,6 +1,6 @@
 use rusqlite::{Connection, Result};
 
-pub const DB_VERSION: i32 = 4; // latest schema version
+pub const DB_VERSION: i32 = 21; // latest schema version
 
 pub mod fluxes_col {
     pub const START_TIME: usize = 0;
@@ -132,6 +132,21 @@ pub const FLUXES_COLUMNS: &[&str] = &[
     "exp_cv",
     "exp_a",
     "exp_b",
+    "h2o_correction",
+    "temperature_station",
+    "pressure_station",
+    "hmr_flux",
+    "hmr_r2",
+    "hmr_adj_r2",
+    "hmr_intercept",
+    "hmr_slope",
+    "hmr_sigma",
+    "hmr_p_value",
+    "hmr_aic",
+    "hmr_rmse",
+    "hmr_cv",
+    "hmr_se",
+    "hmr_kappa",
 ];
 pub const FLUXES_COLUMNS_NO_LINK: &[&str] = &[
     "start_time",
@@ -200,6 +215,21 @@ pub const FLUXES_COLUMNS_NO_LINK: &[&str] = &[
     "exp_cv",
     "exp_a",
     "exp_b",
+    "h2o_correction",
+    "temperature_station",
+    "pressure_station",
+    "hmr_flux",
+    "hmr_r2",
+    "hmr_adj_r2",
+    "hmr_intercept",
+    "hmr_slope",
+    "hmr_sigma",
+    "hmr_p_value",
+    "hmr_aic",
+    "hmr_rmse",
+    "hmr_cv",
+    "hmr_se",
+    "hmr_kappa",
 ];
 pub fn make_select_all_fluxes() -> String {
     let flux_cols_prefixed: Vec<String> =
@@ -392,6 +422,22 @@ pub fn create_flux_table() -> String {
             exp_cv                  FLOAT,
             exp_a                   FLOAT,
             exp_b                   FLOAT,
+            h2o_correction          INTEGER,
+            temperature_station     TEXT,
+            pressure_station        TEXT,
+
+            hmr_flux                FLOAT,
+            hmr_r2                  FLOAT,
+            hmr_adj_r2              FLOAT,
+            hmr_intercept           FLOAT,
+            hmr_slope               FLOAT,
+            hmr_sigma               FLOAT,
+            hmr_p_value             FLOAT,
+            hmr_aic                 FLOAT,
+            hmr_rmse                FLOAT,
+            hmr_cv                  FLOAT,
+            hmr_se                  FLOAT,
+            hmr_kappa               FLOAT,
 
             FOREIGN KEY (cycle_link) REFERENCES cycles(id) ON DELETE CASCADE,
             FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
@@ -485,6 +531,22 @@ pub fn create_flux_history_table() -> String {
             exp_cv                  FLOAT,
             exp_a                   FLOAT,
             exp_b                   FLOAT,
+            h2o_correction          INTEGER,
+            temperature_station     TEXT,
+            pressure_station        TEXT,
+
+            hmr_flux                FLOAT,
+            hmr_r2                  FLOAT,
+            hmr_adj_r2              FLOAT,
+            hmr_intercept           FLOAT,
+            hmr_slope               FLOAT,
+            hmr_sigma               FLOAT,
+            hmr_p_value             FLOAT,
+            hmr_aic                 FLOAT,
+            hmr_rmse                FLOAT,
+            hmr_cv                  FLOAT,
+            hmr_se                  FLOAT,
+            hmr_kappa               FLOAT,
 
             FOREIGN KEY (cycle_link) REFERENCES cycles(id) ON DELETE CASCADE,
             FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
@@ -494,13 +556,208 @@ pub fn create_flux_history_table() -> String {
     .to_owned()
 }
 
+pub fn create_instrument_profiles_table() -> String {
+    "CREATE TABLE IF NOT EXISTS instrument_profiles (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL UNIQUE,
+            definition      TEXT NOT NULL,
+            created_at      TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+        )"
+    .to_owned()
+}
+
+/// Long format, one row per gas so that new gases don't need new columns.
+/// `gas` is `GasType::as_int`, `datetime` is unix time in milliseconds.
+pub fn create_measurements_table() -> String {
+    "CREATE TABLE IF NOT EXISTS measurements (
+            datetime            INTEGER,
+            gas                 INTEGER NOT NULL,
+            value               FLOAT,
+            diag                INTEGER,
+            file_link           INTEGER NOT NULL,
+            project_link        INTEGER NOT NULL,
+            instrument_link     INTEGER NOT NULL,
+
+            FOREIGN KEY (instrument_link) REFERENCES instruments(id),
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+
+            PRIMARY KEY (datetime, instrument_link, project_link, gas)
+        )"
+    .to_owned()
+}
+
+/// User defined gas species, ids start from `gastype::CUSTOM_SPECIES_START`
+pub fn create_gas_species_table() -> String {
+    "CREATE TABLE IF NOT EXISTS gas_species (
+            id              INTEGER PRIMARY KEY,
+            name            TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            mol_mass        FLOAT NOT NULL,
+            unit            TEXT NOT NULL
+        )"
+    .to_owned()
+}
+
+pub fn create_clock_corrections_table() -> String {
+    "CREATE TABLE IF NOT EXISTS instrument_clock_corrections (
+            id                  INTEGER PRIMARY KEY AUTOINCREMENT,
+            instrument_link     INTEGER NOT NULL,
+            valid_from          INTEGER NOT NULL,
+            offset_s            FLOAT NOT NULL,
+            drift_s_per_day     FLOAT NOT NULL DEFAULT 0,
+
+            FOREIGN KEY (instrument_link) REFERENCES instruments(id) ON DELETE CASCADE,
+            UNIQUE (instrument_link, valid_from)
+        )"
+    .to_owned()
+}
+
+/// Temperature and pressure measured inside each chamber, `datetime` in seconds
+pub fn create_chamber_meteo_table() -> String {
+    "CREATE TABLE IF NOT EXISTS chamber_meteo (
+            datetime        INTEGER NOT NULL,
+            chamber_id      TEXT NOT NULL,
+            temperature     FLOAT,
+            pressure        FLOAT,
+            file_link       INTEGER NOT NULL,
+            project_link    INTEGER NOT NULL,
+
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            PRIMARY KEY (datetime, chamber_id, project_link)
+        )"
+    .to_owned()
+}
+
+/// Auxiliary variables in long format, `datetime` in seconds. `chamber_id` is
+/// empty for values logged for the whole project.
+pub fn create_aux_data_table() -> String {
+    "CREATE TABLE IF NOT EXISTS aux_data (
+            datetime        INTEGER NOT NULL,
+            chamber_id      TEXT NOT NULL DEFAULT '',
+            variable        TEXT NOT NULL,
+            value           FLOAT NOT NULL,
+            file_link       INTEGER NOT NULL,
+            project_link    INTEGER NOT NULL,
+
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            PRIMARY KEY (datetime, chamber_id, variable, project_link)
+        )"
+    .to_owned()
+}
+
+/// Chamber volumes measured directly, in m³
+pub fn create_volume_table() -> String {
+    "CREATE TABLE IF NOT EXISTS volume (
+            chamber_id      TEXT NOT NULL,
+            datetime        INTEGER NOT NULL,
+            volume          FLOAT NOT NULL,
+            project_link    INTEGER NOT NULL,
+            file_link       INTEGER NOT NULL,
+
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+
+            PRIMARY KEY (chamber_id, project_link, datetime)
+        )"
+    .to_owned()
+}
+
+/// Snow depths inside chambers, in m
+pub fn create_snow_table() -> String {
+    "CREATE TABLE IF NOT EXISTS snow (
+            chamber_id      TEXT NOT NULL,
+            datetime        INTEGER NOT NULL,
+            snow_depth      FLOAT NOT NULL,
+            project_link    INTEGER NOT NULL,
+            file_link       INTEGER NOT NULL,
+
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+
+            PRIMARY KEY (chamber_id, project_link, datetime)
+        )"
+    .to_owned()
+}
+
+/// Meteo stations of a project, lower `priority` is tried first. `max_gap` in
+/// seconds, the project's meteo_max_gap is used when it's NULL.
+pub fn create_meteo_stations_table() -> String {
+    "CREATE TABLE IF NOT EXISTS meteo_stations (
+            id              INTEGER PRIMARY KEY AUTOINCREMENT,
+            name            TEXT NOT NULL,
+            priority        INTEGER NOT NULL DEFAULT 0,
+            max_gap         INTEGER,
+            project_link    INTEGER NOT NULL,
+
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+            UNIQUE (name, project_link)
+        )"
+    .to_owned()
+}
+
+pub fn create_meteo_table() -> String {
+    "CREATE TABLE IF NOT EXISTS meteo (
+            datetime        INTEGER,
+            temperature     FLOAT,
+            pressure        FLOAT,
+            file_link       INTEGER NOT NULL,
+            station_link    INTEGER NOT NULL,
+            project_link    INTEGER NOT NULL,
+
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
+            FOREIGN KEY (station_link) REFERENCES meteo_stations(id) ON DELETE CASCADE,
+            PRIMARY KEY (datetime, station_link, project_link)
+        )"
+    .to_owned()
+}
+
+/// Chamber geometries, `valid_from`/`valid_to` in seconds. NULL leaves the
+/// period open on that side. Lengths in m, volumes in m³ and areas in m².
+/// Uniqueness of a chamber's periods is enforced with the
+/// idx_chamber_metadata_period index.
+pub fn create_chamber_metadata_table() -> String {
+    "CREATE TABLE IF NOT EXISTS chamber_metadata (
+            id              INTEGER PRIMARY KEY,
+            chamber_id      TEXT NOT NULL,
+            shape           TEXT NOT NULL,
+            diameter        REAL,
+            height          REAL NOT NULL,
+            width           REAL,
+            length          REAL,
+            valid_from      INTEGER,
+            valid_to        INTEGER,
+            file_link       INTEGER NOT NULL,
+            project_link    INTEGER NOT NULL,
+            top_diameter    REAL,
+            volume          REAL,
+            area            REAL,
+            collar_height   REAL,
+            system_volume   REAL,
+            vegetation_volume REAL,
+
+            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
+            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE
+        )"
+    .to_owned()
+}
+
 pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
     let conn = Connection::open("fluxrs.db")?;
 
     // conn.pragma_update(None, "journal_mode", &"WAL")?;
 
-    conn.execute(&format!("PRAGMA user_version = {};", DB_VERSION), [])?;
     conn.execute("PRAGMA foreign_keys = ON", [])?;
+    create_tables(&conn)?;
+
+    Ok(())
+}
+
+/// Creates all tables of the latest schema version
+pub fn create_tables(conn: &Connection) -> Result<()> {
+    conn.execute(&format!("PRAGMA user_version = {};", DB_VERSION), [])?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS projects (
@@ -513,6 +770,11 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
             tz                      TEXT NOT NULL,
             current                 INTEGER NOT NULL,
             main_instrument_link    INTEGER,
+            meteo_interpolation     INTEGER NOT NULL DEFAULT 1,
+            meteo_max_gap           INTEGER NOT NULL DEFAULT 1800,
+            aux_max_gap             INTEGER NOT NULL DEFAULT 3600,
+            elevation               FLOAT,
+            latitude                FLOAT,
             FOREIGN KEY (main_instrument_link) REFERENCES instruments(id)
         )",
         [],
@@ -529,26 +791,7 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
         )",
         [],
     )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS measurements (
-            datetime            INTEGER,
-            ch4                 FLOAT,
-            co2                 FLOAT,
-            h2o                 FLOAT,
-            n2o                 FLOAT,
-            diag                INTEGER,
-            file_link           INTEGER NOT NULL,
-            project_link        INTEGER NOT NULL,
-            instrument_link     INTEGER NOT NULL,
-
-            FOREIGN KEY (instrument_link) REFERENCES instruments(id),
-            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
-            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
-
-            PRIMARY KEY (datetime, instrument_link, project_link)
-        )",
-        [],
-    )?;
+    conn.execute(&create_measurements_table(), [])?;
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_measurements_project_datetime
      ON measurements (project_link, datetime, instrument_link);",
@@ -568,6 +811,8 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
             open_offset     INTEGER NOT NULL,
             end_offset      INTEGER NOT NULL,
             snow_depth      FLOAT NOT NULL,
+            air_temperature FLOAT,
+            note            TEXT,
             file_link       INTEGER NOT NULL,
             project_link    INTEGER NOT NULL,
             instrument_link INTEGER NOT NULL,
@@ -581,20 +826,8 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
         )",
         [],
     )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS meteo (
-            datetime        INTEGER,
-            temperature     FLOAT,
-            pressure        FLOAT,
-            file_link       INTEGER NOT NULL,
-            project_link    INTEGER NOT NULL,
-
-            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
-            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
-            PRIMARY KEY (datetime, project_link)
-        )",
-        [],
-    )?;
+    conn.execute(&create_meteo_stations_table(), [])?;
+    conn.execute(&create_meteo_table(), [])?;
     conn.execute(
         "CREATE TABLE IF NOT EXISTS height (
             chamber_id      TEXT,
@@ -610,23 +843,10 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
         )",
         [],
     )?;
+    conn.execute(&create_chamber_metadata_table(), [])?;
     conn.execute(
-        "CREATE TABLE chamber_metadata (
-            id              INTEGER PRIMARY KEY,
-            chamber_id      TEXT NOT NULL,
-            shape           TEXT NOT NULL,
-            diameter        REAL,
-            height          REAL NOT NULL,
-            width           REAL,
-            length          REAL,
-            file_link       INTEGER NOT NULL,
-            project_link    INTEGER NOT NULL,
-
-            FOREIGN KEY (project_link) REFERENCES projects(id) ON DELETE CASCADE,
-            FOREIGN KEY (file_link) REFERENCES data_files(id) ON DELETE CASCADE,
-
-            UNIQUE(chamber_id, project_link)
-        );",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_chamber_metadata_period
+     ON chamber_metadata (chamber_id, project_link, IFNULL(valid_from, -1));",
         [],
     )?;
 
@@ -661,6 +881,13 @@ pub fn initiate_tables() -> Result<(), Box<dyn std::error::Error>> {
         [],
     )?;
     conn.execute(&create_flux_history_table(), [])?;
+    conn.execute(&create_instrument_profiles_table(), [])?;
+    conn.execute(&create_gas_species_table(), [])?;
+    conn.execute(&create_clock_corrections_table(), [])?;
+    conn.execute(&create_chamber_meteo_table(), [])?;
+    conn.execute(&create_aux_data_table(), [])?;
+    conn.execute(&create_volume_table(), [])?;
+    conn.execute(&create_snow_table(), [])?;
 
     Ok(())
 }
@@ -1,9 +1,19 @@
-use crate::db::fluxes_schema::DB_VERSION;
+use crate::data_formats::meteodata::DEFAULT_METEO_STATION;
+use crate::db::fluxes_schema::{
+    create_aux_data_table, create_chamber_metadata_table, create_chamber_meteo_table,
+    create_clock_corrections_table, create_gas_species_table, create_instrument_profiles_table,
+    create_measurements_table, create_meteo_stations_table, create_meteo_table, create_snow_table,
+    create_volume_table, DB_VERSION,
+};
 use rusqlite::{Connection, OptionalExtension, Result};
 
 pub fn migrate_db() -> Result<()> {
     let conn = Connection::open("fluxrs.db")?;
+    migrate(&conn)
+}
 
+/// Brings a database of any older schema version up to `DB_VERSION`
+pub fn migrate(conn: &Connection) -> Result<()> {
     // user_version is 0 by default in SQLite
     let mut version: i32 = conn.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
     let mut migrated_steps = 0;
@@ -11,28 +21,28 @@ pub fn migrate_db() -> Result<()> {
     // --- Migration 2: add *_source columns ---
     if version < 2 {
         // fluxes.pressure_source
-        let has_pressure_src_fluxes = column_exists(&conn, "fluxes", "pressure_source")?;
+        let has_pressure_src_fluxes = column_exists(conn, "fluxes", "pressure_source")?;
         if !has_pressure_src_fluxes {
             println!("Applying migration v2: add fluxes.pressure_source");
             conn.execute("ALTER TABLE fluxes ADD COLUMN pressure_source INTEGER;", [])?;
         }
 
         // flux_history.pressure_source
-        let has_pressure_src_hist = column_exists(&conn, "flux_history", "pressure_source")?;
+        let has_pressure_src_hist = column_exists(conn, "flux_history", "pressure_source")?;
         if !has_pressure_src_hist {
             println!("Applying migration v2: add flux_history.pressure_source");
             conn.execute("ALTER TABLE flux_history ADD COLUMN pressure_source INTEGER;", [])?;
         }
 
         // fluxes.temperature_source
-        let has_temp_src_fluxes = column_exists(&conn, "fluxes", "temperature_source")?;
+        let has_temp_src_fluxes = column_exists(conn, "fluxes", "temperature_source")?;
         if !has_temp_src_fluxes {
             println!("Applying migration v2: add fluxes.temperature_source");
             conn.execute("ALTER TABLE fluxes ADD COLUMN temperature_source INTEGER;", [])?;
         }
 
         // flux_history.temperature_source
-        let has_temp_src_hist = column_exists(&conn, "flux_history", "temperature_source")?;
+        let has_temp_src_hist = column_exists(conn, "flux_history", "temperature_source")?;
         if !has_temp_src_hist {
             println!("Applying migration v2: add flux_history.temperature_source");
             conn.execute("ALTER TABLE flux_history ADD COLUMN temperature_source INTEGER;", [])?;
@@ -85,28 +95,28 @@ pub fn migrate_db() -> Result<()> {
     // --- Migration 3: add *_dist columns ---
     if version < 3 {
         // fluxes.pressure_dist
-        let has_pressure_dist_fluxes = column_exists(&conn, "fluxes", "pressure_dist")?;
+        let has_pressure_dist_fluxes = column_exists(conn, "fluxes", "pressure_dist")?;
         if !has_pressure_dist_fluxes {
             println!("Applying migration v3: add fluxes.pressure_dist");
             conn.execute("ALTER TABLE fluxes ADD COLUMN pressure_dist INTEGER;", [])?;
         }
 
         // flux_history.pressure_dist
-        let has_pressure_dist_hist = column_exists(&conn, "flux_history", "pressure_dist")?;
+        let has_pressure_dist_hist = column_exists(conn, "flux_history", "pressure_dist")?;
         if !has_pressure_dist_hist {
             println!("Applying migration v3: add flux_history.pressure_dist");
             conn.execute("ALTER TABLE flux_history ADD COLUMN pressure_dist INTEGER;", [])?;
         }
 
         // fluxes.temperature_dist
-        let has_temp_dist_fluxes = column_exists(&conn, "fluxes", "temperature_dist")?;
+        let has_temp_dist_fluxes = column_exists(conn, "fluxes", "temperature_dist")?;
         if !has_temp_dist_fluxes {
             println!("Applying migration v3: add fluxes.temperature_dist");
             conn.execute("ALTER TABLE fluxes ADD COLUMN temperature_dist INTEGER;", [])?;
         }
 
         // flux_history.temperature_dist
-        let has_temp_dist_hist = column_exists(&conn, "flux_history", "temperature_dist")?;
+        let has_temp_dist_hist = column_exists(conn, "flux_history", "temperature_dist")?;
         if !has_temp_dist_hist {
             println!("Applying migration v3: add flux_history.temperature_dist");
             conn.execute("ALTER TABLE flux_history ADD COLUMN temperature_dist INTEGER;", [])?;
@@ -183,6 +193,305 @@ pub fn migrate_db() -> Result<()> {
         version = 4;
         migrated_steps += 1;
     }
+    if version < 5 {
+        println!("Applying migration v5: add instrument_profiles table");
+        conn.execute(&create_instrument_profiles_table(), [])?;
+
+        version = 5;
+        migrated_steps += 1;
+    }
+    if version < 6 {
+        println!("Applying migration v6: add gas_species table");
+        conn.execute(&create_gas_species_table(), [])?;
+
+        // measurements had a column per gas, move to one row per gas
+        if column_exists(conn, "measurements", "co2")? {
+            println!("Applying migration v6: convert measurements to long format");
+            conn.execute_batch("BEGIN;")?;
+            conn.execute("ALTER TABLE measurements RENAME TO measurements_wide;", [])?;
+            conn.execute("DROP INDEX IF EXISTS idx_measurements_project_datetime;", [])?;
+            conn.execute("DROP INDEX IF EXISTS idx_measurements_instrument_link;", [])?;
+            conn.execute(&create_measurements_table(), [])?;
+            // ids are GasType::as_int
+            for (gas, column) in [(0, "co2"), (1, "ch4"), (2, "h2o"), (3, "n2o")] {
+                conn.execute(
+                    &format!(
+                        "INSERT INTO measurements
+                            (datetime, gas, value, diag, file_link, project_link, instrument_link)
+                         SELECT datetime, {gas}, {column}, diag, file_link, project_link, instrument_link
+                         FROM measurements_wide
+                         WHERE {column} IS NOT NULL;"
+                    ),
+                    [],
+                )?;
+            }
+            conn.execute("DROP TABLE measurements_wide;", [])?;
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_measurements_project_datetime
+             ON measurements (project_link, datetime, instrument_link);",
+                [],
+            )?;
+            conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_measurements_instrument_link
+             ON measurements (instrument_link);",
+                [],
+            )?;
+            conn.execute_batch("COMMIT;")?;
+        }
+
+        version = 6;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 7: record the water vapour dilution correction ---
+    if version < 7 {
+        for table in ["fluxes", "flux_history"] {
+            if !column_exists(conn, table, "h2o_correction")? {
+                println!("Applying migration v7: add {table}.h2o_correction");
+                conn.execute(
+                    &format!("ALTER TABLE {table} ADD COLUMN h2o_correction INTEGER;"),
+                    [],
+                )?;
+            }
+        }
+        // old rows are left NULL, they were fitted without checking
+
+        version = 7;
+        migrated_steps += 1;
+    }
+    if version < 8 {
+        println!("Applying migration v8: add instrument_clock_corrections table");
+        conn.execute(&create_clock_corrections_table(), [])?;
+
+        version = 8;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 9: measurement times in milliseconds ---
+    if version < 9 {
+        println!("Applying migration v9: store measurements.datetime in milliseconds");
+        // the primary key only gets spread out, scaling can't collide
+        conn.execute("UPDATE measurements SET datetime = datetime * 1000;", [])?;
+
+        version = 9;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 10: air temperature and notes from manual cycle files ---
+    if version < 10 {
+        for column in ["air_temperature FLOAT", "note TEXT"] {
+            let name = column.split_whitespace().next().unwrap();
+            if !column_exists(conn, "cycles", name)? {
+                println!("Applying migration v10: add cycles.{name}");
+                conn.execute(&format!("ALTER TABLE cycles ADD COLUMN {column};"), [])?;
+            }
+        }
+
+        version = 10;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 11: meteo interpolation per project ---
+    if version < 11 {
+        // existing projects keep the nearest sample they were processed with,
+        // MeteoInterpolation::Nearest, 30 minutes
+        for column in [
+            "meteo_interpolation INTEGER NOT NULL DEFAULT 0",
+            "meteo_max_gap INTEGER NOT NULL DEFAULT 1800",
+        ] {
+            let name = column.split_whitespace().next().unwrap();
+            if !column_exists(conn, "projects", name)? {
+                println!("Applying migration v11: add projects.{name}");
+                conn.execute(&format!("ALTER TABLE projects ADD COLUMN {column};"), [])?;
+            }
+        }
+
+        version = 11;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 12: temperature and pressure inside the chambers ---
+    if version < 12 {
+        println!("Applying migration v12: add chamber_meteo table");
+        conn.execute(&create_chamber_meteo_table(), [])?;
+
+        version = 12;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 13: auxiliary environmental variables ---
+    if version < 13 {
+        println!("Applying migration v13: add aux_data table");
+        conn.execute(&create_aux_data_table(), [])?;
+
+        version = 13;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 14: site elevation and latitude for modelled pressure ---
+    if version < 14 {
+        for column in ["elevation FLOAT", "latitude FLOAT"] {
+            let name = column.split_whitespace().next().unwrap();
+            if !column_exists(conn, "projects", name)? {
+                println!("Applying migration v14: add projects.{name}");
+                conn.execute(&format!("ALTER TABLE projects ADD COLUMN {column};"), [])?;
+            }
+        }
+
+        version = 14;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 15: meteo stations with priorities ---
+    if version < 15 {
+        println!("Applying migration v15: add meteo_stations table");
+        conn.execute(&create_meteo_stations_table(), [])?;
+
+        // existing meteo belongs to a default station of each project
+        if !column_exists(conn, "meteo", "station_link")? {
+            println!("Applying migration v15: add meteo.station_link");
+            conn.execute_batch("BEGIN;")?;
+            conn.execute(
+                "INSERT OR IGNORE INTO meteo_stations (name, priority, project_link)
+                 SELECT DISTINCT ?1, 0, project_link FROM meteo;",
+                [DEFAULT_METEO_STATION],
+            )?;
+            conn.execute("ALTER TABLE meteo RENAME TO meteo_old;", [])?;
+            conn.execute(&create_meteo_table(), [])?;
+            conn.execute(
+                "INSERT INTO meteo
+                    (datetime, temperature, pressure, file_link, station_link, project_link)
+                 SELECT m.datetime, m.temperature, m.pressure, m.file_link, s.id, m.project_link
+                 FROM meteo_old m
+                 JOIN meteo_stations s ON s.project_link = m.project_link AND s.name = ?1;",
+                [DEFAULT_METEO_STATION],
+            )?;
+            conn.execute("DROP TABLE meteo_old;", [])?;
+            conn.execute_batch("COMMIT;")?;
+        }
+
+        for table in ["fluxes", "flux_history"] {
+            for column in ["temperature_station", "pressure_station"] {
+                if !column_exists(conn, table, column)? {
+                    println!("Applying migration v15: add {table}.{column}");
+                    conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} TEXT;"), [])?;
+                }
+            }
+        }
+
+        version = 15;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 16: measured chamber volumes ---
+    if version < 16 {
+        println!("Applying migration v16: add volume table");
+        conn.execute(&create_volume_table(), [])?;
+
+        version = 16;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 17: chamber geometries with validity periods ---
+    if version < 17 {
+        if !column_exists(conn, "chamber_metadata", "valid_from")? {
+            println!("Applying migration v17: add chamber_metadata validity periods");
+            conn.execute_batch("BEGIN;")?;
+            conn.execute("ALTER TABLE chamber_metadata RENAME TO chamber_metadata_old;", [])?;
+            conn.execute(&create_chamber_metadata_table(), [])?;
+            conn.execute(
+                "INSERT INTO chamber_metadata
+                    (id, chamber_id, shape, diameter, height, width, length, file_link, project_link)
+                 SELECT id, chamber_id, shape, diameter, height, width, length, file_link, project_link
+                 FROM chamber_metadata_old;",
+                [],
+            )?;
+            conn.execute("DROP TABLE chamber_metadata_old;", [])?;
+            conn.execute(
+                "CREATE UNIQUE INDEX IF NOT EXISTS idx_chamber_metadata_period
+                 ON chamber_metadata (chamber_id, project_link, IFNULL(valid_from, -1));",
+                [],
+            )?;
+            conn.execute_batch("COMMIT;")?;
+        }
+
+        version = 17;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 18: more chamber shapes and volume terms ---
+    if version < 18 {
+        for column in [
+            "top_diameter REAL",
+            "volume REAL",
+            "area REAL",
+            "collar_height REAL",
+            "system_volume REAL",
+            "vegetation_volume REAL",
+        ] {
+            let name = column.split_whitespace().next().unwrap();
+            if !column_exists(conn, "chamber_metadata", name)? {
+                println!("Applying migration v18: add chamber_metadata.{name}");
+                conn.execute(&format!("ALTER TABLE chamber_metadata ADD COLUMN {column};"), [])?;
+            }
+        }
+
+        version = 18;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 19: snow depths per chamber ---
+    if version < 19 {
+        println!("Applying migration v19: add snow table");
+        conn.execute(&create_snow_table(), [])?;
+
+        version = 19;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 20: HMR model results ---
+    if version < 20 {
+        for table in ["fluxes", "flux_history"] {
+            for column in [
+                "hmr_flux",
+                "hmr_r2",
+                "hmr_adj_r2",
+                "hmr_intercept",
+                "hmr_slope",
+                "hmr_sigma",
+                "hmr_p_value",
+                "hmr_aic",
+                "hmr_rmse",
+                "hmr_cv",
+                "hmr_se",
+                "hmr_kappa",
+            ] {
+                if !column_exists(conn, table, column)? {
+                    println!("Applying migration v20: add {table}.{column}");
+                    conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} FLOAT;"), [])?;
+                }
+            }
+        }
+
+        version = 20;
+        migrated_steps += 1;
+    }
+
+    // --- Migration 21: own max gap for auxiliary variables ---
+    if version < 21 {
+        if !column_exists(conn, "projects", "aux_max_gap")? {
+            println!("Applying migration v21: add projects.aux_max_gap");
+            conn.execute(
+                "ALTER TABLE projects ADD COLUMN aux_max_gap INTEGER NOT NULL DEFAULT 3600;",
+                [],
+            )?;
+            // aux variables were matched within the meteo gap so far
+            conn.execute("UPDATE projects SET aux_max_gap = meteo_max_gap;", [])?;
+        }
+
+        version = 21;
+        migrated_steps += 1;
+    }
 
     // Only bump user_version once, at the end, to the *latest* schema version
     if migrated_steps > 0 {
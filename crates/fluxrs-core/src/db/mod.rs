@@ -1,2 +1,33 @@
 pub mod fluxes_schema;
 pub mod migrate;
+
+/// In-memory database with the full schema and one project, id 1, using the
+/// default instrument, for tests
+#[cfg(test)]
+pub(crate) fn test_conn() -> rusqlite::Connection {
+    use crate::instruments::instruments::get_or_insert_instrument;
+    use crate::project::Project;
+
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    fluxes_schema::create_tables(&conn).unwrap();
+    migrate::migrate(&conn).unwrap();
+
+    let project = Project::default();
+    conn.execute(
+        "INSERT INTO projects (id, project_name, main_gas, deadband, min_calc_len, mode, tz, current)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, 1)",
+        rusqlite::params![
+            project.name,
+            project.main_gas.unwrap().as_int(),
+            project.deadband,
+            project.min_calc_len,
+            project.mode.as_int(),
+            project.tz.to_string(),
+        ],
+    )
+    .unwrap();
+    let instrument_id = get_or_insert_instrument(&conn, &project.instrument, 1).unwrap();
+    conn.execute("UPDATE projects SET main_instrument_link = ?1 WHERE id = 1", [instrument_id])
+        .unwrap();
+    conn
+}
@@ -15,6 +15,10 @@ pub enum ErrorCode {
     ManualInvalid,
     TooManyDiagErrors,
     FailedMeasurement,
+    DiagLaser,
+    DiagCavity,
+    DiagRingDown,
+    DiagWarning,
 }
 
 impl ErrorCode {
@@ -27,6 +31,12 @@ impl ErrorCode {
     pub const MANUAL_INVALID: u16 = 1 << 5;
     pub const MOSTLY_DIAG_ERRORS: u16 = 1 << 6;
     pub const FAILED_MEASUREMENT: u16 = 1 << 7;
+    pub const DIAG_LASER: u16 = 1 << 8;
+    pub const DIAG_CAVITY: u16 = 1 << 9;
+    pub const DIAG_RING_DOWN: u16 = 1 << 10;
+    pub const DIAG_WARNING: u16 = 1 << 11;
+    /// Codes that are reported but don't invalidate the cycle
+    pub const WARNINGS: u16 = Self::DIAG_WARNING;
 
     /// Convert an `ErrorCode` to its corresponding bitmask
     pub fn to_mask(&self) -> u16 {
@@ -46,6 +56,11 @@ impl ErrorCode {
             ErrorCode::TooManyDiagErrors => Self::MOSTLY_DIAG_ERRORS,
             // Currently used for marking a bad measurement
             ErrorCode::FailedMeasurement => Self::FAILED_MEASUREMENT,
+            // decoded instrument diagnostics, see instruments::diag
+            ErrorCode::DiagLaser => Self::DIAG_LASER,
+            ErrorCode::DiagCavity => Self::DIAG_CAVITY,
+            ErrorCode::DiagRingDown => Self::DIAG_RING_DOWN,
+            ErrorCode::DiagWarning => Self::DIAG_WARNING,
         }
     }
 
@@ -61,6 +76,10 @@ impl ErrorCode {
             ErrorCode::ManualInvalid,
             ErrorCode::TooManyDiagErrors,
             ErrorCode::FailedMeasurement,
+            ErrorCode::DiagLaser,
+            ErrorCode::DiagCavity,
+            ErrorCode::DiagRingDown,
+            ErrorCode::DiagWarning,
         ] {
             if mask & error.to_mask() != 0 {
                 errors.push(error);
@@ -68,6 +87,21 @@ impl ErrorCode {
         }
         errors
     }
+
+    /// Codes set from decoded diag values
+    pub fn diag_codes() -> [ErrorCode; 5] {
+        [
+            ErrorCode::ErrorsInMeasurement,
+            ErrorCode::DiagLaser,
+            ErrorCode::DiagCavity,
+            ErrorCode::DiagRingDown,
+            ErrorCode::DiagWarning,
+        ]
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.to_mask() & Self::WARNINGS != 0
+    }
 }
 
 /// Wrapper struct for managing the error bitmask
@@ -100,6 +134,10 @@ impl ErrorMask {
     pub fn from_u16(value: u16) -> Self {
         ErrorMask(value)
     }
+    /// Mask has an error that makes the cycle invalid, warnings don't count
+    pub fn invalidates(&self) -> bool {
+        self.0 & !ErrorCode::WARNINGS != 0
+    }
     pub fn contains(&self, code: ErrorCode) -> bool {
         self.0 & code.to_mask() != 0
     }
@@ -125,6 +163,10 @@ impl fmt::Display for ErrorCode {
             ErrorCode::ManualInvalid => "Manual invalid",
             ErrorCode::TooManyDiagErrors => "Too many instrument diagnostic errors",
             ErrorCode::FailedMeasurement => "Failed measurement",
+            ErrorCode::DiagLaser => "Instrument laser diagnostics",
+            ErrorCode::DiagCavity => "Instrument cavity diagnostics",
+            ErrorCode::DiagRingDown => "Instrument ring-down diagnostics",
+            ErrorCode::DiagWarning => "Instrument diagnostic warnings",
         };
         write!(f, "{}", message)
     }
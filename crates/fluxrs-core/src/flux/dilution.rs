@@ -0,0 +1,103 @@
+use crate::stats::LinReg;
+use std::fmt;
+
+/// How water vapour dilution was removed from a channel before fitting
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DilutionCorrection {
+    /// Channel is already a dry mole fraction
+    #[default]
+    NotNeeded,
+    /// Every point divided by (1 - x_H2O) of the same timestamp
+    PointByPoint,
+    /// H2O had gaps, so x_H2O comes from a line fitted to the H2O in the
+    /// calculation range. For the fitted slope this is the WPL-type correction
+    /// dc_dry/dt = (dc/dt + c_dry * dx_H2O/dt) / (1 - x_H2O).
+    H2oSlope,
+    /// Wet channel without enough H2O data, fitted uncorrected
+    Uncorrected,
+}
+
+impl fmt::Display for DilutionCorrection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DilutionCorrection::NotNeeded => write!(f, "Not needed"),
+            DilutionCorrection::PointByPoint => write!(f, "Point by point"),
+            DilutionCorrection::H2oSlope => write!(f, "H2O slope"),
+            DilutionCorrection::Uncorrected => write!(f, "Uncorrected"),
+        }
+    }
+}
+
+impl DilutionCorrection {
+    pub fn as_int(self) -> i32 {
+        match self {
+            DilutionCorrection::NotNeeded => 0,
+            DilutionCorrection::PointByPoint => 1,
+            DilutionCorrection::H2oSlope => 2,
+            DilutionCorrection::Uncorrected => 3,
+        }
+    }
+
+    pub fn from_int(v: i32) -> Option<DilutionCorrection> {
+        match v {
+            0 => Some(DilutionCorrection::NotNeeded),
+            1 => Some(DilutionCorrection::PointByPoint),
+            2 => Some(DilutionCorrection::H2oSlope),
+            3 => Some(DilutionCorrection::Uncorrected),
+            _ => None,
+        }
+    }
+}
+
+/// Convert wet mole fractions to dry ones.
+///
+/// `h2o` is the water vapour mole fraction in mol/mol at the same timestamps as
+/// `wet`. Points are corrected one by one when every point has H2O, otherwise a
+/// line fitted to the available H2O is used.
+pub fn wet_to_dry(dt: &[f64], wet: &[f64], h2o: &[Option<f64>]) -> (Vec<f64>, DilutionCorrection) {
+    let usable = |w: &f64| w.is_finite() && (0.0..1.0).contains(w);
+
+    if !h2o.is_empty() && h2o.iter().all(|w| w.as_ref().is_some_and(usable)) {
+        let dry = wet.iter().zip(h2o).map(|(c, w)| c / (1.0 - w.unwrap())).collect();
+        return (dry, DilutionCorrection::PointByPoint);
+    }
+
+    let (x, y): (Vec<f64>, Vec<f64>) =
+        dt.iter().zip(h2o).filter_map(|(t, w)| w.filter(usable).map(|w| (*t, w))).unzip();
+    if x.len() < 2 {
+        return (wet.to_vec(), DilutionCorrection::Uncorrected);
+    }
+
+    let h2o_line = LinReg::train(&x, &y);
+    let dry = dt
+        .iter()
+        .zip(wet)
+        .map(|(t, c)| c / (1.0 - h2o_line.calculate(*t).clamp(0.0, 0.99)))
+        .collect();
+    (dry, DilutionCorrection::H2oSlope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_by_point() {
+        let (dry, corr) = wet_to_dry(&[0.0, 1.0], &[400.0, 396.0], &[Some(0.02), Some(0.01)]);
+        assert_eq!(corr, DilutionCorrection::PointByPoint);
+        assert!((dry[0] - 400.0 / 0.98).abs() < 1e-9);
+        assert!((dry[1] - 400.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gaps_use_h2o_line() {
+        let h2o = [Some(0.01), None, Some(0.03)];
+        let (dry, corr) = wet_to_dry(&[0.0, 1.0, 2.0], &[99.0, 98.0, 97.0], &h2o);
+        assert_eq!(corr, DilutionCorrection::H2oSlope);
+        assert!((dry[1] - 100.0).abs() < 1e-9);
+
+        let (dry, corr) = wet_to_dry(&[0.0, 1.0], &[99.0, 98.0], &[None, Some(0.01)]);
+        assert_eq!(corr, DilutionCorrection::Uncorrected);
+        assert_eq!(dry, vec![99.0, 98.0]);
+    }
+}
@@ -4,6 +4,7 @@ pub enum FluxKind {
     Exponential,
     RobLin,
     Poly,
+    Hmr,
 }
 
 impl std::fmt::Display for FluxKind {
@@ -13,6 +14,7 @@ impl std::fmt::Display for FluxKind {
             FluxKind::Exponential => write!(f, "Exponential"),
             FluxKind::RobLin => write!(f, "Robust linear"),
             FluxKind::Poly => write!(f, "Polynomial"),
+            FluxKind::Hmr => write!(f, "HMR"),
         }
     }
 }
@@ -24,6 +26,7 @@ impl FluxKind {
             FluxKind::Exponential => "exponential",
             FluxKind::RobLin => "roblin",
             FluxKind::Poly => "poly",
+            FluxKind::Hmr => "hmr",
         }
     }
     pub fn label(&self) -> &'static str {
@@ -32,10 +35,11 @@ impl FluxKind {
             FluxKind::Exponential => "exponential",
             FluxKind::RobLin => "roblin",
             FluxKind::Poly => "poly",
+            FluxKind::Hmr => "hmr",
         }
     }
     pub fn all() -> &'static [FluxKind] {
         use FluxKind::*;
-        &[Linear, Exponential, RobLin, Poly]
+        &[Linear, Exponential, RobLin, Poly, Hmr]
     }
 }
@@ -0,0 +1,276 @@
+use crate::data_formats::chamberdata::Chamber;
+use crate::flux::flux::{flux_umol_m2_s, GasChannelData, MeteoConditions, TimeRange};
+use crate::flux::fluxfiterror::{FluxFitError, FluxResult};
+use crate::flux::fluxkind::FluxKind;
+use crate::flux::fluxmodel::FluxModel;
+use crate::gaschannel::GasChannel;
+use crate::stats::{adjusted_r2, aic_from_rss, r2_from_predictions, rmse, HmrReg};
+
+use statrs::distribution::{ContinuousCDF, StudentsT};
+
+use std::any::Any;
+
+/// κ bounds as multiples of 1/duration of the fit window. At the lower bound
+/// the curve can't be told apart from a straight line, at the upper bound it
+/// has levelled off within the first twentieth of the window.
+pub const HMR_KAPPA_MIN: f64 = 1e-3;
+pub const HMR_KAPPA_MAX: f64 = 20.0;
+
+#[derive(Clone)]
+pub struct HmrFlux {
+    pub gas_channel: GasChannel,
+    pub flux: f64,
+    pub r2: f64,
+    pub adjusted_r2: f64,
+    pub model: HmrReg,
+    pub p_value: f64,
+    /// standard error of the initial slope f0
+    pub se: f64,
+    pub sigma: f64,
+    pub aic: f64,
+    pub rmse: f64,
+    pub cv: f64,
+    pub range_start: f64,
+    pub range_end: f64,
+}
+
+impl FluxModel for HmrFlux {
+    fn flux(&self) -> Option<f64> {
+        Some(self.flux)
+    }
+
+    fn r2(&self) -> Option<f64> {
+        Some(self.r2)
+    }
+
+    fn adj_r2(&self) -> Option<f64> {
+        Some(self.adjusted_r2)
+    }
+
+    fn kind(&self) -> FluxKind {
+        FluxKind::Hmr
+    }
+    fn gas_channel(&self) -> GasChannel {
+        self.gas_channel.clone()
+    }
+
+    fn predict(&self, x: f64) -> Option<f64> {
+        Some(self.model.calculate(x - self.range_start))
+    }
+
+    fn set_range_start(&mut self, value: f64) {
+        self.range_start = value;
+    }
+
+    fn set_range_end(&mut self, value: f64) {
+        self.range_end = value;
+    }
+
+    fn range_start(&self) -> Option<f64> {
+        Some(self.range_start)
+    }
+
+    fn range_end(&self) -> Option<f64> {
+        Some(self.range_end)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    /// Concentration at the start of the fit window
+    fn intercept(&self) -> Option<f64> {
+        Some(self.model.c0)
+    }
+
+    /// Initial slope f0 = dC/dt at the start of the fit window
+    fn slope(&self) -> Option<f64> {
+        Some(self.model.f0)
+    }
+
+    fn sigma(&self) -> Option<f64> {
+        Some(self.sigma)
+    }
+
+    fn p_value(&self) -> Option<f64> {
+        Some(self.p_value)
+    }
+
+    fn aic(&self) -> Option<f64> {
+        Some(self.aic)
+    }
+
+    fn rmse(&self) -> Option<f64> {
+        Some(self.rmse)
+    }
+
+    fn cv(&self) -> Option<f64> {
+        Some(self.cv)
+    }
+}
+
+impl HmrFlux {
+    pub fn from_data(
+        data: &GasChannelData,
+        range: &TimeRange,
+        meteo: &MeteoConditions,
+        chamber: &Chamber,
+    ) -> FluxResult<Self> {
+        if !data.equal_len() {
+            return Err(FluxFitError::LengthMismatch { len_x: data.xlen(), len_y: data.ylen() });
+        }
+        // three parameters and at least one degree of freedom
+        if data.xlen() < 4 {
+            return Err(FluxFitError::NotEnoughPoints { len: data.xlen(), needed: 4 });
+        }
+
+        let x = data.x();
+        let y = data.y();
+        let n = x.len() as f64;
+
+        let x0 = x[0];
+        let x_norm: Vec<f64> = x.iter().map(|&t| t - x0).collect();
+        let duration = x_norm[x_norm.len() - 1];
+        if !duration.is_finite() || duration <= 0.0 {
+            return Err(FluxFitError::DegenerateX);
+        }
+
+        let model = HmrReg::train(&x_norm, y, HMR_KAPPA_MIN / duration, HMR_KAPPA_MAX / duration)
+            .ok_or(FluxFitError::DegenerateX)?;
+
+        let y_hat: Vec<f64> = x_norm.iter().map(|&xi| model.calculate(xi)).collect();
+        let rss: f64 = y.iter().zip(&y_hat).map(|(&yi, &yhi)| (yi - yhi).powi(2)).sum();
+        let sigma = (rss / (n - 3.0)).sqrt();
+        if !sigma.is_finite() {
+            return Err(FluxFitError::NonFiniteSigma);
+        }
+
+        let rmse_val = rmse(y, &y_hat).unwrap_or(0.0);
+        let y_mean = y.iter().copied().sum::<f64>() / n;
+        let cv = rmse_val / y_mean;
+
+        let r2 = r2_from_predictions(y, &y_hat).unwrap_or(0.0);
+        // like the linear and polynomial fits, adjusted r² counts the predictors
+        // f0 and κ and AIC also the intercept c0, both leave n − 3 dof
+        let adjusted_r2 = adjusted_r2(r2, n as usize, 2);
+        let aic = aic_from_rss(rss, n as usize, 3);
+
+        let se = model.f0_se(&x_norm, sigma).ok_or(FluxFitError::NonFiniteSE)?;
+        if se <= 0.0 {
+            return Err(FluxFitError::NonFiniteSE);
+        }
+        let t_stat = model.f0 / se;
+        if !t_stat.is_finite() {
+            return Err(FluxFitError::NonFiniteTStat);
+        }
+        let dist = StudentsT::new(0.0, 1.0, n - 3.0)
+            .map_err(|_| FluxFitError::StatError("failed to construct StudentsT"))?;
+        let p_value = 2.0 * (1.0 - dist.cdf(t_stat.abs()));
+
+        let flux =
+            flux_umol_m2_s(&data.channel, model.f0, &meteo.temperature, &meteo.pressure, chamber);
+
+        Ok(Self {
+            gas_channel: data.channel.clone(),
+            flux,
+            r2,
+            adjusted_r2,
+            model,
+            p_value,
+            se,
+            sigma,
+            aic,
+            rmse: rmse_val,
+            cv,
+            range_start: range.start,
+            range_end: range.end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concentrationunit::ConcentrationUnit;
+    use crate::data_formats::meteodata::{MeteoPoint, MeteoSource};
+    use crate::gastype::GasType;
+
+    fn test_channel() -> GasChannel {
+        GasChannel::new(GasType::CO2, ConcentrationUnit::Ppm, "test_channel".to_owned())
+    }
+
+    fn test_meteo() -> MeteoConditions {
+        let point = |value| MeteoPoint {
+            value: Some(value),
+            source: MeteoSource::Default,
+            distance_from_target: None,
+        };
+        MeteoConditions::new(point(10.), point(980.))
+    }
+
+    /// `truth` sampled every `step` seconds from t = 1000 with ±0.05 noise
+    fn fit(truth: HmrReg, n: usize, step: f64) -> FluxResult<HmrFlux> {
+        let x: Vec<f64> = (0..n).map(|i| 1000.0 + i as f64 * step).collect();
+        let y: Vec<f64> = x
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| truth.calculate(t - 1000.0) + if i % 2 == 0 { 0.05 } else { -0.05 })
+            .collect();
+        let data = GasChannelData::new(test_channel(), &x, &y);
+        let range = TimeRange::new(x[0], x[n - 1]);
+        HmrFlux::from_data(&data, &range, &test_meteo(), &Chamber::default())
+    }
+
+    #[test]
+    fn fits_saturating_curve() {
+        let flux = fit(HmrReg::from_val(420.0, 0.8, 0.01), 180, 1.0).unwrap();
+
+        assert!((flux.model.kappa - 0.01).abs() < 1e-3);
+        assert!((flux.model.f0 - 0.8).abs() < 0.02);
+        assert!((flux.predict(1000.0).unwrap() - 420.0).abs() < 0.1);
+        assert!((flux.sigma - 0.05).abs() < 0.01);
+        assert!(flux.se > 0.0 && flux.se < 0.01);
+        assert!(flux.p_value < 1e-6);
+        assert!(flux.r2 > 0.99 && flux.adjusted_r2 <= flux.r2);
+        assert!(flux.aic.is_finite());
+
+        // the flux comes from the initial slope, not from a line over the window
+        let expected = flux_umol_m2_s(
+            &test_channel(),
+            flux.model.f0,
+            &test_meteo().temperature,
+            &test_meteo().pressure,
+            &Chamber::default(),
+        );
+        assert_eq!(flux.flux, expected);
+    }
+
+    #[test]
+    fn kappa_bounds_follow_window_duration() {
+        // levels off far faster than the upper bound allows, on a 179 s and a
+        // 1790 s window
+        for step in [1.0, 10.0] {
+            let duration = 179.0 * step;
+            let flux = fit(HmrReg::from_val(420.0, 0.8, 1.0 / step), 180, step).unwrap();
+            let kappa_max = HMR_KAPPA_MAX / duration;
+            assert!((flux.model.kappa - kappa_max).abs() / kappa_max < 1e-3);
+
+            let line = fit(HmrReg::from_val(420.0, 0.8 / step, 0.0), 180, step).unwrap();
+            assert!(line.model.kappa < 10.0 * HMR_KAPPA_MIN / duration);
+        }
+    }
+
+    #[test]
+    fn needs_four_points() {
+        match fit(HmrReg::from_val(420.0, 0.8, 0.01), 3, 1.0) {
+            Err(FluxFitError::NotEnoughPoints { len: 3, needed: 4 }) => {},
+            Err(e) => panic!("Expected NotEnoughPoints error, got {}", e),
+            Ok(_) => panic!("Expected NotEnoughPoints error, got Ok(_)"),
+        }
+        assert!(fit(HmrReg::from_val(420.0, 0.8, 0.01), 4, 1.0).is_ok());
+    }
+}
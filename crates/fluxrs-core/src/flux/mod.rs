@@ -1,19 +1,23 @@
+pub mod dilution;
 pub mod expflux;
 pub mod flux;
 pub mod fluxfiterror;
 pub mod fluxkind;
 pub mod fluxmodel;
 pub mod fluxunit;
+pub mod hmrflux;
 pub mod linflux;
 pub mod polyflux;
 pub mod robflux;
 
+pub use dilution::DilutionCorrection;
 pub use expflux::ExponentialFlux;
 pub use flux::FluxRecord;
 pub use fluxfiterror::{FluxFitError, FluxResult};
 pub use fluxkind::FluxKind;
 pub use fluxmodel::FluxModel;
 pub use fluxunit::FluxUnit;
+pub use hmrflux::HmrFlux;
 pub use linflux::LinearFlux;
 pub use polyflux::PolyFlux;
 pub use robflux::RobustFlux;
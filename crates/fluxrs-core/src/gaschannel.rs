@@ -1,17 +1,48 @@
 use crate::concentrationunit::ConcentrationUnit;
 use crate::gastype::GasType;
 use std::fmt;
+use std::str::FromStr;
+
+/// Whether a channel reports dry or wet (including water vapour) mole fractions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MoleFraction {
+    #[default]
+    Dry,
+    Wet,
+}
+
+impl fmt::Display for MoleFraction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoleFraction::Dry => write!(f, "dry"),
+            MoleFraction::Wet => write!(f, "wet"),
+        }
+    }
+}
+
+impl FromStr for MoleFraction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dry" => Ok(MoleFraction::Dry),
+            "wet" => Ok(MoleFraction::Wet),
+            other => Err(format!("invalid mole fraction: {other}, expected dry or wet")),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct GasChannel {
     pub gas: GasType,
     pub unit: ConcentrationUnit,
     pub instrument_id: String, // optional but often useful
+    pub mole_fraction: MoleFraction,
 }
 
 impl GasChannel {
     pub fn new(gas: GasType, unit: ConcentrationUnit, instrument_id: impl Into<String>) -> Self {
-        Self { gas, unit, instrument_id: instrument_id.into() }
+        Self { gas, unit, instrument_id: instrument_id.into(), mole_fraction: MoleFraction::Dry }
     }
 
     /// Convert a slope reported by THIS channel into ppm/s
@@ -33,4 +64,5 @@ pub struct ChannelConfig {
     pub concentration_col: String, // column name in instrument data for concentration
     pub unit: ConcentrationUnit,   // e.g. ppb, ppm
     pub instrument_id: String,
+    pub mole_fraction: MoleFraction, // wet channels are dilution corrected with H2O
 }
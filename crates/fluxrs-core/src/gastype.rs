@@ -1,5 +1,9 @@
+use crate::concentrationunit::ConcentrationUnit;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::{LazyLock, RwLock};
 
 #[derive(Debug)]
 pub struct ParseGasError(String);
@@ -11,63 +15,143 @@ impl fmt::Display for ParseGasError {
 }
 impl std::error::Error for ParseGasError {}
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
-pub enum GasType {
-    #[default]
-    CO2,
-    CH4,
-    H2O,
-    N2O,
+#[derive(Debug)]
+pub enum SpeciesError {
+    Invalid(String),
+    Sql(rusqlite::Error),
 }
 
-impl fmt::Display for GasType {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl fmt::Display for SpeciesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            GasType::CO2 => write!(f, "CO2"),
-            GasType::CH4 => write!(f, "CH4"),
-            GasType::H2O => write!(f, "H2O"),
-            GasType::N2O => write!(f, "N2O"),
+            SpeciesError::Invalid(msg) => write!(f, "Invalid gas species: {}", msg),
+            SpeciesError::Sql(e) => write!(f, "Database error: {}", e),
         }
     }
 }
 
+impl std::error::Error for SpeciesError {}
+
+impl From<rusqlite::Error> for SpeciesError {
+    fn from(err: rusqlite::Error) -> Self {
+        SpeciesError::Sql(err)
+    }
+}
+
+/// Properties of a gas that are needed to read and calculate fluxes for it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasSpecies {
+    pub name: &'static str,
+    /// g/mol
+    pub mol_mass: f64,
+    /// Unit the concentrations are usually reported in
+    pub unit: ConcentrationUnit,
+}
+
+// Position is the id stored in the db, only append to this list.
+const BUILTIN_SPECIES: &[GasSpecies] = &[
+    GasSpecies { name: "CO2", mol_mass: 44.0, unit: ConcentrationUnit::Ppm },
+    GasSpecies { name: "CH4", mol_mass: 16.0, unit: ConcentrationUnit::Ppb },
+    GasSpecies { name: "H2O", mol_mass: 18.0, unit: ConcentrationUnit::Ppm },
+    GasSpecies { name: "N2O", mol_mass: 44.0, unit: ConcentrationUnit::Ppb },
+    GasSpecies { name: "NH3", mol_mass: 17.0, unit: ConcentrationUnit::Ppb },
+    GasSpecies { name: "CO", mol_mass: 28.0, unit: ConcentrationUnit::Ppb },
+    GasSpecies { name: "13CO2", mol_mass: 45.0, unit: ConcentrationUnit::Ppm },
+    GasSpecies { name: "12CO2", mol_mass: 44.0, unit: ConcentrationUnit::Ppm },
+    GasSpecies { name: "H2S", mol_mass: 34.0, unit: ConcentrationUnit::Ppb },
+];
+
+/// Ids below this are reserved for built-in species
+pub const CUSTOM_SPECIES_START: u16 = 1000;
+
+// Species added by the user, keyed by the id in the gas_species table. Names
+// are leaked so that `GasType` can stay `Copy`.
+static CUSTOM_SPECIES: LazyLock<RwLock<BTreeMap<u16, GasSpecies>>> =
+    LazyLock::new(|| RwLock::new(BTreeMap::new()));
+
+/// A gas species, either built-in or registered with `save_gas_species`.
+///
+/// The id is what gets stored in the db, built-in ids never change.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct GasType(u16);
+
+impl Default for GasType {
+    fn default() -> Self {
+        GasType::CO2
+    }
+}
+
+impl fmt::Debug for GasType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.species().name)
+    }
+}
+
+impl fmt::Display for GasType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.species().name)
+    }
+}
+
 impl FromStr for GasType {
     type Err = ParseGasError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_ascii_lowercase().as_str() {
-            "ch4" => Ok(GasType::CH4),
-            "co2" => Ok(GasType::CO2),
-            "h2o" => Ok(GasType::H2O),
-            "n2o" => Ok(GasType::N2O),
-            other => Err(ParseGasError(format!("Invalid gas: {other}"))),
+        let name = s.trim();
+        if let Some(id) = BUILTIN_SPECIES.iter().position(|sp| sp.name.eq_ignore_ascii_case(name)) {
+            return Ok(GasType(id as u16));
         }
+        CUSTOM_SPECIES
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, sp)| sp.name.eq_ignore_ascii_case(name))
+            .map(|(id, _)| GasType(*id))
+            .ok_or_else(|| ParseGasError(format!("Invalid gas: {}", name.to_ascii_lowercase())))
     }
 }
 impl GasType {
-    pub fn column_name(&self) -> &'static str {
-        match self {
-            GasType::CH4 => "CH4",
-            GasType::CO2 => "CO2",
-            GasType::H2O => "H2O",
-            GasType::N2O => "N2O",
+    pub const CO2: GasType = GasType(0);
+    pub const CH4: GasType = GasType(1);
+    pub const H2O: GasType = GasType(2);
+    pub const N2O: GasType = GasType(3);
+    pub const NH3: GasType = GasType(4);
+    pub const CO: GasType = GasType(5);
+    pub const CO2_13: GasType = GasType(6);
+    pub const CO2_12: GasType = GasType(7);
+    pub const H2S: GasType = GasType(8);
+
+    pub fn builtin_gases() -> Vec<GasType> {
+        (0..BUILTIN_SPECIES.len() as u16).map(GasType).collect()
+    }
+    /// Built-in gases and gases registered from the db
+    pub fn available_gases() -> Vec<GasType> {
+        let mut gases = Self::builtin_gases();
+        gases.extend(CUSTOM_SPECIES.read().unwrap().keys().copied().map(GasType));
+        gases
+    }
+    pub fn species(&self) -> GasSpecies {
+        match BUILTIN_SPECIES.get(self.0 as usize) {
+            Some(species) => *species,
+            // custom ids are only handed out by the registry
+            None => {
+                *CUSTOM_SPECIES.read().unwrap().get(&self.0).expect("gas species is registered")
+            },
         }
     }
+    pub fn column_name(&self) -> &'static str {
+        self.species().name
+    }
     pub fn as_int(&self) -> usize {
-        match self {
-            GasType::CO2 => 0,
-            GasType::CH4 => 1,
-            GasType::H2O => 2,
-            GasType::N2O => 3,
-        }
+        self.0 as usize
     }
     pub fn from_int(i: usize) -> Option<GasType> {
-        match i {
-            0 => Some(GasType::CO2),
-            1 => Some(GasType::CH4),
-            2 => Some(GasType::H2O),
-            3 => Some(GasType::N2O),
-            _ => None,
+        let id = u16::try_from(i).ok()?;
+        if (id as usize) < BUILTIN_SPECIES.len() || CUSTOM_SPECIES.read().unwrap().contains_key(&id)
+        {
+            Some(GasType(id))
+        } else {
+            None
         }
     }
 
@@ -97,28 +181,140 @@ impl GasType {
     }
 
     pub fn mol_mass(&self) -> f64 {
-        match self {
-            GasType::CH4 => 16.0,
-            GasType::CO2 => 44.0,
-            GasType::H2O => 18.0,
-            GasType::N2O => 44.0,
-        }
+        self.species().mol_mass
     }
+    /// Divide a value in the default unit with this to get ppm
     pub fn conv_factor(&self) -> f64 {
-        //
-        match self {
-            GasType::CH4 => 1000.0,
-            GasType::CO2 => 1.0,
-            GasType::H2O => 1.0,
-            GasType::N2O => 1000.0,
+        match self.species().unit {
+            ConcentrationUnit::Ppm => 1.0,
+            ConcentrationUnit::Ppb => 1000.0,
+            ConcentrationUnit::Percent => 1.0 / 10_000.0,
         }
     }
     pub fn unit(&self) -> &str {
-        match self {
-            GasType::CH4 => "ppb",
-            GasType::CO2 => "ppm",
-            GasType::H2O => "ppm",
-            GasType::N2O => "ppb",
+        self.species().unit.as_str()
+    }
+}
+
+fn register_species(id: u16, name: &str, mol_mass: f64, unit: ConcentrationUnit) -> GasType {
+    let mut registry = CUSTOM_SPECIES.write().unwrap();
+    let name: &'static str = match registry.values().find(|sp| sp.name == name) {
+        Some(sp) => sp.name,
+        None => Box::leak(name.to_owned().into_boxed_str()),
+    };
+    registry.insert(id, GasSpecies { name, mol_mass, unit });
+    GasType(id)
+}
+
+/// Add a gas species to the db and register it for this process. Saving an
+/// existing species updates its molar mass and unit but keeps the id.
+pub fn save_gas_species(
+    conn: &Connection,
+    name: &str,
+    mol_mass: f64,
+    unit: ConcentrationUnit,
+) -> Result<GasType, SpeciesError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(SpeciesError::Invalid("name is empty".to_owned()));
+    }
+    if BUILTIN_SPECIES.iter().any(|sp| sp.name.eq_ignore_ascii_case(name)) {
+        return Err(SpeciesError::Invalid(format!("{name} is a built-in gas")));
+    }
+    if !mol_mass.is_finite() || mol_mass <= 0.0 {
+        return Err(SpeciesError::Invalid(format!("molar mass of {name} must be positive")));
+    }
+
+    let existing = conn
+        .query_row(
+            "SELECT id, name FROM gas_species WHERE name = ?1 COLLATE NOCASE",
+            [name],
+            |row| Ok((row.get::<_, u16>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?;
+    let (id, name) = match existing {
+        Some((id, saved_name)) => {
+            conn.execute(
+                "UPDATE gas_species SET mol_mass = ?1, unit = ?2 WHERE id = ?3",
+                params![mol_mass, unit.to_string(), id],
+            )?;
+            (id, saved_name)
+        },
+        None => {
+            let id: u16 = conn.query_row(
+                "SELECT MAX(?1, COALESCE(MAX(id) + 1, ?1)) FROM gas_species",
+                [CUSTOM_SPECIES_START],
+                |row| row.get(0),
+            )?;
+            conn.execute(
+                "INSERT INTO gas_species (id, name, mol_mass, unit) VALUES (?1, ?2, ?3, ?4)",
+                params![id, name, mol_mass, unit.to_string()],
+            )?;
+            (id, name.to_owned())
+        },
+    };
+    Ok(register_species(id, &name, mol_mass, unit))
+}
+
+/// Register every gas species stored in the db, returns the number of species loaded
+pub fn load_gas_species(conn: &Connection) -> Result<usize, SpeciesError> {
+    let mut stmt = conn.prepare("SELECT id, name, mol_mass, unit FROM gas_species")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, u16>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, f64>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut loaded = 0;
+    for row in rows {
+        let (id, name, mol_mass, unit) = row?;
+        match unit.parse::<ConcentrationUnit>() {
+            Ok(unit) => {
+                register_species(id, &name, mol_mass, unit);
+                loaded += 1;
+            },
+            Err(e) => eprintln!("Skipping gas species {}: {}", name, e),
         }
     }
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fluxes_schema::create_gas_species_table;
+
+    #[test]
+    fn builtin_ids_are_stable() {
+        assert_eq!(GasType::CO2.as_int(), 0);
+        assert_eq!(GasType::N2O.as_int(), 3);
+        assert_eq!(GasType::from_int(1), Some(GasType::CH4));
+        assert_eq!("13co2".parse::<GasType>().unwrap(), GasType::CO2_13);
+        assert_eq!(GasType::CH4.conv_factor(), 1000.0);
+        assert!(GasType::from_int(999).is_none());
+    }
+
+    #[test]
+    fn saved_species_get_stable_ids() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(&create_gas_species_table(), []).unwrap();
+
+        let sf6 = save_gas_species(&conn, "SF6", 146.06, ConcentrationUnit::Ppb).unwrap();
+        let cos = save_gas_species(&conn, "COS", 60.07, ConcentrationUnit::Ppb).unwrap();
+        assert_eq!(sf6.as_int(), CUSTOM_SPECIES_START as usize);
+        assert_eq!(cos.as_int(), CUSTOM_SPECIES_START as usize + 1);
+        assert_eq!("sf6".parse::<GasType>().unwrap(), sf6);
+        assert_eq!(sf6.mol_mass(), 146.06);
+
+        // saving again keeps the id
+        let again = save_gas_species(&conn, "sf6", 146.0, ConcentrationUnit::Ppm).unwrap();
+        assert_eq!(again, sf6);
+        assert_eq!(sf6.unit(), "ppm");
+        assert_eq!(load_gas_species(&conn).unwrap(), 2);
+
+        assert!(save_gas_species(&conn, "co2", 44.0, ConcentrationUnit::Ppm).is_err());
+    }
 }
@@ -0,0 +1,214 @@
+use crate::errorcode::ErrorCode;
+use std::fmt;
+use std::str::FromStr;
+
+/// What a diagnostic condition does to the measurement it fires in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagSeverity {
+    /// Shown in cycle details but doesn't flag the cycle
+    Ignore,
+    /// Flags the cycle with `ErrorCode::DiagWarning`, the cycle stays valid
+    Warn,
+    /// Invalidates the cycle
+    Invalid,
+}
+
+impl fmt::Display for DiagSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagSeverity::Ignore => write!(f, "ignore"),
+            DiagSeverity::Warn => write!(f, "warn"),
+            DiagSeverity::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+impl FromStr for DiagSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ignore" => Ok(DiagSeverity::Ignore),
+            "warn" | "warning" => Ok(DiagSeverity::Warn),
+            "invalid" => Ok(DiagSeverity::Invalid),
+            other => Err(format!("invalid diag severity: {other}")),
+        }
+    }
+}
+
+/// Part of the analyzer a condition is about, picks the `ErrorCode` of
+/// invalidating conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagKind {
+    Laser,
+    Cavity,
+    RingDown,
+    Other,
+}
+
+impl DiagKind {
+    fn error_code(self) -> ErrorCode {
+        match self {
+            DiagKind::Laser => ErrorCode::DiagLaser,
+            DiagKind::Cavity => ErrorCode::DiagCavity,
+            DiagKind::RingDown => ErrorCode::DiagRingDown,
+            DiagKind::Other => ErrorCode::ErrorsInMeasurement,
+        }
+    }
+}
+
+impl FromStr for DiagKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "laser" => Ok(DiagKind::Laser),
+            "cavity" => Ok(DiagKind::Cavity),
+            "ring_down" | "ringdown" => Ok(DiagKind::RingDown),
+            "other" => Ok(DiagKind::Other),
+            other => Err(format!("invalid diag kind: {other}")),
+        }
+    }
+}
+
+/// A named condition encoded in one or more bits of the diag value
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagCondition {
+    pub mask: i64,
+    pub name: String,
+    pub severity: DiagSeverity,
+    pub kind: DiagKind,
+}
+
+impl DiagCondition {
+    pub fn new(bit: u32, name: &str, severity: DiagSeverity, kind: DiagKind) -> Self {
+        Self { mask: 1 << bit, name: name.to_owned(), severity, kind }
+    }
+
+    /// Error flagged on the cycle when this condition fires
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self.severity {
+            DiagSeverity::Ignore => None,
+            DiagSeverity::Warn => Some(ErrorCode::DiagWarning),
+            DiagSeverity::Invalid => Some(self.kind.error_code()),
+        }
+    }
+}
+
+/// Condition that fired in a measurement and on how many rows
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredCondition {
+    pub condition: DiagCondition,
+    pub count: usize,
+}
+
+/// Maps the bits of an instrument's diag column to named conditions
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagDecoder {
+    pub conditions: Vec<DiagCondition>,
+    /// Severity of set bits that no condition covers
+    pub unknown: DiagSeverity,
+}
+
+impl Default for DiagDecoder {
+    fn default() -> Self {
+        Self::nonzero()
+    }
+}
+
+impl DiagDecoder {
+    /// For instruments without a known bit layout, any nonzero value invalidates
+    pub fn nonzero() -> Self {
+        Self { conditions: Vec::new(), unknown: DiagSeverity::Invalid }
+    }
+
+    /// DIAG column of the LI-COR trace gas analyzers (LI-7810, LI-7820)
+    pub fn licor() -> Self {
+        use DiagKind::*;
+        use DiagSeverity::*;
+        Self {
+            conditions: vec![
+                DiagCondition::new(0, "Laser temperature unregulated", Invalid, Laser),
+                DiagCondition::new(1, "Laser current out of range", Invalid, Laser),
+                DiagCondition::new(2, "Cavity pressure out of range", Invalid, Cavity),
+                DiagCondition::new(3, "Cavity temperature unregulated", Warn, Cavity),
+                DiagCondition::new(4, "Ring-down time out of range", Invalid, RingDown),
+                DiagCondition::new(5, "Low ring-down count", Warn, RingDown),
+                DiagCondition::new(6, "Data not ready", Invalid, Other),
+                DiagCondition::new(7, "Clock not synchronized", Ignore, Other),
+            ],
+            unknown: Invalid,
+        }
+    }
+
+    /// Conditions set in a single diag value
+    pub fn decode(&self, value: i64) -> Vec<DiagCondition> {
+        if value == 0 {
+            return Vec::new();
+        }
+        let mut fired: Vec<DiagCondition> =
+            self.conditions.iter().filter(|c| value & c.mask != 0).cloned().collect();
+        let known = self.conditions.iter().fold(0, |acc, c| acc | c.mask);
+        let rest = value & !known;
+        if rest != 0 {
+            fired.push(DiagCondition {
+                mask: rest,
+                name: format!("Diagnostic value {rest}"),
+                severity: self.unknown,
+                kind: DiagKind::Other,
+            });
+        }
+        fired
+    }
+
+    /// Every condition that fired in `values`, in decoder order
+    pub fn fired(&self, values: &[i64]) -> Vec<FiredCondition> {
+        let mut fired: Vec<FiredCondition> = Vec::new();
+        for &value in values.iter().filter(|&&v| v != 0) {
+            for condition in self.decode(value) {
+                match fired.iter_mut().find(|f| f.condition.name == condition.name) {
+                    Some(f) => f.count += 1,
+                    None => fired.push(FiredCondition { condition, count: 1 }),
+                }
+            }
+        }
+        fired
+    }
+
+    /// Value has a condition that invalidates the row
+    pub fn is_invalid(&self, value: i64) -> bool {
+        self.decode(value).iter().any(|c| c.severity == DiagSeverity::Invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn licor_bits_are_named() {
+        let decoder = DiagDecoder::licor();
+        let fired = decoder.decode(0b1000_0100);
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].name, "Cavity pressure out of range");
+        assert_eq!(fired[0].error_code(), Some(ErrorCode::DiagCavity));
+        assert_eq!(fired[1].error_code(), None);
+        assert!(decoder.is_invalid(0b100));
+        assert!(!decoder.is_invalid(0b1000));
+        assert!(!decoder.is_invalid(0b1000_0000));
+    }
+
+    #[test]
+    fn unknown_bits_and_counts() {
+        let decoder = DiagDecoder::licor();
+        let fired = decoder.fired(&[0, 8, 8, 256, 0]);
+        assert_eq!(fired.len(), 2);
+        assert_eq!(fired[0].condition.error_code(), Some(ErrorCode::DiagWarning));
+        assert_eq!(fired[0].count, 2);
+        assert_eq!(fired[1].condition.name, "Diagnostic value 256");
+        assert_eq!(fired[1].condition.error_code(), Some(ErrorCode::ErrorsInMeasurement));
+
+        assert!(DiagDecoder::nonzero().is_invalid(1));
+        assert!(!DiagDecoder::nonzero().is_invalid(0));
+    }
+}
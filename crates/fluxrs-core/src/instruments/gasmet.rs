@@ -0,0 +1,353 @@
+use crate::concentrationunit::ConcentrationUnit;
+use crate::gaschannel::{ChannelConfig, MoleFraction};
+use crate::gastype::GasType;
+use crate::instruments::diag::{DiagCondition, DiagDecoder, DiagKind, DiagSeverity};
+use crate::instruments::instruments::{
+    parse_local_in_tz, FileFormat, InstrumentConfig, InstrumentMeasurement, TimeSourceKind,
+};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Gasmet DX4040 / GT5000 result files exported from Calcmet. Tab separated,
+// one row per spectrum with a concentration, compensation and residual column
+// for each gas in the library, eg. "Carbon dioxide CO2 (vol-%)" and "CO2 residual".
+const DATE_COL: &str = "Date";
+const TIME_COL: &str = "Time";
+const DEFAULT_TIME_FMT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Calcmet residuals above this are treated as a bad spectrum fit
+pub const RESIDUAL_LIMIT: f64 = 0.01;
+/// Diag bit set when the total spectrum residual is over the limit, gas
+/// residuals set the bit of their position in `InstrumentConfig::channels`
+pub const SPECTRUM_RESIDUAL_BIT: i64 = 1 << 15;
+
+impl InstrumentConfig {
+    pub fn gasmet() -> Self {
+        let name = "Gasmet";
+        let channel = |gas: GasType, unit: ConcentrationUnit| ChannelConfig {
+            gas,
+            // gases are found by their formula in the column name
+            concentration_col: gas.column_name().to_owned(),
+            unit,
+            instrument_id: name.to_owned(),
+            // FTIR concentrations are wet
+            mole_fraction: MoleFraction::Wet,
+        };
+        let channels = vec![
+            channel(GasType::CO2, ConcentrationUnit::Percent),
+            channel(GasType::CH4, ConcentrationUnit::Ppm),
+            channel(GasType::N2O, ConcentrationUnit::Ppm),
+            channel(GasType::H2O, ConcentrationUnit::Percent),
+            // after H2O so that the residual bits of stored diag values keep their gas
+            channel(GasType::NH3, ConcentrationUnit::Ppm),
+            channel(GasType::CO, ConcentrationUnit::Ppm),
+        ];
+        let cols: Vec<String> = channels.iter().map(|c| c.concentration_col.clone()).collect();
+        let mut conditions: Vec<DiagCondition> = channels
+            .iter()
+            .enumerate()
+            .map(|(bit, c)| {
+                let name = format!("{} residual over limit", c.gas);
+                DiagCondition::new(bit as u32, &name, DiagSeverity::Invalid, DiagKind::Other)
+            })
+            .collect();
+        conditions.push(DiagCondition::new(
+            SPECTRUM_RESIDUAL_BIT.trailing_zeros(),
+            "Spectrum residual over limit",
+            DiagSeverity::Invalid,
+            DiagKind::Other,
+        ));
+        Self {
+            name: name.to_owned(),
+            model: name.to_owned(),
+            serial: None,
+            file_format: FileFormat::GasmetTxt,
+            sep: b'\t',
+            skiprows: 0,
+            meta_rows: false,
+            serial_tag: None,
+            footer_marker: None,
+            has_tz: false,
+            skip_after_header: 0,
+            time_col: TIME_COL.to_owned(),
+            nsecs_col: String::new(),
+            gas_cols: cols.clone(),
+            flux_cols: cols,
+            diag_col: "Residual".to_owned(),
+            diag: DiagDecoder { conditions, unknown: DiagSeverity::Invalid },
+            has_header: true,
+            available_gases: channels.iter().map(|c| c.gas).collect(),
+            channels,
+            time_source: TimeSourceKind::StringFormat,
+            time_fmt: Some(DEFAULT_TIME_FMT.to_owned()),
+        }
+    }
+}
+
+struct GasColumns {
+    // diag bit of the gas residual, its position in the channels
+    bit: usize,
+    value: usize,
+    // multiplier from the unit in the header to the unit of the channel
+    scale: f64,
+    residual: Option<usize>,
+}
+
+/// Read a Calcmet result file. Residual columns are folded into the diag value
+/// so that cycles with bad spectra get flagged like instrument diagnostics.
+pub(crate) fn read_result_file(
+    cfg: &InstrumentConfig,
+    path: &Path,
+    tz: Tz,
+) -> Result<InstrumentMeasurement, Box<dyn Error>> {
+    let file = File::open(path)?;
+    read_results(cfg, BufReader::new(file), tz)
+}
+
+fn read_results<R: BufRead>(
+    cfg: &InstrumentConfig,
+    reader: R,
+    tz: Tz,
+) -> Result<InstrumentMeasurement, Box<dyn Error>> {
+    let sep = cfg.sep as char;
+    let mut lines = reader.lines();
+
+    // Calcmet may write a few lines of run info before the header
+    let mut header: Vec<String> = Vec::new();
+    for line in lines.by_ref() {
+        let line = line?;
+        let cells: Vec<String> = line.split(sep).map(|c| c.trim().to_owned()).collect();
+        if cells.iter().any(|c| c == DATE_COL) && cells.iter().any(|c| c == TIME_COL) {
+            header = cells;
+            break;
+        }
+    }
+    if header.is_empty() {
+        return Err(format!("No header with {} and {} columns found", DATE_COL, TIME_COL).into());
+    }
+    let idx_date = header.iter().position(|h| h == DATE_COL).unwrap_or(0);
+    let idx_time = header.iter().position(|h| h == TIME_COL).unwrap_or(0);
+    let idx_residual = header.iter().position(|h| h.eq_ignore_ascii_case(&cfg.diag_col));
+
+    // the gases in a result file depend on the Calcmet library, so channels
+    // without a column are left out
+    let mut columns: Vec<(GasType, GasColumns)> = Vec::new();
+    for (bit, channel) in cfg.channels.iter().enumerate() {
+        let formula = channel.concentration_col.as_str();
+        let Some(value) = header
+            .iter()
+            .position(|h| names_gas(h, formula) && !is_residual(h) && !is_compensation(h))
+        else {
+            continue;
+        };
+        let scale = match header_unit(&header[value]) {
+            Some(unit) => unit.to_ppm_factor() / channel.unit.to_ppm_factor(),
+            None => 1.0,
+        };
+        let residual = header.iter().position(|h| names_gas(h, formula) && is_residual(h));
+        columns.push((channel.gas, GasColumns { bit, value, scale, residual }));
+    }
+    if columns.is_empty() {
+        return Err("No known gas columns found in header".into());
+    }
+
+    let fmt = cfg.time_fmt.as_deref().unwrap_or(DEFAULT_TIME_FMT);
+    let mut gas_data: HashMap<GasType, Vec<f64>> =
+        columns.iter().map(|(gas, _)| (*gas, Vec::new())).collect();
+    let mut datetime_vec: Vec<i64> = Vec::new();
+    let mut diag_vec: Vec<i64> = Vec::new();
+
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(sep).map(str::trim).collect();
+        if fields.len() < header.len() {
+            continue;
+        }
+
+        let time_str = format!("{} {}", fields[idx_date], fields[idx_time]);
+        datetime_vec.push(parse_local_in_tz(&time_str, fmt, tz)?.timestamp_millis());
+
+        let mut diag = 0;
+        if idx_residual.is_some_and(|i| residual_is_bad(fields[i])) {
+            diag |= SPECTRUM_RESIDUAL_BIT;
+        }
+        for (gas, cols) in &columns {
+            let value = fields[cols.value].parse::<f64>().map(|v| v * cols.scale);
+            if let Some(gas_vector) = gas_data.get_mut(gas) {
+                gas_vector.push(value.unwrap_or(f64::NAN));
+            }
+            if cols.residual.is_some_and(|i| residual_is_bad(fields[i])) {
+                diag |= 1 << cols.bit;
+            }
+        }
+        diag_vec.push(diag);
+    }
+
+    cfg.build_measurement(cfg.serial.clone().unwrap_or_default(), datetime_vec, gas_data, diag_vec)
+}
+
+/// Calcmet doesn't write the analyzer serial, so any alphanumeric serial is accepted
+pub(crate) fn validate_serial(serial: &str) -> bool {
+    let s = serial.trim();
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn names_gas(header: &str, formula: &str) -> bool {
+    header.split(|c: char| c.is_whitespace() || c == '(' || c == ')').any(|w| w == formula)
+}
+
+fn is_residual(header: &str) -> bool {
+    header.to_ascii_lowercase().contains("residual")
+}
+
+fn is_compensation(header: &str) -> bool {
+    header.to_ascii_lowercase().contains("compensation")
+}
+
+// "Carbon dioxide CO2 (vol-%)" -> Percent
+fn header_unit(header: &str) -> Option<ConcentrationUnit> {
+    let start = header.rfind('(')?;
+    let end = header[start..].find(')')? + start;
+    let unit = header[start + 1..end].trim().trim_start_matches("vol-");
+    unit.parse::<ConcentrationUnit>().ok()
+}
+
+fn residual_is_bad(value: &str) -> bool {
+    match value.parse::<f64>() {
+        Ok(v) => !v.is_finite() || v.abs() > RESIDUAL_LIMIT,
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn result_file() -> String {
+        let header = [
+            "Line",
+            "Date",
+            "Time",
+            "Spectrum",
+            "Water vapor H2O (vol-%)",
+            "H2O residual",
+            "Carbon dioxide CO2 (vol-%)",
+            "CO2 residual",
+            "Carbon dioxide CO2 compensation",
+            "Methane CH4 (ppb)",
+            "CH4 residual",
+            "Nitrous oxide N2O (ppm)",
+            "N2O residual",
+            "Ammonia NH3 (ppb)",
+            "NH3 residual",
+            "Carbon monoxide CO (ppm)",
+            "CO residual",
+            "Residual",
+        ];
+        let rows = [
+            [
+                "2",
+                "2024-06-12",
+                "12:00:20",
+                "s2",
+                "1.2",
+                "0.001",
+                "0.0421",
+                "0.002",
+                "0",
+                "2050",
+                "0.5",
+                "0.33",
+                "0.001",
+                "12",
+                "0.001",
+                "0.15",
+                "0.001",
+                "0.004",
+            ],
+            [
+                "1",
+                "2024-06-12",
+                "12:00:00",
+                "s1",
+                "1.1",
+                "0.001",
+                "0.0420",
+                "0.002",
+                "0",
+                "2000",
+                "0.001",
+                "0.33",
+                "0.001",
+                "10",
+                "0.001",
+                "0.14",
+                "0.001",
+                "0.2",
+            ],
+        ];
+        let mut out = String::from("Calcmet result file\n");
+        out.push_str(&header.join("\t"));
+        out.push('\n');
+        for row in rows {
+            out.push_str(&row.join("\t"));
+            out.push('\n');
+        }
+        out
+    }
+
+    #[test]
+    fn reads_gases_and_scales_units() {
+        let cfg = InstrumentConfig::gasmet();
+        let data = read_results(&cfg, Cursor::new(result_file()), Tz::UTC).unwrap();
+
+        assert_eq!(data.datetime, vec![1718193600000, 1718193620000]);
+        // vol-% is kept as is, CH4 is converted from ppb to the ppm channel
+        assert_eq!(data.gas[&GasType::CO2], vec![Some(0.0420), Some(0.0421)]);
+        assert_eq!(data.gas[&GasType::CH4], vec![Some(2.0), Some(2.05)]);
+    }
+
+    #[test]
+    fn reads_nh3_and_co() {
+        let cfg = InstrumentConfig::gasmet();
+        let data = read_results(&cfg, Cursor::new(result_file()), Tz::UTC).unwrap();
+
+        assert_eq!(data.gas[&GasType::NH3], vec![Some(0.01), Some(0.012)]);
+        assert_eq!(data.gas[&GasType::CO], vec![Some(0.14), Some(0.15)]);
+
+        // libraries without them still read
+        let file = result_file()
+            .replace("Ammonia NH3", "Ammonia")
+            .replace("Carbon monoxide CO", "Carbon monoxide");
+        let data = read_results(&cfg, Cursor::new(file), Tz::UTC).unwrap();
+        assert!(!data.gas.contains_key(&GasType::NH3));
+        assert!(!data.gas.contains_key(&GasType::CO));
+        assert_eq!(data.gas[&GasType::CO2], vec![Some(0.0420), Some(0.0421)]);
+    }
+
+    #[test]
+    fn residuals_go_to_diag() {
+        let cfg = InstrumentConfig::gasmet();
+        let data = read_results(&cfg, Cursor::new(result_file()), Tz::UTC).unwrap();
+
+        let ch4_bit = 1 << cfg.channels.iter().position(|c| c.gas == GasType::CH4).unwrap();
+        assert_eq!(data.diag, vec![SPECTRUM_RESIDUAL_BIT, ch4_bit]);
+    }
+
+    #[test]
+    fn header_units() {
+        assert_eq!(header_unit("Carbon dioxide CO2 (vol-%)"), Some(ConcentrationUnit::Percent));
+        assert_eq!(header_unit("Ammonia NH3 (ppm)"), Some(ConcentrationUnit::Ppm));
+        assert_eq!(header_unit("CO2 residual"), None);
+        assert!(names_gas("Carbon monoxide CO (ppm)", "CO"));
+        assert!(!names_gas("Carbon dioxide CO2 (ppm)", "CO"));
+    }
+}
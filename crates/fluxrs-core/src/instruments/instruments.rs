@@ -1,16 +1,18 @@
+use crate::archive::expand_inputs;
 use crate::concentrationunit::ConcentrationUnit;
-use crate::cycle::gaskey::GasKey;
 use crate::data_formats::gasdata::insert_measurements;
 use crate::datatype::DataType;
-use crate::gaschannel::{ChannelConfig, GasChannel};
+use crate::gaschannel::{ChannelConfig, GasChannel, MoleFraction};
 use crate::gastype::GasType;
+use crate::instruments::diag::DiagDecoder;
+use crate::instruments::{gasmet, lgr, picarro, profile};
 use crate::processevent::{InsertEvent, ProcessEvent, ReadEvent};
 use crate::project::Project;
 use crate::utils::{get_or_insert_data_file, touch_if_exists_updated, DataFileError};
 use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone};
 use chrono_tz::{Tz, UTC};
 use rusqlite::{params, Connection, Result};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::fs::File;
@@ -21,6 +23,7 @@ use tokio::sync::mpsc;
 
 pub struct InstrumentMeasurement {
     pub instrument: Instrument,
+    /// Unix time in milliseconds
     pub datetime: Vec<i64>,
     pub gas: HashMap<GasType, Vec<Option<f64>>>,
     pub diag: Vec<i64>,
@@ -85,6 +88,12 @@ pub enum InstrumentType {
     #[default]
     LI7810,
     LI7820,
+    G2508,
+    UGGA,
+    N2OCO,
+    GASMET,
+    /// Instrument described by a user supplied profile, see `profile::InstrumentProfile`
+    Custom(&'static str),
 }
 
 impl fmt::Display for InstrumentType {
@@ -92,6 +101,11 @@ impl fmt::Display for InstrumentType {
         match self {
             InstrumentType::LI7810 => write!(f, "LI-7810"),
             InstrumentType::LI7820 => write!(f, "LI-7820"),
+            InstrumentType::G2508 => write!(f, "G2508"),
+            InstrumentType::UGGA => write!(f, "LGR-UGGA"),
+            InstrumentType::N2OCO => write!(f, "LGR-N2OCO"),
+            InstrumentType::GASMET => write!(f, "Gasmet"),
+            InstrumentType::Custom(name) => write!(f, "{}", name),
         }
     }
 }
@@ -105,27 +119,56 @@ impl FromStr for InstrumentType {
             "li-7820" => Ok(InstrumentType::LI7820),
             "li7810" => Ok(InstrumentType::LI7810),
             "li7820" => Ok(InstrumentType::LI7820),
-            other => Err(ParseInstrumentError(format!("invalid instrument: {other}"))),
+            "g2508" => Ok(InstrumentType::G2508),
+            "picarro-g2508" => Ok(InstrumentType::G2508),
+            "lgr-ugga" => Ok(InstrumentType::UGGA),
+            "ugga" => Ok(InstrumentType::UGGA),
+            "lgr-n2oco" => Ok(InstrumentType::N2OCO),
+            "n2oco" => Ok(InstrumentType::N2OCO),
+            "gasmet" => Ok(InstrumentType::GASMET),
+            other => profile::find_registered(s)
+                .ok_or_else(|| ParseInstrumentError(format!("invalid instrument: {other}"))),
         }
     }
 }
 
 impl InstrumentType {
-    /// Return a list of available instruments (for UI dropdown)
+    /// Instruments that have a reader compiled in
+    pub fn builtin_instruments() -> Vec<InstrumentType> {
+        vec![
+            InstrumentType::LI7810,
+            InstrumentType::LI7820,
+            InstrumentType::G2508,
+            InstrumentType::UGGA,
+            InstrumentType::N2OCO,
+            InstrumentType::GASMET,
+        ]
+    }
+    /// Return a list of available instruments (for UI dropdown), including
+    /// instruments registered from profiles
     pub fn available_instruments() -> Vec<InstrumentType> {
-        vec![InstrumentType::LI7810, InstrumentType::LI7820]
-        // Expand this list as needed
+        let mut instruments = Self::builtin_instruments();
+        instruments.extend(profile::registered_instruments());
+        instruments
     }
     pub fn available_gases(&self) -> Vec<GasType> {
-        match self {
-            InstrumentType::LI7810 => InstrumentConfig::li7810().available_gases,
-            InstrumentType::LI7820 => InstrumentConfig::li7820().available_gases,
-        }
+        self.get_config().available_gases
+    }
+    pub fn diag_decoder(&self) -> DiagDecoder {
+        self.get_config().diag
     }
     pub fn get_config(&self) -> InstrumentConfig {
         match self {
             InstrumentType::LI7810 => InstrumentConfig::li7810(),
             InstrumentType::LI7820 => InstrumentConfig::li7820(),
+            InstrumentType::G2508 => InstrumentConfig::g2508(),
+            InstrumentType::UGGA => InstrumentConfig::ugga(),
+            InstrumentType::N2OCO => InstrumentConfig::n2oco(),
+            InstrumentType::GASMET => InstrumentConfig::gasmet(),
+            // Custom variants are only handed out by the profile registry
+            InstrumentType::Custom(name) => {
+                profile::registered_config(name).expect("instrument profile is registered")
+            },
         }
     }
 
@@ -146,6 +189,10 @@ impl InstrumentType {
         match self {
             InstrumentType::LI7810 => check(&s, "TG10-"),
             InstrumentType::LI7820 => check(&s, "TG20-"),
+            InstrumentType::G2508 => picarro::validate_serial(&s),
+            InstrumentType::UGGA | InstrumentType::N2OCO => lgr::validate_serial(&s),
+            InstrumentType::GASMET => gasmet::validate_serial(&s),
+            InstrumentType::Custom(name) => profile::registered_serial_is_valid(name, serial),
         }
     }
 }
@@ -180,13 +227,57 @@ pub enum TimeSourceKind {
     StringFormat,
 }
 
+impl FromStr for TimeSourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "seconds" => Ok(TimeSourceKind::Seconds),
+            "seconds_and_nanos" => Ok(TimeSourceKind::SecondsAndNanos),
+            "string_format" => Ok(TimeSourceKind::StringFormat),
+            other => Err(format!("invalid time source: {other}")),
+        }
+    }
+}
+
+/// Layout of the raw data files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    /// Delimited text with a single header row, optionally preceded by meta rows
+    Delimited,
+    /// Whitespace separated Picarro .dat files
+    PicarroDat,
+    /// Tab separated Gasmet Calcmet result files
+    GasmetTxt,
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "delimited" => Ok(FileFormat::Delimited),
+            "picarro_dat" => Ok(FileFormat::PicarroDat),
+            "gasmet_txt" => Ok(FileFormat::GasmetTxt),
+            other => Err(format!("invalid file format: {other}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InstrumentConfig {
     pub name: String,
     pub model: String,
     pub serial: Option<String>,
+    pub file_format: FileFormat,
     pub sep: u8,
     pub skiprows: usize,
+    // model, serial and timezone rows before the header like in LI-COR files
+    pub meta_rows: bool,
+    // serial is read from the skipped rows after this tag, eg. "SN:"
+    pub serial_tag: Option<String>,
+    // data ends at a row starting with this, eg. the PGP block in LGR files
+    pub footer_marker: Option<String>,
     pub has_tz: bool,
     pub skip_after_header: usize,
     pub time_col: String,
@@ -194,6 +285,7 @@ pub struct InstrumentConfig {
     pub gas_cols: Vec<String>,
     pub flux_cols: Vec<String>,
     pub diag_col: String,
+    pub diag: DiagDecoder,
     pub has_header: bool,
     pub available_gases: Vec<GasType>,
     pub channels: Vec<ChannelConfig>,
@@ -214,8 +306,12 @@ impl InstrumentConfig {
             name: "LI-7810".to_owned(),
             model: "LI-7810".to_owned(),
             serial: None,
+            file_format: FileFormat::Delimited,
             sep: b'\t',
             skiprows: 2,
+            meta_rows: true,
+            serial_tag: None,
+            footer_marker: None,
             has_tz: true,
             skip_after_header: 1,
             time_col: "SECONDS".to_owned(),
@@ -223,6 +319,7 @@ impl InstrumentConfig {
             gas_cols: vec!["CO2".to_owned(), "CH4".to_owned(), "H2O".to_owned()],
             flux_cols: vec!["CO2".to_owned(), "CH4".to_owned()],
             diag_col: "DIAG".to_owned(),
+            diag: DiagDecoder::licor(),
             has_header: false,
             available_gases: vec![GasType::CO2, GasType::CH4, GasType::H2O],
             channels: vec![
@@ -231,18 +328,21 @@ impl InstrumentConfig {
                     concentration_col: "CO2".to_owned(),
                     unit: ConcentrationUnit::Ppm,
                     instrument_id: "LI-7810".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
                 },
                 ChannelConfig {
                     gas: GasType::CH4,
                     concentration_col: "CH4".to_owned(),
                     unit: ConcentrationUnit::Ppb,
                     instrument_id: "LI-7810".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
                 },
                 ChannelConfig {
                     gas: GasType::H2O,
                     concentration_col: "H2O".to_owned(),
                     unit: ConcentrationUnit::Ppm,
                     instrument_id: "LI-7810".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
                 },
             ],
             time_source: TimeSourceKind::SecondsAndNanos,
@@ -254,8 +354,12 @@ impl InstrumentConfig {
             name: "LI-7820".to_string(),
             model: "LI-7820".to_string(),
             serial: None,
+            file_format: FileFormat::Delimited,
             sep: b'\t',
             skiprows: 2,
+            meta_rows: true,
+            serial_tag: None,
+            footer_marker: None,
             has_tz: true,
             skip_after_header: 1,
             time_col: "SECONDS".to_owned(),
@@ -263,6 +367,7 @@ impl InstrumentConfig {
             gas_cols: vec!["N2O".to_owned(), "H2O".to_owned()],
             flux_cols: vec!["N2O".to_owned()],
             diag_col: "DIAG".to_owned(),
+            diag: DiagDecoder::licor(),
             has_header: false,
             available_gases: vec![GasType::N2O, GasType::H2O],
             channels: vec![
@@ -271,12 +376,14 @@ impl InstrumentConfig {
                     concentration_col: "N2O".to_owned(),
                     unit: ConcentrationUnit::Ppb,
                     instrument_id: "LI-7820".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
                 },
                 ChannelConfig {
                     gas: GasType::H2O,
                     concentration_col: "H2O".to_owned(),
                     unit: ConcentrationUnit::Ppm,
                     instrument_id: "LI-7820".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
                 },
             ],
             time_source: TimeSourceKind::SecondsAndNanos,
@@ -284,9 +391,104 @@ impl InstrumentConfig {
         }
     }
 
+    pub fn g2508() -> Self {
+        Self {
+            name: "G2508".to_owned(),
+            model: "G2508".to_owned(),
+            serial: None,
+            file_format: FileFormat::PicarroDat,
+            sep: b' ',
+            skiprows: 0,
+            meta_rows: false,
+            serial_tag: None,
+            footer_marker: None,
+            has_tz: false,
+            skip_after_header: 0,
+            time_col: "EPOCH_TIME".to_owned(),
+            nsecs_col: String::new(),
+            gas_cols: vec![
+                "CO2_dry".to_owned(),
+                "CH4_dry".to_owned(),
+                "N2O_dry".to_owned(),
+                "NH3".to_owned(),
+                "H2O".to_owned(),
+            ],
+            flux_cols: vec![
+                "CO2_dry".to_owned(),
+                "CH4_dry".to_owned(),
+                "N2O_dry".to_owned(),
+                "NH3".to_owned(),
+            ],
+            diag_col: "ALARM_STATUS".to_owned(),
+            diag: DiagDecoder::nonzero(),
+            has_header: true,
+            available_gases: vec![
+                GasType::CO2,
+                GasType::CH4,
+                GasType::N2O,
+                GasType::NH3,
+                GasType::H2O,
+            ],
+            channels: vec![
+                ChannelConfig {
+                    gas: GasType::CO2,
+                    concentration_col: "CO2_dry".to_owned(),
+                    unit: ConcentrationUnit::Ppm,
+                    instrument_id: "G2508".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
+                },
+                ChannelConfig {
+                    gas: GasType::CH4,
+                    concentration_col: "CH4_dry".to_owned(),
+                    unit: ConcentrationUnit::Ppm,
+                    instrument_id: "G2508".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
+                },
+                ChannelConfig {
+                    gas: GasType::N2O,
+                    concentration_col: "N2O_dry".to_owned(),
+                    unit: ConcentrationUnit::Ppm,
+                    instrument_id: "G2508".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
+                },
+                ChannelConfig {
+                    gas: GasType::NH3,
+                    concentration_col: "NH3".to_owned(),
+                    unit: ConcentrationUnit::Ppb,
+                    instrument_id: "G2508".to_owned(),
+                    mole_fraction: MoleFraction::Wet,
+                },
+                ChannelConfig {
+                    gas: GasType::H2O,
+                    concentration_col: "H2O".to_owned(),
+                    unit: ConcentrationUnit::Percent,
+                    instrument_id: "G2508".to_owned(),
+                    mole_fraction: MoleFraction::Dry,
+                },
+            ],
+            time_source: TimeSourceKind::Seconds,
+            time_fmt: Some("%Y-%m-%d %H:%M:%S%.f".to_owned()),
+        }
+    }
+
+    /// Read a data file. `tz` is used for string timestamps when the file
+    /// doesn't carry its own timezone.
     pub fn read_data_file<P: AsRef<Path>>(
         &self,
         filename: P,
+        tz: Tz,
+    ) -> Result<InstrumentMeasurement, Box<dyn Error>> {
+        match self.file_format {
+            FileFormat::Delimited => self.read_delimited(filename, tz),
+            FileFormat::PicarroDat => picarro::read_dat_file(self, filename.as_ref(), tz),
+            FileFormat::GasmetTxt => gasmet::read_result_file(self, filename.as_ref(), tz),
+        }
+    }
+
+    fn read_delimited<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        tz: Tz,
     ) -> Result<InstrumentMeasurement, Box<dyn Error>> {
         let file = File::open(filename)?;
         let mut rdr = csv::ReaderBuilder::new()
@@ -295,22 +497,37 @@ impl InstrumentConfig {
             .flexible(true)
             .from_reader(file);
 
-        let mut instrument_serial = String::new();
-        let mut instrument_model = String::new();
-        let mut tz_str = String::new();
+        let mut instrument_serial = self.serial.clone().unwrap_or_default();
+        let mut instrument_model = self.model.clone();
+        let mut tz_str = tz.to_string();
 
-        if let Some(result) = rdr.records().next() {
-            instrument_model = result?.get(1).unwrap_or("").to_string();
-        }
-        if let Some(result) = rdr.records().next() {
-            instrument_serial = result?.get(1).unwrap_or("").to_string();
+        if self.meta_rows {
+            if let Some(result) = rdr.records().next() {
+                instrument_model = result?.get(1).unwrap_or("").to_string();
+            }
+            if let Some(result) = rdr.records().next() {
+                instrument_serial = result?.get(1).unwrap_or("").to_string();
+            }
         }
 
         for _ in 0..self.skiprows {
-            rdr.records().next();
+            let Some(result) = rdr.records().next() else {
+                break;
+            };
+            if let Some(tag) = &self.serial_tag {
+                let row = result?.iter().collect::<Vec<_>>().join(" ");
+                if let Some(serial) = serial_after_tag(&row, tag) {
+                    instrument_serial = serial;
+                }
+            }
         }
-        if let Some(result) = rdr.records().next() {
-            tz_str = result?.get(1).unwrap_or("").to_string();
+        if self.meta_rows {
+            if let Some(result) = rdr.records().next() {
+                let file_tz = result?.get(1).unwrap_or("").to_string();
+                if self.has_tz {
+                    tz_str = file_tz;
+                }
+            }
         }
 
         let mut gas_data: HashMap<GasType, Vec<f64>> = HashMap::new();
@@ -323,42 +540,57 @@ impl InstrumentConfig {
         }
 
         let mut gas_indices: HashMap<GasType, usize> = HashMap::new();
-        let idx_diag = header.iter().position(|h| h == self.diag_col).unwrap_or(0);
-        let idx_secs = header.iter().position(|h| h == self.time_col).unwrap_or(0);
-        let idx_nsecs = header.iter().position(|h| h == self.nsecs_col).unwrap_or(0);
-
-        for gas_col in &self.gas_cols {
-            if let Some((i, _)) = header.iter().enumerate().find(|(_, h)| h == gas_col) {
-                if let Ok(gas_type) = gas_col.parse::<GasType>() {
-                    gas_indices.insert(gas_type, i);
-                    gas_data.insert(gas_type, Vec::new());
-                } else {
-                    eprintln!("Warning: Could not parse gas column '{}' as GasType", gas_col);
-                }
-            } else {
-                return Err(format!("Warning: Gas column '{}' not found in header", gas_col).into());
+        let idx_diag = header.iter().position(|h| h.trim() == self.diag_col);
+        let idx_secs = header.iter().position(|h| h.trim() == self.time_col).unwrap_or(0);
+        let idx_nsecs = header.iter().position(|h| h.trim() == self.nsecs_col).unwrap_or(0);
+
+        for channel in &self.channels {
+            match header.iter().position(|h| h.trim() == channel.concentration_col) {
+                Some(i) => {
+                    gas_indices.insert(channel.gas, i);
+                    gas_data.insert(channel.gas, Vec::new());
+                },
+                None => {
+                    return Err(format!(
+                        "Warning: Gas column '{}' not found in header",
+                        channel.concentration_col
+                    )
+                    .into());
+                },
             }
         }
 
+        let file_tz: Tz = tz_str.parse().map_err(|_| format!("Invalid timezone: {}", tz_str))?;
+
         for record in rdr.records().skip(self.skip_after_header) {
             let record = record?;
-            // println!("{:?}", record);
+            if let Some(marker) = &self.footer_marker {
+                if record.get(0).is_some_and(|f| f.trim_start().starts_with(marker.as_str())) {
+                    break;
+                }
+            }
 
             for (&gas_type, &idx) in &gas_indices {
-                let value = record.get(idx).unwrap_or("NaN").parse::<f64>().unwrap_or(f64::NAN);
+                let value =
+                    record.get(idx).unwrap_or("NaN").trim().parse::<f64>().unwrap_or(f64::NAN);
                 if let Some(gas_vector) = gas_data.get_mut(&gas_type) {
                     gas_vector.push(value);
                 }
             }
 
-            if let Ok(val) = record.get(idx_diag).unwrap_or("0").parse::<i64>() {
-                diag_vec.push(val);
-            }
+            let diag = idx_diag
+                .and_then(|i| record.get(i))
+                .and_then(|v| v.trim().parse::<i64>().ok())
+                .unwrap_or(0);
+            diag_vec.push(diag);
 
             let timestamp = match self.time_source {
                 TimeSourceKind::Seconds => {
-                    let sec = record.get(idx_secs).unwrap_or("0").parse::<i64>()?;
-                    parse_secnsec_to_dt(sec, 0, tz_str.clone())
+                    // fractional seconds are kept, fast analyzers log e.g. 1718193600.1
+                    let secs = record.get(idx_secs).unwrap_or("0").trim().parse::<f64>()?;
+                    let sec = secs.floor();
+                    let nsec = ((secs - sec) * 1e9).round().min(999_999_999.0) as u32;
+                    parse_secnsec_to_dt(sec as i64, nsec, tz_str.clone())
                 },
                 TimeSourceKind::SecondsAndNanos => {
                     let sec = record.get(idx_secs).unwrap_or("0").parse::<i64>()?;
@@ -366,23 +598,30 @@ impl InstrumentConfig {
                     parse_secnsec_to_dt(sec, nsec, tz_str.clone())
                 },
                 TimeSourceKind::StringFormat => {
-                    let time_str = record.get(idx_secs).unwrap_or("");
+                    let time_str = record.get(idx_secs).unwrap_or("").trim();
                     let fmt = self.time_fmt.as_deref().unwrap_or("%Y-%m-%d %H:%M:%S");
-                    let tz: Tz = tz_str.parse().expect("Invalid timezone string");
-                    parse_local_in_tz(time_str, fmt, tz)
+                    parse_local_in_tz(time_str, fmt, file_tz)?
                 },
             };
 
-            datetime_vec.push(timestamp.timestamp());
+            datetime_vec.push(timestamp.timestamp_millis());
+        }
+
+        if instrument_model != self.model {
+            return Err("Given instrument model and file instrument model don't match.".into());
         }
 
-        // Sort timestamps and align data accordingly
-        let mut datetime_sorted: Vec<i64> = Vec::new();
-        let mut diag_sorted: Vec<i64> = Vec::new();
-        // let mut diag_sorted: Vec<i64> = Vec::new();
-        let mut sorted_gas_data: HashMap<GasKey, Vec<Option<f64>>> = HashMap::new();
-        let mut instruments: HashSet<Instrument> = HashSet::new();
+        self.build_measurement(instrument_serial, datetime_vec, gas_data, diag_vec)
+    }
 
+    /// Sort the parsed columns by time and wrap them in an `InstrumentMeasurement`
+    pub(crate) fn build_measurement(
+        &self,
+        serial: String,
+        datetime_vec: Vec<i64>,
+        gas_data: HashMap<GasType, Vec<f64>>,
+        diag_vec: Vec<i64>,
+    ) -> Result<InstrumentMeasurement, Box<dyn Error>> {
         let mut indices: Vec<usize> = (0..datetime_vec.len()).collect();
 
         // Sort indices based on the datetime values
@@ -398,24 +637,17 @@ impl InstrumentConfig {
             let sorted: Vec<_> = indices.iter().map(|&i| Some(values[i])).collect();
             sorted_gas_data.insert(gas_type, sorted);
         }
-        if instrument_model != self.model {
-            return Err("Given instrument model and file instrument model don't match.".into());
-        }
 
-        // model_key.insert(instrument_serial.clone(), InstrumentType::from_str(&self.model.clone()));
-        // let model_string = self.model.clone().parse::<InstrumentType>().ok();
-        let ins_model = match self.model.parse::<InstrumentType>() {
+        let ins_model = match self.name.parse::<InstrumentType>() {
             Ok(val) => val,
             Err(_) => {
-                return Err(format!(
-                    "Unexpected invalid instrument type from DB: '{}'",
-                    self.model
-                )
-                .into());
+                return Err(
+                    format!("Unexpected invalid instrument type from DB: '{}'", self.name).into()
+                );
             },
         };
 
-        let instrument = Instrument { model: ins_model, serial: instrument_serial, id: None };
+        let instrument = Instrument { model: ins_model, serial, id: None };
 
         Ok(InstrumentMeasurement {
             instrument,
@@ -431,6 +663,7 @@ impl InstrumentConfig {
             .map(|ch| GasChannel {
                 gas: ch.gas,
                 unit: ch.unit,
+                mole_fraction: ch.mole_fraction,
                 instrument_id: format!(
                     "{}{}",
                     self.model,
@@ -441,6 +674,12 @@ impl InstrumentConfig {
     }
 }
 
+/// Return the first whitespace separated word after `tag`
+fn serial_after_tag(row: &str, tag: &str) -> Option<String> {
+    let start = row.find(tag)? + tag.len();
+    row[start..].split_whitespace().next().map(str::to_owned)
+}
+
 pub fn parse_secnsec_to_dt(sec: i64, nsec: u32, tz_str: String) -> DateTime<Tz> {
     let tz: Tz = tz_str.parse().expect("Invalid timezone string");
     match tz.timestamp_opt(sec, nsec) {
@@ -459,13 +698,21 @@ pub fn get_instrument_by_model(model: InstrumentType) -> Option<InstrumentConfig
     match model {
         InstrumentType::LI7810 => Some(InstrumentConfig::li7810()),
         InstrumentType::LI7820 => Some(InstrumentConfig::li7820()),
-        _ => None,
+        InstrumentType::G2508 => Some(InstrumentConfig::g2508()),
+        InstrumentType::UGGA => Some(InstrumentConfig::ugga()),
+        InstrumentType::N2OCO => Some(InstrumentConfig::n2oco()),
+        InstrumentType::GASMET => Some(InstrumentConfig::gasmet()),
+        InstrumentType::Custom(name) => profile::registered_config(name),
     }
 }
 
-fn parse_local_in_tz<Tz: TimeZone>(time_str: &str, fmt: &str, tz: Tz) -> chrono::DateTime<Tz> {
-    let naive = NaiveDateTime::parse_from_str(time_str, fmt).unwrap();
-    match tz.from_local_datetime(&naive) {
+pub(crate) fn parse_local_in_tz<Tz: TimeZone>(
+    time_str: &str,
+    fmt: &str,
+    tz: Tz,
+) -> Result<chrono::DateTime<Tz>, chrono::ParseError> {
+    let naive = NaiveDateTime::parse_from_str(time_str, fmt)?;
+    let dt = match tz.from_local_datetime(&naive) {
         LocalResult::Single(dt) => dt,
         LocalResult::Ambiguous(a, b) => {
             // pick one or surface an error; here we choose the earlier
@@ -475,7 +722,8 @@ fn parse_local_in_tz<Tz: TimeZone>(time_str: &str, fmt: &str, tz: Tz) -> chrono:
             // invalid local time (skipped by DST); decide how to handle
             tz.from_local_datetime(&(naive - chrono::Duration::hours(1))).unwrap()
         },
-    }
+    };
+    Ok(dt)
 }
 pub fn get_or_insert_instrument(
     conn: &Connection,
@@ -509,26 +757,36 @@ pub fn upload_gas_data_async(
     tz: Tz,
     progress_sender: mpsc::UnboundedSender<ProcessEvent>,
 ) {
-    for path in &selected_paths {
-        match instrument.get_config().read_data_file(path) {
-            Ok(data) => {
-                // inst.serial = Some(data.instrument.serial.clone());
+    let inputs = expand_inputs(&selected_paths);
+    for (path, e) in &inputs.failed {
+        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::gas_fail(
+            path.to_string_lossy().to_string(),
+            e.clone(),
+        )));
+    }
+    for input in &inputs.files {
+        let path = &input.path;
+        match instrument.get_config().read_data_file(path, tz) {
+            Ok(mut data) => {
+                // files without a serial row belong to the project instrument
+                if data.instrument.serial.is_empty() {
+                    if project.instrument.model != *instrument {
+                        let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::gas_fail(
+                            input.label.clone(),
+                            format!(
+                                "File has no serial and {} is not the project instrument",
+                                instrument
+                            ),
+                        )));
+                        continue;
+                    }
+                    data.instrument.serial = project.instrument.serial.clone();
+                }
                 if data.validate_lengths() {
                     let _rows = data.datetime.len();
                     let project_id = project.id.unwrap();
 
-                    let file_name = match path.file_name().and_then(|n| n.to_str()) {
-                        Some(name) => name,
-                        None => {
-                            eprintln!("Skipping path with invalid filename: {:?}", path);
-                            // Optionally notify UI:
-                            let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::gas_fail(
-                                path.to_string_lossy().to_string(),
-                                "Invalid file name (non-UTF8)".to_string(),
-                            )));
-                            return (); // or `continue` if this is in a loop
-                        },
-                    };
+                    let file_name = input.name.as_str();
                     let tx = match conn.transaction() {
                         Ok(tx) => tx,
                         Err(e) => {
@@ -565,7 +823,7 @@ pub fn upload_gas_data_async(
                             ));
                             let _ =
                                 progress_sender.send(ProcessEvent::Read(ReadEvent::FileDetail(
-                                    path.to_string_lossy().to_string(),
+                                    input.label.clone(),
                                     format!("from {}", instrument),
                                 )));
                             if let Err(e) = tx.commit() {
@@ -591,7 +849,7 @@ pub fn upload_gas_data_async(
             },
             Err(e) => {
                 let _ = progress_sender.send(ProcessEvent::Read(ReadEvent::gas_fail(
-                    path.to_str().unwrap().to_owned(),
+                    input.label.clone(),
                     e.to_string(),
                 )));
             },
@@ -0,0 +1,133 @@
+use crate::concentrationunit::ConcentrationUnit;
+use crate::gaschannel::{ChannelConfig, MoleFraction};
+use crate::gastype::GasType;
+use crate::instruments::diag::DiagDecoder;
+use crate::instruments::instruments::{FileFormat, InstrumentConfig, TimeSourceKind};
+
+// Los Gatos Research / ABB Ultraportable analyzers. The files start with a
+// line like "VC:2f90039 BD:Feb 13 2013 SN:14-0061" followed by a comma
+// separated header and local timestamps. An encrypted copy of the data is
+// appended to the end of the file after a PGP marker.
+const SERIAL_TAG: &str = "SN:";
+const FOOTER_MARKER: &str = "-----BEGIN PGP";
+const TIME_FMT: &str = "%m/%d/%Y %H:%M:%S%.f";
+
+fn channel(gas: GasType, col: &str, instrument_id: &str) -> ChannelConfig {
+    ChannelConfig {
+        gas,
+        concentration_col: col.to_owned(),
+        unit: ConcentrationUnit::Ppm,
+        instrument_id: instrument_id.to_owned(),
+        // the Ultraportables report wet mole fractions next to the [H2O] column
+        mole_fraction: MoleFraction::Wet,
+    }
+}
+
+fn lgr_config(name: &str, channels: Vec<ChannelConfig>) -> InstrumentConfig {
+    let cols: Vec<String> = channels.iter().map(|c| c.concentration_col.clone()).collect();
+    InstrumentConfig {
+        name: name.to_owned(),
+        model: name.to_owned(),
+        serial: None,
+        file_format: FileFormat::Delimited,
+        sep: b',',
+        skiprows: 1,
+        meta_rows: false,
+        serial_tag: Some(SERIAL_TAG.to_owned()),
+        footer_marker: Some(FOOTER_MARKER.to_owned()),
+        has_tz: false,
+        skip_after_header: 0,
+        time_col: "Time".to_owned(),
+        nsecs_col: String::new(),
+        gas_cols: cols.clone(),
+        flux_cols: cols,
+        diag_col: String::new(),
+        diag: DiagDecoder::nonzero(),
+        has_header: false,
+        available_gases: channels.iter().map(|c| c.gas).collect(),
+        channels,
+        time_source: TimeSourceKind::StringFormat,
+        time_fmt: Some(TIME_FMT.to_owned()),
+    }
+}
+
+impl InstrumentConfig {
+    /// Ultraportable Greenhouse Gas Analyzer
+    pub fn ugga() -> Self {
+        let name = "LGR-UGGA";
+        lgr_config(
+            name,
+            vec![
+                channel(GasType::CO2, "[CO2]_ppm", name),
+                channel(GasType::CH4, "[CH4]_ppm", name),
+                channel(GasType::H2O, "[H2O]_ppm", name),
+            ],
+        )
+    }
+
+    /// Ultraportable N2O/CO analyzer
+    pub fn n2oco() -> Self {
+        let name = "LGR-N2OCO";
+        lgr_config(
+            name,
+            vec![
+                channel(GasType::N2O, "[N2O]_ppm", name),
+                channel(GasType::CO, "[CO]_ppm", name),
+                channel(GasType::H2O, "[H2O]_ppm", name),
+            ],
+        )
+    }
+}
+
+/// LGR serials look like 14-0061
+pub(crate) fn validate_serial(serial: &str) -> bool {
+    let Some((year, number)) = serial.trim().split_once('-') else {
+        return false;
+    };
+    year.len() == 2
+        && number.len() == 4
+        && year.chars().chain(number.chars()).all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::instruments::InstrumentType;
+    use chrono_tz::Tz;
+    use std::fs;
+
+    const UGGA_FILE: &str = "\
+VC:2f90039 BD:Feb 13 2013 SN:14-0061
+                     Time,      [CH4]_ppm,   [CH4]_ppm_sd,      [H2O]_ppm,   [H2O]_ppm_sd,      [CO2]_ppm,   [CO2]_ppm_sd
+  06/12/2019 12:00:01.510,   2.034000e+00,   0.000000e+00,   1.200000e+04,   0.000000e+00,   4.100000e+02,   0.000000e+00
+  06/12/2019 12:00:00.508,   2.033000e+00,   0.000000e+00,   1.190000e+04,   0.000000e+00,   4.090000e+02,   0.000000e+00
+-----BEGIN PGP MESSAGE-----
+Version: GnuPG v1.4.11 (GNU/Linux)
+-----END PGP MESSAGE-----
+";
+
+    #[test]
+    fn reads_ugga_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ugga.txt");
+        fs::write(&path, UGGA_FILE).unwrap();
+        let data = InstrumentConfig::ugga()
+            .read_data_file(&path, Tz::Europe__Helsinki)
+            .expect("parse failed");
+
+        assert_eq!(data.instrument.model, InstrumentType::UGGA);
+        assert_eq!(data.instrument.serial, "14-0061");
+        // 12:00 local in Helsinki summer time is 09:00 UTC
+        assert_eq!(data.datetime, vec![1560330000508, 1560330001510]);
+        assert_eq!(data.gas[&GasType::CO2], vec![Some(409.0), Some(410.0)]);
+        assert_eq!(data.gas[&GasType::CH4], vec![Some(2.033), Some(2.034)]);
+        assert!(InstrumentType::UGGA.validate_serial(&data.instrument.serial));
+    }
+
+    #[test]
+    fn lgr_serials() {
+        assert!(validate_serial("14-0061"));
+        assert!(!validate_serial("140061"));
+        assert!(!validate_serial("14-061"));
+    }
+}
@@ -1,3 +1,9 @@
+pub mod diag;
+pub mod gasmet;
 pub mod instruments;
+pub mod lgr;
+pub mod picarro;
+pub mod profile;
 
 pub use instruments::{get_or_insert_instrument, Instrument, InstrumentType};
+pub use profile::{load_instrument_profiles, save_instrument_profile, InstrumentProfile};
@@ -0,0 +1,194 @@
+use crate::gastype::GasType;
+use crate::instruments::instruments::{parse_local_in_tz, InstrumentConfig, InstrumentMeasurement};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// Picarro writes both the local DATE/TIME and EPOCH_TIME, older firmware only
+// has DATE/TIME so those are used when EPOCH_TIME is missing.
+const DATE_COL: &str = "DATE";
+const TIME_COL: &str = "TIME";
+const DEFAULT_TIME_FMT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+enum TimeCols {
+    Epoch(usize),
+    DateTime(usize, usize),
+}
+
+/// Read a whitespace separated Picarro G2xxx .dat file.
+///
+/// Only the columns in `cfg.channels` are read. `cfg.diag_col` (ALARM_STATUS)
+/// is stored as the diag value.
+pub(crate) fn read_dat_file(
+    cfg: &InstrumentConfig,
+    path: &Path,
+    tz: Tz,
+) -> Result<InstrumentMeasurement, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let serial = serial_from_file_name(path).or_else(|| cfg.serial.clone()).unwrap_or_default();
+    read_dat(cfg, BufReader::new(file), serial, tz)
+}
+
+fn read_dat<R: BufRead>(
+    cfg: &InstrumentConfig,
+    reader: R,
+    serial: String,
+    tz: Tz,
+) -> Result<InstrumentMeasurement, Box<dyn Error>> {
+    let mut lines = reader.lines();
+
+    let mut header: Vec<String> = Vec::new();
+    for line in lines.by_ref() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            header = line.split_whitespace().map(str::to_owned).collect();
+            break;
+        }
+    }
+    if header.is_empty() {
+        return Err("No header found in Picarro file".into());
+    }
+    let col = |name: &str| header.iter().position(|h| h == name);
+
+    let time_cols = match (col(&cfg.time_col), col(DATE_COL), col(TIME_COL)) {
+        (Some(i), _, _) => TimeCols::Epoch(i),
+        (None, Some(d), Some(t)) => TimeCols::DateTime(d, t),
+        _ => {
+            return Err(format!(
+                "Neither {} nor {} and {} columns found in header",
+                cfg.time_col, DATE_COL, TIME_COL
+            )
+            .into())
+        },
+    };
+
+    let mut gas_indices: HashMap<GasType, usize> = HashMap::new();
+    let mut gas_data: HashMap<GasType, Vec<f64>> = HashMap::new();
+    for channel in &cfg.channels {
+        match col(&channel.concentration_col) {
+            Some(i) => {
+                gas_indices.insert(channel.gas, i);
+                gas_data.insert(channel.gas, Vec::new());
+            },
+            None => {
+                return Err(format!(
+                    "Gas column '{}' not found in header",
+                    channel.concentration_col
+                )
+                .into())
+            },
+        }
+    }
+    let idx_diag = col(&cfg.diag_col);
+    let fmt = cfg.time_fmt.as_deref().unwrap_or(DEFAULT_TIME_FMT);
+
+    let mut datetime_vec: Vec<i64> = Vec::new();
+    let mut diag_vec: Vec<i64> = Vec::new();
+
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // the last row is cut short if the file was copied while the analyzer was logging
+        if fields.len() != header.len() {
+            continue;
+        }
+
+        let timestamp = match time_cols {
+            TimeCols::Epoch(i) => (fields[i].parse::<f64>()? * 1000.0).round() as i64,
+            TimeCols::DateTime(d, t) => {
+                let time_str = format!("{} {}", fields[d], fields[t]);
+                parse_local_in_tz(&time_str, fmt, tz)?.timestamp_millis()
+            },
+        };
+        datetime_vec.push(timestamp);
+
+        for (&gas_type, &idx) in &gas_indices {
+            let value = fields[idx].parse::<f64>().unwrap_or(f64::NAN);
+            if let Some(gas_vector) = gas_data.get_mut(&gas_type) {
+                gas_vector.push(value);
+            }
+        }
+
+        // ALARM_STATUS is written as a float
+        let diag =
+            idx_diag.and_then(|i| fields[i].parse::<f64>().ok()).map(|v| v as i64).unwrap_or(0);
+        diag_vec.push(diag);
+    }
+
+    cfg.build_measurement(serial, datetime_vec, gas_data, diag_vec)
+}
+
+/// Picarro serials are the analyzer name, eg. JFAADS2034 or CFKADS2046
+pub(crate) fn validate_serial(serial: &str) -> bool {
+    let s = serial.trim().to_ascii_uppercase();
+    let Some(pos) = s.find("DS") else {
+        return false;
+    };
+    let (prefix, digits) = (&s[..pos], &s[pos + 2..]);
+    !prefix.is_empty()
+        && prefix.chars().all(|c| c.is_ascii_uppercase())
+        && digits.len() == 4
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+// File names start with the analyzer name: JFAADS2034-20240102-100000Z-DataLog_User.dat
+fn serial_from_file_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let prefix = stem.split('-').next()?;
+    validate_serial(prefix).then(|| prefix.to_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const EPOCH_DAT: &str = "\
+DATE                      TIME                      EPOCH_TIME                ALARM_STATUS              CO2_dry                   CH4_dry                   N2O_dry                   NH3                       H2O
+2024-01-02                10:00:01.512              1704189601.512            0.000000000E+00           4.201000000E+02           2.010000000E+00           3.320000000E-01           5.000000000E-03           1.100000000E+00
+2024-01-02                10:00:00.498              1704189600.498            4.000000000E+00           4.200000000E+02           2.000000000E+00           3.310000000E-01           4.000000000E-03           1.000000000E+00
+2024-01-02                10:00:02.3
+";
+
+    #[test]
+    fn reads_epoch_time_and_sorts() {
+        let cfg = InstrumentConfig::g2508();
+        let data = read_dat(&cfg, Cursor::new(EPOCH_DAT), "JFAADS2034".to_owned(), Tz::UTC)
+            .expect("parse failed");
+
+        assert_eq!(data.datetime, vec![1704189600498, 1704189601512]);
+        assert_eq!(data.diag, vec![4, 0]);
+        assert_eq!(data.gas[&GasType::CO2], vec![Some(420.0), Some(420.1)]);
+        assert_eq!(data.gas[&GasType::N2O], vec![Some(0.331), Some(0.332)]);
+        assert_eq!(data.gas[&GasType::NH3], vec![Some(0.004), Some(0.005)]);
+        assert_eq!(data.gas[&GasType::H2O], vec![Some(1.0), Some(1.1)]);
+    }
+
+    #[test]
+    fn falls_back_to_local_date_and_time() {
+        let dat = "DATE TIME ALARM_STATUS CO2_dry CH4_dry N2O_dry NH3 H2O\n\
+                   2024-01-02 12:00:00.250 0 420.0 2.0 0.33 4.0 1.0\n";
+        let cfg = InstrumentConfig::g2508();
+        let data = read_dat(&cfg, Cursor::new(dat), String::new(), Tz::Europe__Helsinki).unwrap();
+        // 12:00 in Helsinki is 10:00 UTC in winter
+        assert_eq!(data.datetime, vec![1704189600250]);
+    }
+
+    #[test]
+    fn missing_gas_column_is_an_error() {
+        let dat = "DATE TIME EPOCH_TIME ALARM_STATUS CO2_dry CH4_dry H2O\n";
+        let cfg = InstrumentConfig::g2508();
+        assert!(read_dat(&cfg, Cursor::new(dat), String::new(), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn serial_is_read_from_file_name() {
+        let path = Path::new("data/JFAADS2034-20240102-100000Z-DataLog_User.dat");
+        assert_eq!(serial_from_file_name(path), Some("JFAADS2034".to_owned()));
+        assert_eq!(serial_from_file_name(Path::new("export.dat")), None);
+        assert!(!validate_serial("DS1234"));
+    }
+}
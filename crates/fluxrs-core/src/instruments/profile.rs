@@ -0,0 +1,519 @@
+use crate::concentrationunit::ConcentrationUnit;
+use crate::gaschannel::{ChannelConfig, MoleFraction};
+use crate::gastype::{load_gas_species, save_gas_species, GasType, SpeciesError};
+use crate::instruments::diag::{DiagCondition, DiagDecoder, DiagKind, DiagSeverity};
+use crate::instruments::instruments::{
+    FileFormat, InstrumentConfig, InstrumentType, TimeSourceKind,
+};
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
+
+/// Description of an analyzer that fluxrs doesn't ship a reader for.
+///
+/// Profiles are written by the user as TOML or JSON, stored as JSON in the
+/// `instrument_profiles` table and registered at startup so that they parse
+/// like any built-in `InstrumentType`.
+///
+/// ```toml
+/// name = "G4301"
+/// sep = ","
+/// skiprows = 1
+/// time_source = "string_format"
+/// time_col = "Time"
+/// time_fmt = "%m/%d/%Y %H:%M:%S%.3f"
+/// serial_regex = "^G4301-\\d{4}$"
+///
+/// [[channels]]
+/// gas = "CO2"
+/// column = "CO2_dry"
+/// unit = "ppm"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstrumentProfile {
+    /// Name shown in the UI and accepted by `--instrument`
+    pub name: String,
+    /// Model string written in the file preamble, defaults to `name`
+    #[serde(default)]
+    pub model: Option<String>,
+    /// "delimited" (default), "picarro_dat" or "gasmet_txt"
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Single character column separator, "tab" and "\t" are both accepted
+    #[serde(default = "default_sep")]
+    pub sep: String,
+    /// File starts with LI-COR style model, serial and timezone rows
+    #[serde(default)]
+    pub meta_rows: bool,
+    /// Serial is read from the skipped rows after this tag, eg. "SN:"
+    #[serde(default)]
+    pub serial_tag: Option<String>,
+    /// Rows starting with this end the data
+    #[serde(default)]
+    pub footer_marker: Option<String>,
+    /// Rows to skip before the header (after the model and serial rows when `meta_rows`)
+    #[serde(default)]
+    pub skiprows: usize,
+    /// Rows to skip after the header, eg. a units row
+    #[serde(default)]
+    pub skip_after_header: usize,
+    /// Timezone is read from the file preamble instead of being asked on upload
+    #[serde(default)]
+    pub has_tz: bool,
+    /// One of "seconds", "seconds_and_nanos" or "string_format"
+    pub time_source: String,
+    pub time_col: String,
+    #[serde(default)]
+    pub nsecs_col: Option<String>,
+    /// chrono format string, required with "string_format"
+    #[serde(default)]
+    pub time_fmt: Option<String>,
+    #[serde(default)]
+    pub diag_col: Option<String>,
+    /// Named bits of the diag column, any nonzero value invalidates if unset
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diag_bits: Vec<DiagBitProfile>,
+    /// Severity of diag bits not listed in `diag_bits`, defaults to "invalid"
+    #[serde(default)]
+    pub diag_unknown: Option<String>,
+    /// Regex that a valid serial must match, any non-empty serial is accepted if unset
+    #[serde(default)]
+    pub serial_regex: Option<String>,
+    pub channels: Vec<ChannelProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelProfile {
+    pub gas: String,
+    /// Column name of the concentration in the data file
+    pub column: String,
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    /// g/mol, needed when `gas` isn't one fluxrs already knows
+    #[serde(default)]
+    pub mol_mass: Option<f64>,
+    /// "dry" (default) or "wet", wet channels are corrected with the H2O channel
+    #[serde(default)]
+    pub mole_fraction: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagBitProfile {
+    /// Bit number, 0 is the least significant
+    pub bit: u32,
+    pub name: String,
+    /// "ignore", "warn" or "invalid" (default)
+    #[serde(default)]
+    pub severity: Option<String>,
+    /// "laser", "cavity", "ring_down" or "other" (default)
+    #[serde(default)]
+    pub kind: Option<String>,
+}
+
+fn default_sep() -> String {
+    "\t".to_owned()
+}
+
+fn default_unit() -> String {
+    "ppm".to_owned()
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(std::io::Error),
+    Parse(String),
+    Invalid(String),
+    Sql(rusqlite::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io(e) => write!(f, "Could not read profile: {}", e),
+            ProfileError::Parse(msg) => write!(f, "Could not parse profile: {}", msg),
+            ProfileError::Invalid(msg) => write!(f, "Invalid profile: {}", msg),
+            ProfileError::Sql(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(err: std::io::Error) -> Self {
+        ProfileError::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for ProfileError {
+    fn from(err: rusqlite::Error) -> Self {
+        ProfileError::Sql(err)
+    }
+}
+
+impl From<SpeciesError> for ProfileError {
+    fn from(err: SpeciesError) -> Self {
+        match err {
+            SpeciesError::Invalid(msg) => ProfileError::Invalid(msg),
+            SpeciesError::Sql(e) => ProfileError::Sql(e),
+        }
+    }
+}
+
+impl InstrumentProfile {
+    /// Read a profile from a .toml or .json file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, ProfileError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()) {
+            Some(ext) if ext == "toml" => Self::from_toml(&content),
+            Some(ext) if ext == "json" => Self::from_json(&content),
+            _ => {
+                Err(ProfileError::Parse(format!("{} is not a .toml or .json file", path.display())))
+            },
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, ProfileError> {
+        toml::from_str(content).map_err(|e| ProfileError::Parse(e.to_string()))
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, ProfileError> {
+        serde_json::from_str(content).map_err(|e| ProfileError::Parse(e.to_string()))
+    }
+
+    fn sep_byte(&self) -> Result<u8, ProfileError> {
+        match self.sep.as_str() {
+            "\t" | "\\t" | "tab" => Ok(b'\t'),
+            s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+            s => Err(ProfileError::Invalid(format!("separator must be a single character: {s:?}"))),
+        }
+    }
+
+    fn diag_decoder(&self) -> Result<DiagDecoder, ProfileError> {
+        let unknown = match &self.diag_unknown {
+            Some(s) => s.parse::<DiagSeverity>().map_err(ProfileError::Invalid)?,
+            None => DiagSeverity::Invalid,
+        };
+        let mut conditions = Vec::new();
+        for b in &self.diag_bits {
+            if b.bit > 62 {
+                return Err(ProfileError::Invalid(format!("diag bit {} is out of range", b.bit)));
+            }
+            let severity = match &b.severity {
+                Some(s) => s.parse::<DiagSeverity>().map_err(ProfileError::Invalid)?,
+                None => DiagSeverity::Invalid,
+            };
+            let kind = match &b.kind {
+                Some(k) => k.parse::<DiagKind>().map_err(ProfileError::Invalid)?,
+                None => DiagKind::Other,
+            };
+            conditions.push(DiagCondition::new(b.bit, &b.name, severity, kind));
+        }
+        Ok(DiagDecoder { conditions, unknown })
+    }
+
+    /// Build the reader configuration, checking every field on the way
+    pub fn to_config(&self) -> Result<InstrumentConfig, ProfileError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(ProfileError::Invalid("name is empty".to_owned()));
+        }
+        if self.channels.is_empty() {
+            return Err(ProfileError::Invalid("no channels defined".to_owned()));
+        }
+
+        let file_format = match &self.format {
+            Some(f) => f.parse::<FileFormat>().map_err(ProfileError::Invalid)?,
+            None => FileFormat::Delimited,
+        };
+        let time_source =
+            self.time_source.parse::<TimeSourceKind>().map_err(ProfileError::Invalid)?;
+        if matches!(time_source, TimeSourceKind::StringFormat) && self.time_fmt.is_none() {
+            return Err(ProfileError::Invalid(
+                "time_fmt is required with time_source \"string_format\"".to_owned(),
+            ));
+        }
+        if matches!(time_source, TimeSourceKind::SecondsAndNanos) && self.nsecs_col.is_none() {
+            return Err(ProfileError::Invalid(
+                "nsecs_col is required with time_source \"seconds_and_nanos\"".to_owned(),
+            ));
+        }
+        if let Some(re) = &self.serial_regex {
+            Regex::new(re).map_err(|e| ProfileError::Invalid(format!("serial_regex: {e}")))?;
+        }
+
+        let diag = self.diag_decoder()?;
+
+        let mut channels = Vec::new();
+        let mut available_gases = Vec::new();
+        for ch in &self.channels {
+            let gas = ch.gas.parse::<GasType>().map_err(|e| match ch.mol_mass {
+                Some(_) => ProfileError::Invalid(format!("{e}, save the profile to add it")),
+                None => ProfileError::Invalid(format!("{e}, set mol_mass to add it")),
+            })?;
+            let unit = ch.unit.parse::<ConcentrationUnit>().map_err(ProfileError::Invalid)?;
+            let mole_fraction = match &ch.mole_fraction {
+                Some(m) => m.parse::<MoleFraction>().map_err(ProfileError::Invalid)?,
+                None => MoleFraction::Dry,
+            };
+            if available_gases.contains(&gas) {
+                return Err(ProfileError::Invalid(format!("{gas} is defined more than once")));
+            }
+            available_gases.push(gas);
+            channels.push(ChannelConfig {
+                gas,
+                concentration_col: ch.column.clone(),
+                unit,
+                instrument_id: name.to_owned(),
+                mole_fraction,
+            });
+        }
+
+        Ok(InstrumentConfig {
+            name: name.to_owned(),
+            model: self.model.clone().unwrap_or_else(|| name.to_owned()),
+            serial: None,
+            file_format,
+            sep: self.sep_byte()?,
+            skiprows: self.skiprows,
+            meta_rows: self.meta_rows,
+            serial_tag: self.serial_tag.clone(),
+            footer_marker: self.footer_marker.clone(),
+            has_tz: self.has_tz,
+            skip_after_header: self.skip_after_header,
+            time_col: self.time_col.clone(),
+            nsecs_col: self.nsecs_col.clone().unwrap_or_default(),
+            gas_cols: self.channels.iter().map(|c| c.column.clone()).collect(),
+            flux_cols: self.channels.iter().map(|c| c.column.clone()).collect(),
+            diag_col: self.diag_col.clone().unwrap_or_default(),
+            diag,
+            has_header: false,
+            available_gases,
+            channels,
+            time_source,
+            time_fmt: self.time_fmt.clone(),
+        })
+    }
+}
+
+struct RegisteredProfile {
+    config: InstrumentConfig,
+    serial_re: Option<Regex>,
+}
+
+// Keyed by the profile name. Names are leaked once so that `InstrumentType`
+// can stay `Copy`; profiles are only ever added or replaced, never removed.
+static REGISTRY: LazyLock<RwLock<BTreeMap<&'static str, RegisteredProfile>>> =
+    LazyLock::new(|| RwLock::new(BTreeMap::new()));
+
+/// Make the profile available as `InstrumentType::Custom` for this process
+pub fn register_profile(profile: &InstrumentProfile) -> Result<InstrumentType, ProfileError> {
+    let config = profile.to_config()?;
+    if InstrumentType::builtin_instruments()
+        .iter()
+        .any(|i| i.to_string().eq_ignore_ascii_case(&config.name))
+    {
+        return Err(ProfileError::Invalid(format!(
+            "{} is a built-in instrument and can't be redefined",
+            config.name
+        )));
+    }
+    let serial_re = profile.serial_regex.as_deref().map(Regex::new).transpose().ok().flatten();
+
+    let mut registry = REGISTRY.write().unwrap();
+    let key = match registry.keys().find(|k| k.eq_ignore_ascii_case(&config.name)) {
+        Some(k) => *k,
+        None => Box::leak(config.name.clone().into_boxed_str()),
+    };
+    registry.insert(key, RegisteredProfile { config, serial_re });
+    Ok(InstrumentType::Custom(key))
+}
+
+/// Store the profile in the db and register it
+pub fn save_instrument_profile(
+    conn: &Connection,
+    profile: &InstrumentProfile,
+) -> Result<InstrumentType, ProfileError> {
+    for ch in &profile.channels {
+        if let (Err(_), Some(mol_mass)) = (ch.gas.parse::<GasType>(), ch.mol_mass) {
+            let unit = ch.unit.parse::<ConcentrationUnit>().map_err(ProfileError::Invalid)?;
+            save_gas_species(conn, &ch.gas, mol_mass, unit)?;
+        }
+    }
+    let instrument = register_profile(profile)?;
+    let definition =
+        serde_json::to_string(profile).map_err(|e| ProfileError::Parse(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO instrument_profiles (name, definition) VALUES (?1, ?2)
+         ON CONFLICT(name) DO UPDATE SET definition = excluded.definition",
+        params![instrument.to_string(), definition],
+    )?;
+    Ok(instrument)
+}
+
+/// Register every gas species and profile stored in the db, returns the number
+/// of profiles loaded
+pub fn load_instrument_profiles(conn: &Connection) -> Result<usize, ProfileError> {
+    load_gas_species(conn)?;
+
+    let mut stmt =
+        conn.prepare("SELECT name, definition FROM instrument_profiles ORDER BY name")?;
+    let rows =
+        stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+
+    let mut loaded = 0;
+    for row in rows {
+        let (name, definition) = row?;
+        match InstrumentProfile::from_json(&definition).and_then(|p| register_profile(&p)) {
+            Ok(_) => loaded += 1,
+            Err(e) => eprintln!("Skipping instrument profile {}: {}", name, e),
+        }
+    }
+    Ok(loaded)
+}
+
+pub fn registered_instruments() -> Vec<InstrumentType> {
+    REGISTRY.read().unwrap().keys().copied().map(InstrumentType::Custom).collect()
+}
+
+pub fn find_registered(name: &str) -> Option<InstrumentType> {
+    REGISTRY
+        .read()
+        .unwrap()
+        .keys()
+        .find(|k| k.eq_ignore_ascii_case(name.trim()))
+        .copied()
+        .map(InstrumentType::Custom)
+}
+
+pub(crate) fn registered_config(name: &str) -> Option<InstrumentConfig> {
+    REGISTRY.read().unwrap().get(name).map(|p| p.config.clone())
+}
+
+pub(crate) fn registered_serial_is_valid(name: &str, serial: &str) -> bool {
+    let registry = REGISTRY.read().unwrap();
+    let Some(profile) = registry.get(name) else {
+        return false;
+    };
+    let serial = serial.trim();
+    match &profile.serial_re {
+        Some(re) => re.is_match(serial),
+        None => !serial.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::fluxes_schema::{create_gas_species_table, create_instrument_profiles_table};
+
+    const TOML_PROFILE: &str = r#"
+name = "TestAnalyzer"
+sep = ","
+skiprows = 1
+time_source = "string_format"
+time_col = "Time"
+time_fmt = "%Y-%m-%d %H:%M:%S"
+diag_col = "Status"
+diag_unknown = "warn"
+serial_regex = "^TA-\\d{3}$"
+
+[[diag_bits]]
+bit = 0
+name = "Pump failure"
+
+[[diag_bits]]
+bit = 3
+name = "Laser cold"
+severity = "warn"
+kind = "laser"
+
+[[channels]]
+gas = "CO2"
+column = "CO2_dry"
+
+[[channels]]
+gas = "CH4"
+column = "CH4_dry"
+unit = "ppb"
+mole_fraction = "wet"
+"#;
+
+    #[test]
+    fn toml_profile_builds_config() {
+        let profile = InstrumentProfile::from_toml(TOML_PROFILE).unwrap();
+        let cfg = profile.to_config().unwrap();
+        assert_eq!(cfg.sep, b',');
+        assert_eq!(cfg.available_gases, vec![GasType::CO2, GasType::CH4]);
+        assert_eq!(cfg.channels[1].unit, ConcentrationUnit::Ppb);
+        assert_eq!(cfg.channels[0].mole_fraction, MoleFraction::Dry);
+        assert_eq!(cfg.channels[1].mole_fraction, MoleFraction::Wet);
+        assert!(matches!(cfg.time_source, TimeSourceKind::StringFormat));
+        assert_eq!(cfg.diag.conditions[1].mask, 8);
+        assert_eq!(cfg.diag.conditions[1].severity, DiagSeverity::Warn);
+        assert!(cfg.diag.is_invalid(1));
+        assert!(!cfg.diag.is_invalid(8 | 16));
+    }
+
+    #[test]
+    fn registered_profile_parses_as_instrument() {
+        let profile = InstrumentProfile::from_toml(TOML_PROFILE).unwrap();
+        let ins = register_profile(&profile).unwrap();
+        assert_eq!("testanalyzer".parse::<InstrumentType>().unwrap(), ins);
+        assert_eq!(ins.to_string(), "TestAnalyzer");
+        assert!(ins.validate_serial("TA-123"));
+        assert!(!ins.validate_serial("TA-12"));
+        assert!(InstrumentType::available_instruments().contains(&ins));
+    }
+
+    #[test]
+    fn string_format_requires_time_fmt() {
+        let mut profile = InstrumentProfile::from_toml(TOML_PROFILE).unwrap();
+        profile.time_fmt = None;
+        assert!(matches!(profile.to_config(), Err(ProfileError::Invalid(_))));
+    }
+
+    #[test]
+    fn profile_can_add_a_gas() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(&create_gas_species_table(), []).unwrap();
+        conn.execute(&create_instrument_profiles_table(), []).unwrap();
+        // keep the ids clear of the other tests sharing the registry
+        conn.execute(
+            "INSERT INTO gas_species (id, name, mol_mass, unit) VALUES (2000, 'X', 1.0, 'ppm')",
+            [],
+        )
+        .unwrap();
+
+        let mut profile = InstrumentProfile::from_toml(TOML_PROFILE).unwrap();
+        profile.name = "SO2Analyzer".to_owned();
+        profile.channels.push(ChannelProfile {
+            gas: "SO2".to_owned(),
+            column: "SO2_ppb".to_owned(),
+            unit: "ppb".to_owned(),
+            mol_mass: None,
+            mole_fraction: None,
+        });
+        assert!(profile.to_config().is_err());
+
+        profile.channels[2].mol_mass = Some(64.0);
+        let ins = save_instrument_profile(&conn, &profile).unwrap();
+        let so2 = "so2".parse::<GasType>().unwrap();
+        assert_eq!(so2.as_int(), 2001);
+        assert_eq!(so2.mol_mass(), 64.0);
+        assert!(ins.available_gases().contains(&so2));
+    }
+
+    #[test]
+    fn builtin_names_are_rejected() {
+        let mut profile = InstrumentProfile::from_toml(TOML_PROFILE).unwrap();
+        profile.name = "LI-7810".to_owned();
+        assert!(register_profile(&profile).is_err());
+    }
+}
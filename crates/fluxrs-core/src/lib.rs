@@ -4,6 +4,7 @@ use std::error::Error;
 use std::fs::File;
 use std::process;
 
+pub mod archive;
 pub mod concentrationunit;
 pub mod constants;
 pub mod cycle;
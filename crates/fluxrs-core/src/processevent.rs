@@ -82,6 +82,19 @@ impl ReadEvent {
         let file_str = file.as_ref().to_string_lossy().into_owned();
         Self::DataFail { kind: DataType::Height, file: file_str, reason: reason.into() }
     }
+
+    pub fn chamber_meteo_fail(
+        file: impl AsRef<std::path::Path>,
+        reason: impl Into<String>,
+    ) -> Self {
+        let file_str = file.as_ref().to_string_lossy().into_owned();
+        Self::DataFail { kind: DataType::ChamberMeteo, file: file_str, reason: reason.into() }
+    }
+
+    pub fn aux_fail(file: impl AsRef<std::path::Path>, reason: impl Into<String>) -> Self {
+        let file_str = file.as_ref().to_string_lossy().into_owned();
+        Self::DataFail { kind: DataType::Aux, file: file_str, reason: reason.into() }
+    }
 }
 
 impl InsertEvent {
@@ -103,4 +116,12 @@ impl InsertEvent {
     pub fn height_okskip(inserts: usize, skips: usize) -> Self {
         Self::DataOkSkip { kind: DataType::Height, inserts, skips }
     }
+
+    pub fn chamber_meteo_okskip(inserts: usize, skips: usize) -> Self {
+        Self::DataOkSkip { kind: DataType::ChamberMeteo, inserts, skips }
+    }
+
+    pub fn aux_okskip(inserts: usize, skips: usize) -> Self {
+        Self::DataOkSkip { kind: DataType::Aux, inserts, skips }
+    }
 }
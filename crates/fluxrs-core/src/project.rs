@@ -1,3 +1,7 @@
+use crate::data_formats::auxdata::DEFAULT_AUX_MAX_GAP_S;
+use crate::data_formats::meteodata::{
+    barometric_pressure, MeteoInterpolation, DEFAULT_METEO_MAX_GAP_S,
+};
 use crate::gastype::GasType;
 use crate::instruments::{get_or_insert_instrument, Instrument, InstrumentType};
 use crate::mode::Mode;
@@ -33,6 +37,16 @@ pub struct Project {
     pub min_calc_len: f64,
     pub mode: Mode,
     pub tz: Tz,
+    /// How meteo values between samples are found
+    pub meteo_interpolation: MeteoInterpolation,
+    /// Meteo samples further than this from a cycle aren't used, seconds
+    pub meteo_max_gap_s: i64,
+    /// Auxiliary samples further than this from a cycle aren't used, seconds
+    pub aux_max_gap_s: i64,
+    /// Site elevation above sea level in meters, used to model missing pressure
+    pub elevation_m: Option<f64>,
+    /// Site latitude in degrees
+    pub latitude: Option<f64>,
 }
 
 impl Default for Project {
@@ -46,6 +60,11 @@ impl Default for Project {
             min_calc_len: 0.0,
             mode: Mode::default(),
             tz: Tz::UTC,
+            meteo_interpolation: MeteoInterpolation::default(),
+            meteo_max_gap_s: DEFAULT_METEO_MAX_GAP_S,
+            aux_max_gap_s: DEFAULT_AUX_MAX_GAP_S,
+            elevation_m: None,
+            latitude: None,
         }
     }
 }
@@ -84,6 +103,11 @@ impl Project {
     pub fn deadband(&self) -> f64 {
         self.deadband
     }
+    /// Air pressure at the site elevation for `temperature_c`, None if the
+    /// elevation isn't known
+    pub fn modelled_pressure(&self, temperature_c: f64) -> Option<f64> {
+        self.elevation_m.map(|h| barometric_pressure(h, temperature_c, self.latitude))
+    }
 
     pub fn load_instruments(&self) -> rusqlite::Result<FastMap<i64, Instrument>> {
         let conn = Connection::open("fluxrs.db")?;
@@ -172,6 +196,9 @@ impl Project {
         let mode = Mode::from_int(mode_i)?;
 
         let tz = tz_str.parse().expect("Invalid timezone string");
+        let (meteo_interpolation, meteo_max_gap_s) = query_meteo_settings(&conn, id).ok()?;
+        let aux_max_gap_s = query_aux_max_gap(&conn, id).ok()?;
+        let (elevation_m, latitude) = query_site_settings(&conn, id).ok()?;
         // let instrument = InstrumentType::from_str(&instrument_string);
         // let instrument =
         //     instrument_string.parse::<InstrumentType>().expect("Invalid instrument type");
@@ -198,6 +225,11 @@ impl Project {
             min_calc_len,
             mode,
             tz,
+            meteo_interpolation,
+            meteo_max_gap_s,
+            aux_max_gap_s,
+            elevation_m,
+            latitude,
         })
     }
     pub fn save(
@@ -222,8 +254,9 @@ impl Project {
 
         tx.execute(
             "INSERT OR IGNORE INTO projects (
-                project_name, main_gas, deadband, min_calc_len, mode, tz, current
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                project_name, main_gas, deadband, min_calc_len, mode, tz, current,
+                meteo_interpolation, meteo_max_gap, aux_max_gap, elevation, latitude
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 project.name,
                 project.main_gas.unwrap().as_int(),
@@ -232,6 +265,11 @@ impl Project {
                 project.mode.as_int(),
                 project.tz.to_string(),
                 1,
+                project.meteo_interpolation.as_int(),
+                project.meteo_max_gap_s,
+                project.aux_max_gap_s,
+                project.elevation_m,
+                project.latitude,
             ],
         )?;
 
@@ -252,3 +290,73 @@ impl Project {
         Ok(())
     }
 }
+
+/// Meteo interpolation and largest gap of a project
+pub fn query_meteo_settings(
+    conn: &Connection,
+    project_id: i64,
+) -> Result<(MeteoInterpolation, i64)> {
+    conn.query_row(
+        "SELECT meteo_interpolation, meteo_max_gap FROM projects WHERE id = ?1",
+        [project_id],
+        |row| {
+            let interpolation = MeteoInterpolation::from_int(row.get(0)?).unwrap_or_default();
+            Ok((interpolation, row.get(1)?))
+        },
+    )
+}
+
+/// Change how meteo values are found for the cycles of a project, fluxes
+/// change when the project is processed or recalculated again
+pub fn update_meteo_settings(
+    conn: &Connection,
+    project_id: i64,
+    interpolation: MeteoInterpolation,
+    max_gap_s: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE projects SET meteo_interpolation = ?1, meteo_max_gap = ?2 WHERE id = ?3",
+        params![interpolation.as_int(), max_gap_s, project_id],
+    )?;
+    Ok(())
+}
+
+/// Largest distance from a cycle to an auxiliary sample of a project
+pub fn query_aux_max_gap(conn: &Connection, project_id: i64) -> Result<i64> {
+    conn.query_row("SELECT aux_max_gap FROM projects WHERE id = ?1", [project_id], |row| row.get(0))
+}
+
+/// Change how far auxiliary samples are matched to the cycles of a project,
+/// cycles get the new values when the project is processed or recalculated
+pub fn update_aux_max_gap(conn: &Connection, project_id: i64, max_gap_s: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE projects SET aux_max_gap = ?1 WHERE id = ?2",
+        params![max_gap_s, project_id],
+    )?;
+    Ok(())
+}
+
+/// Elevation and latitude of a project's site
+pub fn query_site_settings(
+    conn: &Connection,
+    project_id: i64,
+) -> Result<(Option<f64>, Option<f64>)> {
+    conn.query_row("SELECT elevation, latitude FROM projects WHERE id = ?1", [project_id], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })
+}
+
+/// Change the site elevation and latitude of a project, fluxes change when
+/// the project is processed or recalculated again
+pub fn update_site_settings(
+    conn: &Connection,
+    project_id: i64,
+    elevation_m: Option<f64>,
+    latitude: Option<f64>,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE projects SET elevation = ?1, latitude = ?2 WHERE id = ?3",
+        params![elevation_m, latitude, project_id],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+/// Number of log spaced κ values tried before the golden section refinement
+const KAPPA_GRID: usize = 60;
+const GOLDEN_ITERS: usize = 60;
+
+/// HMR model (Hutchinson & Mosier, Pedersen et al. 2010)
+/// C(t) = φ + f0·exp(−κt)/(−κh), written with the concentration and the
+/// slope at t = 0 as C(t) = c0 + f0·(1 − exp(−κt))/κ. f0 here is dC/dt and
+/// becomes the HMR f0 once multiplied by the chamber height.
+#[derive(Clone, Copy)]
+pub struct HmrReg {
+    /// concentration at t = 0
+    pub c0: f64,
+    /// initial slope dC/dt at t = 0
+    pub f0: f64,
+    /// curvature κ, 1/s
+    pub kappa: f64,
+}
+
+impl fmt::Display for HmrReg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HmrReg")
+    }
+}
+
+/// (1 − exp(−κt))/κ, tends to t when κ goes to 0
+fn saturation(kappa: f64, t: f64) -> f64 {
+    if kappa.abs() < f64::EPSILON {
+        t
+    } else {
+        -(-kappa * t).exp_m1() / kappa
+    }
+}
+
+impl HmrReg {
+    pub fn new() -> Self {
+        Self { c0: 0.0, f0: 0.0, kappa: 0.0 }
+    }
+
+    pub fn from_val(c0: f64, f0: f64, kappa: f64) -> Self {
+        Self { c0, f0, kappa }
+    }
+
+    /// Asymptotic concentration φ = c0 + f0/κ
+    pub fn phi(&self) -> f64 {
+        self.c0 + self.f0 / self.kappa
+    }
+
+    /// Evaluate C(t) = c0 + f0·(1 − exp(−κt))/κ
+    pub fn calculate(&self, x: f64) -> f64 {
+        self.c0 + self.f0 * saturation(self.kappa, x)
+    }
+
+    /// dC/dt = f0·exp(−κt)
+    pub fn derivative(&self, x: f64) -> f64 {
+        self.f0 * (-self.kappa * x).exp()
+    }
+
+    /// Least squares fit of c0 and f0 for a fixed κ, returns (c0, f0, rss)
+    fn fit_linear(x: &[f64], y: &[f64], kappa: f64) -> Option<(f64, f64, f64)> {
+        let n = x.len() as f64;
+        let g: Vec<f64> = x.iter().map(|&t| saturation(kappa, t)).collect();
+        let g_mean = g.iter().sum::<f64>() / n;
+        let y_mean = y.iter().sum::<f64>() / n;
+
+        let mut s_gg = 0.0;
+        let mut s_gy = 0.0;
+        for (&gi, &yi) in g.iter().zip(y) {
+            s_gg += (gi - g_mean).powi(2);
+            s_gy += (gi - g_mean) * (yi - y_mean);
+        }
+        if s_gg <= f64::EPSILON {
+            return None;
+        }
+
+        let f0 = s_gy / s_gg;
+        let c0 = y_mean - f0 * g_mean;
+        let rss = g.iter().zip(y).map(|(&gi, &yi)| (yi - c0 - f0 * gi).powi(2)).sum();
+        Some((c0, f0, rss))
+    }
+
+    /// Nonlinear least squares fit with κ limited to `[kappa_min, kappa_max]`.
+    /// For a fixed κ the model is linear in c0 and f0, so only κ is searched:
+    /// first on a log spaced grid, then by golden section around the best
+    /// grid point. None if the data or the bounds are unusable.
+    pub fn train(x: &[f64], y: &[f64], kappa_min: f64, kappa_max: f64) -> Option<Self> {
+        assert!(x.len() == y.len(), "Input vectors x and y must have the same length");
+        if x.len() < 3 || !(kappa_min > 0.0 && kappa_max > kappa_min) {
+            return None;
+        }
+
+        let (ln_min, ln_max) = (kappa_min.ln(), kappa_max.ln());
+        let step = (ln_max - ln_min) / (KAPPA_GRID - 1) as f64;
+        let rss_at = |ln_k: f64| Self::fit_linear(x, y, ln_k.exp()).map(|(_, _, rss)| rss);
+
+        let mut best: Option<(usize, f64)> = None;
+        for i in 0..KAPPA_GRID {
+            let Some(rss) = rss_at(ln_min + step * i as f64) else { continue };
+            if best.is_none_or(|(_, b)| rss < b) {
+                best = Some((i, rss));
+            }
+        }
+        let (best_i, _) = best?;
+
+        let mut lo = ln_min + step * best_i.saturating_sub(1) as f64;
+        let mut hi = ln_min + step * (best_i + 1).min(KAPPA_GRID - 1) as f64;
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        for _ in 0..GOLDEN_ITERS {
+            let a = hi - ratio * (hi - lo);
+            let b = lo + ratio * (hi - lo);
+            match (rss_at(a), rss_at(b)) {
+                (Some(ra), Some(rb)) if ra <= rb => hi = b,
+                (Some(_), Some(_)) => lo = a,
+                _ => break,
+            }
+        }
+
+        let kappa = ((lo + hi) / 2.0).exp();
+        let (c0, f0, _) = Self::fit_linear(x, y, kappa)?;
+        Some(Self { c0, f0, kappa })
+    }
+
+    /// Standard error of f0 from the Jacobian of all three parameters,
+    /// Cov = σ²(JᵀJ)⁻¹. None if JᵀJ is singular.
+    pub fn f0_se(&self, x: &[f64], sigma: f64) -> Option<f64> {
+        let mut a = [[0.0; 3]; 3];
+        for &t in x {
+            let e = (-self.kappa * t).exp();
+            let g = saturation(self.kappa, t);
+            // ∂g/∂κ = (κt·exp(−κt) − (1 − exp(−κt)))/κ², −t²/2 as κ goes to 0
+            let dg = if self.kappa.abs() < f64::EPSILON {
+                -t * t / 2.0
+            } else {
+                (t * e - g) / self.kappa
+            };
+            let j = [1.0, g, self.f0 * dg];
+            for (row, jr) in a.iter_mut().zip(j) {
+                for (cell, jc) in row.iter_mut().zip(j) {
+                    *cell += jr * jc;
+                }
+            }
+        }
+
+        let det = a[0][0] * (a[1][1] * a[2][2] - a[1][2] * a[2][1])
+            - a[0][1] * (a[1][0] * a[2][2] - a[1][2] * a[2][0])
+            + a[0][2] * (a[1][0] * a[2][1] - a[1][1] * a[2][0]);
+        if !det.is_finite() || det.abs() <= f64::EPSILON {
+            return None;
+        }
+        let inv_11 = (a[0][0] * a[2][2] - a[0][2] * a[2][0]) / det;
+        let se = sigma * inv_11.sqrt();
+        se.is_finite().then_some(se)
+    }
+}
+
+impl Default for HmrReg {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_saturating_curve() {
+        let truth = HmrReg::from_val(420.0, 0.8, 0.01);
+        let x: Vec<f64> = (0..180).map(|i| i as f64).collect();
+        let y: Vec<f64> = x
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| truth.calculate(t) + if i % 2 == 0 { 0.05 } else { -0.05 })
+            .collect();
+
+        let fit = HmrReg::train(&x, &y, 1e-5, 0.1).unwrap();
+        assert!((fit.kappa - 0.01).abs() < 1e-3);
+        assert!((fit.f0 - 0.8).abs() < 0.02);
+        assert!((fit.c0 - 420.0).abs() < 0.1);
+        assert!((fit.phi() - 500.0).abs() < 1.0);
+        assert!(fit.f0_se(&x, 0.05).unwrap() > 0.0);
+
+        // a straight line ends up at the lower bound
+        let line: Vec<f64> = x.iter().map(|&t| 400.0 + 0.5 * t).collect();
+        let fit = HmrReg::train(&x, &line, 1e-5, 0.1).unwrap();
+        assert!(fit.kappa < 1e-4);
+        assert!((fit.f0 - 0.5).abs() < 1e-3);
+    }
+}
@@ -1,10 +1,12 @@
 pub mod expreg;
+pub mod hmrreg;
 pub mod linreg;
 pub mod polyreg;
 pub mod robreg;
 pub mod stats;
 
 pub use expreg::ExpReg;
+pub use hmrreg::HmrReg;
 pub use linreg::LinReg;
 pub use polyreg::PolyReg;
 pub use robreg::RobReg;
@@ -15,6 +15,7 @@ impl UiColor for FluxKind {
             FluxKind::Exponential => Color32::GREEN,
             FluxKind::RobLin => Color32::YELLOW,
             FluxKind::Poly => Color32::ORANGE,
+            FluxKind::Hmr => Color32::LIGHT_BLUE,
         }
     }
     fn stroke(&self) -> Stroke {
@@ -23,6 +24,7 @@ impl UiColor for FluxKind {
             FluxKind::Exponential => Stroke::new(1.5, self.color()),
             FluxKind::RobLin => Stroke::new(1.5, self.color()),
             FluxKind::Poly => Stroke::new(1.5, self.color()),
+            FluxKind::Hmr => Stroke::new(1.5, self.color()),
         }
     }
     fn style(&self) -> LineStyle {
@@ -31,6 +33,7 @@ impl UiColor for FluxKind {
             FluxKind::Exponential => LineStyle::dotted_dense(),
             FluxKind::RobLin => LineStyle::dashed_dense(),
             FluxKind::Poly => LineStyle::dashed_loose(),
+            FluxKind::Hmr => LineStyle::dotted_loose(),
         }
     }
 }
@@ -1,16 +1,26 @@
 use egui::Color32;
 use fluxrs_core::gastype::GasType;
 
+// gases registered by the user cycle through these
+const CUSTOM_COLORS: [Color32; 4] =
+    [Color32::LIGHT_BLUE, Color32::LIGHT_GREEN, Color32::KHAKI, Color32::LIGHT_GRAY];
+
 pub trait GasColor {
     fn color(&self) -> Color32;
 }
 impl GasColor for GasType {
     fn color(&self) -> Color32 {
-        match self {
+        match *self {
             GasType::CH4 => Color32::GREEN,
             GasType::CO2 => Color32::ORANGE,
             GasType::H2O => Color32::CYAN,
             GasType::N2O => Color32::LIGHT_RED,
+            GasType::NH3 => Color32::YELLOW,
+            GasType::CO => Color32::BROWN,
+            GasType::CO2_13 => Color32::GOLD,
+            GasType::CO2_12 => Color32::DARK_RED,
+            GasType::H2S => Color32::LIGHT_YELLOW,
+            other => CUSTOM_COLORS[other.as_int() % CUSTOM_COLORS.len()],
         }
     }
 }
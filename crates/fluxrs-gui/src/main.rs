@@ -25,6 +25,18 @@ fn main() -> eframe::Result {
         process::exit(1);
     }
 
+    match rusqlite::Connection::open("fluxrs.db") {
+        Ok(conn) => {
+            if let Err(e) = fluxrs_core::instruments::load_instrument_profiles(&conn) {
+                eprintln!("Failed to load instrument profiles: {}", e);
+            }
+        },
+        Err(e) => {
+            eprintln!("Err:\n{}", e);
+            process::exit(1);
+        },
+    }
+
     // GUI mode
     let app = FluxApp::new();
     eframe::run_native("fluxrs", Default::default(), Box::new(|_cc| Ok(Box::new(app))))
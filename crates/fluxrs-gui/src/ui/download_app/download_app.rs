@@ -1,10 +1,11 @@
 use chrono::offset::LocalResult;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 use csv::Writer;
+use fluxrs_core::data_formats::auxdata::query_aux;
 use fluxrs_core::data_formats::meteodata::MeteoSource;
 use fluxrs_core::db::fluxes_schema::make_select_all_fluxes;
-use fluxrs_core::flux::{FluxKind, FluxUnit};
+use fluxrs_core::flux::{DilutionCorrection, FluxKind, FluxUnit};
 use fluxrs_core::gastype::GasType;
 use fluxrs_core::processevent::{ProcessEvent, ProgressEvent, QueryEvent};
 use fluxrs_core::project::Project;
@@ -243,11 +244,26 @@ pub fn export_sqlite_to_csv(
         "exp_a",
         "exp_b",
     ];
+    let hmr_drops = [
+        "hmr_flux",
+        "hmr_r2",
+        "hmr_adj_r2",
+        "hmr_intercept",
+        "hmr_slope",
+        "hmr_sigma",
+        "hmr_p_value",
+        "hmr_aic",
+        "hmr_rmse",
+        "hmr_cv",
+        "hmr_se",
+        "hmr_kappa",
+    ];
 
     let lin_enabled = checks.model_checked.get(&FluxKind::Linear).copied().unwrap_or(false);
     let roblin_enabled = checks.model_checked.get(&FluxKind::RobLin).copied().unwrap_or(false);
     let poly_enabled = checks.model_checked.get(&FluxKind::Poly).copied().unwrap_or(false);
     let exp_enabled = checks.model_checked.get(&FluxKind::Exponential).copied().unwrap_or(false);
+    let hmr_enabled = checks.model_checked.get(&FluxKind::Hmr).copied().unwrap_or(false);
 
     if !lin_enabled {
         drop_after_processing.extend(lin_drops);
@@ -261,6 +277,9 @@ pub fn export_sqlite_to_csv(
     if !exp_enabled {
         drop_after_processing.extend(exp_drops);
     }
+    if !hmr_enabled {
+        drop_after_processing.extend(hmr_drops);
+    }
 
     // Which model flux cols are active
     // (&str so we can reuse the literal names directly to look up "lin_flux", etc.)
@@ -277,6 +296,9 @@ pub fn export_sqlite_to_csv(
     if exp_enabled {
         enabled_models.push("exp_flux");
     }
+    if hmr_enabled {
+        enabled_models.push("hmr_flux");
+    }
 
     // Which gases are selected
     let selected_gases: Vec<GasType> = checks
@@ -297,6 +319,7 @@ pub fn export_sqlite_to_csv(
                 && *c != "roblin_flux"
                 && *c != "poly_flux"
                 && *c != "exp_flux"
+                && *c != "hmr_flux"
         })
         .cloned()
         .collect();
@@ -331,6 +354,21 @@ pub fn export_sqlite_to_csv(
         }
     }
 
+    // 3. Auxiliary variables matched to each cycle go last. Names clashing
+    // with a flux column get an "aux_" prefix.
+    let aux = query_aux(&conn, DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC, project)?;
+    let mut aux_headers: FastMap<String, String> = FastMap::default();
+    for name in aux.variables() {
+        let header = if column_names.contains(&name) || final_columns.contains(&name) {
+            format!("aux_{}", name)
+        } else {
+            name.clone()
+        };
+        final_columns.push(header.clone());
+        aux_headers.insert(name, header);
+    }
+    let aux_max_gap = project.aux_max_gap_s;
+
     // We'll need tz for timestamp conversion in rows
     let tz: Tz = project.tz;
 
@@ -343,7 +381,7 @@ pub fn export_sqlite_to_csv(
     let drop_after_processing_closure = drop_after_processing.clone();
     let column_names_closure = column_names.clone();
 
-    // 3. Build rows iterator. Each row -> Vec<String> in final_columns order.
+    // 4. Build rows iterator. Each row -> Vec<String> in final_columns order.
     let rows = stmt.query_map([&project.id.unwrap()], move |row| {
         let mut record: FastMap<String, String> = FastMap::default();
 
@@ -359,6 +397,19 @@ pub fn export_sqlite_to_csv(
             record.insert(col_name.clone(), val);
         }
 
+        // ---- auxiliary values, matched to the cycle start in UTC ----------
+
+        if let (Some(start), Some(chamber_id)) = (
+            record.get("start_time").and_then(|s| s.parse::<i64>().ok()),
+            record.get("chamber_id").cloned(),
+        ) {
+            for (name, value) in aux.values_at(&chamber_id, start, aux_max_gap) {
+                if let Some(header) = aux_headers.get(&name) {
+                    record.insert(header.clone(), value.to_string());
+                }
+            }
+        }
+
         // ---- timestamp + lag normalization --------------------------------
 
         // start_time local transform using lag
@@ -440,6 +491,12 @@ pub fn export_sqlite_to_csv(
             }
         }
 
+        if let Some(i) = record.get("h2o_correction").and_then(|s| s.parse::<i32>().ok()) {
+            if let Some(correction) = DilutionCorrection::from_int(i) {
+                record.insert("h2o_correction".to_string(), correction.to_string());
+            }
+        }
+
         // parse back gas_enum for this row
         let row_gas_opt = record.get("gas").and_then(|s| s.parse::<GasType>().ok());
 
@@ -500,7 +557,7 @@ pub fn export_sqlite_to_csv(
         Ok(row_values)
     })?;
 
-    // 4. Write CSV ----------------------------------------------------------
+    // 5. Write CSV ----------------------------------------------------------
 
     let file = File::create(Path::new(csv_path))?;
     let mut wtr = Writer::from_writer(file);
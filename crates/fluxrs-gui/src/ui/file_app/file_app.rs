@@ -5,9 +5,15 @@ use crate::ui::AsyncCtx;
 use chrono_tz::{Tz, UTC};
 use egui::{Context, RichText, Ui};
 use egui_file::FileDialog;
+use fluxrs_core::data_formats::auxdata::upload_aux_data_async;
 use fluxrs_core::data_formats::chamberdata::upload_chamber_metadata_async;
-use fluxrs_core::data_formats::heightdata::upload_height_data_async;
-use fluxrs_core::data_formats::meteodata::upload_meteo_data_async;
+use fluxrs_core::data_formats::chambermeteo::upload_chamber_meteo_data_async;
+use fluxrs_core::data_formats::chamberseries::{upload_series_async, HEIGHT, SNOW, VOLUME};
+use fluxrs_core::data_formats::cyclecheck::CycleValidation;
+use fluxrs_core::data_formats::cyclemapping::CycleMapping;
+use fluxrs_core::data_formats::meteodata::{
+    upload_meteo_data_async, MeteoStation, DEFAULT_METEO_MAX_GAP_S,
+};
 use fluxrs_core::data_formats::timedata::upload_cycle_data_async;
 use fluxrs_core::datatype::DataType;
 use fluxrs_core::instruments::instruments::upload_gas_data_async;
@@ -32,6 +38,13 @@ pub struct FileApp {
     pub selected_data_type: Option<DataType>,
     pub selected_instrument: Option<InstrumentType>,
     pub reading_in_progress: bool,
+    /// Column mapping used for cycle files, built-in formats are tried if unset
+    pub cycle_mapping: Option<CycleMapping>,
+    pub mapping_dialog: Option<FileDialog>,
+    /// Whether cycle files with overlapping or otherwise bad cycles are inserted
+    pub cycle_validation: CycleValidation,
+    /// Station meteo files are uploaded for
+    pub meteo_station: MeteoStation,
 
     pub tz_prompt_open: bool,
     pub tz_state: TimezonePickerState,
@@ -58,6 +71,10 @@ impl FileApp {
             selected_data_type: None,
             selected_instrument: None,
             reading_in_progress: false,
+            cycle_mapping: None,
+            mapping_dialog: None,
+            cycle_validation: CycleValidation::default(),
+            meteo_station: MeteoStation::default(),
 
             tz_prompt_open: false,
             tz_state: TimezonePickerState::default(),
@@ -121,10 +138,76 @@ impl FileApp {
                     self.selected_data_type = Some(DataType::Chamber);
                     self.open_file_dialog("Select Chamber Metadata File");
                 }
+                if ui.button("Select Chamber Meteo Files").clicked() {
+                    self.selected_data_type = Some(DataType::ChamberMeteo);
+                    self.open_file_dialog("Select Chamber Meteo Files");
+                }
+                if ui.button("Select Auxiliary Data Files").clicked() {
+                    self.selected_data_type = Some(DataType::Aux);
+                    self.open_file_dialog("Select Auxiliary Data Files");
+                }
+                if ui.button("Select Volume Files").clicked() {
+                    self.selected_data_type = Some(DataType::Volume);
+                    self.open_file_dialog("Select Volume Files");
+                }
+                if ui.button("Select Snow Depth Files").clicked() {
+                    self.selected_data_type = Some(DataType::Snow);
+                    self.open_file_dialog("Select Snow Depth Files");
+                }
+            })
+            .response
+        });
+
+        ui.add_enabled(btns_enabled, |ui: &mut egui::Ui| {
+            ui.horizontal(|ui| {
+                let current = match &self.cycle_mapping {
+                    Some(mapping) => format!("Cycle file mapping: {}", mapping.name),
+                    None => "Cycle file mapping: built-in formats".to_owned(),
+                };
+                ui.label(current);
+                if ui.button("Select Mapping File").clicked() {
+                    self.open_mapping_dialog();
+                }
+                if self.cycle_mapping.is_some() && ui.button("Clear").clicked() {
+                    self.cycle_mapping = None;
+                }
+                let mut reject = self.cycle_validation == CycleValidation::Reject;
+                if ui
+                    .checkbox(&mut reject, "Reject cycle files with problems")
+                    .on_hover_text("Overlapping cycles, offsets out of order or no gas data. Problems are always listed in the log.")
+                    .changed()
+                {
+                    self.cycle_validation =
+                        if reject { CycleValidation::Reject } else { CycleValidation::Warn };
+                }
             })
             .response
         });
 
+        ui.add_enabled(btns_enabled, |ui: &mut egui::Ui| {
+            ui.horizontal(|ui| {
+                let station = &mut self.meteo_station;
+                ui.label("Meteo station:");
+                ui.add(egui::TextEdit::singleline(&mut station.name).desired_width(120.0));
+                ui.label("Priority:")
+                    .on_hover_text("Stations are tried from the lowest priority up");
+                ui.add(egui::DragValue::new(&mut station.priority).range(0..=100));
+                let mut own_gap = station.max_gap_s.is_some();
+                if ui
+                    .checkbox(&mut own_gap, "Largest distance to a sample in seconds")
+                    .on_hover_text("The project's largest distance is used when unset")
+                    .changed()
+                {
+                    station.max_gap_s = own_gap.then_some(DEFAULT_METEO_MAX_GAP_S);
+                }
+                if let Some(gap) = &mut station.max_gap_s {
+                    ui.add(egui::DragValue::new(gap).speed(60.0).range(0..=86400));
+                }
+            })
+            .response
+        });
+
+        self.handle_mapping_selection(ctx, log_msgs);
         self.handle_file_selection(ctx, log_msgs, project);
         self.start_processing_if_ready(async_ctx, log_msgs, project);
         self.show_timezone_prompt(ctx);
@@ -142,6 +225,44 @@ impl FileApp {
         self.open_file_dialog = Some(dialog);
     }
 
+    fn open_mapping_dialog(&mut self) {
+        let mut dialog = FileDialog::open_file(self.initial_path.clone())
+            .title("Select Cycle Mapping File")
+            .open_button_text(Cow::from("Select"))
+            .show_rename(false)
+            .show_new_folder(false);
+
+        dialog.open();
+        self.mapping_dialog = Some(dialog);
+    }
+
+    fn handle_mapping_selection(&mut self, ctx: &Context, log_messages: &mut VecDeque<RichText>) {
+        let Some(dialog) = &mut self.mapping_dialog else {
+            return;
+        };
+        dialog.show(ctx);
+        match dialog.state() {
+            egui_file::State::Selected => {
+                if let Some(path) = dialog.path() {
+                    match CycleMapping::from_path(path) {
+                        Ok(mapping) => {
+                            log_messages.push_front(
+                                format!("Using cycle file mapping {}.", mapping.name).into(),
+                            );
+                            self.cycle_mapping = Some(mapping);
+                        },
+                        Err(e) => log_messages.push_front(e.to_string().into()),
+                    }
+                }
+                self.mapping_dialog = None;
+            },
+            egui_file::State::Cancelled | egui_file::State::Closed => {
+                self.mapping_dialog = None;
+            },
+            _ => {},
+        }
+    }
+
     pub fn handle_file_selection(
         &mut self,
         ctx: &Context,
@@ -200,6 +321,9 @@ impl FileApp {
         let log_messages_clone = Arc::clone(&log_messages);
         let project_clone = project.clone();
         let instrument_clone = *instrument;
+        let mapping = self.cycle_mapping.clone();
+        let validation = self.cycle_validation;
+        let station = self.meteo_station.clone();
 
         // Clone what we need to move into the async task
         let sender = async_ctx.prog_sender.clone();
@@ -233,6 +357,8 @@ impl FileApp {
                                         &mut conn,
                                         &project_clone,
                                         tz,
+                                        mapping.as_ref(),
+                                        validation,
                                         blocking_sender.clone(),
                                     );
                                 },
@@ -240,10 +366,12 @@ impl FileApp {
                                     path_list,
                                     &mut conn,
                                     &project_clone,
+                                    &station,
                                     tz,
                                     blocking_sender.clone(),
                                 ),
-                                DataType::Height => upload_height_data_async(
+                                DataType::Height => upload_series_async(
+                                    &HEIGHT,
                                     path_list,
                                     &mut conn,
                                     &project_clone,
@@ -257,6 +385,36 @@ impl FileApp {
                                     tz,
                                     blocking_sender.clone(),
                                 ),
+                                DataType::ChamberMeteo => upload_chamber_meteo_data_async(
+                                    path_list,
+                                    &mut conn,
+                                    &project_clone,
+                                    tz,
+                                    blocking_sender.clone(),
+                                ),
+                                DataType::Aux => upload_aux_data_async(
+                                    path_list,
+                                    &mut conn,
+                                    &project_clone,
+                                    tz,
+                                    blocking_sender.clone(),
+                                ),
+                                DataType::Volume => upload_series_async(
+                                    &VOLUME,
+                                    path_list,
+                                    &mut conn,
+                                    &project_clone,
+                                    tz,
+                                    blocking_sender.clone(),
+                                ),
+                                DataType::Snow => upload_series_async(
+                                    &SNOW,
+                                    path_list,
+                                    &mut conn,
+                                    &project_clone,
+                                    tz,
+                                    blocking_sender.clone(),
+                                ),
                             }
                         }
                     },
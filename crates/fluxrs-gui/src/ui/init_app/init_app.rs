@@ -5,9 +5,11 @@ use crate::ui::AsyncCtx;
 use eframe::egui::Context;
 use egui::{Color32, RichText};
 use fluxrs_core::cycle_processor::{Datasets, Infra, Processor};
+use fluxrs_core::data_formats::auxdata::query_aux_async;
 use fluxrs_core::data_formats::chamberdata::query_chamber_async;
+use fluxrs_core::data_formats::chambermeteo::query_chamber_meteo_async;
+use fluxrs_core::data_formats::chamberseries::{query_series_async, HEIGHT, SNOW, VOLUME};
 use fluxrs_core::data_formats::gasdata::{query_gas_async, QueryError};
-use fluxrs_core::data_formats::heightdata::query_height_async;
 use fluxrs_core::data_formats::meteodata::query_meteo_async;
 use fluxrs_core::data_formats::timedata::query_cycles_async;
 use fluxrs_core::processevent::{ProcessEvent, ProgressEvent, QueryEvent};
@@ -106,8 +108,39 @@ impl InitApp {
                             query_gas_async(arc_conn.clone(), start, end, project.clone()).await;
                         let meteo_result =
                             query_meteo_async(arc_conn.clone(), start, end, project.clone()).await;
-                        let height_result =
-                            query_height_async(arc_conn.clone(), start, end, project.clone()).await;
+                        let chamber_meteo_result = query_chamber_meteo_async(
+                            arc_conn.clone(),
+                            start,
+                            end,
+                            project.clone(),
+                        )
+                        .await;
+                        let aux_result =
+                            query_aux_async(arc_conn.clone(), start, end, project.clone()).await;
+                        let height_result = query_series_async(
+                            arc_conn.clone(),
+                            &HEIGHT,
+                            start,
+                            end,
+                            project.clone(),
+                        )
+                        .await;
+                        let volume_result = query_series_async(
+                            arc_conn.clone(),
+                            &VOLUME,
+                            start,
+                            end,
+                            project.clone(),
+                        )
+                        .await;
+                        let snow_result = query_series_async(
+                            arc_conn.clone(),
+                            &SNOW,
+                            start,
+                            end,
+                            project.clone(),
+                        )
+                        .await;
                         let chamber_result =
                             query_chamber_async(arc_conn.clone(), project.clone()).await;
 
@@ -115,14 +148,22 @@ impl InitApp {
                             cycles_result,
                             gas_result,
                             meteo_result,
+                            chamber_meteo_result,
+                            aux_result,
                             height_result,
+                            volume_result,
+                            snow_result,
                             chamber_result,
                         ) {
                             (
                                 Ok(times),
                                 Ok(gas_data),
                                 Ok(meteo_data),
+                                Ok(chamber_meteo_data),
+                                Ok(aux_data),
                                 Ok(height_data),
+                                Ok(volume_data),
+                                Ok(snow_data),
                                 Ok(chamber_data),
                             ) => {
                                 let _ = sender_clone
@@ -135,7 +176,11 @@ impl InitApp {
                                         Datasets {
                                             gas: Arc::new(gas_data),
                                             meteo: meteo_data,
+                                            chamber_meteo: chamber_meteo_data,
+                                            aux: aux_data,
                                             height: height_data,
+                                            volume: volume_data,
+                                            snow: snow_data,
                                             chambers: chamber_data,
                                         },
                                         Infra { conn: arc_conn, progress: sender_clone.clone() },
@@ -150,7 +195,7 @@ impl InitApp {
                                     )));
                                 }
                             },
-                            (_, Err(err), _, _, _) => {
+                            (_, Err(err), _, _, _, _, _, _, _) => {
                                 let _ = sender_clone
                                     .clone()
                                     .send(ProcessEvent::Done(Err(err.to_string())));
@@ -668,6 +668,10 @@ impl ProcessEventSink for MainApp {
                     DataType::Height => "height",
                     DataType::Cycle => "cycle",
                     DataType::Chamber => "chamber metadata",
+                    DataType::ChamberMeteo => "chamber meteo",
+                    DataType::Aux => "auxiliary",
+                    DataType::Volume => "volume",
+                    DataType::Snow => "snow depth",
                 };
                 let msg = format!("Could not parse as {} file: {}, {}", what, file, reason);
                 self.log_messages.push_front(bad_message(&msg));
@@ -702,6 +706,10 @@ impl ProcessEventSink for MainApp {
                     DataType::Height => "height",
                     DataType::Cycle => "cycle",
                     DataType::Chamber => "chamber metadata",
+                    DataType::ChamberMeteo => "chamber meteo",
+                    DataType::Aux => "auxiliary",
+                    DataType::Volume => "volume",
+                    DataType::Snow => "snow depth",
                 };
                 if *skips == 0 {
                     self.log_messages.push_front(good_message(&format!(
@@ -0,0 +1,253 @@
+use crate::ui::recalc::RecalculateApp;
+use crate::ui::AsyncCtx;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use egui::{Context, RichText, ScrollArea, Ui};
+use fluxrs_core::cycle::cycle::get_instruments_by_project_map;
+use fluxrs_core::data_formats::clockdata::{
+    delete_clock_correction, insert_clock_correction, query_clock_corrections, ClockCorrection,
+    ClockCorrections,
+};
+use fluxrs_core::instruments::instruments::Instrument;
+use fluxrs_core::project::Project;
+use rusqlite::Connection;
+use std::collections::HashSet;
+
+const TIME_FMT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub struct ClockCorrectionApp {
+    project_id: Option<i64>,
+    instruments: Vec<Instrument>,
+    corrections: Vec<ClockCorrection>,
+    selected_ids: HashSet<i64>,
+    instrument_id: Option<i64>,
+    valid_from: String,
+    offset_s: f64,
+    drift_s_per_day: f64,
+    rerun: bool,
+    last_error: Option<String>,
+}
+
+impl Default for ClockCorrectionApp {
+    fn default() -> Self {
+        Self {
+            project_id: None,
+            instruments: Vec::new(),
+            corrections: Vec::new(),
+            selected_ids: HashSet::new(),
+            instrument_id: None,
+            valid_from: String::new(),
+            offset_s: 0.0,
+            drift_s_per_day: 0.0,
+            rerun: true,
+            last_error: None,
+        }
+    }
+}
+
+impl ClockCorrectionApp {
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        ctx: &Context,
+        async_ctx: &mut AsyncCtx,
+        project: Project,
+        recalc: &mut RecalculateApp,
+    ) {
+        if self.project_id != project.id {
+            self.project_id = project.id;
+            self.instrument_id = project.instrument.id;
+            self.reload(&project);
+        }
+        if recalc.calc_in_progress || !recalc.calc_enabled || recalc.query_in_progress {
+            recalc.progress_window(ctx);
+        }
+
+        ui.heading("Instrument clock corrections");
+        ui.label("Instrument timestamps are moved by offset + drift × days since the start of the correction, until the next correction of the same instrument.");
+        ui.label(format!("Times are in {}.", project.tz));
+
+        egui::Grid::new("clock_form").num_columns(2).spacing([16.0, 8.0]).show(ui, |ui| {
+            ui.label("Instrument");
+            let selected = self
+                .instruments
+                .iter()
+                .find(|i| i.id == self.instrument_id)
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            egui::ComboBox::from_id_salt("clock_instrument").selected_text(selected).show_ui(
+                ui,
+                |ui| {
+                    for instrument in &self.instruments {
+                        ui.selectable_value(
+                            &mut self.instrument_id,
+                            instrument.id,
+                            instrument.to_string(),
+                        );
+                    }
+                },
+            );
+            ui.end_row();
+
+            ui.label("Valid from");
+            ui.add(egui::TextEdit::singleline(&mut self.valid_from).hint_text(TIME_FMT));
+            ui.end_row();
+
+            ui.label("Offset (s)");
+            ui.add(egui::DragValue::new(&mut self.offset_s).speed(0.1))
+                .on_hover_text("Negative if the instrument clock is ahead");
+            ui.end_row();
+
+            ui.label("Drift (s/day)");
+            ui.add(egui::DragValue::new(&mut self.drift_s_per_day).speed(0.01))
+                .on_hover_text("Seconds per day the instrument clock loses, negative if it gains");
+            ui.end_row();
+        });
+        ui.checkbox(&mut self.rerun, "Recalculate fluxes of the affected days");
+
+        ui.horizontal(|ui| {
+            if ui.button("Add correction").clicked() {
+                match self.add(&project) {
+                    Ok(c) => self.start_rerun(async_ctx, &project, recalc, &[c]),
+                    Err(e) => self.last_error = Some(e),
+                }
+            }
+            if ui.button("Delete selected").clicked() && !self.selected_ids.is_empty() {
+                match self.delete_selected(&project) {
+                    Ok(removed) => self.start_rerun(async_ctx, &project, recalc, &removed),
+                    Err(e) => self.last_error = Some(e),
+                }
+            }
+        });
+
+        if let Some(err) = &self.last_error {
+            ui.colored_label(egui::Color32::RED, err);
+        }
+        ui.separator();
+
+        let cols = ["", "ID", "Instrument", "Valid from", "Offset (s)", "Drift (s/day)"];
+        ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("clock_table")
+                .striped(true)
+                .num_columns(cols.len())
+                .spacing([16.0, 8.0])
+                .show(ui, |ui| {
+                    for col in cols {
+                        ui.label(RichText::new(col).strong());
+                    }
+                    ui.end_row();
+
+                    for c in &self.corrections {
+                        let id = c.id.unwrap_or_default();
+                        let mut checked = self.selected_ids.contains(&id);
+                        if ui.checkbox(&mut checked, "").changed() {
+                            if checked {
+                                self.selected_ids.insert(id);
+                            } else {
+                                self.selected_ids.remove(&id);
+                            }
+                        }
+                        ui.label(id.to_string());
+                        let instrument = self
+                            .instruments
+                            .iter()
+                            .find(|i| i.id == Some(c.instrument_id))
+                            .map(|i| i.to_string())
+                            .unwrap_or_default();
+                        ui.label(instrument);
+                        let valid_from = DateTime::from_timestamp(c.valid_from, 0)
+                            .map(|dt| dt.with_timezone(&project.tz).format(TIME_FMT).to_string())
+                            .unwrap_or_default();
+                        ui.label(valid_from);
+                        ui.label(c.offset_s.to_string());
+                        ui.label(c.drift_s_per_day.to_string());
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn reload(&mut self, project: &Project) {
+        self.selected_ids.clear();
+        self.last_error = None;
+        let Some(project_id) = project.id else { return };
+        let result = Connection::open("fluxrs.db").and_then(|conn| {
+            let instruments = get_instruments_by_project_map(&conn, project_id)?;
+            let corrections = query_clock_corrections(&conn, project_id)?;
+            Ok((instruments, corrections))
+        });
+        match result {
+            Ok((instruments, corrections)) => {
+                self.instruments = instruments.into_values().collect();
+                self.instruments.sort_by_key(|i| i.id);
+                self.corrections = corrections;
+            },
+            Err(e) => self.last_error = Some(format!("Failed to load clock corrections: {e}")),
+        }
+    }
+
+    fn add(&mut self, project: &Project) -> Result<ClockCorrection, String> {
+        self.last_error = None;
+        let instrument_id = self.instrument_id.ok_or("Select an instrument.")?;
+        let naive = NaiveDateTime::parse_from_str(self.valid_from.trim(), TIME_FMT)
+            .map_err(|e| format!("Invalid start time, use {TIME_FMT}: {e}"))?;
+        let valid_from = project
+            .tz
+            .from_local_datetime(&naive)
+            .single()
+            .ok_or("Ambiguous start time in the project timezone.")?;
+        let correction = ClockCorrection {
+            id: None,
+            instrument_id,
+            valid_from: valid_from.timestamp(),
+            offset_s: self.offset_s,
+            drift_s_per_day: self.drift_s_per_day,
+        };
+        let conn = Connection::open("fluxrs.db").map_err(|e| e.to_string())?;
+        insert_clock_correction(&conn, &correction)
+            .map_err(|e| format!("Failed to save clock correction: {e}"))?;
+        self.reload(project);
+        Ok(correction)
+    }
+
+    fn delete_selected(&mut self, project: &Project) -> Result<Vec<ClockCorrection>, String> {
+        self.last_error = None;
+        let conn = Connection::open("fluxrs.db").map_err(|e| e.to_string())?;
+        let mut removed = Vec::new();
+        for id in self.selected_ids.iter().copied() {
+            match delete_clock_correction(&conn, id) {
+                Ok(Some(c)) => removed.push(c),
+                Ok(None) => {},
+                Err(e) => return Err(format!("Failed to delete id {id}: {e}")),
+            }
+        }
+        self.reload(project);
+        Ok(removed)
+    }
+
+    fn start_rerun(
+        &self,
+        async_ctx: &mut AsyncCtx,
+        project: &Project,
+        recalc: &mut RecalculateApp,
+        changed: &[ClockCorrection],
+    ) {
+        if !self.rerun || changed.is_empty() {
+            return;
+        }
+        // one recalculation over all the days the changes touch
+        let corrections = ClockCorrections::new(self.corrections.clone());
+        let (start, end) = changed
+            .iter()
+            .map(|c| corrections.affected_range(c.instrument_id, c.valid_from))
+            .fold((i64::MAX, i64::MIN), |(s, e), (cs, ce)| (s.min(cs), e.max(ce)));
+        let (Some(start), Some(end)) =
+            (DateTime::from_timestamp(start, 0), DateTime::from_timestamp(end, 0))
+        else {
+            return;
+        };
+        recalc.calc_enabled = false;
+        recalc.query_in_progress = true;
+        recalc.calculate(&async_ctx.runtime, start, end, project, async_ctx.prog_sender.clone());
+    }
+}
@@ -2,10 +2,17 @@ use crate::ui::manage_proj::project_ui::MsgType;
 use crate::ui::manage_proj::project_ui::{clicked_outside_window, ProjectApp};
 use crate::ui::tz_picker::timezone_combo;
 use egui::{Align2, Area, Color32, Context, Frame, Id, Window};
+use egui_file::FileDialog;
+use fluxrs_core::data_formats::auxdata::DEFAULT_AUX_MAX_GAP_S;
+use fluxrs_core::data_formats::meteodata::{MeteoInterpolation, DEFAULT_METEO_MAX_GAP_S};
 use fluxrs_core::instruments::instruments::InstrumentType;
+use fluxrs_core::instruments::{save_instrument_profile, InstrumentProfile};
 use fluxrs_core::mode::Mode;
 use fluxrs_core::project::Project;
+use rusqlite::Connection;
+use std::borrow::Cow;
 use std::error::Error;
+use std::path::Path;
 
 impl ProjectApp {
     pub fn close_proj_create(&mut self) {
@@ -19,12 +26,68 @@ impl ProjectApp {
         self.deadband = 30.;
         self.min_calc_len = 60.;
         self.mode = Mode::default();
+        self.meteo_interpolation = MeteoInterpolation::default();
+        self.meteo_max_gap_s = DEFAULT_METEO_MAX_GAP_S;
+        self.aux_max_gap_s = DEFAULT_AUX_MAX_GAP_S;
+        self.elevation_m = None;
+        self.latitude = None;
         self.message = None;
         self.del_message = None;
         self.project_timezone_str.clear();
         self.project_timezone = None;
         self.tz_state.selected = None;
         self.tz_state.query.clear();
+        self.profile_dialog = None;
+    }
+
+    fn open_profile_dialog(&mut self) {
+        let mut dialog = FileDialog::open_file(std::env::current_dir().ok())
+            .title("Select instrument profile")
+            .open_button_text(Cow::from("Import"))
+            .show_files_filter(Box::new(|p: &Path| {
+                matches!(p.extension().and_then(|e| e.to_str()), Some("toml") | Some("json"))
+            }))
+            .show_rename(false)
+            .show_new_folder(false);
+        dialog.open();
+        self.profile_dialog = Some(dialog);
+    }
+
+    fn show_profile_dialog(&mut self, ctx: &Context) {
+        let Some(dialog) = &mut self.profile_dialog else {
+            return;
+        };
+        dialog.show(ctx);
+        match dialog.state() {
+            egui_file::State::Selected => {
+                if let Some(path) = dialog.path().map(|p| p.to_path_buf()) {
+                    self.import_profile(&path);
+                }
+                self.profile_dialog = None;
+            },
+            egui_file::State::Cancelled | egui_file::State::Closed => {
+                self.profile_dialog = None;
+            },
+            _ => {},
+        }
+    }
+
+    fn import_profile(&mut self, path: &Path) {
+        let result = InstrumentProfile::from_path(path).and_then(|profile| {
+            let conn = Connection::open("fluxrs.db")?;
+            save_instrument_profile(&conn, &profile)
+        });
+        match result {
+            Ok(instrument) => {
+                self.selected_instrument = instrument;
+                self.main_gas = instrument.available_gases().first().copied();
+                self.message =
+                    Some(MsgType::Good(format!("Imported instrument profile '{}'", instrument)));
+            },
+            Err(e) => {
+                self.message = Some(MsgType::Bad(format!("{}: {}", path.display(), e)));
+            },
+        }
     }
 
     pub fn show_proj_create_prompt(&mut self, ctx: &egui::Context) {
@@ -95,6 +158,9 @@ impl ProjectApp {
                             );
                         }
                     });
+                if ui.button("Import instrument profile").clicked() {
+                    self.open_profile_dialog();
+                }
 
                 ui.add_space(10.);
                 ui.label("Instrument serial:");
@@ -149,6 +215,50 @@ impl ProjectApp {
                     },
                 );
 
+                ui.add_space(10.0);
+                ui.label("Meteo values between samples:");
+                egui::ComboBox::from_label("Meteo interpolation")
+                    .selected_text(self.meteo_interpolation.to_string())
+                    .show_ui(ui, |ui| {
+                        can_close = false;
+                        for interpolation in
+                            [MeteoInterpolation::Linear, MeteoInterpolation::Nearest]
+                        {
+                            ui.selectable_value(
+                                &mut self.meteo_interpolation,
+                                interpolation,
+                                interpolation.to_string(),
+                            );
+                        }
+                    });
+                ui.label("Largest distance to a meteo sample in seconds:");
+                ui.add(
+                    egui::DragValue::new(&mut self.meteo_max_gap_s).speed(60.0).range(0..=86400),
+                );
+                ui.label("Largest distance to an auxiliary sample in seconds:");
+                ui.add(egui::DragValue::new(&mut self.aux_max_gap_s).speed(60.0).range(0..=604800));
+
+                ui.add_space(10.0);
+                ui.label("Without pressure data it's modelled from the site elevation:");
+                ui.horizontal(|ui| {
+                    let mut known = self.elevation_m.is_some();
+                    if ui.checkbox(&mut known, "Elevation in meters").changed() {
+                        self.elevation_m = known.then_some(0.0);
+                    }
+                    if let Some(h) = &mut self.elevation_m {
+                        ui.add(egui::DragValue::new(h).speed(10.0).range(-500.0..=9000.0));
+                    }
+                });
+                ui.horizontal(|ui| {
+                    let mut known = self.latitude.is_some();
+                    if ui.checkbox(&mut known, "Latitude in degrees").changed() {
+                        self.latitude = known.then_some(0.0);
+                    }
+                    if let Some(lat) = &mut self.latitude {
+                        ui.add(egui::DragValue::new(lat).speed(0.1).range(-90.0..=90.0));
+                    }
+                });
+
                 ui.add_space(10.0);
 
                 let enable_add_proj = !self.project_name.trim().is_empty()
@@ -201,6 +311,10 @@ impl ProjectApp {
                     ui.label(egui::RichText::new(text).color(color));
                 }
             });
+        if self.profile_dialog.is_some() {
+            can_close = false;
+            self.show_profile_dialog(ctx);
+        }
         if clicked_outside_window(ctx, wr.as_ref()) && can_close {
             self.close_proj_create();
         }
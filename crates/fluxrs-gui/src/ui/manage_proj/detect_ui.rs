@@ -0,0 +1,247 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use egui::{Color32, RichText, ScrollArea, Ui};
+use egui_plot::{Line, Plot, PlotPoints, VLine};
+use fluxrs_core::cycle::detect::{
+    assign_chambers, detect_cycles, insert_detected_cycles, parse_chamber_sequence, query_main_gas,
+    DetectSettings, DetectedCycle,
+};
+use fluxrs_core::project::Project;
+use rusqlite::Connection;
+
+const TIME_FMT: &str = "%Y-%m-%d %H:%M:%S";
+/// Points drawn of the concentration series at most
+const MAX_PLOT_POINTS: usize = 20_000;
+
+/// Proposes cycles from the main gas when the chamber controller log is
+/// missing, the proposals are reviewed before they are inserted
+#[derive(Default)]
+pub struct CycleDetectApp {
+    project_id: Option<i64>,
+    start: String,
+    end: String,
+    settings: DetectSettings,
+    sequence: String,
+    proposals: Vec<DetectedCycle>,
+    accepted: Vec<bool>,
+    plot_points: Vec<[f64; 2]>,
+    message: Option<String>,
+    last_error: Option<String>,
+}
+
+impl CycleDetectApp {
+    pub fn ui(&mut self, ui: &mut Ui, project: Project) {
+        if self.project_id != project.id {
+            *self = Self { project_id: project.id, ..Self::default() };
+        }
+
+        ui.heading("Detect cycles from concentration");
+        ui.label("Chamber closures are found from the slope of the main gas of the main instrument, and openings from the return to ambient. Review the proposed cycles before inserting them.");
+        ui.label(format!("Times are in {}.", project.tz));
+
+        egui::Grid::new("detect_form").num_columns(2).spacing([16.0, 8.0]).show(ui, |ui| {
+            ui.label("Start");
+            ui.add(egui::TextEdit::singleline(&mut self.start).hint_text(TIME_FMT));
+            ui.end_row();
+
+            ui.label("End");
+            ui.add(egui::TextEdit::singleline(&mut self.end).hint_text(TIME_FMT));
+            ui.end_row();
+
+            let unit = project.main_gas.map(|g| g.to_string()).unwrap_or_default();
+            ui.label(format!("Min slope ({unit}/s)"));
+            ui.add(
+                egui::DragValue::new(&mut self.settings.min_slope)
+                    .speed(0.001)
+                    .range(0.0..=f64::MAX),
+            )
+            .on_hover_text("Slope that starts a closure, in either direction");
+            ui.end_row();
+
+            ui.label("Slope window (s)");
+            ui.add(egui::DragValue::new(&mut self.settings.window_s).range(3..=600));
+            ui.end_row();
+
+            ui.label("Closure length (s)");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.min_duration_s)
+                        .range(1..=86400)
+                        .prefix("min "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.settings.max_duration_s)
+                        .range(1..=86400)
+                        .prefix("max "),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Ambient tolerance");
+            ui.add(
+                egui::DragValue::new(&mut self.settings.ambient_tolerance)
+                    .speed(0.01)
+                    .range(0.0..=1.0),
+            )
+            .on_hover_text(
+                "Fraction of the closure change left when the concentration is back at ambient",
+            );
+            ui.end_row();
+
+            ui.label("Before close / after open (s)");
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut self.settings.lead_s).range(0..=3600));
+                ui.add(egui::DragValue::new(&mut self.settings.tail_s).range(0..=3600));
+            });
+            ui.end_row();
+
+            ui.label("Chamber sequence");
+            ui.add(egui::TextEdit::singleline(&mut self.sequence).hint_text("1, 2, 3"))
+                .on_hover_text(
+                    "Chamber ids in measurement order, repeated over the detected cycles",
+                );
+            ui.end_row();
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Detect").clicked() {
+                if let Err(e) = self.detect(&project) {
+                    self.last_error = Some(e);
+                }
+            }
+            if ui.button("Apply sequence").clicked() {
+                assign_chambers(&mut self.proposals, &parse_chamber_sequence(&self.sequence));
+            }
+            let selected = self.accepted.iter().filter(|a| **a).count();
+            if ui
+                .add_enabled(
+                    selected > 0,
+                    egui::Button::new(format!("Insert selected ({selected})")),
+                )
+                .clicked()
+            {
+                if let Err(e) = self.insert(&project) {
+                    self.last_error = Some(e);
+                }
+            }
+        });
+
+        if let Some(err) = &self.last_error {
+            ui.colored_label(Color32::RED, err);
+        }
+        if let Some(msg) = &self.message {
+            ui.label(msg);
+        }
+        if self.plot_points.is_empty() {
+            return;
+        }
+        ui.separator();
+        self.plot(ui, project.tz);
+
+        let cols = ["", "Chamber", "Start", "Close (s)", "Open (s)", "End (s)"];
+        ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("detect_table")
+                .striped(true)
+                .num_columns(cols.len())
+                .spacing([16.0, 8.0])
+                .show(ui, |ui| {
+                    for col in cols {
+                        ui.label(RichText::new(col).strong());
+                    }
+                    ui.end_row();
+
+                    for (cycle, accepted) in self.proposals.iter_mut().zip(self.accepted.iter_mut())
+                    {
+                        ui.checkbox(accepted, "");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut cycle.chamber_id).desired_width(80.0),
+                        );
+                        ui.label(format_time(cycle.start_time, project.tz));
+                        ui.label(cycle.close_offset.to_string());
+                        ui.label(cycle.open_offset.to_string());
+                        ui.label(cycle.end_offset.to_string());
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn plot(&self, ui: &mut Ui, tz: Tz) {
+        Plot::new("detect_plot")
+            .height(250.0)
+            .x_axis_formatter(move |mark, _| format_time(mark.value as i64, tz))
+            .show(ui, |plot_ui| {
+                plot_ui
+                    .line(Line::new("Concentration", PlotPoints::from(self.plot_points.clone())));
+                for (cycle, accepted) in self.proposals.iter().zip(&self.accepted) {
+                    let color = if *accepted { Color32::GREEN } else { Color32::GRAY };
+                    plot_ui.vline(VLine::new("Close", cycle.close_time() as f64).color(color));
+                    plot_ui.vline(VLine::new("Open", cycle.open_time() as f64).color(Color32::RED));
+                }
+            });
+    }
+
+    fn detect(&mut self, project: &Project) -> Result<(), String> {
+        self.last_error = None;
+        self.message = None;
+        if project.main_gas.is_none() {
+            return Err("The project has no main gas.".to_owned());
+        }
+        let start = parse_time(&self.start, project.tz)?;
+        let end = parse_time(&self.end, project.tz)?;
+        if end <= start {
+            return Err("End has to be after start.".to_owned());
+        }
+        let conn = Connection::open("fluxrs.db").map_err(|e| e.to_string())?;
+        let series = query_main_gas(&conn, project, start, end)
+            .map_err(|e| format!("Failed to query gas data: {e}"))?;
+        if series.is_empty() {
+            return Err("No gas data in the range.".to_owned());
+        }
+
+        self.settings.chamber_sequence = parse_chamber_sequence(&self.sequence);
+        self.proposals = detect_cycles(&series, &self.settings);
+        self.accepted = vec![true; self.proposals.len()];
+        let step = series.len().div_ceil(MAX_PLOT_POINTS);
+        self.plot_points =
+            series.iter().step_by(step).map(|(t, v)| [*t as f64 / 1000.0, *v]).collect();
+        self.message = Some(format!("{} cycles found.", self.proposals.len()));
+        Ok(())
+    }
+
+    fn insert(&mut self, project: &Project) -> Result<(), String> {
+        self.last_error = None;
+        let accepted: Vec<DetectedCycle> = self
+            .proposals
+            .iter()
+            .zip(&self.accepted)
+            .filter(|(_, a)| **a)
+            .map(|(c, _)| c.clone())
+            .collect();
+        if accepted.iter().any(|c| c.chamber_id.trim().is_empty()) {
+            return Err("Every selected cycle needs a chamber id.".to_owned());
+        }
+        let mut conn = Connection::open("fluxrs.db").map_err(|e| e.to_string())?;
+        let (inserts, skips) = insert_detected_cycles(&mut conn, project, &accepted)
+            .map_err(|e| format!("Failed to insert cycles: {e}"))?;
+        self.message = Some(format!(
+            "Inserted {inserts} cycles, skipped {skips} duplicates. Process the range to calculate fluxes."
+        ));
+        Ok(())
+    }
+}
+
+fn parse_time(s: &str, tz: Tz) -> Result<i64, String> {
+    let naive = NaiveDateTime::parse_from_str(s.trim(), TIME_FMT)
+        .map_err(|e| format!("Invalid time '{}', use {TIME_FMT}: {e}", s.trim()))?;
+    tz.from_local_datetime(&naive)
+        .single()
+        .map(|dt| dt.timestamp())
+        .ok_or_else(|| format!("Ambiguous time '{}' in the project timezone.", s.trim()))
+}
+
+fn format_time(t: i64, tz: Tz) -> String {
+    DateTime::from_timestamp(t, 0)
+        .map(|dt| dt.with_timezone(&tz).format(TIME_FMT).to_string())
+        .unwrap_or_default()
+}
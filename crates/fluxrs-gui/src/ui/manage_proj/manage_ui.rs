@@ -1,5 +1,6 @@
+use crate::ui::manage_proj::clock_ui::ClockCorrectionApp;
 use crate::ui::manage_proj::datepickerstate::DateRangePickerState;
-use crate::ui::manage_proj::project_ui::input_block_overlay;
+use crate::ui::manage_proj::detect_ui::CycleDetectApp;
 use crate::ui::AsyncCtx;
 
 use crate::ui::recalc::RecalculateApp;
@@ -76,40 +77,7 @@ impl DeleteMeasurementApp {
             || !self.recalc.calc_enabled
             || self.recalc.query_in_progress
         {
-            input_block_overlay(ctx, "blocker22");
-
-            Window::new("tester")
-                .title_bar(false)
-                .collapsible(false)
-                .resizable(false)
-                .anchor(Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
-                .frame(
-                    Frame::window(&ctx.style())
-                        .fill(Color32::from_rgb(30, 30, 30))
-                        .corner_radius(8)
-                        .inner_margin(egui::Margin::symmetric(16, 12)),
-                )
-                .show(ctx, |ui| {
-                    ui.add(egui::Spinner::new());
-
-                    if self.recalc.query_in_progress {
-                        ui.label("Querying data, this can take a while for large time ranges.");
-                    } else if self.recalc.calc_in_progress {
-                        ui.label("Recalculating fluxes");
-                    }
-
-                    if let Some((_, total)) = self.recalc.cycles_state {
-                        let total = total.max(1); // avoid division by zero
-                        let fraction =
-                            (self.recalc.cycles_progress as f32 / total as f32).clamp(0.0, 1.0);
-                        let pb = egui::widgets::ProgressBar::new(fraction)
-                            .desired_width(200.)
-                            .corner_radius(1)
-                            .show_percentage()
-                            .text(format!("{}/{}", self.recalc.cycles_progress, total));
-                        ui.add(pb);
-                    }
-                });
+            self.recalc.progress_window(ctx);
         }
         ui.horizontal(|ui| {
             if ui.button("Refresh").clicked() {
@@ -119,7 +87,12 @@ impl DeleteMeasurementApp {
                 match self.delete_selected() {
                     Ok(n) if n > 0 => {
                         match self.datatype {
-                            DataType::Meteo | DataType::Height | DataType::Chamber => {
+                            DataType::Meteo
+                            | DataType::Height
+                            | DataType::Chamber
+                            | DataType::ChamberMeteo
+                            | DataType::Volume
+                            | DataType::Snow => {
                                 self.recalc.calc_enabled = false;
                                 self.recalc.query_in_progress = true;
                                 self.recalc.calculate_all(
@@ -129,7 +102,7 @@ impl DeleteMeasurementApp {
                                 );
                             },
                             _ => {
-                                // No recalculation for Gas or Cycle deletions
+                                // No recalculation for Gas, Cycle or Aux deletions
                             },
                         }
                     },
@@ -276,7 +249,13 @@ enum ManagePanel {
     DeleteMeteo,
     DeleteHeight,
     DeleteChamber,
+    DeleteChamberMeteo,
+    DeleteAux,
+    DeleteVolume,
+    DeleteSnow,
     DeleteFlux,
+    ClockCorrections,
+    DetectCycles,
     Empty,
 }
 impl Default for ManagePanel {
@@ -291,6 +270,8 @@ pub struct ManageApp {
     project: Project,
     live_panel: ManagePanel,
     del_measurement: DeleteMeasurementApp,
+    clock: ClockCorrectionApp,
+    detect: CycleDetectApp,
 }
 
 impl Default for ManageApp {
@@ -307,6 +288,8 @@ impl ManageApp {
             live_panel: ManagePanel::default(),
             project: Project::default(),
             del_measurement: DeleteMeasurementApp::new(Project::default(), DataType::Gas),
+            clock: ClockCorrectionApp::default(),
+            detect: CycleDetectApp::default(),
         }
     }
 }
@@ -415,6 +398,36 @@ impl ManageApp {
                                     ManagePanel::DeleteChamber,
                                     "Delete chamber metadata",
                                 );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::DeleteChamberMeteo,
+                                    "Delete chamber meteo data",
+                                );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::DeleteAux,
+                                    "Delete auxiliary data",
+                                );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::DeleteVolume,
+                                    "Delete volume data",
+                                );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::DeleteSnow,
+                                    "Delete snow depth data",
+                                );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::ClockCorrections,
+                                    "Clock corrections",
+                                );
+                                ui.selectable_value(
+                                    &mut self.live_panel,
+                                    ManagePanel::DetectCycles,
+                                    "Detect cycles",
+                                );
                             })
                             .response
                         });
@@ -450,6 +463,43 @@ impl ManageApp {
                             DataType::Chamber,
                         );
                     },
+                    ManagePanel::DeleteChamberMeteo => {
+                        self.del_measurement.ui(
+                            ui,
+                            ctx,
+                            async_ctx,
+                            project_clone,
+                            DataType::ChamberMeteo,
+                        );
+                    },
+                    ManagePanel::DeleteAux => {
+                        self.del_measurement.ui(ui, ctx, async_ctx, project_clone, DataType::Aux);
+                    },
+                    ManagePanel::DeleteVolume => {
+                        self.del_measurement.ui(
+                            ui,
+                            ctx,
+                            async_ctx,
+                            project_clone,
+                            DataType::Volume,
+                        );
+                    },
+                    ManagePanel::DeleteSnow => {
+                        self.del_measurement.ui(ui, ctx, async_ctx, project_clone, DataType::Snow);
+                    },
+                    ManagePanel::ClockCorrections => {
+                        // shares the recalculation state so progress events reach it
+                        self.clock.ui(
+                            ui,
+                            ctx,
+                            async_ctx,
+                            project_clone,
+                            &mut self.del_measurement.recalc,
+                        );
+                    },
+                    ManagePanel::DetectCycles => {
+                        self.detect.ui(ui, project_clone);
+                    },
                     ManagePanel::Empty => {},
                     ManagePanel::DeleteFlux => {},
                 }
@@ -1,6 +1,8 @@
+pub mod clock_ui;
 pub mod create_ui;
 pub mod datepickerstate;
 pub mod delete_ui;
+pub mod detect_ui;
 pub mod manage_ui;
 pub mod project_ui;
 
@@ -5,12 +5,15 @@ use crate::ui::{AsyncCtx, ProgReceiver, ProgSender};
 use chrono_tz::Tz;
 use egui::Color32;
 use egui::{Area, Button, Context, Id};
+use egui_file::FileDialog;
+use fluxrs_core::data_formats::auxdata::DEFAULT_AUX_MAX_GAP_S;
+use fluxrs_core::data_formats::meteodata::{MeteoInterpolation, DEFAULT_METEO_MAX_GAP_S};
 use fluxrs_core::gastype::GasType;
 use fluxrs_core::instruments::instruments::get_or_insert_instrument;
 use fluxrs_core::instruments::instruments::{Instrument, InstrumentType};
 use fluxrs_core::mode::Mode;
-use fluxrs_core::project::Project;
 use fluxrs_core::project::ProjectExistsError;
+use fluxrs_core::project::{query_aux_max_gap, query_meteo_settings, query_site_settings, Project};
 use std::fmt;
 use std::process;
 
@@ -46,10 +49,16 @@ pub struct ProjectApp {
     pub project_name: String,
     pub selected_instrument: InstrumentType,
     pub selected_serial: String,
+    pub profile_dialog: Option<FileDialog>,
     pub main_gas: Option<GasType>,
     pub deadband: f64,
     pub min_calc_len: f64,
     pub mode: Mode,
+    pub meteo_interpolation: MeteoInterpolation,
+    pub meteo_max_gap_s: i64,
+    pub aux_max_gap_s: i64,
+    pub elevation_m: Option<f64>,
+    pub latitude: Option<f64>,
     pub tz_state: TimezonePickerState,
     pub project_timezone: Option<Tz>, // store the choice (or keep as String if you prefer)
     pub project_timezone_str: String,
@@ -72,6 +81,7 @@ impl Default for ProjectApp {
             project_name: String::new(),
             selected_instrument: InstrumentType::default(),
             selected_serial: String::new(),
+            profile_dialog: None,
             main_gas: Some(GasType::default()),
             min_calc_len: 60.,
             deadband: 30.,
@@ -79,6 +89,11 @@ impl Default for ProjectApp {
             project_timezone: None,
             project_timezone_str: String::new(),
             mode: Mode::default(),
+            meteo_interpolation: MeteoInterpolation::default(),
+            meteo_max_gap_s: DEFAULT_METEO_MAX_GAP_S,
+            aux_max_gap_s: DEFAULT_AUX_MAX_GAP_S,
+            elevation_m: None,
+            latitude: None,
             message: None,
             del_message: None,
             proj_create_open: false,
@@ -188,6 +203,11 @@ impl ProjectApp {
             mode: self.mode,
             min_calc_len: self.min_calc_len,
             tz: self.project_timezone.unwrap_or_default(),
+            meteo_interpolation: self.meteo_interpolation,
+            meteo_max_gap_s: self.meteo_max_gap_s,
+            aux_max_gap_s: self.aux_max_gap_s,
+            elevation_m: self.elevation_m,
+            latitude: self.latitude,
         })
     }
 
@@ -240,6 +260,9 @@ impl ProjectApp {
             let min_calc_len = row.get(*column_index.get("min_calc_len").unwrap())?;
             let tz_str: String = row.get(*column_index.get("tz").unwrap())?;
             let tz: Tz = tz_str.parse().expect("Invalid timezone string");
+            let (meteo_interpolation, meteo_max_gap_s) = query_meteo_settings(&conn, id)?;
+            let aux_max_gap_s = query_aux_max_gap(&conn, id)?;
+            let (elevation_m, latitude) = query_site_settings(&conn, id)?;
             let instrument = Instrument {
                 model: instrument_model,
                 serial: instrument_serial,
@@ -255,6 +278,11 @@ impl ProjectApp {
                 main_gas,
                 mode,
                 tz,
+                meteo_interpolation,
+                meteo_max_gap_s,
+                aux_max_gap_s,
+                elevation_m,
+                latitude,
             };
 
             self.all_projects.push(proj)
@@ -323,6 +351,9 @@ impl ProjectApp {
                     },
                 };
                 let tz: Tz = tz_str.parse().expect("Invalid timezone string");
+                let (meteo_interpolation, meteo_max_gap_s) = query_meteo_settings(&conn, id)?;
+                let aux_max_gap_s = query_aux_max_gap(&conn, id)?;
+                let (elevation_m, latitude) = query_site_settings(&conn, id)?;
 
                 let instrument = Instrument { model: instrument, serial, id: Some(instrument_id) };
                 let project = Project {
@@ -334,6 +365,11 @@ impl ProjectApp {
                     min_calc_len,
                     mode,
                     tz,
+                    meteo_interpolation,
+                    meteo_max_gap_s,
+                    aux_max_gap_s,
+                    elevation_m,
+                    latitude,
                 };
 
                 self.project = Some(project); // assuming you have this field
@@ -1,7 +1,8 @@
 use fluxrs_core::cycle::cycle::load_cycles;
 use fluxrs_core::cycle_recalcer::{Datasets, Infra, Recalcer};
 use fluxrs_core::data_formats::chamberdata::query_chamber_async;
-use fluxrs_core::data_formats::heightdata::query_height_async;
+use fluxrs_core::data_formats::chambermeteo::query_chamber_meteo_async;
+use fluxrs_core::data_formats::chamberseries::{query_series_async, HEIGHT, SNOW, VOLUME};
 use fluxrs_core::data_formats::meteodata::query_meteo_async;
 use fluxrs_core::processevent::{
     InsertEvent, ProcessEvent, ProcessEventSink, ProgressEvent, QueryEvent, ReadEvent,
@@ -76,12 +77,38 @@ impl RecalculateApp {
             .await;
             let meteo_result =
                 query_meteo_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
+            let chamber_meteo_result =
+                query_chamber_meteo_async(arc_conn.clone(), start_date, end_date, proj.clone())
+                    .await;
             let height_result =
-                query_height_async(arc_conn.clone(), start_date, end_date, proj.clone()).await;
+                query_series_async(arc_conn.clone(), &HEIGHT, start_date, end_date, proj.clone())
+                    .await;
+            let volume_result =
+                query_series_async(arc_conn.clone(), &VOLUME, start_date, end_date, proj.clone())
+                    .await;
+            let snow_result =
+                query_series_async(arc_conn.clone(), &SNOW, start_date, end_date, proj.clone())
+                    .await;
             let chamber_result = query_chamber_async(arc_conn.clone(), proj.clone()).await;
 
-            match (cycle_result, meteo_result, height_result, chamber_result) {
-                (Ok(cycle_data), Ok(meteo_data), Ok(height_data), Ok(chamber_data)) => {
+            match (
+                cycle_result,
+                meteo_result,
+                chamber_meteo_result,
+                height_result,
+                volume_result,
+                snow_result,
+                chamber_result,
+            ) {
+                (
+                    Ok(cycle_data),
+                    Ok(meteo_data),
+                    Ok(chamber_meteo_data),
+                    Ok(height_data),
+                    Ok(volume_data),
+                    Ok(snow_data),
+                    Ok(chamber_data),
+                ) => {
                     let _ = progress_sender.send(ProcessEvent::Query(QueryEvent::QueryComplete));
                     let _ = progsender
                         .send(ProcessEvent::Progress(ProgressEvent::Recalced(0, cycle_data.len())));
@@ -90,7 +117,10 @@ impl RecalculateApp {
                             proj.clone(),
                             Datasets {
                                 meteo: meteo_data,
+                                chamber_meteo: chamber_meteo_data,
                                 height: height_data,
+                                volume: volume_data,
+                                snow: snow_data,
                                 chambers: chamber_data,
                             },
                             Infra { conn: arc_conn, progress: progress_sender },
@@ -172,6 +202,42 @@ impl RecalculateApp {
             }
     });
     }
+    /// Blocks the input and shows the query and recalculation progress
+    pub fn progress_window(&self, ctx: &Context) {
+        input_block_overlay(ctx, "blocker22");
+
+        Window::new("tester")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .frame(
+                Frame::window(&ctx.style())
+                    .fill(Color32::from_rgb(30, 30, 30))
+                    .corner_radius(8)
+                    .inner_margin(egui::Margin::symmetric(16, 12)),
+            )
+            .show(ctx, |ui| {
+                ui.add(egui::Spinner::new());
+
+                if self.query_in_progress {
+                    ui.label("Querying data, this can take a while for large time ranges.");
+                } else if self.calc_in_progress {
+                    ui.label("Recalculating fluxes");
+                }
+
+                if let Some((_, total)) = self.cycles_state {
+                    let total = total.max(1); // avoid division by zero
+                    let fraction = (self.cycles_progress as f32 / total as f32).clamp(0.0, 1.0);
+                    let pb = egui::widgets::ProgressBar::new(fraction)
+                        .desired_width(200.)
+                        .corner_radius(1)
+                        .show_percentage()
+                        .text(format!("{}/{}", self.cycles_progress, total));
+                    ui.add(pb);
+                }
+            });
+    }
     pub fn calculate_all(
         &mut self,
         runtime: &tokio::runtime::Runtime,
@@ -3,11 +3,18 @@ pub struct EnableFit {
     pub show_polyfit: bool,
     pub show_roblinfit: bool,
     pub show_expfit: bool,
+    pub show_hmrfit: bool,
 }
 
 impl EnableFit {
     pub fn new() -> Self {
-        Self { show_linfit: true, show_polyfit: true, show_roblinfit: true, show_expfit: true }
+        Self {
+            show_linfit: true,
+            show_polyfit: true,
+            show_roblinfit: true,
+            show_expfit: true,
+            show_hmrfit: true,
+        }
     }
 }
 
@@ -373,6 +373,11 @@ impl ValidationApp {
                 let errors = ErrorCode::from_mask(cycle.error_code.0);
                 let mut error_messages: Vec<String> =
                     errors.iter().map(|error| error.to_string()).collect();
+                for fired in cycle.fired_diag_conditions() {
+                    if fired.condition.error_code().is_some() {
+                        error_messages.push(format!("  {}", fired.condition.name));
+                    }
+                }
 
                 if gas_invalid {
                     error_messages.push("Gas marked as invalid".to_owned());
@@ -406,6 +411,9 @@ impl ValidationApp {
                 if self.show_fits.show_expfit {
                     self.plot_model_fit(plot_ui, key, FluxKind::Exponential);
                 }
+                if self.show_fits.show_hmrfit {
+                    self.plot_model_fit(plot_ui, key, FluxKind::Hmr);
+                }
             }
             if let Some(data) = cycle.gas_v.get(key) {
                 let dt_v = &cycle.get_dt_v(&key.id);
@@ -1928,11 +1936,10 @@ pub fn init_gas_plot(
         .coordinates_formatter(
             Corner::RightBottom,
             CoordinatesFormatter::new(move |value, _| {
-                let timestamp = value.x as i64;
-                let datetime = DateTime::from_timestamp(timestamp, 0)
+                let datetime = DateTime::from_timestamp_millis((value.x * 1000.0).round() as i64)
                     .map(|dt| {
                         let local = dt.with_timezone(&tz);
-                        local.format("%Y-%m-%d %H:%M:%S").to_string()
+                        local.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
                     })
                     .unwrap_or_else(|| format!("{:.1}", value.x));
 
@@ -1948,11 +1955,10 @@ pub fn init_gas_plot(
         .label_formatter({
             let tz_local = tz;
             move |_, value| {
-                let timestamp = value.x as i64;
-                let datetime = DateTime::from_timestamp(timestamp, 0)
+                let datetime = DateTime::from_timestamp_millis((value.x * 1000.0).round() as i64)
                     .map(|dt| {
                         let local = dt.with_timezone(&tz_local.clone());
-                        local.format("%Y-%m-%d %H:%M:%S").to_string()
+                        local.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
                     })
                     .unwrap_or_else(|| format!("{:.1}", value.x));
 
@@ -2146,6 +2152,7 @@ fn marker_shape_for_flux_kind(kind: FluxKind) -> MarkerShape {
         FluxKind::Poly => MarkerShape::Square,
         FluxKind::RobLin => MarkerShape::Diamond,
         FluxKind::Exponential => MarkerShape::Plus,
+        FluxKind::Hmr => MarkerShape::Cross,
     }
 }
 
@@ -119,7 +119,7 @@ impl CycleFilter {
             r2_thresh,
             rmse_thresh,
             t0_thresh,
-        ) && cycle.error_code.0 == 0;
+        ) && !cycle.error_code.invalidates();
 
         let trace_visible = self.visible_traces.get(&cycle.chamber_id).copied().unwrap_or(true);
         let bad_ok = self.show_bad || !cycle.error_code.contains(ErrorCode::FailedMeasurement);
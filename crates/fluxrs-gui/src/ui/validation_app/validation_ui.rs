@@ -21,6 +21,7 @@ use fluxrs_core::data_formats::meteodata::MeteoSource;
 use fluxrs_core::errorcode::ErrorCode;
 use fluxrs_core::flux::{FluxKind, FluxUnit};
 use fluxrs_core::gastype::GasType;
+use fluxrs_core::instruments::diag::DiagSeverity;
 use fluxrs_core::instruments::instruments::Instrument;
 use fluxrs_core::mode::Mode;
 use fluxrs_core::project::Project;
@@ -248,6 +249,7 @@ impl ValidationApp {
                 show_exp_model = ui
                     .checkbox(&mut self.show_fits.show_expfit, "Show exponential model")
                     .clicked();
+                ui.checkbox(&mut self.show_fits.show_hmrfit, "Show HMR model");
             });
 
             ui.vertical(|ui| {
@@ -2753,7 +2755,7 @@ impl ValidationApp {
                     ui.label(format!("{:.2}", cycle.chamber_height));
                     ui.end_row();
                     ui.label("Chamber volume:");
-                    ui.label(format!("{:.2} cm3", cycle.chamber.volume_m3() * 1e+6));
+                    ui.label(format!("{:.2} cm3", cycle.chamber.adjusted_volume() * 1e+6));
                     ui.end_row();
                     ui.label("Chamber area:");
                     ui.label(format!("{:.2} cm2", cycle.chamber.area_m2() * 1e+4));
@@ -2767,9 +2769,24 @@ impl ValidationApp {
                     }
                     ui.end_row();
 
+                    // meteo the main gas fluxes were calculated with
+                    let main_key =
+                        GasKey::from((&cycle.main_gas, &cycle.main_instrument.id.unwrap()));
+                    let meteo = cycle.meteo_for(&main_key);
+                    let (temp_station, press_station) = cycle.meteo_stations_for(&main_key);
+                    let with_station = |text: String, station: Option<&str>| match station {
+                        Some(name) => format!("{} — {}", text, name),
+                        None => text,
+                    };
                     ui.label("Air temperature");
-                    let temp_text = format!("{}", cycle.meteo.temperature());
-                    if cycle.meteo.temperature_source() != MeteoSource::Raw {
+                    let temp_text = with_station(format!("{}", meteo.temperature()), temp_station);
+                    if !matches!(
+                        meteo.temperature_source(),
+                        MeteoSource::Raw
+                            | MeteoSource::Interpolated
+                            | MeteoSource::Cycle
+                            | MeteoSource::Chamber
+                    ) {
                         ui.colored_label(Color32::ORANGE, temp_text);
                     } else {
                         ui.label(temp_text);
@@ -2777,16 +2794,53 @@ impl ValidationApp {
                     ui.end_row();
 
                     ui.label("Air pressure");
-                    let press_text = format!("{}", cycle.meteo.pressure());
-                    if cycle.meteo.pressure.source != MeteoSource::Raw {
+                    let press_text = with_station(format!("{}", meteo.pressure()), press_station);
+                    if !matches!(
+                        meteo.pressure_source(),
+                        MeteoSource::Raw | MeteoSource::Interpolated | MeteoSource::Chamber
+                    ) {
                         ui.colored_label(Color32::ORANGE, press_text);
                     } else {
                         ui.label(press_text);
                     }
                     ui.end_row();
+
+                    if let Some(note) = &cycle.note {
+                        ui.label("Note:");
+                        ui.label(note);
+                        ui.end_row();
+                    }
+
+                    for (name, value) in &cycle.aux {
+                        ui.label(format!("{}:", name));
+                        ui.label(format!("{:.3}", value));
+                        ui.end_row();
+                    }
                 });
                 ui.separator();
 
+                let fired = cycle.fired_diag_conditions();
+                if !fired.is_empty() {
+                    egui::Grid::new("cycle_diag_grid").striped(true).show(ui, |ui| {
+                        ui.label("Diagnostics");
+                        ui.label("Rows");
+                        ui.label("Severity");
+                        ui.end_row();
+                        for f in &fired {
+                            let color = match f.condition.severity {
+                                DiagSeverity::Invalid => Color32::RED,
+                                DiagSeverity::Warn => Color32::ORANGE,
+                                DiagSeverity::Ignore => ui.visuals().text_color(),
+                            };
+                            ui.colored_label(color, &f.condition.name);
+                            ui.label(f.count.to_string());
+                            ui.label(f.condition.severity.to_string());
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+                }
+
                 for model in FluxKind::all() {
                     ui.heading(model.label()); // Or .to_string() if you don’t have label()
 